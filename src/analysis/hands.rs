@@ -0,0 +1,148 @@
+use super::{tile_index, TILE_TYPES};
+use crate::models::{Event, KanType, Round};
+use crate::tile::Tile;
+
+/// Concealed tiles of every seat, replayed event by event
+///
+/// Tiles that cannot be mapped to a tile type are ignored, so a damaged log
+/// yields a best-effort hand rather than an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcealedHands {
+    /// Tile counts per tile type for each seat
+    pub counts: [[u8; TILE_TYPES]; 4],
+    /// Called melds per seat, closed kans included
+    pub melds: [u8; 4],
+    last_discard: Option<usize>,
+}
+
+impl ConcealedHands {
+    /// Hands as dealt at the start of `round`
+    pub fn new(round: &Round) -> Self {
+        let mut counts = [[0u8; TILE_TYPES]; 4];
+        for (hand, tiles) in counts.iter_mut().zip(&round.init.initial_hands) {
+            for t in tiles.iter().filter_map(|tile| tile_index(tile)) {
+                hand[t] += 1;
+            }
+        }
+        Self {
+            counts,
+            melds: [0; 4],
+            last_discard: None,
+        }
+    }
+
+    /// Number of concealed tiles held by `seat`
+    pub fn len(&self, seat: u8) -> usize {
+        self.counts[seat as usize % 4]
+            .iter()
+            .map(|&c| c as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self, seat: u8) -> bool {
+        self.len(seat) == 0
+    }
+
+    /// Update the hands for one event
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Draw { seat, tile, .. } => {
+                if let Some(t) = tile_index(tile) {
+                    self.counts[*seat as usize % 4][t] += 1;
+                }
+            }
+            Event::Discard { seat, tile, .. } => {
+                if let Some(t) = tile_index(tile) {
+                    self.remove(*seat, t);
+                    self.last_discard = Some(t);
+                }
+            }
+            Event::Chi { who, tiles, .. } | Event::Pon { who, tiles, .. } => {
+                let claimed = self.last_discard.take();
+                self.remove_meld(*who, tiles, claimed);
+                self.melds[*who as usize % 4] += 1;
+            }
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                ..
+            } => match kan_type {
+                KanType::Ankan => {
+                    self.remove_meld(*who, tiles, None);
+                    self.melds[*who as usize % 4] += 1;
+                }
+                KanType::Minkan => {
+                    let claimed = self.last_discard.take();
+                    self.remove_meld(*who, tiles, claimed);
+                    self.melds[*who as usize % 4] += 1;
+                }
+                // Upgrades an existing pon with one tile from the hand
+                KanType::Kakan => {
+                    if let Some(t) = tiles.first().and_then(|tile| tile_index(tile)) {
+                        self.remove(*who, t);
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn remove(&mut self, seat: u8, tile: usize) {
+        let count = &mut self.counts[seat as usize % 4][tile];
+        *count = count.saturating_sub(1);
+    }
+
+    /// Remove meld tiles from the hand, except the claimed discard
+    fn remove_meld(&mut self, seat: u8, tiles: &[Tile], mut claimed: Option<usize>) {
+        for t in tiles.iter().filter_map(|tile| tile_index(tile)) {
+            if claimed == Some(t) {
+                claimed = None;
+                continue;
+            }
+            self.remove(seat, t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_replay_draws_discards_and_calls() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,44,48" hai1="1,5,9,13,17,21,25,29,33,37,41,45,49" hai2="2,6,10,14,18,22,26,30,34,38,42,46,50" hai3="3,7,11,15,19,23,27,31,35,39,43,47,51"/>
+<T52/>
+<D0/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let round = &game.rounds[0];
+
+        let mut hands = ConcealedHands::new(round);
+        assert_eq!(hands.len(0), 13);
+        for event in &round.events {
+            hands.apply(event);
+        }
+        assert_eq!(hands.len(0), 13);
+        assert_eq!(hands.counts[0][0], 0); // 1m discarded
+        assert_eq!(hands.counts[0][13], 1); // 5p drawn (id 52)
+
+        // A pon claims the last discard; seat 1 only holds one more 1m
+        hands.apply(&Event::Pon {
+            who: 1,
+            tiles: [
+                "1m".parse().unwrap(),
+                "1m".parse().unwrap(),
+                "1m".parse().unwrap(),
+            ],
+            from: 0,
+            claimed: None,
+        });
+        assert_eq!(hands.melds[1], 1);
+        assert_eq!(hands.counts[1][0], 0);
+        assert_eq!(hands.len(1), 12);
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+use super::{discard_safety, shanten, tile_index, waits, ConcealedHands, SafetyClass, TILE_TYPES};
+use crate::models::{Event, KanType, Round};
+use crate::tile::Tile;
+
+/// Kind of likely mistake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MistakeKind {
+    /// Riichi declared while a winning tile is already in the player's river
+    FuritenRiichi,
+    /// A closed hand drew its winning tile and discarded instead of winning
+    MissedTsumo,
+    /// A player in riichi let a winning tile pass without being furiten
+    MissedRon,
+    /// Dealt a dangerous tile into a riichi while holding a genbutsu tile
+    DealInWithSafeTile,
+}
+
+/// A likely mistake found while replaying a round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mistake {
+    /// Index into `Round::events`
+    pub event: usize,
+    pub seat: u8,
+    pub kind: MistakeKind,
+    pub tile: Tile,
+    /// The other player involved: the discarder of a missed ron or the riichi
+    /// player that was dealt into
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub against: Option<u8>,
+}
+
+/// Flag likely mistakes in one round
+///
+/// These are heuristics: hands are reconstructed from the log and yaku are
+/// not evaluated, so a missed win is only reported where a yaku is certain
+/// (riichi, or menzen tsumo for a closed hand).
+pub fn detect_mistakes(round: &Round) -> Vec<Mistake> {
+    let mut mistakes = Vec::new();
+    let safety = discard_safety(round);
+
+    let mut hands = ConcealedHands::new(round);
+    let mut rivers = [[false; TILE_TYPES]; 4];
+    let mut passed = [[false; TILE_TYPES]; 4];
+    let mut declared = [false; 4];
+    let mut in_riichi = [false; 4];
+    // Riichi players who let a winning tile pass stay furiten for the round
+    let mut missed = [false; 4];
+    let mut opened = [false; 4];
+    let mut winning_draw: [Option<usize>; 4] = [None; 4];
+
+    for (i, event) in round.events.iter().enumerate() {
+        match event {
+            Event::Draw { seat, tile, .. } => {
+                let s = *seat as usize % 4;
+                hands.apply(event);
+                let complete = shanten(&hands.counts[s], hands.melds[s]) == -1;
+                winning_draw[s] =
+                    (complete && !opened[s] && tile_index(tile).is_some()).then_some(i);
+            }
+            Event::Discard { seat, tile, .. } => {
+                let s = *seat as usize % 4;
+                let Some(t) = tile_index(tile) else {
+                    continue;
+                };
+                let held = hands.counts[s];
+                hands.apply(event);
+
+                if let Some(draw) = winning_draw[s].take() {
+                    if let Event::Draw { tile, .. } = &round.events[draw] {
+                        mistakes.push(Mistake {
+                            event: draw,
+                            seat: s as u8,
+                            kind: MistakeKind::MissedTsumo,
+                            tile: *tile,
+                            against: None,
+                        });
+                    }
+                }
+
+                rivers[s][t] = true;
+                if declared[s] {
+                    declared[s] = false;
+                    in_riichi[s] = true;
+                    let furiten = waits(&hands.counts[s], hands.melds[s])
+                        .iter()
+                        .any(|&w| rivers[s][w]);
+                    if furiten {
+                        mistakes.push(Mistake {
+                            event: i,
+                            seat: s as u8,
+                            kind: MistakeKind::FuritenRiichi,
+                            tile: *tile,
+                            against: None,
+                        });
+                    }
+                }
+
+                let winners = wins_after(round, i);
+                for r in 0..4 {
+                    if r == s || !in_riichi[r] {
+                        continue;
+                    }
+                    let riichi_waits = waits(&hands.counts[r], hands.melds[r]);
+                    if winners.contains(&(r as u8)) {
+                        let class = safety
+                            .iter()
+                            .find(|d| d.event == i && d.against as usize == r)
+                            .map(|d| d.class);
+                        let had_safe =
+                            (0..TILE_TYPES).any(|h| held[h] > 0 && (rivers[r][h] || passed[r][h]));
+                        if class == Some(SafetyClass::Dangerous) && had_safe {
+                            mistakes.push(Mistake {
+                                event: i,
+                                seat: s as u8,
+                                kind: MistakeKind::DealInWithSafeTile,
+                                tile: *tile,
+                                against: Some(r as u8),
+                            });
+                        }
+                    } else if riichi_waits.contains(&t) {
+                        let furiten = missed[r] || riichi_waits.iter().any(|&w| rivers[r][w]);
+                        if !furiten {
+                            mistakes.push(Mistake {
+                                event: i,
+                                seat: r as u8,
+                                kind: MistakeKind::MissedRon,
+                                tile: *tile,
+                                against: Some(s as u8),
+                            });
+                        }
+                        missed[r] = true;
+                    }
+                }
+
+                for r in 0..4 {
+                    if in_riichi[r] {
+                        passed[r][t] = true;
+                    }
+                }
+            }
+            Event::Reach { who, step: 1, .. } => declared[*who as usize % 4] = true,
+            Event::Chi { who, .. } | Event::Pon { who, .. } => {
+                hands.apply(event);
+                opened[*who as usize % 4] = true;
+            }
+            Event::Kan { who, kan_type, .. } => {
+                hands.apply(event);
+                if !matches!(kan_type, KanType::Ankan) {
+                    opened[*who as usize % 4] = true;
+                }
+            }
+            Event::Agari { who, .. } => winning_draw[*who as usize % 4] = None,
+            _ => hands.apply(event),
+        }
+    }
+
+    mistakes
+}
+
+/// Seats that win off the discard at `index`
+fn wins_after(round: &Round, index: usize) -> Vec<u8> {
+    round.events[index + 1..]
+        .iter()
+        .take_while(|e| matches!(e, Event::Agari { .. } | Event::Dora { .. }))
+        .filter_map(|e| match e {
+            Event::Agari { who, from, .. } if who != from => Some(*who),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_detect_mistakes() {
+        // Seat 0: 123m 456m 789m 123p 9s waiting on 9s; seat 1 holds a 4p
+        let init = r#"<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,44,104" hai1="1,5,9,13,17,21,25,29,33,37,41,49,105" hai2="" hai3=""/>"#;
+        let xml = format!(
+            r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+{init}
+<T48/><REACH who="0" step="1"/><D48/><REACH who="0" step="2"/>
+<U52/><E105/>
+<T106/><D106/>
+<RYUUKYOKU type="nm" sc="240,0,250,0,250,0,250,0"/>
+{init}
+<T48/><REACH who="0" step="1"/><D48/><REACH who="0" step="2"/>
+<U52/><E105/>
+<AGARI who="0" fromWho="1" sc="240,80,250,-80,250,0,250,0"/>
+<INIT seed="2,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,104,105" hai1="" hai2="" hai3=""/>
+<T44/><D44/>
+<T88/><REACH who="0" step="1"/><D88/>
+<RYUUKYOKU type="nm" sc="250,0,250,0,250,0,250,0"/>
+</mjloggm>"#
+        );
+        let game = parse_mjlog(Cursor::new(xml.into_bytes())).unwrap();
+
+        let kinds = |round: usize| -> Vec<(usize, u8, MistakeKind, Option<u8>)> {
+            detect_mistakes(&game.rounds[round])
+                .into_iter()
+                .map(|m| (m.event, m.seat, m.kind, m.against))
+                .collect()
+        };
+
+        assert_eq!(
+            kinds(0),
+            vec![
+                (5, 0, MistakeKind::MissedRon, Some(1)),
+                (6, 0, MistakeKind::MissedTsumo, None),
+            ]
+        );
+        assert_eq!(
+            kinds(1),
+            vec![(5, 1, MistakeKind::DealInWithSafeTile, Some(0))]
+        );
+        assert_eq!(
+            kinds(2),
+            vec![
+                (0, 0, MistakeKind::MissedTsumo, None),
+                (4, 0, MistakeKind::FuritenRiichi, None),
+            ]
+        );
+    }
+}
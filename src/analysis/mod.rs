@@ -0,0 +1,23 @@
+//! Analysis passes that derive information not present in the log itself
+
+pub mod hands;
+pub mod mistakes;
+pub mod safety;
+pub mod shanten;
+
+pub use hands::ConcealedHands;
+pub use mistakes::{detect_mistakes, Mistake, MistakeKind};
+pub use safety::{discard_safety, DiscardSafety, SafetyClass};
+pub use shanten::{
+    annotate_shanten, annotate_waits, chiitoitsu_shanten, kokushi_shanten, regular_shanten,
+    shanten, waits,
+};
+
+pub use crate::tile::TILE_TYPES;
+
+use crate::tile::tile_string_to_id;
+
+/// Tile type index (0-33) of a tile string
+pub(crate) fn tile_index(tile: &str) -> Option<usize> {
+    tile_string_to_id(tile).ok().map(|id| id as usize / 4)
+}
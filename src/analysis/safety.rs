@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+
+use super::{tile_index, TILE_TYPES};
+use crate::models::{Event, KanType, Round};
+use crate::tile::tile_id_to_string;
+use crate::tile::Tile;
+
+/// Safety of a discard against a player in riichi, from safest to most dangerous
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SafetyClass {
+    /// Already discarded by the riichi player, or passed after the declaration
+    Genbutsu,
+    /// Cannot complete a two-sided wait because of the riichi player's discards
+    Suji,
+    /// Every two-sided wait on the tile needs a tile with three or more visible copies
+    OneChance,
+    Dangerous,
+}
+
+/// Classification of one discard against one riichi player
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscardSafety {
+    /// Index into `Round::events`
+    pub event: usize,
+    pub seat: u8,
+    pub tile: Tile,
+    /// Seat of the player in riichi
+    pub against: u8,
+    pub class: SafetyClass,
+}
+
+/// Classify every discard made while another player is in riichi
+///
+/// Visible tiles are the discards, melds and dora indicators seen so far;
+/// the discarder's own hand is not taken into account.
+pub fn discard_safety(round: &Round) -> Vec<DiscardSafety> {
+    let mut result = Vec::new();
+    let mut visible = [0u8; TILE_TYPES];
+    // Tiles each seat has discarded
+    let mut rivers = [[false; TILE_TYPES]; 4];
+    // Tiles discarded by anyone since each seat's riichi
+    let mut passed = [[false; TILE_TYPES]; 4];
+    let mut declared = [false; 4];
+    let mut in_riichi = [false; 4];
+    let mut last_discard: Option<usize> = None;
+
+    if let Some(t) = tile_index(&tile_id_to_string(round.init.dora_indicator)) {
+        visible[t] += 1;
+    }
+
+    for (i, event) in round.events.iter().enumerate() {
+        match event {
+            Event::Discard { seat, tile, .. } => {
+                let seat = *seat as usize % 4;
+                let Some(t) = tile_index(tile) else {
+                    continue;
+                };
+
+                for against in 0..4 {
+                    if against == seat || !in_riichi[against] {
+                        continue;
+                    }
+                    result.push(DiscardSafety {
+                        event: i,
+                        seat: seat as u8,
+                        tile: *tile,
+                        against: against as u8,
+                        class: classify(t, &rivers[against], &passed[against], &visible),
+                    });
+                }
+
+                visible[t] += 1;
+                rivers[seat][t] = true;
+                for (other, passed) in passed.iter_mut().enumerate() {
+                    if in_riichi[other] {
+                        passed[t] = true;
+                    }
+                }
+                // The riichi takes effect once the declaration tile is discarded
+                if declared[seat] {
+                    declared[seat] = false;
+                    in_riichi[seat] = true;
+                }
+                last_discard = Some(t);
+            }
+            Event::Reach { who, step: 1, .. } => declared[*who as usize % 4] = true,
+            Event::Chi { tiles, .. } | Event::Pon { tiles, .. } => {
+                add_meld(&mut visible, tiles, last_discard.take());
+            }
+            Event::Kan {
+                tiles, kan_type, ..
+            } => match kan_type {
+                KanType::Ankan => add_meld(&mut visible, tiles, None),
+                KanType::Minkan => add_meld(&mut visible, tiles, last_discard.take()),
+                // Only the added tile is new; the pon was already counted
+                KanType::Kakan => add_meld(&mut visible, &tiles[..tiles.len().min(1)], None),
+            },
+            Event::Dora { indicator, .. } => {
+                if let Some(t) = tile_index(indicator) {
+                    visible[t] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Count meld tiles as visible, except the claimed discard that already was
+fn add_meld(visible: &mut [u8; TILE_TYPES], tiles: &[Tile], claimed: Option<usize>) {
+    for t in tiles.iter().filter_map(|tile| tile_index(tile)) {
+        visible[t] += 1;
+    }
+    if let Some(t) = claimed {
+        visible[t] = visible[t].saturating_sub(1);
+    }
+}
+
+fn classify(
+    tile: usize,
+    river: &[bool; TILE_TYPES],
+    passed: &[bool; TILE_TYPES],
+    visible: &[u8; TILE_TYPES],
+) -> SafetyClass {
+    if river[tile] || passed[tile] {
+        return SafetyClass::Genbutsu;
+    }
+
+    // Honors can only be waited on as a pair or single
+    if tile >= 27 {
+        return if visible[tile] >= 3 {
+            SafetyClass::OneChance
+        } else {
+            SafetyClass::Dangerous
+        };
+    }
+
+    let number = tile % 9; // 0-based
+
+    // Two-sided waits that complete with this tile: (shape, shape, other wait)
+    let mut shapes = Vec::with_capacity(2);
+    if number <= 5 {
+        shapes.push((tile + 1, tile + 2, tile + 3));
+    }
+    if number >= 3 {
+        shapes.push((tile - 2, tile - 1, tile - 3));
+    }
+
+    let safe_by_suji = shapes.iter().all(|&(_, _, other)| river[other]);
+    if safe_by_suji {
+        return SafetyClass::Suji;
+    }
+
+    let blocked = shapes
+        .iter()
+        .all(|&(a, b, _)| visible[a] >= 3 || visible[b] >= 3);
+    if blocked {
+        SafetyClass::OneChance
+    } else {
+        SafetyClass::Dangerous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    fn round(events: &str) -> Round {
+        let xml = format!(
+            r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,135" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>{}</mjloggm>"#,
+            events
+        );
+        parse_mjlog(Cursor::new(xml.into_bytes()))
+            .unwrap()
+            .rounds
+            .remove(0)
+    }
+
+    fn classes(round: &Round) -> Vec<(String, SafetyClass)> {
+        discard_safety(round)
+            .into_iter()
+            .map(|d| (d.tile.to_string(), d.class))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_riichi_no_classification() {
+        let round = round("<D0/><E4/><F8/>");
+        assert!(discard_safety(&round).is_empty());
+    }
+
+    #[test]
+    fn test_genbutsu_and_suji() {
+        // Seat 0 discards 4m (12) then declares riichi with 9p (68)
+        let round = round(concat!(
+            "<D12/><REACH who=\"0\" step=\"1\"/><D68/>",
+            "<E13/>", // 4m: genbutsu
+            "<F0/>",  // 1m: suji of 4m
+            "<G28/>", // 8m: dangerous
+            "<E29/>", // 8m: passed by seat 3, now genbutsu
+            "<F16/>", // 5m: 2m-5m not covered, dangerous
+        ));
+
+        let classes = classes(&round);
+        assert_eq!(
+            classes,
+            vec![
+                ("4m".to_string(), SafetyClass::Genbutsu),
+                ("1m".to_string(), SafetyClass::Suji),
+                ("8m".to_string(), SafetyClass::Dangerous),
+                ("8m".to_string(), SafetyClass::Genbutsu),
+                ("5m".to_string(), SafetyClass::Dangerous),
+            ]
+        );
+        assert!(discard_safety(&round).iter().all(|d| d.against == 0));
+    }
+
+    #[test]
+    fn test_one_chance_and_honors() {
+        // Three 8s are visible before the riichi: 9s can only be hit by a 7-8 shape
+        let round = round(concat!(
+            "<E100/><F101/><G102/>",
+            "<REACH who=\"0\" step=\"1\"/><D0/>",
+            "<E104/>", // 9s: one-chance
+            "<F108/>", // east: dangerous
+            "<G109/><E110/>",
+            "<F111/>", // last east after passes is genbutsu anyway
+        ));
+
+        let classes = classes(&round);
+        assert_eq!(classes[0], ("9s".to_string(), SafetyClass::OneChance));
+        assert_eq!(classes[1], ("east".to_string(), SafetyClass::Dangerous));
+        assert_eq!(classes[4], ("east".to_string(), SafetyClass::Genbutsu));
+    }
+
+    #[test]
+    fn test_riichi_player_discards_not_classified() {
+        let round = round("<REACH who=\"1\" step=\"1\"/><E0/><E4/><D8/>");
+        let result = discard_safety(&round);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].seat, 0);
+        assert_eq!(result[0].against, 1);
+    }
+}
@@ -0,0 +1,321 @@
+use super::{tile_index, ConcealedHands, TILE_TYPES};
+use crate::models::{Event, Round};
+use crate::tile::Tile;
+
+/// Tile type indices of terminals and honors (yaochuuhai)
+const YAOCHUU: [usize; 13] = [0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33];
+
+/// Shanten number of a concealed hand: the minimum of the regular,
+/// seven pairs and thirteen orphans forms
+///
+/// `counts` holds the concealed tiles per tile type and `melds` the number of
+/// called (or closed kan) melds. 0 means tenpai and -1 a complete hand.
+///
+/// # Examples
+/// ```
+/// use tenhou_log_parser::analysis::shanten;
+///
+/// // 123m 456p 789s 11z plus isolated 2z 3z: one tile away from tenpai
+/// let mut counts = [0u8; 34];
+/// for t in [0, 1, 2, 12, 13, 14, 24, 25, 26, 27, 27, 28, 29] {
+///     counts[t] += 1;
+/// }
+/// assert_eq!(shanten(&counts, 0), 1);
+/// ```
+pub fn shanten(counts: &[u8; TILE_TYPES], melds: u8) -> i8 {
+    let regular = regular_shanten(counts, melds);
+    if melds > 0 {
+        return regular;
+    }
+    regular
+        .min(chiitoitsu_shanten(counts))
+        .min(kokushi_shanten(counts))
+}
+
+/// Tile types that would complete a tenpai hand
+///
+/// Tiles of which the hand already holds all four copies are not waits.
+pub fn waits(counts: &[u8; TILE_TYPES], melds: u8) -> Vec<usize> {
+    let mut counts = *counts;
+    (0..TILE_TYPES)
+        .filter(|&t| {
+            if counts[t] >= 4 {
+                return false;
+            }
+            counts[t] += 1;
+            let complete = shanten(&counts, melds) == -1;
+            counts[t] -= 1;
+            complete
+        })
+        .collect()
+}
+
+/// Fill in `shanten` on every draw and discard of `round`, from the hand
+/// of the seat that acted as it is right after the action
+pub fn annotate_shanten(round: &mut Round) {
+    let mut hands = ConcealedHands::new(round);
+    for event in &mut round.events {
+        hands.apply(event);
+        if let Event::Draw { seat, shanten, .. } | Event::Discard { seat, shanten, .. } = event {
+            let seat = *seat as usize % 4;
+            *shanten = Some(self::shanten(&hands.counts[seat], hands.melds[seat]));
+        }
+    }
+}
+
+/// Fill in `waits` and `furiten` on every discard of `round` that leaves
+/// the discarder tenpai
+///
+/// Furiten here is the discarder having one of the waits in their own
+/// river; missed wins and riichi furiten are not considered.
+pub fn annotate_waits(round: &mut Round) {
+    let mut hands = ConcealedHands::new(round);
+    let mut rivers = [[false; TILE_TYPES]; 4];
+    for event in &mut round.events {
+        hands.apply(event);
+        let Event::Discard {
+            seat,
+            tile,
+            waits: tile_waits,
+            furiten,
+            ..
+        } = event
+        else {
+            continue;
+        };
+        let seat = *seat as usize % 4;
+        if let Some(t) = tile_index(tile) {
+            rivers[seat][t] = true;
+        }
+        if shanten(&hands.counts[seat], hands.melds[seat]) != 0 {
+            continue;
+        }
+        let found = waits(&hands.counts[seat], hands.melds[seat]);
+        *furiten = found.iter().any(|&t| rivers[seat][t]);
+        *tile_waits = found.into_iter().map(Tile::from_index).collect();
+    }
+}
+
+/// Shanten number for four melds and a pair
+pub fn regular_shanten(counts: &[u8; TILE_TYPES], melds: u8) -> i8 {
+    let mut counts = *counts;
+    let mut best = 8 - 2 * melds as i8;
+
+    // Without a designated pair
+    search(&mut counts, 0, melds, 0, false, &mut best);
+
+    for t in 0..TILE_TYPES {
+        if counts[t] >= 2 {
+            counts[t] -= 2;
+            search(&mut counts, 0, melds, 0, true, &mut best);
+            counts[t] += 2;
+        }
+    }
+
+    best
+}
+
+/// Shanten number for seven distinct pairs
+pub fn chiitoitsu_shanten(counts: &[u8; TILE_TYPES]) -> i8 {
+    let pairs = counts.iter().filter(|&&c| c >= 2).count() as i8;
+    let kinds = counts.iter().filter(|&&c| c >= 1).count() as i8;
+    6 - pairs + (7 - kinds).max(0)
+}
+
+/// Shanten number for thirteen orphans
+pub fn kokushi_shanten(counts: &[u8; TILE_TYPES]) -> i8 {
+    let kinds = YAOCHUU.iter().filter(|&&t| counts[t] >= 1).count() as i8;
+    let pair = YAOCHUU.iter().any(|&t| counts[t] >= 2);
+    13 - kinds - pair as i8
+}
+
+fn search(
+    counts: &mut [u8; TILE_TYPES],
+    start: usize,
+    mentsu: u8,
+    taatsu: u8,
+    pair: bool,
+    best: &mut i8,
+) {
+    let Some(i) = (start..TILE_TYPES).find(|&t| counts[t] > 0) else {
+        // Blocks beyond four don't help
+        let taatsu = taatsu.min(4u8.saturating_sub(mentsu));
+        let shanten = 8 - 2 * mentsu as i8 - taatsu as i8 - pair as i8;
+        *best = (*best).min(shanten);
+        return;
+    };
+    let sequence = i < 27 && i % 9 <= 6;
+
+    // Complete sets
+    if counts[i] >= 3 {
+        counts[i] -= 3;
+        search(counts, i, mentsu + 1, taatsu, pair, best);
+        counts[i] += 3;
+    }
+    if sequence && counts[i + 1] > 0 && counts[i + 2] > 0 {
+        take(counts, &[i, i + 1, i + 2]);
+        search(counts, i, mentsu + 1, taatsu, pair, best);
+        give(counts, &[i, i + 1, i + 2]);
+    }
+
+    // Partial sets
+    if mentsu + taatsu < 4 {
+        if counts[i] >= 2 {
+            counts[i] -= 2;
+            search(counts, i, mentsu, taatsu + 1, pair, best);
+            counts[i] += 2;
+        }
+        if i < 27 && i % 9 <= 7 && counts[i + 1] > 0 {
+            take(counts, &[i, i + 1]);
+            search(counts, i, mentsu, taatsu + 1, pair, best);
+            give(counts, &[i, i + 1]);
+        }
+        if sequence && counts[i + 2] > 0 {
+            take(counts, &[i, i + 2]);
+            search(counts, i, mentsu, taatsu + 1, pair, best);
+            give(counts, &[i, i + 2]);
+        }
+    }
+
+    // Leave this tile isolated
+    counts[i] -= 1;
+    search(counts, i, mentsu, taatsu, pair, best);
+    counts[i] += 1;
+}
+
+fn take(counts: &mut [u8; TILE_TYPES], tiles: &[usize]) {
+    for &t in tiles {
+        counts[t] -= 1;
+    }
+}
+
+fn give(counts: &mut [u8; TILE_TYPES], tiles: &[usize]) {
+    for &t in tiles {
+        counts[t] += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse "123m456p789s1234567z" style notation into counts
+    fn hand(notation: &str) -> [u8; TILE_TYPES] {
+        let mut counts = [0u8; TILE_TYPES];
+        let mut digits = Vec::new();
+        for c in notation.chars() {
+            if let Some(d) = c.to_digit(10) {
+                digits.push(d as usize);
+                continue;
+            }
+            let base = match c {
+                'm' => 0,
+                'p' => 9,
+                's' => 18,
+                'z' => 27,
+                _ => panic!("bad suit {}", c),
+            };
+            for d in digits.drain(..) {
+                counts[base + d - 1] += 1;
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn test_complete_and_tenpai() {
+        assert_eq!(shanten(&hand("123m456p789s11122z"), 0), -1);
+        assert_eq!(shanten(&hand("123m456p789s1112z"), 0), 0);
+        assert_eq!(shanten(&hand("123m456p78s11z"), 1), 0);
+        assert_eq!(shanten(&hand("11z"), 4), -1);
+        assert_eq!(shanten(&hand("1z"), 4), 0);
+    }
+
+    #[test]
+    fn test_waits() {
+        assert_eq!(waits(&hand("23m456p789s11122z"), 0), vec![0, 3]);
+        assert_eq!(
+            waits(&hand("1112345678999m"), 0),
+            (0..9).collect::<Vec<_>>()
+        );
+        assert_eq!(waits(&hand("1122m3344p5566s7z"), 0), vec![33]);
+        assert!(waits(&hand("13579m13579p135s"), 0).is_empty());
+    }
+
+    #[test]
+    fn test_annotate_shanten() {
+        // Seat 0 is one away from tenpai with 123m 456p 789s 11z 2z 3z,
+        // draws a second south and lets go of west
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,48,52,56,96,100,104,108,109,112,116" hai1="" hai2="" hai3=""/>
+<T113/><D116/>
+</mjloggm>"#;
+        let options = crate::ParserOptions {
+            annotate_shanten: true,
+            ..Default::default()
+        };
+        let output = crate::parse_mjlog_with_options(std::io::Cursor::new(xml), &options).unwrap();
+        let shanten: Vec<Option<i8>> = output.rounds[0]
+            .events
+            .iter()
+            .map(|event| match event {
+                Event::Draw { shanten, .. } | Event::Discard { shanten, .. } => *shanten,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(shanten, [Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn test_annotate_waits() {
+        // Seat 0 lets go of 7p to wait on 5s alone, then draws 7p again
+        // and lets go of 5s to wait on 4p-7p, furiten on its own 7p
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="4,8,48,52,56,96,100,104,108,109,110,60,89" hai1="" hai2="" hai3=""/>
+<T0/><D60/><T61/><D89/>
+</mjloggm>"#;
+        let options = crate::ParserOptions {
+            annotate_waits: true,
+            ..Default::default()
+        };
+        let output = crate::parse_mjlog_with_options(std::io::Cursor::new(xml), &options).unwrap();
+        let discards: Vec<(Vec<String>, bool)> = output.rounds[0]
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Discard { waits, furiten, .. } => {
+                    Some((waits.iter().map(Tile::to_string).collect(), *furiten))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            discards,
+            [
+                (vec!["5s".to_string()], false),
+                (vec!["4p".to_string(), "7p".to_string()], true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regular_shanten() {
+        assert_eq!(regular_shanten(&hand("13579m13579p135s"), 0), 4);
+        assert_eq!(regular_shanten(&hand("123m456p79s11z235z"), 0), 2);
+        assert_eq!(regular_shanten(&hand("1234567m1234567z"), 0), 4);
+    }
+
+    #[test]
+    fn test_chiitoitsu_shanten() {
+        assert_eq!(chiitoitsu_shanten(&hand("1122m3344p5566s7z")), 0);
+        assert_eq!(chiitoitsu_shanten(&hand("11112222m3344p5z")), 4);
+        assert_eq!(shanten(&hand("1122m3344p5566s7z"), 0), 0);
+    }
+
+    #[test]
+    fn test_kokushi_shanten() {
+        assert_eq!(kokushi_shanten(&hand("19m19p19s1234567z")), 0);
+        assert_eq!(kokushi_shanten(&hand("119m19p19s1234567z")), -1);
+        assert_eq!(shanten(&hand("129m19p19s123456z"), 0), 1);
+    }
+}
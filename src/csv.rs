@@ -0,0 +1,65 @@
+//! Events as flat table rows, for spreadsheets and pandas
+//!
+//! Every event of every round is one row with the columns of `COLUMNS`, in
+//! that order. The column set is stable: columns are only ever appended.
+//! Cells that do not apply to an event are empty, tiles and scores of one
+//! event are joined with spaces, and a cell holding the separator, a quote or
+//! a line break (player names may) is quoted, with quotes doubled.
+
+use crate::dataset::events::{event_rows, events_table};
+use crate::models::ParserOutput;
+
+pub use crate::dataset::events::COLUMNS;
+
+/// Comma-separated rows of `output`, with a `COLUMNS` header
+pub fn to_csv(output: &ParserOutput) -> String {
+    to_table(output, ',')
+}
+
+/// Tab-separated rows of `output`, with a `COLUMNS` header
+pub fn to_tsv(output: &ParserOutput) -> String {
+    to_table(output, '\t')
+}
+
+fn to_table(output: &ParserOutput, separator: char) -> String {
+    let mut text = Vec::new();
+    // Writing to memory cannot fail
+    let _ = events_table(&event_rows(output)).write_delimited(&mut text, separator);
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_to_csv() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+<UN n0="%41%2C%22B%22" n1="C%2CD" n2="D" n3="E"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,1" hai1="" hai2="" hai3=""/>
+<T4/><D0/><REACH who="1" step="1"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let csv = to_csv(&output);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert_eq!(
+            &lines[1][output.game_id.len()..],
+            r#",0,Round 1,0,draw,0,"A,""B""",2m,,,,,,,"#
+        );
+        assert_eq!(
+            &lines[2][output.game_id.len()..],
+            r#",0,Round 1,1,discard,0,"A,""B""",1m,,,false,,,,"#
+        );
+        assert_eq!(
+            &lines[3][output.game_id.len()..],
+            ",0,Round 1,2,reach,1,\"C,D\",,,,,1,,,"
+        );
+
+        // Commas need no quoting between tabs
+        let tsv = to_tsv(&output);
+        assert!(tsv.lines().nth(3).unwrap().contains("\treach\t1\tC,D\t"));
+    }
+}
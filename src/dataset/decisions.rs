@@ -0,0 +1,384 @@
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{tile_index, waits, ConcealedHands, TILE_TYPES};
+use crate::models::{Event, KanType, Round};
+use crate::tile::Tile;
+
+/// Live wall size of a four-player game after the deal
+const WALL_TILES: u32 = 70;
+
+/// Riichi deposit in score units
+const RIICHI_DEPOSIT: i32 = 10;
+
+/// What a player could see when making a decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Observation {
+    pub round_number: u32,
+    pub honba: u32,
+    pub kyoutaku: u32,
+    pub dealer_seat: u8,
+    /// Dora indicators revealed so far
+    pub dora_indicators: Vec<Tile>,
+    pub scores: [i32; 4],
+    /// Tiles left in the live wall
+    pub wall_remaining: u32,
+    /// The deciding player's concealed tiles, sorted
+    pub hand: Vec<Tile>,
+    /// Called melds per seat
+    pub melds: [Vec<Vec<Tile>>; 4],
+    /// Discards per seat, including tiles that were later called
+    pub discards: [Vec<Tile>; 4],
+    /// Seats with an accepted riichi
+    pub riichi: [bool; 4],
+}
+
+/// The action taken at a decision point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Action {
+    Discard {
+        tile: Tile,
+        riichi: bool,
+    },
+    Tsumo,
+    Ron,
+    Chi {
+        tiles: [Tile; 3],
+    },
+    Pon {
+        tiles: [Tile; 3],
+    },
+    Kan {
+        kan_type: KanType,
+        tiles: Vec<Tile>,
+    },
+    /// Declined to call or win on another player's discard
+    Pass,
+}
+
+/// A discard offered to the other players
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Offer {
+    pub seat: u8,
+    pub tile: Tile,
+}
+
+/// One decision point: a state and the action taken from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Decision {
+    /// Index into `Round::events` of the action, or of the discard for a pass
+    pub event: usize,
+    pub seat: u8,
+    /// The discard being responded to, for call, ron and pass decisions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offer: Option<Offer>,
+    pub state: Observation,
+    pub action: Action,
+}
+
+/// Replay state of a round as seen from the table
+struct Table {
+    base: Observation,
+    hands: ConcealedHands,
+}
+
+impl Table {
+    fn new(round: &Round) -> Self {
+        Self {
+            base: Observation {
+                round_number: round.init.round_number,
+                honba: round.init.honba,
+                kyoutaku: round.init.kyoutaku,
+                dealer_seat: round.dealer_seat,
+                dora_indicators: vec![Tile::from_id(round.init.dora_indicator)],
+                scores: round.init.initial_scores,
+                wall_remaining: WALL_TILES,
+                hand: Vec::new(),
+                melds: Default::default(),
+                discards: Default::default(),
+                riichi: [false; 4],
+            },
+            hands: ConcealedHands::new(round),
+        }
+    }
+
+    fn observe(&self, seat: u8) -> Observation {
+        let hand = self.hands.counts[seat as usize % 4]
+            .iter()
+            .enumerate()
+            .flat_map(|(t, &n)| std::iter::repeat_n(Tile::from_index(t), n as usize))
+            .collect();
+        Observation {
+            hand,
+            ..self.base.clone()
+        }
+    }
+
+    fn apply(&mut self, event: &Event) {
+        self.hands.apply(event);
+        let state = &mut self.base;
+        match event {
+            Event::Draw { .. } => state.wall_remaining = state.wall_remaining.saturating_sub(1),
+            Event::Discard { seat, tile, .. } => state.discards[*seat as usize % 4].push(*tile),
+            Event::Chi { who, tiles, .. } | Event::Pon { who, tiles, .. } => {
+                state.melds[*who as usize % 4].push(tiles.to_vec())
+            }
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                ..
+            } => {
+                let melds = &mut state.melds[*who as usize % 4];
+                let upgraded = matches!(kan_type, KanType::Kakan)
+                    .then(|| {
+                        let t = tiles.first().and_then(|tile| tile_index(tile));
+                        melds.iter_mut().find(|m| {
+                            m.len() == 3 && m.first().and_then(|tile| tile_index(tile)) == t
+                        })
+                    })
+                    .flatten();
+                match upgraded {
+                    Some(meld) => *meld = tiles.clone(),
+                    None => melds.push(tiles.clone()),
+                }
+            }
+            Event::Dora { indicator, .. } => state.dora_indicators.push(*indicator),
+            Event::Reach { who, step: 2, .. } => {
+                let s = *who as usize % 4;
+                state.riichi[s] = true;
+                state.scores[s] -= RIICHI_DEPOSIT;
+                state.kyoutaku += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `seat` could call or win on `tile` discarded by `from`
+    fn can_respond(&self, seat: u8, from: u8, tile: usize) -> bool {
+        let s = seat as usize % 4;
+        let counts = &self.hands.counts[s];
+        if waits(counts, self.hands.melds[s]).contains(&tile) {
+            return true;
+        }
+        if self.base.riichi[s] || self.base.wall_remaining == 0 {
+            return false;
+        }
+        if counts[tile] >= 2 {
+            return true;
+        }
+        if seat != (from + 1) % 4 || tile >= 27 {
+            return false;
+        }
+        let number = tile % 9;
+        (number.saturating_sub(2)..=number.min(6)).any(|start| {
+            let base = tile - number + start;
+            (base..base + 3).all(|t| t == tile || counts[t] > 0)
+        })
+    }
+}
+
+/// Decision points of one round, in event order
+///
+/// Each discard, win, call and closed or added kan becomes a decision with
+/// the state just before it. Players who could have won on or called a
+/// discard but did not get a `Pass` decision; this uses tile counts only, so
+/// yaku and furiten are not checked.
+pub fn decisions(round: &Round) -> Vec<Decision> {
+    let mut table = Table::new(round);
+    let mut decisions = Vec::new();
+    let mut declared = [false; 4];
+    // Events already applied ahead of their turn
+    let mut applied = 0;
+
+    for (i, event) in round.events.iter().enumerate() {
+        if i < applied {
+            continue;
+        }
+        match event {
+            Event::Reach { who, step: 1, .. } => declared[*who as usize % 4] = true,
+            Event::Discard { seat, tile, .. } => {
+                let s = *seat as usize % 4;
+                decisions.push(Decision {
+                    event: i,
+                    seat: *seat,
+                    offer: None,
+                    state: table.observe(*seat),
+                    action: Action::Discard {
+                        tile: *tile,
+                        riichi: std::mem::take(&mut declared[s]),
+                    },
+                });
+                table.apply(event);
+                // Riichi is accepted before anyone calls the declaration tile
+                applied = i + 1;
+                while let Some(accept @ Event::Reach { step: 2, .. }) = round.events.get(applied) {
+                    table.apply(accept);
+                    applied += 1;
+                }
+                let offer = Offer {
+                    seat: *seat,
+                    tile: *tile,
+                };
+                decisions.extend(responses(round, &table, i, &offer));
+                continue;
+            }
+            Event::Kan {
+                who,
+                tiles,
+                kan_type: kan_type @ (KanType::Ankan | KanType::Kakan),
+                ..
+            } => decisions.push(Decision {
+                event: i,
+                seat: *who,
+                offer: None,
+                state: table.observe(*who),
+                action: Action::Kan {
+                    kan_type: kan_type.clone(),
+                    tiles: tiles.clone(),
+                },
+            }),
+            Event::Agari { who, from, .. } if who == from => decisions.push(Decision {
+                event: i,
+                seat: *who,
+                offer: None,
+                state: table.observe(*who),
+                action: Action::Tsumo,
+            }),
+            _ => {}
+        }
+        table.apply(event);
+    }
+
+    decisions
+}
+
+/// Decisions of the other players on the discard at `index`
+fn responses(round: &Round, table: &Table, index: usize, offer: &Offer) -> Vec<Decision> {
+    let Some(tile) = tile_index(&offer.tile).filter(|&t| t < TILE_TYPES) else {
+        return Vec::new();
+    };
+    let following: Vec<(usize, &Event)> = round
+        .events
+        .iter()
+        .enumerate()
+        .skip(index + 1)
+        .filter(|(_, e)| !matches!(e, Event::Reach { step: 2, .. }))
+        .take_while(|(_, e)| !matches!(e, Event::Draw { .. } | Event::Discard { .. }))
+        .collect();
+
+    let mut decisions = Vec::new();
+    for seat in (1..4).map(|d| (offer.seat + d) % 4) {
+        let taken = following.iter().find_map(|&(i, e)| {
+            let action = match e {
+                Event::Agari { who, from, .. } if *who == seat && who != from => Action::Ron,
+                Event::Chi { who, tiles, .. } if *who == seat => Action::Chi { tiles: *tiles },
+                Event::Pon { who, tiles, .. } if *who == seat => Action::Pon { tiles: *tiles },
+                Event::Kan {
+                    who,
+                    tiles,
+                    kan_type: KanType::Minkan,
+                    ..
+                } if *who == seat => Action::Kan {
+                    kan_type: KanType::Minkan,
+                    tiles: tiles.clone(),
+                },
+                _ => return None,
+            };
+            Some((i, action))
+        });
+        let (event, action) = match taken {
+            Some(taken) => taken,
+            None if table.can_respond(seat, offer.seat, tile) => (index, Action::Pass),
+            None => continue,
+        };
+        decisions.push(Decision {
+            event,
+            seat,
+            offer: Some(offer.clone()),
+            state: table.observe(seat),
+            action,
+        });
+    }
+    decisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_decisions() {
+        // Seat 1 holds two 1m (ids 1, 2) and can pon seat 0's 1m; seat 2 then
+        // passes on the 3p (id 44) that seat 1 discards after the pon
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,40,80,120,124,128,132,100,101,102,103,104,105" hai1="1,2,44,60,64,68,72,76,84,88,92,96,108" hai2="45,46,52,56,112,116,3,6,10,14,18,22,26" hai3="30,34,38,42,50,54,58,62,66,70,74,78,82"/>
+<T106/><REACH who="0" step="1"/><D0/><REACH who="0" step="2"/>
+<N who="1" m="1"/>
+<E44/>
+<RYUUKYOKU type="nm" sc="240,0,250,0,250,0,250,0"/>
+</mjloggm>"#;
+        let mut game = parse_mjlog(Cursor::new(xml)).unwrap();
+        // Meld decoding is not needed here; make the call explicit
+        game.rounds[0].events[4] = Event::Pon {
+            who: 1,
+            tiles: [
+                "1m".parse().unwrap(),
+                "1m".parse().unwrap(),
+                "1m".parse().unwrap(),
+            ],
+            from: 0,
+            claimed: None,
+        };
+        let decisions = decisions(&game.rounds[0]);
+
+        let summary: Vec<(usize, u8, &Action)> = decisions
+            .iter()
+            .map(|d| (d.event, d.seat, &d.action))
+            .collect();
+        assert_eq!(summary.len(), 4);
+        assert_eq!(
+            summary[0],
+            (
+                2,
+                0,
+                &Action::Discard {
+                    tile: "1m".parse().unwrap(),
+                    riichi: true
+                }
+            )
+        );
+        assert!(matches!(summary[1], (4, 1, Action::Pon { .. })));
+        assert!(matches!(summary[2], (5, 1, Action::Discard { .. })));
+        assert_eq!(summary[3], (5, 2, &Action::Pass));
+
+        let first = &decisions[0].state;
+        assert_eq!(first.hand.len(), 14);
+        assert_eq!(first.wall_remaining, 69);
+        assert_eq!(first.dora_indicators, vec!["5p"]);
+        assert!(!first.riichi[0]);
+
+        let pon = &decisions[1];
+        assert_eq!(pon.offer.as_ref().unwrap().tile, "1m");
+        assert_eq!(pon.state.hand.len(), 13);
+        assert!(pon.state.riichi[0]);
+        assert_eq!(pon.state.scores[0], 240);
+        assert_eq!(pon.state.kyoutaku, 1);
+
+        let pass = &decisions[3].state;
+        assert_eq!(pass.melds[1], vec![vec!["1m", "1m", "1m"]]);
+        assert_eq!(pass.discards[1], vec!["3p"]);
+
+        let json = serde_json::to_value(&decisions[0]).unwrap();
+        assert_eq!(json["action"]["type"], "discard");
+        assert_eq!(json["state"]["wallRemaining"], 69);
+    }
+}
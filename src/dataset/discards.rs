@@ -0,0 +1,347 @@
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{discard_safety, shanten, tile_index, ConcealedHands, SafetyClass};
+use crate::models::{Event, ParserOutput};
+use crate::stats::export::escape;
+use crate::stats::RoundResult;
+use crate::tile::Tile;
+
+/// How the round ended for the player who made a discard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RoundOutcome {
+    Win,
+    DealIn,
+    /// Another player won by tsumo
+    TsumoByOther,
+    /// Another player won off someone else's discard
+    OtherRon,
+    ExhaustiveDraw,
+    AbortiveDraw,
+    /// The log ends before the round does
+    Incomplete,
+}
+
+impl RoundOutcome {
+    fn of(result: &RoundResult, seat: u8) -> Self {
+        if result.won(seat) {
+            RoundOutcome::Win
+        } else if result.dealt_in(seat) {
+            RoundOutcome::DealIn
+        } else if result.tsumo_by_other(seat) {
+            RoundOutcome::TsumoByOther
+        } else if !result.wins.is_empty() {
+            RoundOutcome::OtherRon
+        } else if result.exhaustive_draw {
+            RoundOutcome::ExhaustiveDraw
+        } else if result.draw {
+            RoundOutcome::AbortiveDraw
+        } else {
+            RoundOutcome::Incomplete
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RoundOutcome::Win => "win",
+            RoundOutcome::DealIn => "dealIn",
+            RoundOutcome::TsumoByOther => "tsumoByOther",
+            RoundOutcome::OtherRon => "otherRon",
+            RoundOutcome::ExhaustiveDraw => "exhaustiveDraw",
+            RoundOutcome::AbortiveDraw => "abortiveDraw",
+            RoundOutcome::Incomplete => "incomplete",
+        }
+    }
+}
+
+fn safety_str(class: SafetyClass) -> &'static str {
+    match class {
+        SafetyClass::Genbutsu => "genbutsu",
+        SafetyClass::Suji => "suji",
+        SafetyClass::OneChance => "oneChance",
+        SafetyClass::Dangerous => "dangerous",
+    }
+}
+
+/// One discard with its context and the eventual round outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscardRow {
+    pub game_id: String,
+    /// Index into `ParserOutput::rounds`
+    pub round: usize,
+    /// Index into `Round::events`
+    pub event: usize,
+    pub seat: u8,
+    /// The discarder's own discard count, starting at 1
+    pub turn: u32,
+    pub tile: Tile,
+    /// The discarded tile is the one just drawn
+    pub tsumogiri: bool,
+    /// Shanten of the hand left after the discard
+    pub shanten: i8,
+    /// This discard declares riichi
+    pub riichi_declared: bool,
+    /// The discarder was already in riichi
+    pub in_riichi: bool,
+    pub opponents_in_riichi: u8,
+    /// Most dangerous class of the tile against the opponents in riichi
+    pub safety: Option<SafetyClass>,
+    pub outcome: RoundOutcome,
+    /// The discarder's net score change for the round, in points
+    pub score_delta: i32,
+}
+
+/// Column names, in the order `write_csv` and the Parquet schema use
+pub const COLUMNS: [&str; 14] = [
+    "gameId",
+    "round",
+    "event",
+    "seat",
+    "turn",
+    "tile",
+    "tsumogiri",
+    "shanten",
+    "riichiDeclared",
+    "inRiichi",
+    "opponentsInRiichi",
+    "safety",
+    "outcome",
+    "scoreDelta",
+];
+
+/// One row per discard in `game`
+pub fn discard_rows(game: &ParserOutput) -> Vec<DiscardRow> {
+    let mut rows = Vec::new();
+
+    for (r, round) in game.rounds.iter().enumerate() {
+        let result = RoundResult::from_round(round);
+        let safety = discard_safety(round);
+        let mut hands = ConcealedHands::new(round);
+        let mut turns = [0u32; 4];
+        let mut declared = [false; 4];
+        let mut in_riichi = [false; 4];
+        let mut last_draw: [Option<usize>; 4] = [None; 4];
+
+        for (i, event) in round.events.iter().enumerate() {
+            hands.apply(event);
+            match event {
+                Event::Draw { seat, tile, .. } => last_draw[*seat as usize % 4] = tile_index(tile),
+                Event::Reach { who, step: 1, .. } => declared[*who as usize % 4] = true,
+                Event::Discard { seat, tile, .. } => {
+                    let s = *seat as usize % 4;
+                    turns[s] += 1;
+                    let against: Vec<SafetyClass> = safety
+                        .iter()
+                        .filter(|d| d.event == i)
+                        .map(|d| d.class)
+                        .collect();
+                    rows.push(DiscardRow {
+                        game_id: game.game_id.clone(),
+                        round: r,
+                        event: i,
+                        seat: *seat,
+                        turn: turns[s],
+                        tile: *tile,
+                        tsumogiri: last_draw[s]
+                            .take()
+                            .is_some_and(|t| tile_index(tile) == Some(t)),
+                        shanten: shanten(&hands.counts[s], hands.melds[s]),
+                        riichi_declared: declared[s],
+                        in_riichi: in_riichi[s],
+                        opponents_in_riichi: against.len() as u8,
+                        safety: against.into_iter().max(),
+                        outcome: RoundOutcome::of(&result, *seat),
+                        score_delta: result.deltas[s],
+                    });
+                    if std::mem::take(&mut declared[s]) {
+                        in_riichi[s] = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    rows
+}
+
+/// Write rows as CSV with a `COLUMNS` header
+pub fn write_csv<W: Write>(rows: &[DiscardRow], mut writer: W) -> std::io::Result<()> {
+    writeln!(writer, "{}", COLUMNS.join(","))?;
+    for row in rows {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            escape(&row.game_id, ','),
+            row.round,
+            row.event,
+            row.seat,
+            row.turn,
+            row.tile,
+            row.tsumogiri,
+            row.shanten,
+            row.riichi_declared,
+            row.in_riichi,
+            row.opponents_in_riichi,
+            row.safety.map_or("", safety_str),
+            row.outcome.as_str(),
+            row.score_delta
+        )?;
+    }
+    Ok(())
+}
+
+/// Write rows as a single row group Parquet file with `COLUMNS` as columns
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: Write + Send>(
+    rows: &[DiscardRow],
+    writer: W,
+) -> parquet::errors::Result<()> {
+    use std::sync::Arc;
+
+    use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int32Type, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    let schema = Arc::new(parse_message_type(
+        "message discard {
+            REQUIRED BYTE_ARRAY gameId (UTF8);
+            REQUIRED INT64 round;
+            REQUIRED INT64 event;
+            REQUIRED INT32 seat;
+            REQUIRED INT32 turn;
+            REQUIRED BYTE_ARRAY tile (UTF8);
+            REQUIRED BOOLEAN tsumogiri;
+            REQUIRED INT32 shanten;
+            REQUIRED BOOLEAN riichiDeclared;
+            REQUIRED BOOLEAN inRiichi;
+            REQUIRED INT32 opponentsInRiichi;
+            OPTIONAL BYTE_ARRAY safety (UTF8);
+            REQUIRED BYTE_ARRAY outcome (UTF8);
+            REQUIRED INT32 scoreDelta;
+        }",
+    )?);
+    let mut file =
+        SerializedFileWriter::new(writer, schema, Arc::new(WriterProperties::default()))?;
+    let mut group = file.next_row_group()?;
+
+    let strings = |f: &dyn Fn(&DiscardRow) -> &str| -> Vec<ByteArray> {
+        rows.iter().map(|r| ByteArray::from(f(r))).collect()
+    };
+    let int32 = |f: &dyn Fn(&DiscardRow) -> i32| -> Vec<i32> { rows.iter().map(f).collect() };
+    let bools = |f: &dyn Fn(&DiscardRow) -> bool| -> Vec<bool> { rows.iter().map(f).collect() };
+    let int64 = |f: &dyn Fn(&DiscardRow) -> usize| -> Vec<i64> {
+        rows.iter().map(|r| f(r) as i64).collect()
+    };
+
+    macro_rules! column {
+        ($ty:ty, $values:expr) => {
+            column!($ty, $values, None)
+        };
+        ($ty:ty, $values:expr, $levels:expr) => {{
+            let mut column = group
+                .next_column()?
+                .ok_or_else(|| parquet::errors::ParquetError::General("missing column".into()))?;
+            column.typed::<$ty>().write_batch(&$values, $levels, None)?;
+            column.close()?;
+        }};
+    }
+
+    column!(ByteArrayType, strings(&|r| &r.game_id));
+    column!(Int64Type, int64(&|r| r.round));
+    column!(Int64Type, int64(&|r| r.event));
+    column!(Int32Type, int32(&|r| r.seat as i32));
+    column!(Int32Type, int32(&|r| r.turn as i32));
+    column!(ByteArrayType, strings(&|r| &r.tile));
+    column!(BoolType, bools(&|r| r.tsumogiri));
+    column!(Int32Type, int32(&|r| r.shanten as i32));
+    column!(BoolType, bools(&|r| r.riichi_declared));
+    column!(BoolType, bools(&|r| r.in_riichi));
+    column!(Int32Type, int32(&|r| r.opponents_in_riichi as i32));
+    let safety: Vec<ByteArray> = rows
+        .iter()
+        .filter_map(|r| r.safety.map(|c| ByteArray::from(safety_str(c))))
+        .collect();
+    let levels: Vec<i16> = rows.iter().map(|r| r.safety.is_some() as i16).collect();
+    column!(ByteArrayType, safety, Some(&levels));
+    column!(ByteArrayType, strings(&|r| r.outcome.as_str()));
+    column!(Int32Type, int32(&|r| r.score_delta));
+
+    group.close()?;
+    file.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    fn game() -> ParserOutput {
+        // Seat 0 declares riichi with a tsumogiri 5p; seat 1 then deals in
+        // with a 1m that is dangerous against the riichi
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,108" ten="250,250,250,250" oya="0" hai0="4,8,12,16,20,24,28,32,36,40,44,48,112" hai1="0,60,64,68,72,76,80,84,88,92,96,100,104" hai2="" hai3=""/>
+<T53/><REACH who="0" step="1"/><D53/><REACH who="0" step="2"/>
+<U113/><E0/>
+<AGARI who="0" fromWho="1" sc="240,80,250,-80,250,0,250,0"/>
+</mjloggm>"#;
+        parse_mjlog(Cursor::new(xml)).unwrap()
+    }
+
+    #[test]
+    fn test_discard_rows() {
+        let rows = discard_rows(&game());
+        assert_eq!(rows.len(), 2);
+
+        let riichi = &rows[0];
+        assert_eq!(
+            (riichi.seat, riichi.turn, riichi.tile.as_str()),
+            (0, 1, "5p")
+        );
+        assert!(riichi.tsumogiri && riichi.riichi_declared && !riichi.in_riichi);
+        assert_eq!(riichi.opponents_in_riichi, 0);
+        assert_eq!(riichi.safety, None);
+        assert_eq!(riichi.outcome, RoundOutcome::Win);
+        assert_eq!(riichi.score_delta, 8000);
+
+        let deal_in = &rows[1];
+        assert_eq!((deal_in.seat, deal_in.tile.as_str()), (1, "1m"));
+        assert!(!deal_in.tsumogiri);
+        assert_eq!(deal_in.opponents_in_riichi, 1);
+        assert_eq!(deal_in.safety, Some(SafetyClass::Dangerous));
+        assert_eq!(deal_in.outcome, RoundOutcome::DealIn);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut out = Vec::new();
+        write_csv(&discard_rows(&game()), &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].ends_with(",true,false,0,,win,8000"));
+        assert!(lines[2].ends_with(",1,dangerous,dealIn,-8000"));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_write_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let file = tempfile::tempfile().unwrap();
+        write_parquet(&discard_rows(&game()), &file).unwrap();
+        let reader = SerializedFileReader::new(file).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        assert_eq!(
+            metadata.file_metadata().schema_descr().num_columns(),
+            COLUMNS.len()
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Integer-encoded decision states for numeric training pipelines
+//!
+//! Every decision point becomes one row of each array below (row-major, `N`
+//! decisions). Tiles are encoded as counts over the 34 tile types, seats are
+//! absolute, and scores are in units of 100 points.
+//!
+//! | array | shape | contents |
+//! |-------|-------|----------|
+//! | `info` | `[N, 8]` | game, round, seat, round number, honba, kyoutaku, dealer seat, tiles left in the wall |
+//! | `hand` | `[N, 34]` | the deciding player's concealed tiles |
+//! | `melds` | `[N, 4, 34]` | called tiles per seat |
+//! | `discards` | `[N, 4, 34]` | discarded tiles per seat |
+//! | `dora` | `[N, 34]` | dora indicators |
+//! | `scores` | `[N, 4]` | scores per seat |
+//! | `riichi` | `[N, 4]` | 1 for seats in riichi |
+//! | `action` | `[N, 2]` | action code (`ACTION_*`) and tile type, -1 when there is none |
+
+use super::{Action, Decision};
+use crate::analysis::{tile_index, TILE_TYPES};
+use crate::tile::Tile;
+
+pub const ACTION_DISCARD: i8 = 0;
+pub const ACTION_RIICHI: i8 = 1;
+pub const ACTION_TSUMO: i8 = 2;
+pub const ACTION_RON: i8 = 3;
+pub const ACTION_CHI: i8 = 4;
+pub const ACTION_PON: i8 = 5;
+pub const ACTION_KAN: i8 = 6;
+pub const ACTION_PASS: i8 = 7;
+
+/// Columns of the `info` array
+pub const INFO_COLUMNS: usize = 8;
+
+/// Decision points encoded as flat row-major arrays
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EncodedDecisions {
+    pub len: usize,
+    pub info: Vec<i32>,
+    pub hand: Vec<u8>,
+    pub melds: Vec<u8>,
+    pub discards: Vec<u8>,
+    pub dora: Vec<u8>,
+    pub scores: Vec<i32>,
+    pub riichi: Vec<u8>,
+    pub action: Vec<i8>,
+}
+
+fn counts<'a>(tiles: impl IntoIterator<Item = &'a Tile>) -> [u8; TILE_TYPES] {
+    let mut counts = [0u8; TILE_TYPES];
+    for t in tiles.into_iter().filter_map(|tile| tile_index(tile)) {
+        counts[t] += 1;
+    }
+    counts
+}
+
+impl EncodedDecisions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append one decision from round `round` of game `game`
+    pub fn push(&mut self, game: usize, round: usize, decision: &Decision) {
+        let state = &decision.state;
+        self.len += 1;
+        self.info.extend([
+            game as i32,
+            round as i32,
+            decision.seat as i32,
+            state.round_number as i32,
+            state.honba as i32,
+            state.kyoutaku as i32,
+            state.dealer_seat as i32,
+            state.wall_remaining as i32,
+        ]);
+        self.hand.extend(counts(&state.hand));
+        for melds in &state.melds {
+            self.melds.extend(counts(melds.iter().flatten()));
+        }
+        for river in &state.discards {
+            self.discards.extend(counts(river));
+        }
+        self.dora.extend(counts(&state.dora_indicators));
+        self.scores.extend(state.scores);
+        self.riichi.extend(state.riichi.map(u8::from));
+
+        let offered = decision.offer.as_ref().map(|o| o.tile.as_str());
+        let (code, tile) = match &decision.action {
+            Action::Discard { tile, riichi } => (
+                if *riichi {
+                    ACTION_RIICHI
+                } else {
+                    ACTION_DISCARD
+                },
+                Some(tile.as_str()),
+            ),
+            Action::Tsumo => (ACTION_TSUMO, None),
+            Action::Ron => (ACTION_RON, offered),
+            Action::Chi { .. } => (ACTION_CHI, offered),
+            Action::Pon { .. } => (ACTION_PON, offered),
+            Action::Kan { tiles, .. } => (ACTION_KAN, tiles.first().map(Tile::as_str)),
+            Action::Pass => (ACTION_PASS, offered),
+        };
+        let tile = tile.and_then(tile_index).map_or(-1, |t| t as i8);
+        self.action.extend([code, tile]);
+    }
+
+    /// Write the arrays as a NumPy `.npz` archive
+    #[cfg(feature = "npz")]
+    pub fn write_npz<W: std::io::Write + std::io::Seek>(&self, writer: W) -> std::io::Result<()> {
+        use npyz::npz::NpzWriter;
+        use npyz::WriterBuilder;
+
+        fn array<T: npyz::AutoSerialize + Copy, W: std::io::Write + std::io::Seek>(
+            npz: &mut NpzWriter<W>,
+            name: &str,
+            shape: &[u64],
+            values: &[T],
+        ) -> std::io::Result<()> {
+            let mut writer = npz
+                .array::<T>(name, Default::default())?
+                .default_dtype()
+                .shape(shape)
+                .begin_nd()?;
+            writer.extend(values.iter().copied())?;
+            writer.finish()
+        }
+
+        let n = self.len as u64;
+        let tiles = TILE_TYPES as u64;
+        let mut npz = NpzWriter::new(writer);
+        array(&mut npz, "info", &[n, INFO_COLUMNS as u64], &self.info)?;
+        array(&mut npz, "hand", &[n, tiles], &self.hand)?;
+        array(&mut npz, "melds", &[n, 4, tiles], &self.melds)?;
+        array(&mut npz, "discards", &[n, 4, tiles], &self.discards)?;
+        array(&mut npz, "dora", &[n, tiles], &self.dora)?;
+        array(&mut npz, "scores", &[n, 4], &self.scores)?;
+        array(&mut npz, "riichi", &[n, 4], &self.riichi)?;
+        array(&mut npz, "action", &[n, 2], &self.action)?;
+        npz.zip_writer().finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::decisions;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    fn encoded() -> EncodedDecisions {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,44,48" hai1="" hai2="" hai3=""/>
+<T1/><REACH who="0" step="1"/><D1/><REACH who="0" step="2"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let mut encoded = EncodedDecisions::new();
+        for decision in decisions(&game.rounds[0]) {
+            encoded.push(3, 0, &decision);
+        }
+        encoded
+    }
+
+    #[test]
+    fn test_encode_decisions() {
+        let encoded = encoded();
+        assert_eq!(encoded.len, 1);
+        assert_eq!(encoded.info, vec![3, 0, 0, 0, 0, 0, 0, 69]);
+        assert_eq!(encoded.hand.len(), TILE_TYPES);
+        assert_eq!(encoded.hand[0], 2);
+        assert_eq!(encoded.hand[12], 1);
+        assert_eq!(encoded.melds, vec![0; 4 * TILE_TYPES]);
+        assert_eq!(encoded.dora[13], 1);
+        assert_eq!(encoded.scores, vec![250; 4]);
+        assert_eq!(encoded.action, vec![ACTION_RIICHI, 0]);
+    }
+
+    #[cfg(feature = "npz")]
+    #[test]
+    fn test_write_npz() {
+        let mut buffer = Cursor::new(Vec::new());
+        encoded().write_npz(&mut buffer).unwrap();
+
+        let mut npz = npyz::npz::NpzArchive::new(Cursor::new(buffer.into_inner())).unwrap();
+        let shape = npz.by_name("hand").unwrap().unwrap().shape().to_vec();
+        assert_eq!(shape, vec![1, TILE_TYPES as u64]);
+        let action: Vec<i8> = npz.by_name("action").unwrap().unwrap().into_vec().unwrap();
+        assert_eq!(action, vec![ACTION_RIICHI, 0]);
+    }
+}
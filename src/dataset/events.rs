@@ -0,0 +1,266 @@
+//! One row per event, the table behind `--format csv` and the events dataset
+
+use serde::{Deserialize, Serialize};
+
+use super::table::{Table, Values};
+use crate::models::{Event, KanType, ParserOutput, RyuukyokuReason};
+
+/// One event with the round it belongs to
+///
+/// Cells that do not apply to the event are `None` or empty; tiles and
+/// scores of one event are joined with spaces.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventRow {
+    pub game_id: String,
+    /// Index into `ParserOutput::rounds`
+    pub round: usize,
+    pub round_id: String,
+    /// Index into `Round::events`
+    pub event: usize,
+    /// The event's `type` in the JSON output
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The acting seat, `seat` or `who` of the event
+    pub seat: Option<u8>,
+    /// Name of the acting seat's player
+    pub player: String,
+    /// Tile drawn or discarded, dora indicator or winning tile
+    pub tile: String,
+    /// Tiles of a call, or the winning hand
+    pub tiles: String,
+    /// Seat called from, or dealt in
+    pub from: Option<u8>,
+    /// Whether a discard declares riichi
+    pub riichi: Option<bool>,
+    /// Kan type, riichi step, draw reason or unknown tag
+    pub detail: String,
+    pub han: Option<u32>,
+    pub fu: Option<u32>,
+    pub scores: String,
+}
+
+/// Column names, in the order `events_table` uses; columns are only ever
+/// appended
+pub const COLUMNS: [&str; 15] = [
+    "gameId", "round", "roundId", "event", "type", "seat", "player", "tile", "tiles", "from",
+    "riichi", "detail", "han", "fu", "scores",
+];
+
+/// One row per event of every round of `game`
+pub fn event_rows(game: &ParserOutput) -> Vec<EventRow> {
+    let mut rows = Vec::new();
+    for (r, round) in game.rounds.iter().enumerate() {
+        for (i, event) in round.events.iter().enumerate() {
+            let mut row = cells(event);
+            row.player = row
+                .seat
+                .and_then(|seat| game.players.get(seat as usize))
+                .map_or_else(String::new, |p| p.player_id.clone());
+            rows.push(EventRow {
+                game_id: game.game_id.clone(),
+                round: r,
+                round_id: round.round_id.clone(),
+                event: i,
+                kind: type_name(event).to_string(),
+                ..row
+            });
+        }
+    }
+    rows
+}
+
+/// Rows as a table with `COLUMNS` as columns
+pub fn events_table(rows: &[EventRow]) -> Table {
+    let text =
+        |f: fn(&EventRow) -> &String| Values::Text(rows.iter().map(|r| f(r).clone()).collect());
+    let int = |f: fn(&EventRow) -> Option<i64>| Values::Int(rows.iter().map(f).collect());
+    let columns = vec![
+        text(|r| &r.game_id),
+        int(|r| Some(r.round as i64)),
+        text(|r| &r.round_id),
+        int(|r| Some(r.event as i64)),
+        text(|r| &r.kind),
+        int(|r| r.seat.map(i64::from)),
+        text(|r| &r.player),
+        text(|r| &r.tile),
+        text(|r| &r.tiles),
+        int(|r| r.from.map(i64::from)),
+        Values::Bool(rows.iter().map(|r| r.riichi).collect()),
+        text(|r| &r.detail),
+        int(|r| r.han.map(i64::from)),
+        int(|r| r.fu.map(i64::from)),
+        text(|r| &r.scores),
+    ];
+    Table {
+        columns: COLUMNS.into_iter().zip(columns).collect(),
+    }
+}
+
+fn joined<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The event's `type` in the JSON output
+fn type_name(event: &Event) -> &str {
+    match event {
+        Event::Draw { .. } => "draw",
+        Event::Discard { .. } => "discard",
+        Event::Chi { .. } => "chi",
+        Event::Pon { .. } => "pon",
+        Event::Kan { .. } => "kan",
+        Event::Dora { .. } => "dora",
+        Event::Reach { .. } => "reach",
+        Event::Agari { .. } => "agari",
+        Event::Ryuukyoku { .. } => "ryuukyoku",
+        Event::Disconnect { .. } => "disconnect",
+        Event::Reconnect { .. } => "reconnect",
+        Event::Unknown { .. } => "unknown",
+    }
+}
+
+/// The cells of `event` that depend on its type
+fn cells(event: &Event) -> EventRow {
+    match event {
+        Event::Draw { seat, tile, .. } => EventRow {
+            seat: Some(*seat),
+            tile: tile.to_string(),
+            ..Default::default()
+        },
+        Event::Discard {
+            seat,
+            tile,
+            is_riichi,
+            ..
+        } => EventRow {
+            seat: Some(*seat),
+            tile: tile.to_string(),
+            riichi: Some(*is_riichi),
+            ..Default::default()
+        },
+        Event::Chi {
+            who, tiles, from, ..
+        }
+        | Event::Pon {
+            who, tiles, from, ..
+        } => EventRow {
+            seat: Some(*who),
+            tiles: joined(tiles),
+            from: Some(*from),
+            ..Default::default()
+        },
+        Event::Kan {
+            who,
+            tiles,
+            kan_type,
+            from,
+            ..
+        } => EventRow {
+            seat: Some(*who),
+            tiles: joined(tiles),
+            from: *from,
+            detail: match kan_type {
+                KanType::Ankan => "ankan",
+                KanType::Minkan => "minkan",
+                KanType::Kakan => "kakan",
+            }
+            .to_string(),
+            ..Default::default()
+        },
+        Event::Dora { indicator, .. } => EventRow {
+            tile: indicator.to_string(),
+            ..Default::default()
+        },
+        Event::Reach {
+            who, step, scores, ..
+        } => EventRow {
+            seat: Some(*who),
+            detail: step.to_string(),
+            // Only the deposit on step 2 carries scores
+            scores: if *step == 2 {
+                joined(scores)
+            } else {
+                String::new()
+            },
+            ..Default::default()
+        },
+        Event::Agari {
+            who,
+            from,
+            han,
+            fu,
+            hand,
+            winning_tile,
+            scores,
+            ..
+        } => EventRow {
+            seat: Some(*who),
+            tile: winning_tile.map(|t| t.to_string()).unwrap_or_default(),
+            tiles: joined(hand),
+            from: Some(*from),
+            han: Some(*han),
+            fu: Some(*fu),
+            scores: joined(scores),
+            ..Default::default()
+        },
+        Event::Ryuukyoku { reason, scores, .. } => EventRow {
+            detail: match reason {
+                RyuukyokuReason::Normal => "nm",
+                RyuukyokuReason::Yao9 => "yao9",
+                RyuukyokuReason::Kaze4 => "kaze4",
+                RyuukyokuReason::Reach4 => "reach4",
+                RyuukyokuReason::Ron3 => "ron3",
+                RyuukyokuReason::Kan4 => "kan4",
+            }
+            .to_string(),
+            scores: joined(scores),
+            ..Default::default()
+        },
+        Event::Disconnect { who } | Event::Reconnect { who } => EventRow {
+            seat: Some(*who),
+            ..Default::default()
+        },
+        Event::Unknown { tag, .. } => EventRow {
+            detail: tag.clone(),
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_event_rows() {
+        // An open kan of 4m from seat 3, its dora, then an exhaustive draw
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,1,2" hai1="" hai2="" hai3=""/>
+<N who="0" m="3075"/><DORA hai="8"/>
+<RYUUKYOKU ba="0,0" sc="250,15,250,-5,250,-5,250,-5"/>
+</mjloggm>"#;
+        let rows = event_rows(&parse_mjlog(Cursor::new(xml)).unwrap());
+        assert_eq!(rows.len(), 3);
+        let kan = &rows[0];
+        assert_eq!(
+            (kan.kind.as_str(), kan.seat, kan.from),
+            ("kan", Some(0), Some(3))
+        );
+        assert_eq!((kan.player.as_str(), kan.detail.as_str()), ("A", "minkan"));
+        assert_eq!(kan.tiles, "4m 4m 4m 4m");
+        assert_eq!(
+            (rows[1].kind.as_str(), rows[1].tile.as_str()),
+            ("dora", "3m")
+        );
+        let draw = &rows[2];
+        assert_eq!((draw.seat, draw.detail.as_str()), (None, "nm"));
+        assert_eq!(draw.scores, "15 -5 -5 -5");
+        assert_eq!(events_table(&rows).rows(), 3);
+    }
+}
@@ -0,0 +1,15 @@
+//! Datasets for model training and analysis, derived by replaying rounds
+
+pub mod decisions;
+pub mod discards;
+pub mod encoded;
+pub mod events;
+pub mod rounds;
+pub mod table;
+
+pub use decisions::{decisions, Action, Decision, Observation, Offer};
+pub use discards::{discard_rows, DiscardRow, RoundOutcome};
+pub use encoded::EncodedDecisions;
+pub use events::{event_rows, events_table, EventRow};
+pub use rounds::{round_rows, rounds_table, RoundRow};
+pub use table::{Table, Values};
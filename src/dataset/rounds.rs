@@ -0,0 +1,152 @@
+//! One row per round: where it stood at the start and how it ended
+
+use serde::{Deserialize, Serialize};
+
+use super::table::{Table, Values};
+use crate::models::ParserOutput;
+use crate::stats::RoundResult;
+
+/// How a round ended and what it did to the scores
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundRow {
+    pub game_id: String,
+    /// Index into `ParserOutput::rounds`
+    pub round: usize,
+    pub round_id: String,
+    /// `Init::round_number`, 0 for East 1
+    pub round_number: u32,
+    pub honba: u32,
+    pub kyoutaku: u32,
+    pub dealer: u8,
+    /// Number of events in the round
+    pub events: usize,
+    /// "win", "exhaustiveDraw", "abortiveDraw" or "incomplete"
+    pub outcome: String,
+    /// The first winner, and the seat that dealt in unless it was a tsumo
+    pub winner: Option<u8>,
+    pub from: Option<u8>,
+    pub han: Option<u32>,
+    pub fu: Option<u32>,
+    /// Net score change of each seat over the round, in points
+    pub deltas: [i32; 4],
+}
+
+/// Column names, in the order `rounds_table` uses
+pub const COLUMNS: [&str; 17] = [
+    "gameId",
+    "round",
+    "roundId",
+    "roundNumber",
+    "honba",
+    "kyoutaku",
+    "dealer",
+    "events",
+    "outcome",
+    "winner",
+    "from",
+    "han",
+    "fu",
+    "delta0",
+    "delta1",
+    "delta2",
+    "delta3",
+];
+
+/// One row per round of `game`
+pub fn round_rows(game: &ParserOutput) -> Vec<RoundRow> {
+    game.rounds
+        .iter()
+        .enumerate()
+        .map(|(r, round)| {
+            let result = RoundResult::from_round(round);
+            let win = result.wins.first();
+            let outcome = if win.is_some() {
+                "win"
+            } else if result.exhaustive_draw {
+                "exhaustiveDraw"
+            } else if result.draw {
+                "abortiveDraw"
+            } else {
+                "incomplete"
+            };
+            RoundRow {
+                game_id: game.game_id.clone(),
+                round: r,
+                round_id: round.round_id.clone(),
+                round_number: round.init.round_number,
+                honba: round.init.honba,
+                kyoutaku: round.init.kyoutaku,
+                dealer: round.dealer_seat,
+                events: round.events.len(),
+                outcome: outcome.to_string(),
+                winner: win.map(|w| w.who),
+                from: win.filter(|w| w.from != w.who).map(|w| w.from),
+                han: win.map(|w| w.han),
+                fu: win.map(|w| w.fu),
+                deltas: result.deltas,
+            }
+        })
+        .collect()
+}
+
+/// Rows as a table with `COLUMNS` as columns
+pub fn rounds_table(rows: &[RoundRow]) -> Table {
+    let int = |f: &dyn Fn(&RoundRow) -> Option<i64>| Values::Int(rows.iter().map(f).collect());
+    let mut columns = vec![
+        Values::Text(rows.iter().map(|r| r.game_id.clone()).collect()),
+        int(&|r| Some(r.round as i64)),
+        Values::Text(rows.iter().map(|r| r.round_id.clone()).collect()),
+        int(&|r| Some(r.round_number.into())),
+        int(&|r| Some(r.honba.into())),
+        int(&|r| Some(r.kyoutaku.into())),
+        int(&|r| Some(r.dealer.into())),
+        int(&|r| Some(r.events as i64)),
+        Values::Text(rows.iter().map(|r| r.outcome.clone()).collect()),
+        int(&|r| r.winner.map(i64::from)),
+        int(&|r| r.from.map(i64::from)),
+        int(&|r| r.han.map(i64::from)),
+        int(&|r| r.fu.map(i64::from)),
+    ];
+    for seat in 0..4 {
+        columns.push(int(&|r| Some(r.deltas[seat].into())));
+    }
+    Table {
+        columns: COLUMNS.into_iter().zip(columns).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_rows() {
+        // Seat 1 deals into seat 0, then the second round is cut short
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,1,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" ten="30,3900,0" sc="250,39,250,-39,250,0,250,0"/>
+<INIT seed="0,2,0,1,2,52" ten="289,211,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/>
+</mjloggm>"#;
+        let rows = round_rows(&parse_mjlog(Cursor::new(xml)).unwrap());
+        assert_eq!(rows.len(), 2);
+        let win = &rows[0];
+        assert_eq!(win.outcome, "win");
+        assert_eq!(
+            (win.winner, win.from, win.han, win.fu),
+            (Some(0), Some(1), Some(0), Some(30))
+        );
+        assert_eq!(win.deltas, [3900, -3900, 0, 0]);
+        assert_eq!((rows[1].honba, rows[1].outcome.as_str()), (2, "incomplete"));
+
+        let table = rounds_table(&rows);
+        assert_eq!(table.rows(), 2);
+        assert_eq!(
+            table.columns[13],
+            ("delta0", Values::Int(vec![Some(3900), Some(0)]))
+        );
+    }
+}
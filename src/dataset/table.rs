@@ -0,0 +1,220 @@
+//! Column-oriented tables, written as CSV, Parquet or Arrow IPC
+//!
+//! Integer and boolean cells may be missing: empty in CSV, null in Parquet
+//! and Arrow. Text cells are always present, empty when they do not apply.
+
+use std::io::Write;
+
+use crate::stats::export::escape;
+
+/// The values of one column
+#[derive(Debug, Clone, PartialEq)]
+pub enum Values {
+    Text(Vec<String>),
+    Int(Vec<Option<i64>>),
+    Bool(Vec<Option<bool>>),
+}
+
+impl Values {
+    fn len(&self) -> usize {
+        match self {
+            Values::Text(values) => values.len(),
+            Values::Int(values) => values.len(),
+            Values::Bool(values) => values.len(),
+        }
+    }
+
+    fn cell(&self, row: usize) -> String {
+        match self {
+            Values::Text(values) => values[row].clone(),
+            Values::Int(values) => values[row].map_or_else(String::new, |v| v.to_string()),
+            Values::Bool(values) => values[row].map_or_else(String::new, |v| v.to_string()),
+        }
+    }
+}
+
+/// Named columns of equal length
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    pub columns: Vec<(&'static str, Values)>,
+}
+
+impl Table {
+    pub fn rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, values)| values.len())
+    }
+
+    /// Write a header and one line per row, cells separated by `separator`
+    pub fn write_delimited<W: Write>(&self, mut writer: W, separator: char) -> std::io::Result<()> {
+        let joiner = separator.to_string();
+        let names: Vec<&str> = self.columns.iter().map(|(name, _)| *name).collect();
+        writeln!(writer, "{}", names.join(&joiner))?;
+        for row in 0..self.rows() {
+            let cells: Vec<String> = self
+                .columns
+                .iter()
+                .map(|(_, values)| escape(&values.cell(row), separator))
+                .collect();
+            writeln!(writer, "{}", cells.join(&joiner))?;
+        }
+        Ok(())
+    }
+
+    /// Write the table as a single row group Parquet file
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W: Write + Send>(&self, writer: W) -> parquet::errors::Result<()> {
+        use std::sync::Arc;
+
+        use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let fields: String = self
+            .columns
+            .iter()
+            .map(|(column, values)| match values {
+                Values::Text(_) => format!("REQUIRED BYTE_ARRAY {} (UTF8);", column),
+                Values::Int(_) => format!("OPTIONAL INT64 {};", column),
+                Values::Bool(_) => format!("OPTIONAL BOOLEAN {};", column),
+            })
+            .collect();
+        let schema = Arc::new(parse_message_type(&format!(
+            "message schema {{ {} }}",
+            fields
+        ))?);
+        let mut file =
+            SerializedFileWriter::new(writer, schema, Arc::new(WriterProperties::default()))?;
+        let mut group = file.next_row_group()?;
+
+        for (_, values) in &self.columns {
+            let mut column = group
+                .next_column()?
+                .ok_or_else(|| parquet::errors::ParquetError::General("missing column".into()))?;
+            match values {
+                Values::Text(values) => {
+                    let values: Vec<ByteArray> =
+                        values.iter().map(|v| ByteArray::from(v.as_str())).collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, None, None)?;
+                }
+                Values::Int(values) => {
+                    let (present, levels) = levels(values);
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&present, Some(&levels), None)?;
+                }
+                Values::Bool(values) => {
+                    let (present, levels) = levels(values);
+                    column
+                        .typed::<BoolType>()
+                        .write_batch(&present, Some(&levels), None)?;
+                }
+            }
+            column.close()?;
+        }
+
+        group.close()?;
+        file.close()?;
+        Ok(())
+    }
+
+    /// Write the table as an Arrow IPC file (Feather v2) of one record batch
+    #[cfg(feature = "arrow")]
+    pub fn write_arrow<W: Write>(&self, writer: W) -> Result<(), arrow_schema::ArrowError> {
+        use std::sync::Arc;
+
+        use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray};
+        use arrow_schema::{DataType, Field, Schema};
+
+        let mut fields = Vec::new();
+        let mut arrays: Vec<ArrayRef> = Vec::new();
+        for (column, values) in &self.columns {
+            let (data_type, nullable, array): (_, _, ArrayRef) = match values {
+                Values::Text(values) => (
+                    DataType::Utf8,
+                    false,
+                    Arc::new(StringArray::from_iter_values(values)),
+                ),
+                Values::Int(values) => (
+                    DataType::Int64,
+                    true,
+                    Arc::new(Int64Array::from(values.clone())),
+                ),
+                Values::Bool(values) => (
+                    DataType::Boolean,
+                    true,
+                    Arc::new(BooleanArray::from(values.clone())),
+                ),
+            };
+            fields.push(Field::new(*column, data_type, nullable));
+            arrays.push(array);
+        }
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+        let mut file = arrow_ipc::writer::FileWriter::try_new(writer, &schema)?;
+        file.write(&batch)?;
+        file.finish()
+    }
+}
+
+/// The present values of an optional column and its definition levels
+#[cfg(feature = "parquet")]
+fn levels<T: Copy>(values: &[Option<T>]) -> (Vec<T>, Vec<i16>) {
+    (
+        values.iter().flatten().copied().collect(),
+        values.iter().map(|v| v.is_some() as i16).collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        Table {
+            columns: vec![
+                ("name", Values::Text(vec!["a,b".into(), "c".into()])),
+                ("count", Values::Int(vec![Some(1), None])),
+                ("flag", Values::Bool(vec![None, Some(true)])),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_write_delimited() {
+        let mut out = Vec::new();
+        table().write_delimited(&mut out, ',').unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "name,count,flag\n\"a,b\",1,\nc,,true\n"
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_write_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let file = tempfile::tempfile().unwrap();
+        table().write_parquet(&file).unwrap();
+        let reader = SerializedFileReader::new(file).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        assert_eq!(metadata.file_metadata().schema_descr().num_columns(), 3);
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_write_arrow() {
+        let mut out = Vec::new();
+        table().write_arrow(&mut out).unwrap();
+        let reader =
+            arrow_ipc::reader::FileReader::try_new(std::io::Cursor::new(out), None).unwrap();
+        let batches: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches[0].num_rows(), 2);
+        assert_eq!(batches[0].schema().field(1).name(), "count");
+        assert_eq!(batches[0].column(1).null_count(), 1);
+    }
+}
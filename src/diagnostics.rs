@@ -0,0 +1,315 @@
+//! Parse errors rendered with source snippets (`diagnostics` feature)
+//!
+//! A failed parse is diagnosed by scanning the decoded log again and
+//! pointing at the first attribute that the parser cannot accept, with a
+//! hint about the expected shape. Failing that, the tag in the error's
+//! location is pointed at.
+
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceSpan};
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::Reader;
+
+use crate::encoding::detect_encoding;
+use crate::error::{ParserError, Result};
+use crate::parser::open_input;
+
+/// A parse error located in the log text
+#[derive(Debug)]
+pub struct ParseDiagnostic {
+    /// `ParserError::code` of the underlying error
+    pub code: &'static str,
+    pub message: String,
+    pub label: Option<(SourceSpan, String)>,
+    pub help: Option<String>,
+    source: NamedSource<String>,
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
+
+impl Diagnostic for ParseDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|h| Box::new(h) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let (span, label) = self.label.as_ref()?;
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(label.clone()),
+            *span,
+        ))))
+    }
+}
+
+impl ParseDiagnostic {
+    /// The diagnostic as plain text, without colors
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        miette::GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode_nocolor())
+            .render_report(&mut out, self)
+            .expect("writing to a String cannot fail");
+        out
+    }
+}
+
+/// A problem found in one tag
+struct Finding {
+    span: SourceSpan,
+    label: String,
+    help: String,
+}
+
+/// Diagnose `error`, raised while parsing the log text `source`
+pub fn diagnose(name: &str, source: &str, error: &ParserError) -> ParseDiagnostic {
+    let finding = find_problem(source);
+    let (label, help) = match (finding, error) {
+        (Some(f), _) => (Some((f.span, f.label)), Some(f.help)),
+        (None, ParserError::TruncatedLog { offset, .. }) => {
+            let span: SourceSpan = ((*offset).min(source.len()), 0).into();
+            (
+                Some((span, "log ends here".to_string())),
+                Some("the log stops in the middle of a round".to_string()),
+            )
+        }
+        (None, error) => match error.location() {
+            Some(location) => {
+                let offset = location.offset.min(source.len());
+                let len = source[offset..].find('>').map_or(0, |end| end + 1);
+                (
+                    Some(((offset, len).into(), "raised in this tag".to_string())),
+                    None,
+                )
+            }
+            None => (None, None),
+        },
+    };
+    ParseDiagnostic {
+        code: error.code(),
+        message: error.to_string(),
+        label,
+        help,
+        source: NamedSource::new(name, source.to_string()),
+    }
+}
+
+/// Diagnose `error`, raised while parsing the mjlog file at `path`
+pub fn diagnose_file(path: &Path, error: &ParserError) -> Result<ParseDiagnostic> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes)?;
+    let (source, _, _) = detect_encoding(&bytes).decode(&bytes);
+    Ok(diagnose(&path.display().to_string(), &source, error))
+}
+
+/// The first tag in `source` that the parser would reject
+fn find_problem(source: &str) -> Option<Finding> {
+    let mut reader = Reader::from_str(source);
+    reader.trim_text(true);
+    loop {
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(e) => {
+                let at = reader.buffer_position().min(source.len());
+                return Some(Finding {
+                    span: (at, 0).into(),
+                    label: "malformed XML".to_string(),
+                    help: e.to_string(),
+                });
+            }
+        };
+        match event {
+            XmlEvent::Start(ref e) | XmlEvent::Empty(ref e) => {
+                let end = reader.buffer_position().min(source.len());
+                let start = source[..end].rfind('<').unwrap_or(0);
+                if let Some(finding) = check_tag(e, start, &source[start..end]) {
+                    return Some(finding);
+                }
+            }
+            XmlEvent::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Minimum number of comma-separated fields the parser requires
+fn field_count(tag: &str, attribute: &str) -> Option<(usize, &'static str)> {
+    match (tag, attribute) {
+        ("INIT", "seed") => Some((
+            6,
+            "seed is round number, honba, riichi sticks, two dice and the dora indicator",
+        )),
+        ("INIT", "ten") => Some((4, "ten lists the four starting scores in units of 100")),
+        _ => None,
+    }
+}
+
+/// Attributes the parser reads as comma-separated integers
+fn is_numeric(tag: &str, attribute: &str) -> bool {
+    match tag {
+        "INIT" => matches!(
+            attribute,
+            "seed" | "ten" | "oya" | "hai0" | "hai1" | "hai2" | "hai3"
+        ),
+        "GO" => matches!(attribute, "type" | "lobby"),
+        "UN" => attribute == "dan",
+        "N" => matches!(attribute, "who" | "m"),
+        "DORA" => attribute == "hai",
+        "REACH" => matches!(attribute, "who" | "step" | "ten"),
+        "AGARI" => matches!(attribute, "who" | "fromWho" | "ten" | "ba" | "sc" | "owari"),
+        "RYUUKYOKU" => matches!(attribute, "ba" | "sc" | "owari"),
+        _ => false,
+    }
+}
+
+/// Whether one field fits the integer type the parser reads it into
+fn parses(attribute: &str, field: &str) -> bool {
+    match attribute {
+        "who" | "fromWho" | "oya" | "step" => field.parse::<u8>().is_ok(),
+        _ => field.trim().parse::<i64>().is_ok(),
+    }
+}
+
+fn check_tag(element: &BytesStart, start: usize, text: &str) -> Option<Finding> {
+    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+
+    for attr in element.attributes() {
+        let Ok(attr) = attr else {
+            return Some(Finding {
+                span: (start + 1, name.len()).into(),
+                label: "malformed attribute".to_string(),
+                help: "attributes are written as name=\"value\"".to_string(),
+            });
+        };
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        if !is_numeric(&name, &key) {
+            continue;
+        }
+        let value = String::from_utf8_lossy(&attr.value).into_owned();
+        let value_start = text
+            .find(&format!("{}=\"", key))
+            .map_or(start, |i| start + i + key.len() + 2);
+        let span: SourceSpan = (value_start, value.len()).into();
+
+        let fields: Vec<&str> = if value.is_empty() {
+            Vec::new()
+        } else {
+            value.split(',').collect()
+        };
+        if let Some((expected, help)) = field_count(&name, &key) {
+            if fields.len() < expected {
+                return Some(Finding {
+                    span,
+                    label: format!("{} has {} fields, expected {}", key, fields.len(), expected),
+                    help: help.to_string(),
+                });
+            }
+        }
+        if let Some(bad) = fields.iter().find(|f| !parses(&key, f)) {
+            return Some(Finding {
+                span,
+                label: format!("'{}' is not a valid number", bad),
+                help: if fields.len() > 1 {
+                    format!("{} takes comma-separated integers", key)
+                } else {
+                    format!("{} takes an integer", key)
+                },
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    fn diagnosed(xml: &str) -> ParseDiagnostic {
+        let error = parse_mjlog(Cursor::new(xml.as_bytes())).unwrap_err();
+        diagnose("game.xml", xml, &error)
+    }
+
+    #[test]
+    fn test_seed_field_count() {
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+</mjloggm>"#;
+        let diagnostic = diagnosed(xml);
+        assert_eq!(diagnostic.code, "invalid-format");
+        let (span, label) = diagnostic.label.as_ref().unwrap();
+        assert_eq!(label, "seed has 5 fields, expected 6");
+        assert_eq!(&xml[span.offset()..span.offset() + span.len()], "0,0,0,1,2");
+
+        let rendered = diagnostic.render();
+        assert!(rendered.contains("game.xml"));
+        assert!(rendered.contains("seed has 5 fields, expected 6"));
+        assert!(rendered.contains("help: seed is round number"));
+    }
+
+    #[test]
+    fn test_bad_number_and_short_ten() {
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<REACH who="x" step="1"/>
+</mjloggm>"#;
+        let diagnostic = diagnosed(xml);
+        assert_eq!(diagnostic.code, "parse-int");
+        assert_eq!(diagnostic.label.unwrap().1, "'x' is not a valid number");
+
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+</mjloggm>"#;
+        let diagnostic = diagnosed(xml);
+        let (span, label) = diagnostic.label.unwrap();
+        assert_eq!(label, "ten has 3 fields, expected 4");
+        assert_eq!(
+            &xml[span.offset()..span.offset() + span.len()],
+            "250,250,250"
+        );
+    }
+
+    #[test]
+    fn test_points_at_error_location() {
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<N who="1"/>
+</mjloggm>"#;
+        let diagnostic = diagnosed(xml);
+        assert_eq!(diagnostic.code, "invalid-format");
+        let (span, label) = diagnostic.label.unwrap();
+        assert_eq!(label, "raised in this tag");
+        assert_eq!(
+            &xml[span.offset()..span.offset() + span.len()],
+            r#"<N who="1"/>"#
+        );
+    }
+
+    #[test]
+    fn test_truncated_log_points_at_end() {
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T52/><D52/>"#;
+        let diagnostic = diagnosed(xml);
+        assert_eq!(diagnostic.code, "truncated-log");
+        assert_eq!(diagnostic.label.unwrap().1, "log ends here");
+    }
+}
@@ -0,0 +1,254 @@
+//! Choosing the decoder for a log
+//!
+//! Tenhou serves logs as Shift_JIS, but some mirrors store them converted to
+//! UTF-8, often keeping the original XML declaration. A byte order mark or a
+//! declaration naming anything but Shift_JIS is trusted; otherwise a log
+//! whose non-ASCII bytes are valid UTF-8, which Shift_JIS text almost never
+//! is, is read as UTF-8, and any other log as Shift_JIS.
+
+use std::io::Read;
+
+use encoding_rs::{Decoder, Encoding, SHIFT_JIS, UTF_8};
+
+/// Encoding of an input log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// Detected from the log with [`detect_encoding`]
+    #[default]
+    Auto,
+    ShiftJis,
+    Utf8,
+}
+
+impl InputEncoding {
+    /// The encoding to decode `bytes`, the whole log, with
+    pub fn resolve(self, bytes: &[u8]) -> &'static Encoding {
+        match self {
+            InputEncoding::Auto => detect_encoding(bytes),
+            InputEncoding::ShiftJis => SHIFT_JIS,
+            InputEncoding::Utf8 => UTF_8,
+        }
+    }
+}
+
+/// The encoding of the log `bytes`
+pub fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    declared_encoding(bytes).unwrap_or_else(|| guess_encoding(bytes))
+}
+
+/// Encoding named by a byte order mark or by the XML declaration at the
+/// start of `bytes`, unless the declaration names Shift_JIS
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+    if !bytes.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = bytes.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&bytes[..end]).ok()?;
+    let value = &declaration[declaration.find("encoding")? + "encoding".len()..];
+    let value = value.trim_start_matches(|c: char| c == '=' || c.is_ascii_whitespace());
+    let quote = value.chars().next()?;
+    let label = value[1..].split(quote).next()?;
+    // A UTF-16 declaration on text readable as ASCII is stale
+    Encoding::for_label(label.as_bytes())
+        .map(Encoding::output_encoding)
+        .filter(|&encoding| encoding != SHIFT_JIS)
+}
+
+/// UTF-8 if `bytes` has non-ASCII bytes and they are valid UTF-8, allowing
+/// for a character cut off at the end; Shift_JIS otherwise
+fn guess_encoding(bytes: &[u8]) -> &'static Encoding {
+    let utf8 = match std::str::from_utf8(bytes) {
+        Ok(text) => !text.is_ascii(),
+        Err(e) => e.error_len().is_none() && !bytes[..e.valid_up_to()].is_ascii(),
+    };
+    if utf8 {
+        UTF_8
+    } else {
+        SHIFT_JIS
+    }
+}
+
+/// Input decoded to UTF-8 as it is read
+///
+/// With `InputEncoding::Auto`, a byte order mark or declaration in the first
+/// chunk decides the encoding; failing that, ASCII passes through unchanged
+/// until the first chunk with other bytes is guessed from.
+///
+/// Invalid sequences are replaced with U+FFFD, whose offsets in the decoded
+/// text are kept in `replacements`.
+pub(crate) struct DecodingReader<R> {
+    inner: R,
+    decoder: Option<Decoder>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    /// Start of the decoded bytes not yet handed out
+    pos: usize,
+    /// Decoded bytes before `output`
+    decoded: usize,
+    replacements: Vec<usize>,
+    /// Whether a chunk has been read, after which no BOM or declaration
+    /// can follow
+    started: bool,
+    eof: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub(crate) fn new(inner: R, encoding: InputEncoding) -> Self {
+        let decoder = match encoding {
+            InputEncoding::Auto => None,
+            InputEncoding::ShiftJis => Some(SHIFT_JIS.new_decoder_with_bom_removal()),
+            InputEncoding::Utf8 => Some(UTF_8.new_decoder_with_bom_removal()),
+        };
+        Self {
+            inner,
+            decoder,
+            input: vec![0; 8192],
+            output: Vec::new(),
+            pos: 0,
+            decoded: 0,
+            replacements: Vec::new(),
+            started: false,
+            eof: false,
+        }
+    }
+
+    /// The encoding decoded from, unless the input so far is ASCII
+    pub(crate) fn encoding(&self) -> Option<&'static Encoding> {
+        self.decoder.as_ref().map(Decoder::encoding)
+    }
+
+    /// Offsets in the decoded text of the replaced invalid sequences
+    pub(crate) fn replacements(&self) -> &[usize] {
+        &self.replacements
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub(crate) fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Pick the decoder from `chunk` unless it could still be either
+    fn choose(&mut self, chunk: &[u8]) {
+        if self.decoder.is_none() {
+            let declared = if self.started {
+                None
+            } else {
+                declared_encoding(chunk)
+            };
+            self.started = true;
+            self.decoder = declared
+                .or_else(|| (self.eof || !chunk.is_ascii()).then(|| guess_encoding(chunk)))
+                .map(Encoding::new_decoder_with_bom_removal);
+        }
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.output.len() && !self.eof {
+            let n = self.inner.read(&mut self.input)?;
+            self.eof = n == 0;
+            let chunk = std::mem::take(&mut self.input);
+            let last = self.eof;
+            self.decoded += self.output.len();
+            self.choose(&chunk[..n]);
+            match &mut self.decoder {
+                Some(decoder) => {
+                    let capacity = decoder.max_utf8_buffer_length(n).unwrap_or(n * 3 + 16);
+                    self.output.resize(capacity, 0);
+                    let (_, _, written, replaced) =
+                        decoder.decode_to_utf8(&chunk[..n], &mut self.output, last);
+                    self.output.truncate(written);
+                    if replaced {
+                        let text = String::from_utf8_lossy(&self.output);
+                        self.replacements.extend(
+                            text.match_indices(char::REPLACEMENT_CHARACTER)
+                                .map(|(offset, _)| self.decoded + offset),
+                        );
+                    }
+                }
+                // Still undecided, so the chunk is ASCII
+                None => {
+                    self.output.clear();
+                    self.output.extend_from_slice(&chunk[..n]);
+                }
+            }
+            self.input = chunk;
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encoding() {
+        let (sjis, _, _) = SHIFT_JIS.encode(r#"<mjloggm ver="2.3"><UN n0="天鳳"/></mjloggm>"#);
+        let utf8 = r#"<mjloggm ver="2.3"><UN n0="天鳳"/></mjloggm>"#.as_bytes();
+        assert_eq!(detect_encoding(&sjis), SHIFT_JIS);
+        assert_eq!(detect_encoding(utf8), UTF_8);
+        assert_eq!(detect_encoding(b"<mjloggm/>"), SHIFT_JIS);
+        // Cut off in the middle of a character
+        assert_eq!(detect_encoding(&utf8[..31]), UTF_8);
+
+        // A stale Shift_JIS declaration does not outweigh the content
+        let declared = |encoding: &str, body: &[u8]| {
+            let mut bytes =
+                format!(r#"<?xml version="1.0" encoding='{}'?>"#, encoding).into_bytes();
+            bytes.extend_from_slice(body);
+            bytes
+        };
+        assert_eq!(detect_encoding(&declared("Shift_JIS", utf8)), UTF_8);
+        assert_eq!(detect_encoding(&declared("UTF-8", b"<mjloggm/>")), UTF_8);
+        assert_eq!(
+            detect_encoding(&declared("EUC-JP", b"<mjloggm/>")),
+            encoding_rs::EUC_JP
+        );
+        assert_eq!(detect_encoding(&declared("UTF-16", b"<mjloggm/>")), UTF_8);
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBF<mjloggm/>"), UTF_8);
+
+        assert_eq!(InputEncoding::ShiftJis.resolve(utf8), SHIFT_JIS);
+        assert_eq!(InputEncoding::Auto.resolve(utf8), UTF_8);
+    }
+
+    #[test]
+    fn test_decoding_reader() {
+        let text = format!("{}<UN n0=\"天鳳\"/>", "<GO/>".repeat(4000));
+        let read = |bytes: &[u8], encoding| {
+            let mut out = String::new();
+            DecodingReader::new(bytes, encoding)
+                .read_to_string(&mut out)
+                .unwrap();
+            out
+        };
+        let (sjis, _, _) = SHIFT_JIS.encode(&text);
+        // The names come well after the first chunk
+        assert_eq!(read(&sjis, InputEncoding::Auto), text);
+        assert_eq!(read(text.as_bytes(), InputEncoding::Auto), text);
+        let with_bom = [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat();
+        assert_eq!(read(&with_bom, InputEncoding::Auto), text);
+        assert_ne!(read(text.as_bytes(), InputEncoding::ShiftJis), text);
+
+        // Offsets of invalid sequences count decoded bytes across chunks
+        let mut broken = sjis.clone().into_owned();
+        broken.extend_from_slice(b"\x81\x20\xFF");
+        let mut reader = DecodingReader::new(broken.as_slice(), InputEncoding::ShiftJis);
+        let mut out = String::new();
+        reader.read_to_string(&mut out).unwrap();
+        let first = out.find(char::REPLACEMENT_CHARACTER).unwrap();
+        assert_eq!(reader.replacements(), [first, out.len() - 3]);
+        assert_eq!(reader.encoding(), Some(SHIFT_JIS));
+    }
+}
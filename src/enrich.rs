@@ -0,0 +1,146 @@
+//! Player metadata from a log-index service (`enrich` feature)
+//!
+//! Logs only carry a player's rank and rate at the time of the game. Report
+//! generators often want more, such as a rate history or a game count, which
+//! log-index services publish per player name. The service is configurable:
+//! `HttpPlayerIndex` fetches a URL built from a template, and any other
+//! source can implement `PlayerIndex`.
+
+use log::warn;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use crate::error::{ParserError, Result};
+use crate::models::{ParseWarning, ParserOutput, Player, PlayerProfile, WarningKind};
+
+/// A source of player profiles keyed by player name
+pub trait PlayerIndex {
+    /// Profile of `name`, or `None` if the index does not know the player
+    fn lookup(&self, name: &str) -> Result<Option<PlayerProfile>>;
+}
+
+/// Placeholder in a URL template replaced by the percent-encoded player name
+pub const NAME_PLACEHOLDER: &str = "{name}";
+
+/// Looks players up over HTTP
+///
+/// The service answers each request with a JSON `PlayerProfile`
+/// (`{"games": 1234, "rateHistory": [1850.2, 1861.0]}`) or 404 for an unknown
+/// player.
+pub struct HttpPlayerIndex {
+    url_template: String,
+    agent: ureq::Agent,
+}
+
+impl HttpPlayerIndex {
+    /// Index at `url_template`, which must contain `{name}`
+    pub fn new(url_template: impl Into<String>) -> Result<Self> {
+        let url_template = url_template.into();
+        if !url_template.contains(NAME_PLACEHOLDER) {
+            return Err(ParserError::invalid_format(format!(
+                "Player index URL has no {} placeholder: {}",
+                NAME_PLACEHOLDER, url_template
+            )));
+        }
+        Ok(Self {
+            url_template,
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(10))
+                .build(),
+        })
+    }
+
+    /// URL of `name`'s profile
+    pub fn url(&self, name: &str) -> String {
+        let name = utf8_percent_encode(name, NON_ALPHANUMERIC).to_string();
+        self.url_template.replace(NAME_PLACEHOLDER, &name)
+    }
+}
+
+impl PlayerIndex for HttpPlayerIndex {
+    fn lookup(&self, name: &str) -> Result<Option<PlayerProfile>> {
+        match self.agent.get(&self.url(name)).call() {
+            Ok(response) => Ok(Some(response.into_json()?)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(ParserError::Io(std::io::Error::other(e))),
+        }
+    }
+}
+
+/// Look up every named player of `output` and attach the profiles found
+///
+/// Failed lookups do not abort: each becomes a warning on `output`. Returns
+/// the number of players that got a profile.
+pub fn enrich(output: &mut ParserOutput, index: &dyn PlayerIndex) -> usize {
+    let mut enriched = 0;
+    let mut failures = Vec::new();
+    for player in &mut output.players {
+        if player.player_id.is_empty() || player.player_id == Player::PLACEHOLDER_ID {
+            continue;
+        }
+        match index.lookup(&player.player_id) {
+            Ok(profile) => {
+                enriched += usize::from(profile.is_some());
+                player.profile = profile;
+            }
+            Err(e) => failures.push(format!(
+                "Player lookup for seat {} failed: {}",
+                player.seat, e
+            )),
+        }
+    }
+    for failure in failures {
+        warn!("{}", failure);
+        output
+            .warnings
+            .push(ParseWarning::new(WarningKind::Enrich, failure));
+    }
+    enriched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    struct Fixed;
+
+    impl PlayerIndex for Fixed {
+        fn lookup(&self, name: &str) -> Result<Option<PlayerProfile>> {
+            match name {
+                "A" => Ok(Some(PlayerProfile {
+                    games: Some(120),
+                    rate_history: vec![1800.0, 1812.5],
+                })),
+                "B" => Err(ParserError::invalid_format("service unavailable")),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    #[test]
+    fn test_enrich_players() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="NoName"/></mjloggm>"#;
+        let mut output = parse_mjlog(Cursor::new(xml)).unwrap();
+        assert_eq!(enrich(&mut output, &Fixed), 1);
+
+        let profile = output.players[0].profile.as_ref().unwrap();
+        assert_eq!(profile.games, Some(120));
+        assert!(output.players[1..].iter().all(|p| p.profile.is_none()));
+        assert!(output.warnings.iter().any(|w| w.message.contains("seat 1")));
+
+        let json = serde_json::to_value(&output.players).unwrap();
+        assert_eq!(json[0]["profile"]["rateHistory"][1], 1812.5);
+        assert!(json[2].get("profile").is_none());
+    }
+
+    #[test]
+    fn test_http_index_url() {
+        let index = HttpPlayerIndex::new("https://example.com/player/{name}.json").unwrap();
+        assert_eq!(
+            index.url("ASAPIN 2"),
+            "https://example.com/player/ASAPIN%202.json"
+        );
+        assert!(HttpPlayerIndex::new("https://example.com/player").is_err());
+    }
+}
@@ -1,5 +1,8 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::models::ParserOutput;
+
 pub type Result<T> = std::result::Result<T, ParserError>;
 
 #[derive(Error, Debug)]
@@ -25,21 +28,191 @@ pub enum ParserError {
     #[error("Schema validation error: {0}")]
     Schema(String),
 
-    #[error("Parse error: {message} at {context}")]
-    Parse { message: String, context: String },
+    #[error("Parse error: {message} at {context}{}", located(.location))]
+    Parse {
+        message: String,
+        context: String,
+        /// The tag being parsed, for errors raised in one
+        location: Option<ErrorLocation>,
+    },
 
     #[error("Invalid tile ID: {0}")]
     InvalidTileId(u32),
 
-    #[error("Invalid format: {0}")]
-    InvalidFormat(String),
+    #[error("Invalid format: {message}{}", located(.location))]
+    InvalidFormat {
+        message: String,
+        /// The tag being parsed, for errors raised in one
+        location: Option<ErrorLocation>,
+    },
+
+    /// The log ends in the middle of a round; `partial` holds everything
+    /// parsed up to `offset` in the decoded text
+    #[error("Log truncated at offset {offset}")]
+    TruncatedLog {
+        offset: usize,
+        partial: Box<ParserOutput>,
+    },
+
+    /// `ParserOptions::cancellation` was cancelled during the parse
+    #[error("Parse cancelled")]
+    Cancelled,
+
+    /// The parse ran past `ParserOptions::deadline`
+    #[error("Parse deadline exceeded")]
+    DeadlineExceeded,
+
+    /// The log is larger than one of the limits in `ParserOptions` allows
+    #[error("Limit exceeded: {limit} over {max}{}", located(.location))]
+    LimitExceeded {
+        limit: Limit,
+        max: u64,
+        /// The tag that went over the limit; None for `Limit::InputBytes`
+        location: Option<ErrorLocation>,
+    },
+}
+
+/// A resource limit set in `ParserOptions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Limit {
+    /// `ParserOptions::max_input_bytes`, counted before decoding
+    InputBytes,
+    /// `ParserOptions::max_rounds`
+    Rounds,
+    /// `ParserOptions::max_events_per_round`
+    EventsPerRound,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Limit::InputBytes => "input bytes",
+            Limit::Rounds => "round count",
+            Limit::EventsPerRound => "events per round",
+        })
+    }
+}
+
+/// The tag of a log an error was raised in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorLocation {
+    /// Byte offset of the tag in the log, decoded to UTF-8
+    pub offset: usize,
+    /// Name of the tag
+    pub tag: String,
+    /// Number of the round the tag belongs to, 1 for the first as in
+    /// `Round::round_id`; for an INIT tag, the round it starts. None before
+    /// the first round
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<usize>,
+}
+
+impl std::fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}> at byte {}", self.tag, self.offset)?;
+        if let Some(round) = self.round {
+            write!(f, " in round {}", round)?;
+        }
+        Ok(())
+    }
+}
+
+/// Display suffix for an error's location, if it has one
+fn located(location: &Option<ErrorLocation>) -> String {
+    location
+        .as_ref()
+        .map_or_else(String::new, |location| format!(" ({})", location))
+}
+
+/// An error in a form that pipelines can branch on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// `ParserError::code` of the error
+    pub code: String,
+    pub message: String,
+    /// Where in the log the error was raised, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<ErrorLocation>,
 }
 
 impl ParserError {
+    /// Stable identifier of the error's variant
+    ///
+    /// Codes are never renamed or reused; new variants get new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::Io(_) => "io",
+            ParserError::Xml(_) => "xml",
+            ParserError::Utf8(_) => "utf8",
+            ParserError::ParseInt(_) => "parse-int",
+            ParserError::Attr(_) => "attribute",
+            ParserError::Encoding(_) => "encoding",
+            ParserError::Schema(_) => "schema",
+            ParserError::Parse { .. } => "parse",
+            ParserError::InvalidTileId(_) => "invalid-tile-id",
+            ParserError::InvalidFormat { .. } => "invalid-format",
+            ParserError::TruncatedLog { .. } => "truncated-log",
+            ParserError::Cancelled => "cancelled",
+            ParserError::DeadlineExceeded => "deadline-exceeded",
+            ParserError::LimitExceeded { .. } => "limit-exceeded",
+        }
+    }
+
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            code: self.code().to_string(),
+            message: self.to_string(),
+            location: self.location().cloned(),
+        }
+    }
+
+    /// The tag a `Parse`, `InvalidFormat` or `LimitExceeded` error was raised in
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        match self {
+            ParserError::Parse { location, .. }
+            | ParserError::InvalidFormat { location, .. }
+            | ParserError::LimitExceeded { location, .. } => location.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn location_mut(&mut self) -> Option<&mut ErrorLocation> {
+        match self {
+            ParserError::Parse { location, .. }
+            | ParserError::InvalidFormat { location, .. }
+            | ParserError::LimitExceeded { location, .. } => location.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// The error with its location moved `by` bytes later, for a part of a
+    /// log parsed on its own
+    pub(crate) fn shifted(mut self, by: usize) -> Self {
+        if let Some(location) = self.location_mut() {
+            location.offset += by;
+        }
+        self
+    }
+
+    /// Give a `Parse`, `InvalidFormat` or `LimitExceeded` error without a
+    /// location this one; other errors are returned as they are
+    pub fn with_location(mut self, at: ErrorLocation) -> Self {
+        if let ParserError::Parse { location, .. }
+        | ParserError::InvalidFormat { location, .. }
+        | ParserError::LimitExceeded { location, .. } = &mut self
+        {
+            location.get_or_insert(at);
+        }
+        self
+    }
+
     pub fn parse(message: impl Into<String>, context: impl Into<String>) -> Self {
         Self::Parse {
             message: message.into(),
             context: context.into(),
+            location: None,
         }
     }
 
@@ -52,7 +225,10 @@ impl ParserError {
     }
 
     pub fn invalid_format(message: impl Into<String>) -> Self {
-        Self::InvalidFormat(message.into())
+        Self::InvalidFormat {
+            message: message.into(),
+            location: None,
+        }
     }
 }
 
@@ -65,7 +241,9 @@ mod tests {
         // Test parse error constructor
         let parse_err = ParserError::parse("test message", "test context");
         match parse_err {
-            ParserError::Parse { message, context } => {
+            ParserError::Parse {
+                message, context, ..
+            } => {
                 assert_eq!(message, "test message");
                 assert_eq!(context, "test context");
             }
@@ -93,8 +271,8 @@ mod tests {
         // Test invalid format error constructor
         let format_err = ParserError::invalid_format("format test");
         match format_err {
-            ParserError::InvalidFormat(msg) => {
-                assert_eq!(msg, "format test");
+            ParserError::InvalidFormat { message, .. } => {
+                assert_eq!(message, "format test");
             }
             _ => panic!("Expected InvalidFormat variant"),
         }
@@ -157,6 +335,42 @@ mod tests {
         assert!(matches!(parser_err, ParserError::Xml(_)));
     }
 
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(ParserError::InvalidTileId(999).code(), "invalid-tile-id");
+        assert_eq!(ParserError::parse("a", "b").code(), "parse");
+        let io = ParserError::from(std::io::Error::other("disk"));
+        assert_eq!(io.code(), "io");
+
+        let report = ParserError::invalid_format("bad format").report();
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["code"], "invalid-format");
+        assert_eq!(json["message"], "Invalid format: bad format");
+        assert!(json.get("location").is_none());
+
+        let located = ParserError::invalid_format("bad format").with_location(ErrorLocation {
+            offset: 120,
+            tag: "INIT".to_string(),
+            round: Some(3),
+        });
+        assert_eq!(
+            located.to_string(),
+            "Invalid format: bad format (<INIT> at byte 120 in round 3)"
+        );
+        let json = serde_json::to_value(located.report()).unwrap();
+        assert_eq!(
+            json["location"],
+            serde_json::json!({ "offset": 120, "tag": "INIT", "round": 3 })
+        );
+        // Errors of other kinds have no location to take
+        let io = ParserError::from(std::io::Error::other("disk")).with_location(ErrorLocation {
+            offset: 0,
+            tag: "GO".to_string(),
+            round: None,
+        });
+        assert!(io.location().is_none());
+    }
+
     #[test]
     fn test_error_debug() {
         let parse_err = ParserError::parse("debug test", "context");
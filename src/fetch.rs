@@ -0,0 +1,56 @@
+//! Logs downloaded over HTTP(S) (`http` feature)
+
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+
+use crate::error::{ParserError, Result};
+use crate::models::ParserOutput;
+use crate::parser::parse_mjlog;
+pub use crate::replay::tenhou_log_id;
+use crate::replay::TENHOU_VIEWER_URL;
+
+/// Where Tenhou serves the mjlog of a log ID
+pub const TENHOU_LOG_URL: &str = "https://tenhou.net/0/log/?";
+
+/// Download the mjlog at `url`, gunzipping it if the server sent it
+/// compressed
+pub fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
+    read_body(ureq::get(url))
+}
+
+fn read_body(request: ureq::Request) -> Result<Vec<u8>> {
+    let response = request
+        .call()
+        .map_err(|e| ParserError::Io(std::io::Error::other(e)))?;
+    let mut body = Vec::new();
+    response.into_reader().read_to_end(&mut body)?;
+    if body.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(body.as_slice()).read_to_end(&mut decoded)?;
+        body = decoded;
+    }
+    Ok(body)
+}
+
+/// Download and parse the mjlog at `url`
+pub fn fetch_log(url: &str) -> Result<ParserOutput> {
+    parse_mjlog(Cursor::new(fetch_bytes(url)?))
+}
+
+/// Download the mjlog of a Tenhou log ID or replay URL
+///
+/// The log server only answers requests that look like they come from the
+/// replay viewer, so the request carries its referer and a browser user
+/// agent.
+pub fn fetch_tenhou_bytes(id_or_url: &str) -> Result<Vec<u8>> {
+    let id = tenhou_log_id(id_or_url).ok_or_else(|| {
+        ParserError::invalid_format(format!("not a Tenhou log ID or replay URL: {}", id_or_url))
+    })?;
+    read_body(
+        ureq::get(&format!("{}{}", TENHOU_LOG_URL, id))
+            .set("Referer", &format!("{}?log={}", TENHOU_VIEWER_URL, id))
+            .set("User-Agent", "Mozilla/5.0 (compatible; tenhou-log-parser)")
+            .set("Accept-Encoding", "gzip"),
+    )
+}
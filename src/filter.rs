@@ -0,0 +1,247 @@
+//! Round filters
+//!
+//! A `RoundFilter` selects rounds by how they went. Filters combine with
+//! `and`, `or` and `!`. Set as `ParserOptions::round_filter`, a filter drops
+//! each non-matching round as soon as the round is complete, so rounds of
+//! no interest are never kept.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::models::{Event, Round, RyuukyokuReason};
+
+/// A predicate over completed rounds
+#[derive(Debug, Clone)]
+pub struct RoundFilter(Predicate);
+
+#[derive(Clone)]
+enum Predicate {
+    Yakuman,
+    Winner(u8),
+    DealtIn(u8),
+    Ryuukyoku,
+    ExhaustiveDraw,
+    Dealer(u8),
+    Riichi(u8),
+    Custom(Arc<dyn Fn(&Round) -> bool + Send + Sync>),
+    Not(Box<Predicate>),
+    All(Vec<Predicate>),
+    Any(Vec<Predicate>),
+}
+
+impl fmt::Debug for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::Yakuman => f.write_str("Yakuman"),
+            Predicate::Winner(seat) => f.debug_tuple("Winner").field(seat).finish(),
+            Predicate::DealtIn(seat) => f.debug_tuple("DealtIn").field(seat).finish(),
+            Predicate::Ryuukyoku => f.write_str("Ryuukyoku"),
+            Predicate::ExhaustiveDraw => f.write_str("ExhaustiveDraw"),
+            Predicate::Dealer(seat) => f.debug_tuple("Dealer").field(seat).finish(),
+            Predicate::Riichi(seat) => f.debug_tuple("Riichi").field(seat).finish(),
+            Predicate::Custom(_) => f.write_str("Custom"),
+            Predicate::Not(inner) => f.debug_tuple("Not").field(inner).finish(),
+            Predicate::All(all) => f.debug_tuple("All").field(all).finish(),
+            Predicate::Any(any) => f.debug_tuple("Any").field(any).finish(),
+        }
+    }
+}
+
+impl RoundFilter {
+    /// Rounds with a yakuman win
+    ///
+    /// A win counts when a yaku is worth 13 han or more, or when the hand
+    /// itself paid at least the yakuman limit (32000, or 48000 for the
+    /// dealer), which also catches counted yakuman.
+    pub fn has_yakuman() -> Self {
+        Self(Predicate::Yakuman)
+    }
+
+    /// Rounds won by `seat`
+    pub fn winner(seat: u8) -> Self {
+        Self(Predicate::Winner(seat))
+    }
+
+    /// Rounds where `seat` dealt into another player's ron
+    pub fn dealt_in(seat: u8) -> Self {
+        Self(Predicate::DealtIn(seat))
+    }
+
+    /// Rounds that ended in an exhaustive or abortive draw
+    pub fn ended_in_ryuukyoku() -> Self {
+        Self(Predicate::Ryuukyoku)
+    }
+
+    /// Rounds that ended because the wall ran out
+    pub fn ended_in_exhaustive_draw() -> Self {
+        Self(Predicate::ExhaustiveDraw)
+    }
+
+    /// Rounds dealt by `seat`
+    pub fn dealer(seat: u8) -> Self {
+        Self(Predicate::Dealer(seat))
+    }
+
+    /// Rounds where `seat` declared riichi
+    pub fn riichi(seat: u8) -> Self {
+        Self(Predicate::Riichi(seat))
+    }
+
+    /// Rounds for which `f` returns true
+    pub fn custom(f: impl Fn(&Round) -> bool + Send + Sync + 'static) -> Self {
+        Self(Predicate::Custom(Arc::new(f)))
+    }
+
+    /// Rounds matching both filters
+    pub fn and(self, other: Self) -> Self {
+        match self.0 {
+            Predicate::All(mut all) => {
+                all.push(other.0);
+                Self(Predicate::All(all))
+            }
+            first => Self(Predicate::All(vec![first, other.0])),
+        }
+    }
+
+    /// Rounds matching either filter
+    pub fn or(self, other: Self) -> Self {
+        match self.0 {
+            Predicate::Any(mut any) => {
+                any.push(other.0);
+                Self(Predicate::Any(any))
+            }
+            first => Self(Predicate::Any(vec![first, other.0])),
+        }
+    }
+
+    pub fn matches(&self, round: &Round) -> bool {
+        self.0.matches(round)
+    }
+}
+
+impl std::ops::Not for RoundFilter {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(Predicate::Not(Box::new(self.0)))
+    }
+}
+
+impl Predicate {
+    fn matches(&self, round: &Round) -> bool {
+        match self {
+            Predicate::Yakuman => round.events.iter().any(|event| is_yakuman(round, event)),
+            Predicate::Winner(seat) => round
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::Agari { who, .. } if who == seat)),
+            Predicate::DealtIn(seat) => round.events.iter().any(
+                |e| matches!(e, Event::Agari { who, from, .. } if from == seat && who != seat),
+            ),
+            Predicate::Ryuukyoku => round
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::Ryuukyoku { .. })),
+            Predicate::ExhaustiveDraw => round.events.iter().any(|e| {
+                matches!(
+                    e,
+                    Event::Ryuukyoku {
+                        reason: RyuukyokuReason::Normal,
+                        ..
+                    }
+                )
+            }),
+            Predicate::Dealer(seat) => round.dealer_seat == *seat,
+            Predicate::Riichi(seat) => round
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::Reach { who, step: 1, .. } if who == seat)),
+            Predicate::Custom(f) => f(round),
+            Predicate::Not(inner) => !inner.matches(round),
+            Predicate::All(all) => all.iter().all(|p| p.matches(round)),
+            Predicate::Any(any) => any.iter().any(|p| p.matches(round)),
+        }
+    }
+}
+
+/// Yakuman limit paid by a non-dealer, in units of 100 points
+const YAKUMAN: i32 = 320;
+
+fn is_yakuman(round: &Round, event: &Event) -> bool {
+    let Event::Agari {
+        who, yakus, payout, ..
+    } = event
+    else {
+        return false;
+    };
+    let limit = if *who == round.dealer_seat {
+        YAKUMAN * 3 / 2
+    } else {
+        YAKUMAN
+    };
+    yakus.iter().any(|y| y.value >= 13) || payout.is_some_and(|p| p.hand >= limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_mjlog, parse_mjlog_with_options, ParserOptions};
+    use std::io::Cursor;
+
+    const LOG: &str = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<REACH who="1" step="1"/><D0/>
+<AGARI ba="0,0" who="2" fromWho="1" ten="40,32000,5" yaku="1,1" sc="250,0,250,-320,250,320,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU ba="0,0" sc="250,15,250,-15,250,15,250,-15"/>
+<INIT seed="2,0,0,1,2,52" ten="250,250,250,250" oya="2" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI ba="0,0" who="2" fromWho="2" ten="30,36000,5" yaku="1,1" sc="250,-120,250,-120,250,360,250,-120"/>
+</mjloggm>"#;
+
+    #[test]
+    fn test_filters() {
+        let rounds = parse_mjlog(Cursor::new(LOG)).unwrap().rounds;
+        let matching = |filter: RoundFilter| -> Vec<usize> {
+            (0..rounds.len())
+                .filter(|&i| filter.matches(&rounds[i]))
+                .collect()
+        };
+
+        // A dealer's 36000 is a sanbaiman, not a yakuman
+        assert_eq!(matching(RoundFilter::has_yakuman()), vec![0]);
+        assert_eq!(matching(RoundFilter::winner(2)), vec![0, 2]);
+        assert_eq!(matching(RoundFilter::dealt_in(1)), vec![0]);
+        assert_eq!(matching(RoundFilter::ended_in_ryuukyoku()), vec![1]);
+        assert_eq!(matching(RoundFilter::riichi(1)), vec![0]);
+        assert_eq!(
+            matching(RoundFilter::winner(2).and(!RoundFilter::dealer(0))),
+            vec![2]
+        );
+        assert_eq!(
+            matching(RoundFilter::has_yakuman().or(RoundFilter::ended_in_exhaustive_draw())),
+            vec![0, 1]
+        );
+        assert_eq!(
+            matching(RoundFilter::custom(|r| r.init.round_number == 2)),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_filter_while_parsing() {
+        let options = ParserOptions {
+            round_filter: Some(RoundFilter::winner(2)),
+            ..Default::default()
+        };
+        let output = parse_mjlog_with_options(Cursor::new(LOG), &options).unwrap();
+        let ids: Vec<&str> = output.rounds.iter().map(|r| r.round_id.as_str()).collect();
+        assert_eq!(ids, vec!["Round 1", "Round 3"]);
+
+        let parallel = ParserOptions {
+            parallel: true,
+            ..options
+        };
+        let output = parse_mjlog_with_options(Cursor::new(LOG), &parallel).unwrap();
+        assert_eq!(output.rounds.len(), 2);
+    }
+}
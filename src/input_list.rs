@@ -0,0 +1,226 @@
+//! Input lists for batch commands
+//!
+//! Batch jobs driven by other tools can run into the OS limit on command
+//! line length. Instead of arguments, such jobs pass a file with one input
+//! per line: a path, a glob such as `logs/2024*/**/*.mjlog`, or an
+//! `http(s)://` URL. Blank lines and lines starting with `#` are skipped.
+//! Relative paths and globs are taken from the current directory, as they
+//! would be on the command line.
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use crate::error::{ParserError, Result};
+
+/// One entry of an input list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEntry {
+    /// A file or directory
+    Path(PathBuf),
+    /// Files matching a wildcard pattern
+    Glob(String),
+    Url(String),
+}
+
+impl InputEntry {
+    pub fn parse(line: &str) -> Self {
+        if line.starts_with("http://") || line.starts_with("https://") {
+            InputEntry::Url(line.to_string())
+        } else if line.contains(['*', '?']) {
+            InputEntry::Glob(line.to_string())
+        } else {
+            InputEntry::Path(PathBuf::from(line))
+        }
+    }
+}
+
+/// Read the entries of an input list
+pub fn read_input_list<R: BufRead>(reader: R) -> Result<Vec<InputEntry>> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        entries.push(InputEntry::parse(line));
+    }
+    Ok(entries)
+}
+
+/// Files and directories matching `pattern`, in sorted order
+///
+/// `*` matches any run of characters and `?` one character within a path
+/// component; a `**` component matches any number of directories.
+pub fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let pattern = Path::new(pattern);
+    let mut base = PathBuf::new();
+    let mut components = pattern.components().peekable();
+    // Start from the longest prefix without wildcards
+    while let Some(component) = components.peek() {
+        if component.as_os_str().to_string_lossy().contains(['*', '?']) {
+            break;
+        }
+        base.push(component);
+        components.next();
+    }
+    let rest: Vec<String> = components
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    let base = if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    };
+    let mut matches = Vec::new();
+    walk(&base, &rest, &mut matches)?;
+    // Drop the "./" added for patterns relative to the current directory
+    if !pattern.starts_with(".") {
+        for path in &mut matches {
+            if let Ok(stripped) = path.strip_prefix(".") {
+                *path = stripped.to_path_buf();
+            }
+        }
+    }
+    matches.sort();
+    matches.dedup();
+    Ok(matches)
+}
+
+fn walk(dir: &Path, rest: &[String], matches: &mut Vec<PathBuf>) -> Result<()> {
+    let Some((component, rest)) = rest.split_first() else {
+        if dir.exists() {
+            matches.push(dir.to_path_buf());
+        }
+        return Ok(());
+    };
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    if component == "**" {
+        // Zero directories, then one more level of any directory
+        walk(dir, rest, matches)?;
+        for entry in read_sorted(dir)? {
+            if entry.is_dir() {
+                walk(&entry, &rest_with_globstar(rest), matches)?;
+            }
+        }
+        return Ok(());
+    }
+    for entry in read_sorted(dir)? {
+        let name = entry
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if wildcard_match(component, &name) {
+            walk(&entry, rest, matches)?;
+        }
+    }
+    Ok(())
+}
+
+fn rest_with_globstar(rest: &[String]) -> Vec<String> {
+    std::iter::once("**".to_string())
+        .chain(rest.iter().cloned())
+        .collect()
+}
+
+fn read_sorted(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(dir)
+        .map_err(ParserError::Io)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Whether `name` matches a single-component wildcard `pattern`
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position after the last `*` and the name position it was tried at
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((after_star, tried)) => {
+                    p = after_star;
+                    n = tried + 1;
+                    backtrack = Some((after_star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_input_list() {
+        let list =
+            "# nightly batch\nlogs/a.mjlog\n\n  logs/*.xml.gz  \nhttps://example.com/log.xml\n";
+        let entries = read_input_list(Cursor::new(list)).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                InputEntry::Path(PathBuf::from("logs/a.mjlog")),
+                InputEntry::Glob("logs/*.xml.gz".to_string()),
+                InputEntry::Url("https://example.com/log.xml".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.mjlog", "2024010100gm-00a9.mjlog"));
+        assert!(wildcard_match("game?.xml", "game1.xml"));
+        assert!(wildcard_match("*a*b", "xaxxab"));
+        assert!(!wildcard_match("*.mjlog", "game.xml"));
+        assert!(!wildcard_match("game?.xml", "game10.xml"));
+    }
+
+    #[test]
+    fn test_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "a.xml",
+            "b.mjlog",
+            "sub/c.xml",
+            "sub/deep/d.xml",
+            "sub/e.txt",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let pattern = format!("{}/*.xml", root.display());
+        assert_eq!(glob(&pattern).unwrap(), vec![root.join("a.xml")]);
+
+        let pattern = format!("{}/**/*.xml", root.display());
+        assert_eq!(
+            glob(&pattern).unwrap(),
+            vec![
+                root.join("a.xml"),
+                root.join("sub/c.xml"),
+                root.join("sub/deep/d.xml")
+            ]
+        );
+    }
+}
@@ -1,9 +1,59 @@
+pub mod analysis;
+pub mod csv;
+pub mod dataset;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod encoding;
+#[cfg(feature = "enrich")]
+pub mod enrich;
 pub mod error;
+#[cfg(feature = "http")]
+pub mod fetch;
+pub mod filter;
+pub mod input_list;
+pub mod majsoul;
+pub mod manifest;
+pub mod meld;
 pub mod models;
+pub mod normalize;
 pub mod parser;
+pub mod replay;
+#[cfg(feature = "riichi-city")]
+pub mod riichi_city;
+pub mod schema;
+pub mod spool;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod state;
+pub mod stats;
+pub mod tenhou6;
+#[cfg(test)]
+mod test_support;
 pub mod tile;
+pub mod transcript;
+pub mod validation;
+pub mod wall;
 
-pub use error::{ParserError, Result};
-pub use models::{Event, KanType, ParserOutput, Player, Round, Rules, RyuukyokuReason, Yaku};
-pub use parser::{parse_file, parse_mjlog, parse_stream, ParserOptions};
-pub use tile::{tile_id_to_string, tile_string_to_id};
+pub use encoding::{detect_encoding, InputEncoding};
+pub use error::{ErrorLocation, ErrorReport, Limit, ParserError, Result};
+pub use filter::RoundFilter;
+pub use models::{
+    Dan, DoraSource, DoraTiming, EndReason, Event, EventId, GameLength, GameResult, Gender,
+    KanType, ParseWarning, ParserOutput, Payout, Player, PlayerProfile, RawTag, Room, Round,
+    RoundWind, Rules, RyuukyokuReason, ScoreWarning, Transfer, TransferReason, Wall, WallMismatch,
+    WallMismatchKind, WarningKind, Yaku, YakuKind,
+};
+pub use parser::{
+    file_options, open_input, parse_events, parse_file, parse_files_parallel, parse_mjlog,
+    parse_mjlog_file, parse_mjlog_games, parse_mjlog_with_options, parse_mjlog_with_warnings,
+    parse_stream, CancellationToken, LogEvent, LogEvents, ParserOptions, Strictness,
+};
+pub use tile::{
+    counts_to_ids, counts_to_tiles, ids_to_compact, ids_to_counts, indicator_to_dora,
+    parse_compact, parse_compact_ids, tile_asset, tile_id_to_string, tile_id_to_type,
+    tile_string_to_id, tile_type_to_string, tiles_to_compact, tiles_to_counts, with_tile_format,
+    Suit, Tile, TileAsset, TileFormat, TileType,
+};
+pub use validation::{
+    check_scores, validate, IssueCode, Severity, ValidationIssue, ValidationReport,
+};
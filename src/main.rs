@@ -1,19 +1,85 @@
-use std::path::PathBuf;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use log::{error, info};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::{error, info, warn};
 
-use tenhou_log_parser::{parse_file, parse_stream, ParserOptions};
+use tenhou_log_parser::input_list::{self, InputEntry};
+use tenhou_log_parser::manifest::{write_checksum, Manifest};
+use tenhou_log_parser::stats::{self, Export, ExportFormat};
+use tenhou_log_parser::{analysis, csv, dataset, majsoul, normalize, replay, tenhou6, transcript};
+use tenhou_log_parser::{
+    file_options, open_input, parse_mjlog_file, parse_mjlog_games, validate, with_tile_format,
+    InputEncoding, ParserOptions, Severity, Strictness, TileFormat,
+};
 
 #[derive(Parser)]
 #[command(name = "tenhou-log-parser")]
 #[command(about = "A parser for Tenhou mjlog files to JSON conversion")]
 #[command(version)]
-struct Args {
-    /// Input mjlog file (.xml or .xml.gz)
-    #[arg(value_name = "INPUT")]
-    input: PathBuf,
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+
+    /// Enable verbose logging
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Write a .sha256 sidecar (sha256sum format) next to every output file
+    #[arg(long, global = true)]
+    checksum: bool,
+
+    /// Print the JSON Schema of the output and exit
+    #[arg(long, exclusive = true)]
+    emit_schema: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check an mjlog file for integrity problems
+    Validate(ValidateArgs),
+    /// Aggregate per-player statistics over one or more mjlog files
+    Stats(StatsArgs),
+    /// Flag likely mistakes (missed wins, furiten riichi, careless deal-ins)
+    Mistakes(MistakesArgs),
+    /// Export state-action pairs for model training as JSON Lines
+    Dataset(DatasetArgs),
+    /// Rewrite an mjlog as indented UTF-8 XML for diffing and grepping
+    Normalize(NormalizeArgs),
+    /// Check that an mjlog and Tenhou's JSON replay of it describe the same game
+    Verify(VerifyArgs),
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Input mjlog files (.xml, .mjlog or .xml.gz), directories or globs
+    #[arg(value_name = "INPUT", required_unless_present_any = ["emit_schema", "url", "input_list"])]
+    inputs: Vec<PathBuf>,
+
+    /// Also read inputs from a file with one path or glob per line ("-" for
+    /// stdin)
+    #[arg(long, value_name = "FILE")]
+    input_list: Option<PathBuf>,
+
+    /// Download the log of a Tenhou replay URL or log ID instead of reading
+    /// a file (requires the `http` feature)
+    #[arg(long, value_name = "URL_OR_ID", conflicts_with_all = ["inputs", "input_list", "out_dir"])]
+    url: Option<String>,
+
+    /// Directory to write one JSON per input into, converting every log
+    /// found in the inputs
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "stream"])]
+    out_dir: Option<PathBuf>,
+
+    /// Number of files to convert at once (0 = one per CPU core)
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
 
     /// Output JSON file path
     #[arg(short, long, value_name = "FILE")]
@@ -23,85 +89,1625 @@ struct Args {
     #[arg(short, long)]
     force: bool,
 
-    /// Enable verbose logging
-    #[arg(short, long)]
-    verbose: bool,
-
     /// Output to stdout instead of file
     #[arg(long)]
     stream: bool,
 
+    /// Write each game of a log holding several to its own file, numbered
+    /// from 1 after the output name (game-1.json, game-2.json, ...); without
+    /// it, such a log is written as a JSON array of games
+    #[arg(long, conflicts_with = "stream")]
+    split_games: bool,
+
+    /// Write minified JSON instead of indented JSON
+    #[arg(long)]
+    compact: bool,
+
+    /// Gzip the output; files default to a .json.gz extension
+    #[arg(long)]
+    gzip: bool,
+
+    /// Write every tile as an {"id", "name"} object, keeping its tile ID
+    #[arg(long)]
+    tile_ids: bool,
+
     /// JSON Schema file for validation
     #[arg(long, value_name = "FILE")]
     schema: Option<PathBuf>,
+
+    /// Fail on malformed tags instead of skipping them
+    #[arg(long)]
+    strict: bool,
+
+    /// Keep each event's source tag and attributes in the output (for debugging)
+    #[arg(long)]
+    include_raw: bool,
+
+    /// Also keep the exact text and byte range of each source tag
+    #[arg(long)]
+    raw_source: bool,
+
+    /// Reconstruct each round's wall from the SHUFFLE seed and annotate draws
+    #[arg(long)]
+    annotate_wall: bool,
+
+    /// Check starting hands, draws and dora indicators against the wall
+    /// rebuilt from the SHUFFLE seed and list the differences in the
+    /// output's wallMismatches
+    #[arg(long)]
+    verify_wall: bool,
+
+    /// Emit starting hands sorted in tile order
+    #[arg(long)]
+    sort_hands: bool,
+
+    /// Keep completed rounds in a temporary file while parsing to bound memory use
+    #[arg(long)]
+    spill_rounds: bool,
+
+    /// Parse the rounds of the log on several threads
+    #[arg(long)]
+    parallel: bool,
+
+    /// Derive the game ID from the input so identical input gives identical output
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Game ID to write instead of the Tenhou log ID taken from the input's
+    /// file name or `--url`, or the random UUID used without one
+    #[arg(long, value_name = "ID", conflicts_with = "out_dir")]
+    game_id: Option<String>,
+
+    /// Also write per-player win, deal-in, riichi and call rates, average
+    /// win and average placement over the inputs to FILE (CSV for .csv,
+    /// JSON otherwise)
+    #[arg(long, value_name = "FILE")]
+    stats: Option<PathBuf>,
+
+    /// Also write a link into Tenhou's replay viewer for each round to FILE
+    /// (CSV for .csv, JSON otherwise); the log ID is taken from the input's
+    /// file name or `--url`, and the viewer follows the `--pov` seat
+    #[arg(long, value_name = "FILE", conflicts_with = "out_dir")]
+    emit_urls: Option<PathBuf>,
+
+    /// Annotate events with derived information, comma-separated
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KIND")]
+    annotate: Vec<Annotation>,
+
+    /// Keep each seat's hand, melds and river after every event (round.state)
+    #[arg(long)]
+    track_state: bool,
+
+    /// Replay score changes and list rounds that do not add up to the next
+    /// round's scores in the output's scoreWarnings
+    #[arg(long)]
+    validate_scores: bool,
+
+    /// Hide the tiles this seat could not see: other players' hands and draws
+    #[arg(long, value_name = "SEAT", value_parser = clap::value_parser!(u8).range(0..4))]
+    pov: Option<u8>,
+
+    /// Attach player profiles from a log-index service; `{name}` in the URL
+    /// is replaced by each player's name
+    #[cfg(feature = "enrich")]
+    #[arg(long, value_name = "URL")]
+    enrich: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+
+    /// With `--format sqlite`, add the games to an existing database;
+    /// games already in it are replaced
+    #[arg(long)]
+    append: bool,
+
+    /// Input format, detected from the content when not given
+    #[arg(long, value_enum, alias = "input-format")]
+    from: Option<InputFormat>,
+
+    /// Encoding of mjlog input
+    #[arg(long, value_enum, default_value_t = Encoding::Auto)]
+    encoding: Encoding,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Encoding {
+    /// From a byte order mark, the XML declaration or the content
+    Auto,
+    /// Shift_JIS, as Tenhou serves logs
+    ShiftJis,
+    /// UTF-8, as some mirrors store logs
+    #[value(name = "utf-8")]
+    Utf8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// Tenhou mjlog XML
+    Mjlog,
+    /// tenhou.net/6 JSON replay
+    Tenhou6,
+    /// RiichiCity game record JSON
+    #[cfg(feature = "riichi-city")]
+    RiichiCity,
+}
+
+/// Tell a JSON replay from an mjlog by its first non-blank byte
+fn detect_format(input: &Path) -> Result<InputFormat> {
+    let mut reader = std::io::BufReader::new(open_input(input)?);
+    loop {
+        let buf = reader.fill_buf()?;
+        let Some(&byte) = buf.first() else {
+            return Ok(InputFormat::Mjlog);
+        };
+        if !byte.is_ascii_whitespace() {
+            return Ok(match byte {
+                b'[' | b'{' => InputFormat::Tenhou6,
+                _ => InputFormat::Mjlog,
+            });
+        }
+        reader.consume(1);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Annotation {
+    /// The acting seat's shanten on every draw and discard
+    Shanten,
+    /// Waits and furiten on every discard that leaves the discarder tenpai
+    Waits,
+    /// Each event's sequence number, the acting seat's turn (junme) and
+    /// the tiles left in the live wall, in round.counters
+    Counters,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// This crate's JSON model
+    Json,
+    /// MahjongSoul game record JSON
+    Majsoul,
+    /// Plain-text turn-by-turn narration
+    Transcript,
+    /// Tenhou's own JSON replay format (tenhou.net/6), as read by its viewer
+    Tenhou6,
+    /// One comma-separated row per event, for spreadsheets and pandas
+    Csv,
+    /// The same rows separated by tabs
+    Tsv,
+    /// Games, players, rounds and events of every input in one SQLite
+    /// database
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// The JSON model as MessagePack, fields by name
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+    /// The JSON model as CBOR
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Input mjlog file (.xml or .xml.gz)
+    #[arg(value_name = "INPUT")]
+    input: PathBuf,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Input mjlog files or directories containing them
+    #[arg(value_name = "INPUT", required_unless_present = "input_list")]
+    inputs: Vec<PathBuf>,
+
+    /// Output file path, CSV if it ends in .csv and JSON otherwise (stdout if omitted)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Also write a discard-by-turn heatmap (CSV if the file ends in .csv, JSON otherwise)
+    #[arg(long, value_name = "FILE")]
+    heatmap: Option<PathBuf>,
+
+    /// Restrict the heatmap to one player name
+    #[arg(long, value_name = "NAME", requires = "heatmap")]
+    player: Option<String>,
+
+    /// Also write yaku frequencies (CSV if the file ends in .csv, JSON otherwise)
+    #[arg(long, value_name = "FILE")]
+    yaku: Option<PathBuf>,
+
+    /// Compare two players over the games they both played
+    #[arg(long, num_args = 2, value_names = ["PLAYER", "OPPONENT"])]
+    head_to_head: Option<Vec<String>>,
+
+    /// Also write session standings over the inputs in the given order (CSV if
+    /// the file ends in .csv, JSON otherwise)
+    #[arg(long, value_name = "FILE")]
+    session: Option<PathBuf>,
+
+    #[command(flatten)]
+    batch: BatchArgs,
+}
+
+#[derive(Args)]
+struct MistakesArgs {
+    /// Input mjlog file (.xml or .xml.gz)
+    #[arg(value_name = "INPUT")]
+    input: PathBuf,
+
+    /// Print the findings as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct DatasetArgs {
+    /// Input mjlog files or directories containing them
+    #[arg(value_name = "INPUT", required_unless_present = "input_list")]
+    inputs: Vec<PathBuf>,
+
+    /// Output file path (stdout if omitted); discard, event and round tables
+    /// are written as CSV, or as Parquet when the file ends in .parquet; event
+    /// and round tables also as Arrow IPC when it ends in .arrow or .feather;
+    /// decisions are written as JSON Lines, or as a NumPy archive when the
+    /// file ends in .npz
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// What to export
+    #[arg(long, value_enum, default_value_t = DatasetKind::Decisions)]
+    kind: DatasetKind,
+
+    #[command(flatten)]
+    batch: BatchArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DatasetKind {
+    /// State-action pairs as JSON Lines
+    Decisions,
+    /// One row per discard with shanten, safety and round outcome
+    Discards,
+    /// One row per event, the columns of `--format csv`
+    Events,
+    /// One row per round with its outcome and score changes
+    Rounds,
+}
+
+#[derive(Args)]
+struct NormalizeArgs {
+    /// Input mjlog file (.xml or .xml.gz)
+    #[arg(value_name = "INPUT")]
+    input: PathBuf,
+
+    /// Output XML file path (stdout if omitted)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Gzip the output file
+    #[arg(long, requires = "output")]
+    gzip: bool,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Input mjlog file (.xml or .xml.gz)
+    #[arg(value_name = "MJLOG")]
+    mjlog: PathBuf,
+
+    /// The same game as tenhou.net/6 JSON (.json or .json.gz)
+    #[arg(value_name = "REPLAY")]
+    replay: PathBuf,
+
+    /// Print the mismatches as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Options shared by commands that read many logs
+#[derive(Args)]
+struct BatchArgs {
+    /// Also read inputs from a file with one path, glob or URL per line
+    /// ("-" for stdin)
+    #[arg(long, value_name = "FILE")]
+    input_list: Option<PathBuf>,
+
+    /// Write a JSON manifest of the inputs, their content hashes and duplicate clusters
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Skip inputs whose content duplicates an earlier input
+    #[arg(long)]
+    skip_duplicates: bool,
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let cli = Cli::parse();
+    if cli.emit_schema {
+        println!("{}", tenhou_log_parser::schema::OUTPUT_SCHEMA.trim_end());
+        return Ok(());
+    }
 
     // Initialize logger
-    let log_level = if args.verbose { "debug" } else { "info" };
+    let log_level = if cli.verbose { "debug" } else { "info" };
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level)).init();
 
-    // Validate input file
-    if !args.input.exists() {
-        error!("Input file does not exist: {:?}", args.input);
+    // The single log a command reads, for pointing into it on a parse error
+    #[cfg(feature = "diagnostics")]
+    let input = match &cli.command {
+        Some(Command::Validate(args)) => Some(args.input.clone()),
+        Some(Command::Mistakes(args)) => Some(args.input.clone()),
+        Some(Command::Verify(args)) => Some(args.mjlog.clone()),
+        None => match cli.convert.inputs.as_slice() {
+            [input] => Some(input.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    if cli.checksum && cli.convert.split_games {
+        anyhow::bail!("--checksum does not cover the files of --split-games");
+    }
+    let outputs = if cli.checksum {
+        output_paths(&cli)
+    } else {
+        Vec::new()
+    };
+
+    let result = match cli.command {
+        Some(Command::Validate(args)) => run_validate(args),
+        Some(Command::Stats(args)) => run_stats(args),
+        Some(Command::Mistakes(args)) => run_mistakes(args),
+        Some(Command::Dataset(args)) => run_dataset(args),
+        Some(Command::Normalize(args)) => run_normalize(args),
+        Some(Command::Verify(args)) => run_verify(args),
+        None => run_convert(cli.convert, cli.verbose),
+    };
+
+    #[cfg(feature = "diagnostics")]
+    if let (Err(e), Some(input)) = (&result, input) {
+        print_diagnostic(&input, e);
+    }
+    result?;
+
+    for path in outputs {
+        let sidecar = write_checksum(&path)
+            .with_context(|| format!("Failed to write checksum for {:?}", path))?;
+        info!("Wrote checksum to: {:?}", sidecar);
+    }
+    Ok(())
+}
+
+/// Files a command writes, as opposed to printing to stdout
+fn output_paths(cli: &Cli) -> Vec<PathBuf> {
+    let (outputs, batch) = match &cli.command {
+        Some(Command::Stats(args)) => (
+            vec![
+                args.output.clone(),
+                args.heatmap.clone(),
+                args.yaku.clone(),
+                args.session.clone(),
+            ],
+            Some(&args.batch),
+        ),
+        Some(Command::Dataset(args)) => (vec![args.output.clone()], Some(&args.batch)),
+        Some(Command::Normalize(args)) => (vec![args.output.clone()], None),
+        Some(Command::Validate(_) | Command::Mistakes(_) | Command::Verify(_)) => {
+            (Vec::new(), None)
+        }
+        None if cli.convert.stream => (
+            vec![cli.convert.stats.clone(), cli.convert.emit_urls.clone()],
+            None,
+        ),
+        #[cfg(feature = "sqlite")]
+        None if cli.convert.format == OutputFormat::Sqlite => (
+            vec![cli.convert.output.clone(), cli.convert.stats.clone()],
+            None,
+        ),
+        None if cli.convert.url.is_some() => (
+            vec![
+                url_output_path(&cli.convert),
+                cli.convert.stats.clone(),
+                cli.convert.emit_urls.clone(),
+            ],
+            None,
+        ),
+        None => (
+            convert_targets(&cli.convert)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(_, output)| Some(output))
+                .chain([cli.convert.stats.clone(), cli.convert.emit_urls.clone()])
+                .collect(),
+            None,
+        ),
+    };
+    outputs
+        .into_iter()
+        .chain(batch.map(|b| b.manifest.clone()))
+        .flatten()
+        .collect()
+}
+
+/// Render a parse error with the offending part of the log underlined
+#[cfg(feature = "diagnostics")]
+fn print_diagnostic(input: &Path, error: &anyhow::Error) {
+    let Some(parse_error) = error
+        .chain()
+        .find_map(|e| e.downcast_ref::<tenhou_log_parser::ParserError>())
+    else {
+        return;
+    };
+    if let Ok(diagnostic) = tenhou_log_parser::diagnostics::diagnose_file(input, parse_error) {
+        eprintln!("{:?}", miette::Report::new(diagnostic));
+    }
+}
+
+fn check_input_exists(input: &Path) {
+    if !input.exists() {
+        error!("Input file does not exist: {:?}", input);
         std::process::exit(1);
     }
+}
 
+fn run_convert(args: ConvertArgs, verbose: bool) -> Result<()> {
     let options = ParserOptions {
-        verbose: args.verbose,
-        validate_schema: args.schema,
-    };
-
-    if args.stream {
-        // Stream mode: output to stdout
-        let file = std::fs::File::open(&args.input)
-            .with_context(|| format!("Failed to open input file: {:?}", args.input))?;
-
-        let reader: Box<dyn std::io::Read> = if args
-            .input
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.ends_with("gz"))
-            .unwrap_or(false)
-        {
-            Box::new(flate2::read::GzDecoder::new(file))
+        verbose,
+        validate_schema: args.schema.clone(),
+        include_raw: args.include_raw,
+        raw_source: args.raw_source,
+        annotate_wall: args.annotate_wall,
+        verify_wall: args.verify_wall,
+        sort_hands: args.sort_hands,
+        spill_rounds: args.spill_rounds,
+        parallel: args.parallel,
+        deterministic: args.deterministic,
+        game_id: args.game_id.clone(),
+        round_filter: None,
+        cancellation: None,
+        deadline: None,
+        validate_scores: args.validate_scores,
+        track_state: args.track_state,
+        annotate_shanten: args.annotate.contains(&Annotation::Shanten),
+        annotate_waits: args.annotate.contains(&Annotation::Waits),
+        annotate_counters: args.annotate.contains(&Annotation::Counters),
+        compact: args.compact,
+        gzip: args.gzip,
+        tile_ids: args.tile_ids,
+        strictness: if args.strict {
+            Strictness::Strict
         } else {
-            Box::new(file)
+            Strictness::Lenient
+        },
+        encoding: match args.encoding {
+            Encoding::Auto => InputEncoding::Auto,
+            Encoding::ShiftJis => InputEncoding::ShiftJis,
+            Encoding::Utf8 => InputEncoding::Utf8,
+        },
+        max_input_bytes: None,
+        max_rounds: None,
+        max_events_per_round: None,
+    };
+
+    let batch = args.out_dir.is_some()
+        || args.input_list.is_some()
+        || args.inputs.len() > 1
+        || args.inputs.iter().any(|p| p.is_dir() || is_glob(p));
+    if args.emit_urls.is_some() && batch {
+        anyhow::bail!("--emit-urls takes a single input file or --url");
+    }
+    if args.game_id.is_some() && batch {
+        anyhow::bail!("--game-id takes a single input file or --url");
+    }
+
+    #[cfg(feature = "sqlite")]
+    if args.format == OutputFormat::Sqlite {
+        return run_sqlite_convert(&args, &options);
+    }
+
+    if let Some(url) = &args.url {
+        let games = convert_url(url, &args, &options)?;
+        write_replay_links(&args, replay::tenhou_log_id(url), &games)?;
+        return write_stats_summary(&args, &games);
+    }
+
+    if batch {
+        return run_batch_convert(&args, &options);
+    }
+
+    let input = args.inputs.first().context("No input file given")?;
+
+    // Validate input file
+    check_input_exists(input);
+
+    let output = (!args.stream).then(|| resolve_output_path(input, &args));
+    let games = convert_file(input, output.as_deref(), &args, &options)?;
+    write_replay_links(&args, replay::file_log_id(input), &games)?;
+    write_stats_summary(&args, &games)
+}
+
+/// Write the `--emit-urls` links to each round of the converted game, if
+/// asked for
+fn write_replay_links(
+    args: &ConvertArgs,
+    log_id: Option<&str>,
+    games: &[tenhou_log_parser::ParserOutput],
+) -> Result<()> {
+    let Some(path) = &args.emit_urls else {
+        return Ok(());
+    };
+    let log_id = log_id.context(
+        "--emit-urls needs the Tenhou log ID, from an input named like 2023010100gm-00a9-0000-1a2b3c4d.mjlog or from --url",
+    )?;
+    let [game] = games else {
+        anyhow::bail!("--emit-urls takes a log of one game, not {}", games.len());
+    };
+    let links = replay::round_links(log_id, game, args.pov.unwrap_or(0));
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let writer = std::io::BufWriter::new(file);
+    match ExportFormat::from_path(path) {
+        ExportFormat::Csv => dataset::Table {
+            columns: vec![
+                (
+                    "round",
+                    dataset::Values::Int(links.iter().map(|l| Some(l.round as i64)).collect()),
+                ),
+                (
+                    "roundId",
+                    dataset::Values::Text(links.iter().map(|l| l.round_id.clone()).collect()),
+                ),
+                (
+                    "url",
+                    dataset::Values::Text(links.iter().map(|l| l.url.clone()).collect()),
+                ),
+            ],
+        }
+        .write_delimited(writer, ','),
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(writer, &links).map_err(std::io::Error::other)
+        }
+    }
+    .context("Failed to write replay links")?;
+    info!("Wrote {} replay link(s) to: {:?}", links.len(), path);
+    Ok(())
+}
+
+/// Write the `--stats` summary of the converted games, if asked for
+fn write_stats_summary(
+    args: &ConvertArgs,
+    games: &[tenhou_log_parser::ParserOutput],
+) -> Result<()> {
+    let Some(path) = &args.stats else {
+        return Ok(());
+    };
+    let summary = stats::summary(&stats::collect(games));
+    write_export(&summary, path).context("Failed to write statistics")?;
+    info!(
+        "Wrote statistics for {} game(s) to: {:?}",
+        games.len(),
+        path
+    );
+    Ok(())
+}
+
+/// Convert every log found in the inputs, going on past failures and
+/// summing them up at the end
+fn run_batch_convert(args: &ConvertArgs, options: &ParserOptions) -> Result<()> {
+    if args.stream || args.output.is_some() {
+        anyhow::bail!("--stream and --output take a single input file; use --out-dir for several");
+    }
+    let targets = convert_targets(args)?;
+    let results = map_parallel(&targets, args.jobs, |(input, output)| {
+        if output.exists() && !args.force {
+            Err(anyhow::anyhow!(
+                "Output file already exists: {:?}. Use --force to overwrite.",
+                output
+            ))
+        } else {
+            output
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .with_context(|| format!("Failed to create directory for {:?}", output))
+                .and_then(|()| convert_file(input, Some(output), args, options))
+                .inspect_err(|_| {
+                    // Leave no half-written output behind
+                    let _ = std::fs::remove_file(output);
+                })
+        }
+    });
+
+    // Reported in input order, however the work was scheduled
+    let mut failures = Vec::new();
+    let mut games = Vec::new();
+    for ((input, _), result) in targets.iter().zip(results) {
+        match result {
+            Ok(game) => games.extend(game),
+            Err(e) => {
+                error!("{:?}: {:#}", input, e);
+                failures.push(input);
+            }
+        }
+    }
+    write_stats_summary(args, &games)?;
+
+    info!(
+        "Converted {} of {} file(s), {} failed",
+        targets.len() - failures.len(),
+        targets.len(),
+        failures.len()
+    );
+    for input in &failures {
+        info!("  failed: {:?}", input);
+    }
+    if !failures.is_empty() {
+        anyhow::bail!(
+            "{} of {} file(s) failed to convert",
+            failures.len(),
+            targets.len()
+        );
+    }
+    Ok(())
+}
+
+/// Apply `f` to every item on `jobs` threads, keeping the results in item
+/// order; a `jobs` of 0 uses one thread per available core
+fn map_parallel<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let jobs = match jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+    if jobs <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..jobs.min(items.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break done;
+                        };
+                        done.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("conversion worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Each input log and the file its conversion goes to
+///
+/// With `--out-dir`, logs found in a directory keep their path below it, so
+/// files of the same name in different subdirectories do not collide.
+fn convert_targets(args: &ConvertArgs) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut patterns = args.inputs.clone();
+    if let Some(list) = &args.input_list {
+        for entry in read_input_list(list)? {
+            match entry {
+                InputEntry::Path(path) => patterns.push(path),
+                InputEntry::Glob(pattern) => patterns.push(PathBuf::from(pattern)),
+                InputEntry::Url(url) => anyhow::bail!(
+                    "{} in {:?}: convert takes URLs one at a time with --url",
+                    url,
+                    list
+                ),
+            }
+        }
+    }
+
+    let mut targets = Vec::new();
+    for pattern in &patterns {
+        let paths = if is_glob(pattern) {
+            let matches = input_list::glob(&pattern.to_string_lossy())
+                .with_context(|| format!("Failed to expand {:?}", pattern))?;
+            if matches.is_empty() {
+                warn!("No inputs match {:?}", pattern);
+            }
+            matches
+        } else {
+            vec![pattern.clone()]
         };
+        for path in paths {
+            for input in expand_inputs(std::slice::from_ref(&path))? {
+                let output = match &args.out_dir {
+                    Some(dir) => {
+                        let relative = input
+                            .strip_prefix(&path)
+                            .ok()
+                            .filter(|r| !r.as_os_str().is_empty())
+                            .or_else(|| input.file_name().map(Path::new))
+                            .unwrap_or(&input);
+                        default_output_path(&dir.join(relative), args)
+                    }
+                    None => default_output_path(&input, args),
+                };
+                targets.push((input, output));
+            }
+        }
+    }
+    if targets.len() == 1 && args.out_dir.is_none() {
+        if let Some(output) = &args.output {
+            targets[0].1 = output.clone();
+        }
+    }
+    Ok(targets)
+}
+
+/// Convert one log, to `output` or to stdout when there is none
+///
+/// The parsed games are handed back when `--stats` or `--emit-urls` needs
+/// them.
+fn convert_file(
+    input: &Path,
+    output_path: Option<&Path>,
+    args: &ConvertArgs,
+    options: &ParserOptions,
+) -> Result<Vec<tenhou_log_parser::ParserOutput>> {
+    let from = match args.from {
+        Some(from) => from,
+        None => detect_format(input)
+            .with_context(|| format!("Failed to read input file: {:?}", input))?,
+    };
+    let mut games = parse_input(input, from, options)?;
+    match output_path.filter(|_| args.split_games && games.len() > 1) {
+        Some(output_path) => {
+            for (index, game) in games.iter_mut().enumerate() {
+                let path = numbered_path(output_path, index + 1);
+                if path.exists() && !args.force {
+                    anyhow::bail!(
+                        "Output file already exists: {:?}. Use --force to overwrite.",
+                        path
+                    );
+                }
+                write_converted(std::slice::from_mut(game), Some(&path), args)?;
+            }
+        }
+        None => write_converted(&mut games, output_path, args)?,
+    }
+    if args.stats.is_none() && args.emit_urls.is_none() {
+        games.clear();
+    }
+    Ok(games)
+}
+
+/// `path` with `-{number}` added to its name before the extensions, so
+/// game.json.gz becomes game-2.json.gz
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = match name.split_once('.') {
+        Some((stem, extensions)) => format!("{}-{}.{}", stem, number, extensions),
+        None => format!("{}-{}", name, number),
+    };
+    path.with_file_name(name)
+}
+
+/// Parse one log of a known format into the JSON model
+///
+/// Tenhou logs are given the log ID in their file name as game ID, unless
+/// `--game-id` overrides it.
+fn parse_input(
+    input: &Path,
+    from: InputFormat,
+    options: &ParserOptions,
+) -> Result<Vec<tenhou_log_parser::ParserOutput>> {
+    let options = file_options(input, options);
+    let output = match from {
+        // Archives may concatenate several games in one file
+        InputFormat::Mjlog => open_input(input).and_then(|r| parse_mjlog_games(r, &options)),
+        InputFormat::Tenhou6 => open_input(input)
+            .and_then(|r| tenhou6::parse_tenhou6_with_options(r, &options))
+            .map(|game| vec![game]),
+        #[cfg(feature = "riichi-city")]
+        InputFormat::RiichiCity => std::fs::File::open(input)
+            .map_err(Into::into)
+            .and_then(tenhou_log_parser::riichi_city::parse_riichi_city)
+            .map(|mut game| {
+                if let Some(id) = &options.game_id {
+                    game.game_id = id.clone();
+                }
+                vec![game]
+            }),
+    };
+    output.with_context(|| format!("Failed to parse log from {:?}", input))
+}
+
+/// Apply the edits asked for on the command line to a parsed log
+fn prepare_output(output: &mut tenhou_log_parser::ParserOutput, args: &ConvertArgs) -> Result<()> {
+    // Only the serde formats stream `--spill-rounds` rounds from their spool
+    let converted = match args.format {
+        OutputFormat::Majsoul
+        | OutputFormat::Transcript
+        | OutputFormat::Tenhou6
+        | OutputFormat::Csv
+        | OutputFormat::Tsv => true,
+        #[cfg(feature = "sqlite")]
+        OutputFormat::Sqlite => true,
+        _ => false,
+    };
+    if converted || args.stats.is_some() || args.emit_urls.is_some() {
+        output
+            .load_rounds()
+            .context("Failed to read back spilled rounds")?;
+    }
+    if args.sort_hands {
+        output.sort_hands();
+    }
+    if let Some(seat) = args.pov {
+        output.redact_for_seat(seat);
+    }
+    #[cfg(feature = "enrich")]
+    if let Some(template) = &args.enrich {
+        let index = tenhou_log_parser::enrich::HttpPlayerIndex::new(template.as_str())?;
+        let found = tenhou_log_parser::enrich::enrich(output, &index);
+        info!("Found profiles for {} player(s)", found);
+    }
+    Ok(())
+}
+
+/// Write parsed games in the requested format, to `output_path` or stdout
+fn write_converted(
+    games: &mut [tenhou_log_parser::ParserOutput],
+    output_path: Option<&Path>,
+    args: &ConvertArgs,
+) -> Result<()> {
+    for output in games.iter_mut() {
+        prepare_output(output, args)?;
+    }
+    let writer: Box<dyn std::io::Write> = match output_path {
+        None => Box::new(std::io::stdout()),
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create output file: {:?}", path))?;
+            info!("Writing converted log to: {:?}", path);
+            Box::new(std::io::BufWriter::new(file))
+        }
+    };
+    if args.gzip {
+        let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+        write_format(games, &mut encoder, args)?;
+        encoder.finish().context("Failed to finish gzip output")?;
+        Ok(())
+    } else {
+        write_format(games, writer, args)
+    }
+}
+
+/// Write parsed games to `writer` in the requested format; several games
+/// only as a JSON array
+fn write_format<W: Write>(
+    games: &[tenhou_log_parser::ParserOutput],
+    mut writer: W,
+    args: &ConvertArgs,
+) -> Result<()> {
+    let tile_format = if args.tile_ids {
+        TileFormat::Object
+    } else {
+        TileFormat::Name
+    };
+    let [output] = games else {
+        if args.format != OutputFormat::Json {
+            anyhow::bail!(
+                "The log holds {} games, which only JSON output can hold together; use --split-games",
+                games.len()
+            );
+        }
+        return with_tile_format(tile_format, || write_json(writer, &games, args.compact))
+            .context("Failed to write converted log");
+    };
+    with_tile_format(tile_format, || match args.format {
+        OutputFormat::Json => write_json(writer, output, args.compact),
+        OutputFormat::Majsoul => write_json(writer, &majsoul::to_majsoul(output), args.compact),
+        OutputFormat::Tenhou6 => serde_json::to_writer(writer, &tenhou6::to_tenhou6(output)),
+        OutputFormat::Csv => writer
+            .write_all(csv::to_csv(output).as_bytes())
+            .and_then(|()| writer.flush())
+            .map_err(serde_json::Error::io),
+        OutputFormat::Tsv => writer
+            .write_all(csv::to_tsv(output).as_bytes())
+            .and_then(|()| writer.flush())
+            .map_err(serde_json::Error::io),
+        OutputFormat::Transcript => writer
+            .write_all(transcript::to_transcript(output).as_bytes())
+            .and_then(|()| writer.flush())
+            .map_err(serde_json::Error::io),
+        #[cfg(feature = "sqlite")]
+        OutputFormat::Sqlite => Err(serde::ser::Error::custom(
+            "--format sqlite writes one database; give it with --output",
+        )),
+        #[cfg(feature = "msgpack")]
+        OutputFormat::Msgpack => rmp_serde::encode::write_named(&mut writer, output)
+            .map_err(serde::ser::Error::custom)
+            .and_then(|()| writer.flush().map_err(serde_json::Error::io)),
+        #[cfg(feature = "cbor")]
+        OutputFormat::Cbor => ciborium::into_writer(output, &mut writer)
+            .map_err(serde::ser::Error::custom)
+            .and_then(|()| writer.flush().map_err(serde_json::Error::io)),
+    })
+    .context("Failed to write converted log")
+}
 
-        parse_stream(reader, std::io::stdout(), &options)
-            .context("Failed to parse mjlog to stdout")?;
+/// Write `value` as indented JSON, or minified when `compact`
+fn write_json<W: Write, T: serde::Serialize>(
+    writer: W,
+    value: &T,
+    compact: bool,
+) -> serde_json::Result<()> {
+    if compact {
+        serde_json::to_writer(writer, value)
     } else {
-        // File mode: output to file
-        let output_path = match args.output {
-            Some(path) => path,
-            None => {
-                let mut path = args.input.clone();
-                path.set_extension("json");
+        serde_json::to_writer_pretty(writer, value)
+    }
+}
+
+/// Convert every input into the single SQLite database named by `--output`
+#[cfg(feature = "sqlite")]
+fn run_sqlite_convert(args: &ConvertArgs, options: &ParserOptions) -> Result<()> {
+    if args.url.is_some() || args.stream || args.out_dir.is_some() {
+        anyhow::bail!("--format sqlite writes one database; give it with --output");
+    }
+    if args.emit_urls.is_some() {
+        anyhow::bail!("--emit-urls takes a single input file or --url");
+    }
+    let path = args
+        .output
+        .as_deref()
+        .context("--format sqlite needs the database file as --output")?;
+    if path.exists() && !args.append {
+        if !args.force {
+            anyhow::bail!(
+                "Output file already exists: {:?}. Use --append to add to it or --force to overwrite.",
                 path
+            );
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove existing database: {:?}", path))?;
+    }
+    let mut db = tenhou_log_parser::sqlite::SqliteWriter::open(path)
+        .with_context(|| format!("Failed to open database: {:?}", path))?;
+
+    let targets = convert_targets(args)?;
+    let mut failures = 0;
+    let mut games = Vec::new();
+    for (input, _) in &targets {
+        let result = args
+            .from
+            .map_or_else(|| detect_format(input), Ok)
+            .and_then(|from| parse_input(input, from, options))
+            .and_then(|mut found| {
+                for output in &mut found {
+                    prepare_output(output, args)?;
+                    db.insert(output)
+                        .with_context(|| format!("Failed to write {:?} to the database", input))?;
+                }
+                Ok(found)
+            });
+        match result {
+            Ok(found) if args.stats.is_some() => games.extend(found),
+            Ok(_) => {}
+            Err(e) => {
+                error!("{:?}: {:#}", input, e);
+                failures += 1;
             }
-        };
+        }
+    }
+    write_stats_summary(args, &games)?;
 
-        // Check if output file exists and force flag
-        if output_path.exists() && !args.force {
-            error!(
-                "Output file already exists: {:?}. Use --force to overwrite.",
-                output_path
-            );
+    info!(
+        "Wrote {} of {} log(s) to: {:?}",
+        targets.len() - failures,
+        targets.len(),
+        path
+    );
+    if failures > 0 {
+        anyhow::bail!("{} of {} log(s) failed", failures, targets.len());
+    }
+    Ok(())
+}
+
+/// Download and convert the log of a Tenhou replay URL or log ID
+#[cfg(feature = "http")]
+fn convert_url(
+    url: &str,
+    args: &ConvertArgs,
+    options: &ParserOptions,
+) -> Result<Vec<tenhou_log_parser::ParserOutput>> {
+    use tenhou_log_parser::fetch;
+
+    let id = fetch::tenhou_log_id(url)
+        .with_context(|| format!("Not a Tenhou log ID or replay URL: {}", url))?;
+    // Named after the log ID unless given
+    let output_path = (!args.stream).then(|| resolve_output_path(Path::new(id), args));
+    let options = ParserOptions {
+        game_id: Some(options.game_id.as_deref().unwrap_or(id).to_string()),
+        ..options.clone()
+    };
+    info!("Downloading log {}", id);
+    let mut output = fetch::fetch_tenhou_bytes(id)
+        .and_then(|bytes| tenhou_log_parser::parse_mjlog_with_options(bytes.as_slice(), &options))
+        .with_context(|| format!("Failed to fetch mjlog for {}", id))?;
+    write_converted(
+        std::slice::from_mut(&mut output),
+        output_path.as_deref(),
+        args,
+    )?;
+    let keep = args.stats.is_some() || args.emit_urls.is_some();
+    Ok(if keep { vec![output] } else { Vec::new() })
+}
+
+/// Output of `--url`, for `--checksum`
+fn url_output_path(args: &ConvertArgs) -> Option<PathBuf> {
+    #[cfg(feature = "http")]
+    if args.output.is_none() {
+        let id = tenhou_log_parser::fetch::tenhou_log_id(args.url.as_deref()?)?;
+        return Some(default_output_path(Path::new(id), args));
+    }
+    args.output.clone()
+}
+
+#[cfg(not(feature = "http"))]
+fn convert_url(
+    url: &str,
+    _args: &ConvertArgs,
+    _options: &ParserOptions,
+) -> Result<Vec<tenhou_log_parser::ParserOutput>> {
+    anyhow::bail!(
+        "Cannot fetch {}: URL inputs require building with `--features http`",
+        url
+    )
+}
+
+/// The input with a .json extension, or .json.gz with `--gzip`
+fn default_output_path(input: &Path, args: &ConvertArgs) -> PathBuf {
+    input.with_extension(if args.gzip { "json.gz" } else { "json" })
+}
+
+/// Output path for a conversion, `--output` or the default for the input
+///
+/// Exits when the file already exists and `force` is not set.
+fn resolve_output_path(input: &Path, args: &ConvertArgs) -> PathBuf {
+    let output_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output_path(input, args));
+
+    // Check if output file exists and force flag
+    if output_path.exists() && !args.force {
+        error!(
+            "Output file already exists: {:?}. Use --force to overwrite.",
+            output_path
+        );
+        std::process::exit(1);
+    }
+
+    output_path
+}
+
+fn run_validate(args: ValidateArgs) -> Result<()> {
+    check_input_exists(&args.input);
+
+    let output = match parse_mjlog_file(&args.input) {
+        Ok(output) => output,
+        Err(e) if args.json => {
+            // Keep stdout machine-readable when the log cannot be parsed at all
+            let error = serde_json::json!({ "error": e.report() });
+            println!("{}", serde_json::to_string_pretty(&error)?);
             std::process::exit(1);
         }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to parse mjlog from {:?}", args.input))
+        }
+    };
+    let report = validate(&output);
 
-        parse_file(&args.input, &output_path, &options).with_context(|| {
-            format!(
-                "Failed to parse mjlog from {:?} to {:?}",
-                args.input, output_path
-            )
+    if args.json {
+        serde_json::to_writer_pretty(std::io::stdout(), &report)
+            .context("Failed to write validation report")?;
+        println!();
+    } else {
+        for issue in &report.issues {
+            let location = match (issue.round, issue.event) {
+                (Some(round), Some(event)) => format!("round {} event {}: ", round, event),
+                (Some(round), None) => format!("round {}: ", round),
+                _ => String::new(),
+            };
+            println!(
+                "[{:?}] {:?} ({}): {}{}",
+                issue.severity,
+                issue.category,
+                issue.code.as_str(),
+                location,
+                issue.message
+            );
+        }
+        println!(
+            "{} error(s), {} warning(s)",
+            report.count(Severity::Error),
+            report.count(Severity::Warning)
+        );
+    }
+
+    if !report.is_valid() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn run_normalize(args: NormalizeArgs) -> Result<()> {
+    check_input_exists(&args.input);
+
+    match &args.output {
+        Some(path) => {
+            normalize::normalize_file(&args.input, path, args.gzip)
+                .with_context(|| format!("Failed to normalize {:?}", args.input))?;
+            info!("Wrote normalized log to: {:?}", path);
+        }
+        None => {
+            let input = tenhou_log_parser::open_input(&args.input)?;
+            normalize::normalize(input, std::io::stdout().lock())
+                .with_context(|| format!("Failed to normalize {:?}", args.input))?;
+        }
+    }
+    Ok(())
+}
+
+fn run_verify(args: VerifyArgs) -> Result<()> {
+    check_input_exists(&args.mjlog);
+    check_input_exists(&args.replay);
+
+    let output = parse_mjlog_file(&args.mjlog)
+        .with_context(|| format!("Failed to parse mjlog from {:?}", args.mjlog))?;
+    let replay: tenhou6::Tenhou6Log = open_input(&args.replay)
+        .map_err(anyhow::Error::from)
+        .and_then(|reader| Ok(serde_json::from_reader(reader)?))
+        .with_context(|| format!("Failed to read replay from {:?}", args.replay))?;
+    let mismatches = tenhou6::verify(&output, &replay);
+
+    if args.json {
+        serde_json::to_writer_pretty(std::io::stdout(), &mismatches)
+            .context("Failed to write mismatches")?;
+        println!();
+    } else {
+        for mismatch in &mismatches {
+            let location = match (mismatch.round, mismatch.seat) {
+                (Some(round), Some(seat)) => format!("round {} seat {}: ", round, seat),
+                (Some(round), None) => format!("round {}: ", round),
+                _ => String::new(),
+            };
+            println!(
+                "{}{}: mjlog {} != tenhou6 {}",
+                location, mismatch.field, mismatch.mjlog, mismatch.tenhou6
+            );
+        }
+        println!("{} mismatch(es)", mismatches.len());
+    }
+
+    if !mismatches.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Findings for one round, as printed by the `mistakes` subcommand
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RoundMistakes {
+    round: usize,
+    round_id: String,
+    mistakes: Vec<analysis::Mistake>,
+}
+
+fn run_mistakes(args: MistakesArgs) -> Result<()> {
+    check_input_exists(&args.input);
+
+    let output = parse_mjlog_file(&args.input)
+        .with_context(|| format!("Failed to parse mjlog from {:?}", args.input))?;
+    let rounds: Vec<RoundMistakes> = output
+        .rounds
+        .iter()
+        .enumerate()
+        .map(|(i, round)| RoundMistakes {
+            round: i,
+            round_id: round.round_id.clone(),
+            mistakes: analysis::detect_mistakes(round),
+        })
+        .filter(|r| !r.mistakes.is_empty())
+        .collect();
+
+    if args.json {
+        serde_json::to_writer_pretty(std::io::stdout(), &rounds)
+            .context("Failed to write findings")?;
+        println!();
+        return Ok(());
+    }
+
+    for round in &rounds {
+        println!("round {} ({}):", round.round, round.round_id);
+        for mistake in &round.mistakes {
+            let name = output
+                .players
+                .get(mistake.seat as usize)
+                .map_or("?", |p| p.player_id.as_str());
+            let against = match mistake.against {
+                Some(seat) => format!(" (seat {})", seat),
+                None => String::new(),
+            };
+            println!(
+                "  event {}: {} [seat {}] {:?} {}{}",
+                mistake.event, name, mistake.seat, mistake.kind, mistake.tile, against
+            );
+        }
+    }
+    let total: usize = rounds.iter().map(|r| r.mistakes.len()).sum();
+    println!("{} finding(s)", total);
+
+    Ok(())
+}
+
+/// One line of the `dataset` output
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DecisionRecord<'a> {
+    game_id: &'a str,
+    round: usize,
+    #[serde(flatten)]
+    decision: dataset::Decision,
+}
+
+fn run_dataset(args: DatasetArgs) -> Result<()> {
+    let mut writer: Box<dyn Write + Send> = match &args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create output file: {:?}", path))?,
+        )),
+        None => Box::new(std::io::stdout()),
+    };
+
+    if args.kind == DatasetKind::Discards {
+        let mut rows = Vec::new();
+        for_each_game(&args.inputs, &args.batch, |output| {
+            rows.extend(dataset::discard_rows(&output));
+            Ok(())
         })?;
+        if has_extension(args.output.as_deref(), "parquet") {
+            #[cfg(feature = "parquet")]
+            dataset::discards::write_parquet(&rows, writer)
+                .context("Failed to write Parquet dataset")?;
+            #[cfg(not(feature = "parquet"))]
+            anyhow::bail!("Parquet output requires building with `--features parquet`");
+        } else {
+            dataset::discards::write_csv(&rows, &mut writer).context("Failed to write dataset")?;
+            writer.flush()?;
+        }
+        if let Some(path) = &args.output {
+            info!("Wrote {} discard(s) to: {:?}", rows.len(), path);
+        }
+        return Ok(());
+    }
+
+    if matches!(args.kind, DatasetKind::Events | DatasetKind::Rounds) {
+        let mut events = Vec::new();
+        let mut rounds = Vec::new();
+        for_each_game(&args.inputs, &args.batch, |output| {
+            match args.kind {
+                DatasetKind::Events => events.extend(dataset::event_rows(&output)),
+                _ => rounds.extend(dataset::round_rows(&output)),
+            }
+            Ok(())
+        })?;
+        let (table, name) = match args.kind {
+            DatasetKind::Events => (dataset::events_table(&events), "event"),
+            _ => (dataset::rounds_table(&rounds), "round"),
+        };
+        write_table(&table, args.output.as_deref(), writer)?;
+        if let Some(path) = &args.output {
+            info!("Wrote {} {}(s) to: {:?}", table.rows(), name, path);
+        }
+        return Ok(());
+    }
+
+    if has_extension(args.output.as_deref(), "npz") {
+        #[cfg(feature = "npz")]
+        return write_npz_dataset(&args, writer);
+        #[cfg(not(feature = "npz"))]
+        anyhow::bail!("NumPy output requires building with `--features npz`");
+    }
+
+    let mut count = 0;
+    for_each_game(&args.inputs, &args.batch, |output| {
+        for (round, r) in output.rounds.iter().enumerate() {
+            for decision in dataset::decisions(r) {
+                let record = DecisionRecord {
+                    game_id: &output.game_id,
+                    round,
+                    decision,
+                };
+                serde_json::to_writer(&mut writer, &record).context("Failed to write decision")?;
+                writeln!(writer)?;
+                count += 1;
+            }
+        }
+        Ok(())
+    })?;
+    writer.flush()?;
+
+    if let Some(path) = &args.output {
+        info!("Wrote {} decision(s) to: {:?}", count, path);
+    }
+    Ok(())
+}
+
+/// Write a dataset table in the format named by the output's extension
+fn write_table(
+    table: &dataset::Table,
+    output: Option<&Path>,
+    mut writer: Box<dyn Write + Send>,
+) -> Result<()> {
+    if has_extension(output, "parquet") {
+        #[cfg(feature = "parquet")]
+        return table
+            .write_parquet(writer)
+            .context("Failed to write Parquet dataset");
+        #[cfg(not(feature = "parquet"))]
+        anyhow::bail!("Parquet output requires building with `--features parquet`");
+    }
+    if has_extension(output, "arrow") || has_extension(output, "feather") {
+        #[cfg(feature = "arrow")]
+        return table
+            .write_arrow(writer)
+            .context("Failed to write Arrow dataset");
+        #[cfg(not(feature = "arrow"))]
+        anyhow::bail!("Arrow output requires building with `--features arrow`");
+    }
+    table
+        .write_delimited(&mut writer, ',')
+        .context("Failed to write dataset")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Encode every decision point into a NumPy `.npz` archive
+#[cfg(feature = "npz")]
+fn write_npz_dataset(args: &DatasetArgs, mut writer: Box<dyn Write + Send>) -> Result<()> {
+    let mut encoded = dataset::EncodedDecisions::new();
+    let mut game = 0;
+    for_each_game(&args.inputs, &args.batch, |output| {
+        for (round, r) in output.rounds.iter().enumerate() {
+            for decision in dataset::decisions(r) {
+                encoded.push(game, round, &decision);
+            }
+        }
+        game += 1;
+        Ok(())
+    })?;
+
+    // The archive needs a seekable writer
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    encoded
+        .write_npz(&mut buffer)
+        .context("Failed to write NumPy dataset")?;
+    writer.write_all(buffer.get_ref())?;
+    writer.flush()?;
+
+    if let Some(path) = &args.output {
+        info!("Wrote {} decision(s) to: {:?}", encoded.len, path);
+    }
+    Ok(())
+}
+
+fn has_extension(path: Option<&Path>, extension: &str) -> bool {
+    path.and_then(|p| p.extension())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
 
-        info!("Successfully parsed mjlog to: {:?}", output_path);
+/// Parse every input in order, tracking duplicates in a manifest
+fn for_each_game(
+    inputs: &[PathBuf],
+    batch: &BatchArgs,
+    mut f: impl FnMut(tenhou_log_parser::ParserOutput) -> Result<()>,
+) -> Result<()> {
+    let mut manifest = Manifest::new();
+    for source in batch_sources(inputs, batch)? {
+        let (name, output) = match source {
+            Source::File(input) => (
+                input.display().to_string(),
+                parse_mjlog_file(&input)
+                    .with_context(|| format!("Failed to parse mjlog from {:?}", input))?,
+            ),
+            Source::Url(url) => {
+                let output = fetch_url(&url)?;
+                (url, output)
+            }
+        };
+        if !manifest.add(name.clone(), &output) && batch.skip_duplicates {
+            info!("Skipping duplicate: {}", name);
+            continue;
+        }
+        f(output)?;
     }
 
+    if manifest.duplicate_count() > 0 {
+        info!(
+            "{} duplicate file(s) in {} cluster(s)",
+            manifest.duplicate_count(),
+            manifest.duplicates.len()
+        );
+    }
+    if let Some(path) = &batch.manifest {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create manifest: {:?}", path))?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &manifest)
+            .context("Failed to write manifest")?;
+    }
+    Ok(())
+}
+
+/// Where one game of a batch comes from
+enum Source {
+    File(PathBuf),
+    Url(String),
+}
+
+/// The inputs given as arguments followed by those of `--input-list`
+fn batch_sources(inputs: &[PathBuf], batch: &BatchArgs) -> Result<Vec<Source>> {
+    let mut sources: Vec<Source> = expand_inputs(inputs)?
+        .into_iter()
+        .map(Source::File)
+        .collect();
+    let Some(list) = &batch.input_list else {
+        return Ok(sources);
+    };
+
+    for entry in read_input_list(list)? {
+        match entry {
+            InputEntry::Path(path) => {
+                sources.extend(expand_inputs(&[path])?.into_iter().map(Source::File))
+            }
+            InputEntry::Glob(pattern) => {
+                let matches = input_list::glob(&pattern)
+                    .with_context(|| format!("Failed to expand {:?}", pattern))?;
+                if matches.is_empty() {
+                    warn!("No inputs match {:?}", pattern);
+                }
+                let matches: Vec<PathBuf> = matches
+                    .into_iter()
+                    .filter(|p| p.is_dir() || is_mjlog_path(p))
+                    .collect();
+                sources.extend(expand_inputs(&matches)?.into_iter().map(Source::File));
+            }
+            InputEntry::Url(url) => sources.push(Source::Url(url)),
+        }
+    }
+    Ok(sources)
+}
+
+/// The entries of an `--input-list` file, or of stdin for "-"
+fn read_input_list(list: &Path) -> Result<Vec<InputEntry>> {
+    if list.as_os_str() == "-" {
+        input_list::read_input_list(std::io::stdin().lock())
+    } else {
+        let file = std::fs::File::open(list)
+            .with_context(|| format!("Failed to open input list: {:?}", list))?;
+        input_list::read_input_list(std::io::BufReader::new(file))
+    }
+    .with_context(|| format!("Failed to read input list: {:?}", list))
+}
+
+#[cfg(feature = "http")]
+fn fetch_url(url: &str) -> Result<tenhou_log_parser::ParserOutput> {
+    tenhou_log_parser::fetch::fetch_log(url)
+        .with_context(|| format!("Failed to fetch mjlog from {}", url))
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_url(url: &str) -> Result<tenhou_log_parser::ParserOutput> {
+    anyhow::bail!(
+        "Cannot fetch {}: URL inputs require building with `--features http`",
+        url
+    )
+}
+
+/// Recursively expand directories into the mjlog files they contain
+fn expand_inputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read directory: {:?}", path))?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort();
+            let nested: Vec<PathBuf> = entries
+                .into_iter()
+                .filter(|p| p.is_dir() || is_mjlog_path(p))
+                .collect();
+            files.extend(expand_inputs(&nested)?);
+        } else {
+            check_input_exists(path);
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.contains(['*', '?']))
+}
+
+fn is_mjlog_path(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|s| s.to_str()),
+        Some("xml" | "mjlog" | "gz")
+    )
+}
+
+fn run_stats(args: StatsArgs) -> Result<()> {
+    let mut games = Vec::new();
+    for_each_game(&args.inputs, &args.batch, |output| {
+        games.push(output);
+        Ok(())
+    })?;
+
+    let mut report = stats::collect(&games);
+    if let Some([player, opponent]) = args.head_to_head.as_deref() {
+        report.head_to_head = Some(stats::head_to_head(&games, player, opponent));
+    }
+
+    if let Some(path) = &args.heatmap {
+        let heatmap = stats::discard_heatmap(&games, args.player.as_deref());
+        write_export(&heatmap, path).context("Failed to write heatmap")?;
+        info!("Wrote discard heatmap to: {:?}", path);
+    }
+
+    if let Some(path) = &args.session {
+        write_export(&stats::session(&games), path).context("Failed to write session")?;
+        info!("Wrote session standings to: {:?}", path);
+    }
+
+    if let Some(path) = &args.yaku {
+        write_export(&report.yaku, path).context("Failed to write yaku frequencies")?;
+        info!("Wrote yaku frequencies to: {:?}", path);
+    }
+
+    match args.output {
+        Some(path) => {
+            write_export(&report, &path).context("Failed to write statistics")?;
+            info!(
+                "Wrote statistics for {} game(s) to: {:?}",
+                games.len(),
+                path
+            );
+        }
+        None => {
+            report
+                .write_json(std::io::stdout())
+                .context("Failed to write statistics")?;
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+/// Write statistics to `path` as CSV or JSON depending on its extension
+fn write_export<T: Export>(value: &T, path: &Path) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create output file: {:?}", path))?;
+    value.export(ExportFormat::from_path(path), std::io::BufWriter::new(file))?;
     Ok(())
 }
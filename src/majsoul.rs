@@ -0,0 +1,465 @@
+//! Conversion to the MahjongSoul game record layout
+//!
+//! The output mirrors the JSON form of MahjongSoul's `GameDetailRecords`
+//! protobuf as produced by community record downloaders: a `head` with the
+//! players and a list of `{ "name": ".lq.RecordXxx", "data": { … } }` actions
+//! using the protobuf's snake_case field names.
+//!
+//! Tenhou logs don't carry everything MahjongSoul records do. Red fives are
+//! written as ordinary fives, yaku (`fans`) are omitted and tenpai at an
+//! exhaustive draw is inferred from the noten payments.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Event, KanType, ParserOutput, Round, RyuukyokuReason};
+use crate::stats::SCORE_UNIT;
+use crate::tile::tile_id_to_string;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MajsoulRecord {
+    pub head: MajsoulHead,
+    pub data: MajsoulData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MajsoulHead {
+    pub uuid: String,
+    pub accounts: Vec<MajsoulAccount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MajsoulAccount {
+    pub seat: u8,
+    pub nickname: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MajsoulData {
+    pub records: Vec<MajsoulAction>,
+}
+
+/// Riichi acceptance, attached to the action following the declaration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiqiSuccess {
+    pub seat: u8,
+    pub score: i32,
+    pub liqibang: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HuleInfo {
+    pub seat: u8,
+    pub zimo: bool,
+    /// Han
+    pub count: u32,
+    pub fu: u32,
+    pub point_sum: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoTilePlayer {
+    pub tingpai: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoTileScore {
+    pub old_scores: Vec<i32>,
+    pub delta_scores: Vec<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "name", content = "data")]
+pub enum MajsoulAction {
+    #[serde(rename = ".lq.RecordNewRound")]
+    NewRound {
+        chang: u32,
+        ju: u32,
+        ben: u32,
+        liqibang: u32,
+        dora: String,
+        doras: Vec<String>,
+        scores: Vec<i32>,
+        tiles0: Vec<String>,
+        tiles1: Vec<String>,
+        tiles2: Vec<String>,
+        tiles3: Vec<String>,
+    },
+    #[serde(rename = ".lq.RecordDealTile")]
+    DealTile {
+        seat: u8,
+        tile: String,
+        #[serde(skip_serializing_if = "Vec::is_empty", default)]
+        doras: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        liqi: Option<LiqiSuccess>,
+    },
+    #[serde(rename = ".lq.RecordDiscardTile")]
+    DiscardTile {
+        seat: u8,
+        tile: String,
+        is_liqi: bool,
+        /// Tsumogiri: the tile just drawn was discarded
+        moqie: bool,
+    },
+    #[serde(rename = ".lq.RecordChiPengGang")]
+    ChiPengGang {
+        seat: u8,
+        /// 0 chi, 1 pon, 2 open kan
+        #[serde(rename = "type")]
+        kind: u8,
+        tiles: Vec<String>,
+        froms: Vec<u8>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        liqi: Option<LiqiSuccess>,
+    },
+    #[serde(rename = ".lq.RecordAnGangAddGang")]
+    AnGangAddGang {
+        seat: u8,
+        /// 2 added kan, 3 closed kan
+        #[serde(rename = "type")]
+        kind: u8,
+        tiles: String,
+    },
+    #[serde(rename = ".lq.RecordHule")]
+    Hule {
+        hules: Vec<HuleInfo>,
+        old_scores: Vec<i32>,
+        delta_scores: Vec<i32>,
+        scores: Vec<i32>,
+    },
+    #[serde(rename = ".lq.RecordNoTile")]
+    NoTile {
+        liujumanguan: bool,
+        players: Vec<NoTilePlayer>,
+        scores: Vec<NoTileScore>,
+    },
+    #[serde(rename = ".lq.RecordLiuJu")]
+    LiuJu {
+        /// 1 nine terminals, 2 four winds, 3 four kans, 4 four riichi, 5 triple ron
+        #[serde(rename = "type")]
+        kind: u8,
+    },
+}
+
+/// MahjongSoul tile notation: honors become `1z`..`7z`
+pub fn majsoul_tile(tile: &str) -> String {
+    let honor = match tile {
+        "east" => "1z",
+        "south" => "2z",
+        "west" => "3z",
+        "north" => "4z",
+        "white" => "5z",
+        "green" => "6z",
+        "red" => "7z",
+        other => other,
+    };
+    honor.to_string()
+}
+
+/// Convert a parsed Tenhou log into a MahjongSoul record
+pub fn to_majsoul(output: &ParserOutput) -> MajsoulRecord {
+    let accounts = output
+        .players
+        .iter()
+        .map(|p| MajsoulAccount {
+            seat: p.seat,
+            nickname: p.player_id.clone(),
+        })
+        .collect();
+
+    let mut records = Vec::new();
+    for round in &output.rounds {
+        convert_round(round, &mut records);
+    }
+
+    MajsoulRecord {
+        head: MajsoulHead {
+            uuid: output.game_id.clone(),
+            accounts,
+        },
+        data: MajsoulData { records },
+    }
+}
+
+fn convert_round(round: &Round, records: &mut Vec<MajsoulAction>) {
+    let init = &round.init;
+    let mut scores: Vec<i32> = init.initial_scores.iter().map(|s| s * SCORE_UNIT).collect();
+    let mut liqibang = init.kyoutaku;
+    let dora = majsoul_tile(&tile_id_to_string(init.dora_indicator));
+    let mut doras = vec![dora.clone()];
+    let hand = |seat: usize| -> Vec<String> {
+        init.initial_hands
+            .get(seat)
+            .map(|h| h.iter().map(|t| majsoul_tile(t)).collect())
+            .unwrap_or_default()
+    };
+
+    records.push(MajsoulAction::NewRound {
+        chang: init.round_number / 4,
+        ju: init.round_number % 4,
+        ben: init.honba,
+        liqibang,
+        dora,
+        doras: doras.clone(),
+        scores: scores.clone(),
+        tiles0: hand(0),
+        tiles1: hand(1),
+        tiles2: hand(2),
+        tiles3: hand(3),
+    });
+
+    let mut last_draw: [Option<&str>; 4] = [None; 4];
+    let mut declaring = [false; 4];
+    let mut new_dora = false;
+    let mut liqi: Option<LiqiSuccess> = None;
+    let mut events = round.events.iter().peekable();
+
+    while let Some(event) = events.next() {
+        match event {
+            Event::Draw { seat, tile, .. } => {
+                last_draw[*seat as usize % 4] = Some(tile);
+                records.push(MajsoulAction::DealTile {
+                    seat: *seat,
+                    tile: majsoul_tile(tile),
+                    doras: if std::mem::take(&mut new_dora) {
+                        doras.clone()
+                    } else {
+                        Vec::new()
+                    },
+                    liqi: liqi.take(),
+                });
+            }
+            Event::Discard { seat, tile, .. } => {
+                let s = *seat as usize % 4;
+                records.push(MajsoulAction::DiscardTile {
+                    seat: *seat,
+                    tile: majsoul_tile(tile),
+                    is_liqi: std::mem::take(&mut declaring[s]),
+                    moqie: last_draw[s].take() == Some(tile.as_str()),
+                });
+            }
+            Event::Reach { who, step: 1, .. } => declaring[*who as usize % 4] = true,
+            Event::Reach { who, step: 2, .. } => {
+                let s = *who as usize % 4;
+                scores[s] -= 10 * SCORE_UNIT;
+                liqibang += 1;
+                liqi = Some(LiqiSuccess {
+                    seat: *who,
+                    score: scores[s],
+                    liqibang,
+                });
+            }
+            Event::Reach { .. } => {}
+            Event::Chi {
+                who, tiles, from, ..
+            }
+            | Event::Pon {
+                who, tiles, from, ..
+            } => {
+                last_draw[*who as usize % 4] = None;
+                records.push(MajsoulAction::ChiPengGang {
+                    seat: *who,
+                    kind: if matches!(event, Event::Chi { .. }) {
+                        0
+                    } else {
+                        1
+                    },
+                    tiles: tiles.iter().map(|t| majsoul_tile(t)).collect(),
+                    froms: vec![*who, *who, *from],
+                    liqi: liqi.take(),
+                });
+            }
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                from,
+                ..
+            } => match kan_type {
+                KanType::Minkan => {
+                    let mut froms = vec![*who; tiles.len()];
+                    if let (Some(last), Some(from)) = (froms.last_mut(), from) {
+                        *last = *from;
+                    }
+                    records.push(MajsoulAction::ChiPengGang {
+                        seat: *who,
+                        kind: 2,
+                        tiles: tiles.iter().map(|t| majsoul_tile(t)).collect(),
+                        froms,
+                        liqi: liqi.take(),
+                    });
+                }
+                KanType::Ankan | KanType::Kakan => {
+                    records.push(MajsoulAction::AnGangAddGang {
+                        seat: *who,
+                        kind: if matches!(kan_type, KanType::Ankan) {
+                            3
+                        } else {
+                            2
+                        },
+                        tiles: tiles.first().map(|t| majsoul_tile(t)).unwrap_or_default(),
+                    });
+                }
+            },
+            Event::Dora { indicator, .. } => {
+                doras.push(majsoul_tile(indicator));
+                new_dora = true;
+            }
+            Event::Agari { .. } => {
+                // A double ron is a single record with one entry per winner
+                let old_scores = scores.clone();
+                let mut hules = Vec::new();
+                let mut delta_scores = vec![0; 4];
+                let mut current = Some(event);
+                while let Some(Event::Agari {
+                    who,
+                    from,
+                    han,
+                    fu,
+                    scores: deltas,
+                    ..
+                }) = current
+                {
+                    hules.push(HuleInfo {
+                        seat: *who,
+                        zimo: who == from,
+                        count: *han,
+                        fu: *fu,
+                        point_sum: deltas[*who as usize % 4] * SCORE_UNIT,
+                    });
+                    for (total, delta) in delta_scores.iter_mut().zip(deltas) {
+                        *total += delta * SCORE_UNIT;
+                    }
+                    current = events.next_if(|e| matches!(e, Event::Agari { .. }));
+                }
+                for (score, delta) in scores.iter_mut().zip(&delta_scores) {
+                    *score += delta;
+                }
+                records.push(MajsoulAction::Hule {
+                    hules,
+                    old_scores,
+                    delta_scores,
+                    scores: scores.clone(),
+                });
+            }
+            Event::Ryuukyoku {
+                reason,
+                scores: deltas,
+                tenpai,
+                ..
+            } => {
+                let kind = match reason {
+                    RyuukyokuReason::Normal => {
+                        let delta_scores: Vec<i32> =
+                            deltas.iter().map(|d| d * SCORE_UNIT).collect();
+                        let paid = delta_scores.iter().any(|&d| d != 0);
+                        // Older logs lack the hands; then a noten payment
+                        // tells who was tenpai
+                        let shown = tenpai.iter().any(Option::is_some);
+                        records.push(MajsoulAction::NoTile {
+                            liujumanguan: false,
+                            players: delta_scores
+                                .iter()
+                                .zip(tenpai)
+                                .map(|(&d, hand)| NoTilePlayer {
+                                    tingpai: if shown { hand.is_some() } else { paid && d > 0 },
+                                })
+                                .collect(),
+                            scores: vec![NoTileScore {
+                                old_scores: scores.clone(),
+                                delta_scores,
+                            }],
+                        });
+                        continue;
+                    }
+                    RyuukyokuReason::Yao9 => 1,
+                    RyuukyokuReason::Kaze4 => 2,
+                    RyuukyokuReason::Kan4 => 3,
+                    RyuukyokuReason::Reach4 => 4,
+                    RyuukyokuReason::Ron3 => 5,
+                };
+                records.push(MajsoulAction::LiuJu { kind });
+            }
+            // MahjongSoul records have no equivalent
+            Event::Disconnect { .. } | Event::Reconnect { .. } | Event::Unknown { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_to_majsoul() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="5,1,0,1,2,124" ten="250,250,250,250" oya="1" hai0="0,4,8,12,16,20,24,28,32,36,40,44,108" hai1="" hai2="" hai3=""/>
+<T52/><REACH who="0" step="1"/><D52/><REACH who="0" step="2" ten="240,250,250,250"/>
+<U53/><E53/>
+<AGARI who="0" fromWho="1" ten="40,3900,0" sc="240,49,250,-39,250,0,250,0"/>
+<AGARI who="2" fromWho="1" ten="30,1000,0" sc="289,0,211,-10,250,10,250,0"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let record = to_majsoul(&game);
+
+        assert_eq!(record.head.accounts.len(), 4);
+        assert_eq!(record.head.accounts[2].nickname, "C");
+
+        let actions = &record.data.records;
+        assert_eq!(actions.len(), 6);
+        match &actions[0] {
+            MajsoulAction::NewRound {
+                chang,
+                ju,
+                dora,
+                tiles0,
+                ..
+            } => {
+                assert_eq!((*chang, *ju), (1, 1));
+                assert_eq!(dora, "5z");
+                assert_eq!(tiles0.last().map(String::as_str), Some("1z"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            actions[2],
+            MajsoulAction::DiscardTile {
+                seat: 0,
+                tile: "5p".into(),
+                is_liqi: true,
+                moqie: true,
+            }
+        );
+        match &actions[3] {
+            MajsoulAction::DealTile { liqi, .. } => assert_eq!(
+                liqi,
+                &Some(LiqiSuccess {
+                    seat: 0,
+                    score: 24000,
+                    liqibang: 1,
+                })
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+        match &actions[5] {
+            MajsoulAction::Hule {
+                hules,
+                delta_scores,
+                scores,
+                ..
+            } => {
+                assert_eq!(hules.len(), 2);
+                assert_eq!(hules[0].point_sum, 4900);
+                assert_eq!(delta_scores, &vec![4900, -4900, 1000, 0]);
+                assert_eq!(scores, &vec![28900, 20100, 26000, 25000]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["data"]["records"][0]["name"], ".lq.RecordNewRound");
+        assert_eq!(json["data"]["records"][2]["data"]["is_liqi"], true);
+    }
+}
@@ -0,0 +1,183 @@
+//! Batch manifests and duplicate detection
+//!
+//! The same game is often downloaded more than once under different file
+//! names. Each parsed game gets a content hash over its rules, players and
+//! rounds (the generated `gameId` and parser warnings are left out), so
+//! copies hash the same regardless of where they came from.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::{ParserOutput, Player, Round, Rules};
+
+/// The parts of a game that identify it
+#[derive(Serialize)]
+struct Canonical<'a> {
+    rules: &'a Rules,
+    players: &'a [Player],
+    rounds: &'a [Round],
+}
+
+/// SHA-256 of the game's canonical JSON, as lowercase hex
+pub fn content_hash(game: &ParserOutput) -> String {
+    let canonical = Canonical {
+        rules: &game.rules,
+        players: &game.players,
+        rounds: &game.rounds,
+    };
+    let json = serde_json::to_vec(&canonical).expect("models always serialize");
+    hex(&Sha256::digest(json))
+}
+
+/// SHA-256 of a file's bytes, as lowercase hex
+pub fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Write `<path>.sha256` next to `path` in the format `sha256sum -c` reads,
+/// returning the sidecar's path
+pub fn write_checksum(path: &Path) -> std::io::Result<PathBuf> {
+    let hash = file_hash(path)?;
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".sha256");
+    let sidecar = PathBuf::from(sidecar);
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let mut file = std::fs::File::create(&sidecar)?;
+    writeln!(file, "{}  {}", hash, name)?;
+    Ok(sidecar)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// One input file of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub path: String,
+    pub hash: String,
+    pub rounds: usize,
+    /// First file in the batch with the same content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+}
+
+/// Inputs of a batch run and the duplicates among them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+    /// Paths sharing a content hash, first occurrence first; only clusters of
+    /// two or more files are listed
+    pub duplicates: Vec<Vec<String>>,
+    #[serde(skip)]
+    first_seen: BTreeMap<String, usize>,
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a parsed file; returns false if it duplicates an earlier one
+    pub fn add(&mut self, path: impl Into<String>, game: &ParserOutput) -> bool {
+        let path = path.into();
+        let hash = content_hash(game);
+        let duplicate_of = self
+            .first_seen
+            .get(&hash)
+            .map(|&i| self.files[i].path.clone());
+
+        match &duplicate_of {
+            Some(first) => match self.duplicates.iter_mut().find(|c| &c[0] == first) {
+                Some(cluster) => cluster.push(path.clone()),
+                None => self.duplicates.push(vec![first.clone(), path.clone()]),
+            },
+            None => {
+                self.first_seen.insert(hash.clone(), self.files.len());
+            }
+        }
+
+        let unique = duplicate_of.is_none();
+        self.files.push(ManifestEntry {
+            path,
+            hash,
+            rounds: game.rounds.len(),
+            duplicate_of,
+        });
+        unique
+    }
+
+    /// Number of files that duplicate an earlier one
+    pub fn duplicate_count(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|f| f.duplicate_of.is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{all_last, game};
+
+    /// The same game except for how many points seat 0 starts with
+    fn starting_at(score: u32) -> ParserOutput {
+        let body = all_last(&format!("{},250,250,250", score), "<T0/><D0/>");
+        game(["A", "B", "C", "D"], &body)
+    }
+
+    #[test]
+    fn test_content_hash_ignores_game_id() {
+        let (a, b) = (starting_at(250), starting_at(250));
+        assert_ne!(a.game_id, b.game_id);
+        assert_eq!(content_hash(&a), content_hash(&b));
+        assert_ne!(content_hash(&a), content_hash(&starting_at(240)));
+        assert_eq!(content_hash(&a).len(), 64);
+    }
+
+    #[test]
+    fn test_write_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.json");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let sidecar = write_checksum(&path).unwrap();
+        assert_eq!(sidecar, dir.path().join("out.json.sha256"));
+        assert_eq!(
+            std::fs::read_to_string(sidecar).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  out.json\n"
+        );
+    }
+
+    #[test]
+    fn test_manifest_clusters() {
+        let mut manifest = Manifest::new();
+        assert!(manifest.add("a.xml", &starting_at(250)));
+        assert!(manifest.add("b.xml", &starting_at(240)));
+        assert!(!manifest.add("a copy.xml", &starting_at(250)));
+        assert!(!manifest.add("a again.xml", &starting_at(250)));
+
+        assert_eq!(manifest.duplicate_count(), 2);
+        assert_eq!(
+            manifest.duplicates,
+            vec![vec!["a.xml", "a copy.xml", "a again.xml"]]
+        );
+        assert_eq!(manifest.files[2].duplicate_of.as_deref(), Some("a.xml"));
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert!(json["files"][0].get("duplicateOf").is_none());
+        assert_eq!(json["files"][3]["duplicateOf"], "a.xml");
+    }
+}
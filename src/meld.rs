@@ -0,0 +1,228 @@
+//! Decoding of the `m` bitfield of `<N>` tags
+//!
+//! Tenhou packs a call into 16 bits. The low two bits give the seat the
+//! tile came from, counted counter-clockwise from the caller (1 = next
+//! seat, 3 = previous seat, 0 = the caller itself). The rest depends on the
+//! call:
+//!
+//! - bit 2 set: chi; bits 10-15 hold `base * 3 + called` and bits 3-8 the
+//!   copy (0-3) of each of the three tiles
+//! - bit 3 set: pon, and bit 4 set: an added kan; bits 9-15 hold
+//!   `type * 3 + called` and bits 5-6 the copy left out of the pon
+//! - bit 5 set: a north tile set aside in three-player games
+//! - otherwise: a closed or open kan; bits 8-15 hold the called tile ID
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ParserError, Result};
+use crate::models::{Event, KanType};
+use crate::tile::Tile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MeldKind {
+    Chi,
+    Pon,
+    Ankan,
+    Minkan,
+    Kakan,
+    /// North set aside as a bonus tile in three-player games
+    Nukidora,
+}
+
+/// A decoded call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Meld {
+    pub kind: MeldKind,
+    /// Tile IDs of the meld in tile order; for an added kan the added tile
+    /// comes first
+    pub tiles: Vec<u32>,
+    /// Seat offset of the player the tile came from, 0 for the caller
+    pub from: u8,
+}
+
+impl Meld {
+    /// Decode an `m` attribute
+    pub fn decode(m: u32) -> Result<Self> {
+        if m > 0xFFFF {
+            return Err(ParserError::invalid_format(format!(
+                "meld code {} does not fit in 16 bits",
+                m
+            )));
+        }
+        let from = (m & 3) as u8;
+
+        if m & 0x4 != 0 {
+            let base = (m >> 10) / 3;
+            // Sequences start at 1-7 of one of the three suits
+            let first = (base / 7 * 9 + base % 7) * 4;
+            let tiles = (0..3)
+                .map(|i| first + 4 * i + ((m >> (3 + 2 * i)) & 3))
+                .collect();
+            return Ok(Self {
+                kind: MeldKind::Chi,
+                tiles,
+                from,
+            });
+        }
+
+        if m & 0x18 != 0 {
+            let first = (m >> 9) / 3 * 4;
+            let unused = (m >> 5) & 3;
+            let copies = (0..4).map(|i| first + i);
+            if m & 0x8 != 0 {
+                return Ok(Self {
+                    kind: MeldKind::Pon,
+                    tiles: copies.filter(|&id| id != first + unused).collect(),
+                    from,
+                });
+            }
+            let tiles = std::iter::once(first + unused)
+                .chain(copies.filter(|&id| id != first + unused))
+                .collect();
+            return Ok(Self {
+                kind: MeldKind::Kakan,
+                tiles,
+                from,
+            });
+        }
+
+        if m & 0x20 != 0 {
+            return Ok(Self {
+                kind: MeldKind::Nukidora,
+                tiles: vec![(m >> 8) & 0xFF],
+                from,
+            });
+        }
+
+        let first = (m >> 8) / 4 * 4;
+        Ok(Self {
+            kind: if from == 0 {
+                MeldKind::Ankan
+            } else {
+                MeldKind::Minkan
+            },
+            tiles: (first..first + 4).collect(),
+            from,
+        })
+    }
+
+    /// Seat the tile came from, for a call made by `who`
+    pub fn from_seat(&self, who: u8) -> u8 {
+        (who + self.from) % 4
+    }
+
+    pub fn to_tiles(&self) -> Vec<Tile> {
+        self.tiles.iter().map(|&id| Tile::from_id(id)).collect()
+    }
+
+    pub fn tile_strings(&self) -> Vec<String> {
+        self.to_tiles().iter().map(Tile::to_string).collect()
+    }
+
+    /// The event for this call made by `who`; `None` for a set-aside north,
+    /// which no event models
+    pub fn to_event(&self, who: u8) -> Option<Event> {
+        let tiles = self.to_tiles();
+        let three =
+            |tiles: Vec<Tile>| -> [Tile; 3] { tiles.try_into().unwrap_or([Tile::HIDDEN; 3]) };
+        let (kan_type, from) = match self.kind {
+            MeldKind::Chi => {
+                return Some(Event::Chi {
+                    who,
+                    tiles: three(tiles),
+                    from: self.from_seat(who),
+                    claimed: None,
+                })
+            }
+            MeldKind::Pon => {
+                return Some(Event::Pon {
+                    who,
+                    tiles: three(tiles),
+                    from: self.from_seat(who),
+                    claimed: None,
+                })
+            }
+            MeldKind::Nukidora => return None,
+            MeldKind::Ankan => (KanType::Ankan, None),
+            MeldKind::Kakan => (KanType::Kakan, None),
+            MeldKind::Minkan => (KanType::Minkan, Some(self.from_seat(who))),
+        };
+        Some(Event::Kan {
+            who,
+            tiles,
+            kan_type,
+            from,
+            claimed: None,
+            dora: None,
+            rinshan: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(meld: &Meld) -> Vec<String> {
+        meld.tile_strings()
+    }
+
+    #[test]
+    fn test_decode_chi() {
+        // 3p 4p 5p (red) called from the previous seat
+        let m = ((9 * 3 + 2) << 10) | 0x4 | 3;
+        let meld = Meld::decode(m).unwrap();
+        assert_eq!(meld.kind, MeldKind::Chi);
+        assert_eq!(meld.tiles, vec![44, 48, 52]);
+        assert_eq!(strings(&meld), vec!["3p", "4p", "5p"]);
+        assert_eq!(meld.from_seat(0), 3);
+    }
+
+    #[test]
+    fn test_decode_pon_and_kakan() {
+        // Pon of east from the previous seat, copy 0 left out
+        let meld = Meld::decode(41483).unwrap();
+        assert_eq!(meld.kind, MeldKind::Pon);
+        assert_eq!(meld.tiles, vec![109, 110, 111]);
+        assert_eq!(meld.from_seat(3), 2);
+
+        // The same pon upgraded with the left-out copy
+        let meld = Meld::decode(41483 ^ 0x8 ^ 0x10).unwrap();
+        assert_eq!(meld.kind, MeldKind::Kakan);
+        assert_eq!(meld.tiles, vec![108, 109, 110, 111]);
+    }
+
+    #[test]
+    fn test_decode_kans() {
+        let ankan = Meld::decode(20 << 8).unwrap();
+        assert_eq!(ankan.kind, MeldKind::Ankan);
+        assert_eq!(strings(&ankan), vec!["6m"; 4]);
+        assert!(matches!(
+            ankan.to_event(2),
+            Some(Event::Kan {
+                kan_type: KanType::Ankan,
+                from: None,
+                ..
+            })
+        ));
+
+        let minkan = Meld::decode((131 << 8) | 2).unwrap();
+        assert_eq!(minkan.kind, MeldKind::Minkan);
+        assert_eq!(minkan.tiles, vec![128, 129, 130, 131]);
+        assert!(matches!(
+            minkan.to_event(3),
+            Some(Event::Kan {
+                kan_type: KanType::Minkan,
+                from: Some(1),
+                ..
+            })
+        ));
+
+        assert!(Meld::decode(0x20 | (120 << 8))
+            .unwrap()
+            .to_event(0)
+            .is_none());
+        assert!(Meld::decode(0x10000).is_err());
+    }
+}
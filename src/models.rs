@@ -1,6 +1,15 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::tile::Tile;
+
+/// A parsed game
+///
+/// Serialized by hand so that spilled rounds can be streamed in place of
+/// `rounds`; the fields keep the names and order they are declared with.
+#[derive(Debug, Clone, Deserialize)]
 pub struct ParserOutput {
     #[serde(rename = "mjlogVersion")]
     pub mjlog_version: String,
@@ -9,9 +18,167 @@ pub struct ParserOutput {
     pub rules: Rules,
     pub players: Vec<Player>,
     pub rounds: Vec<Round>,
+    /// Play went past the scheduled last round wind (延長戦)
+    #[serde(default)]
+    pub overtime: bool,
+    /// How the game ended, when the source tells
+    #[serde(rename = "endReason", default, skip_serializing_if = "Option::is_none")]
+    pub end_reason: Option<EndReason>,
+    /// Final scores and placements, when the log reaches the end of the game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<GameResult>,
+    /// Everything that made the output less complete or less reliable than
+    /// the log, in the order it was found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ParseWarning>,
+    /// Seats whose score after a round does not match the score changes
+    /// logged in it; only checked with `ParserOptions::validate_scores`
+    #[serde(
+        rename = "scoreWarnings",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub score_warnings: Vec<ScoreWarning>,
+    /// Tiles that are not where the wall rebuilt from the SHUFFLE seed puts
+    /// them; only checked with `ParserOptions::verify_wall`
+    #[serde(
+        rename = "wallMismatches",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub wall_mismatches: Vec<WallMismatch>,
+    /// Disconnects and reconnects outside a round, in the lobby before the
+    /// first INIT or between two rounds
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<Connection>,
+    /// Rounds left in a spool file by `ParserOptions::spill_rounds`,
+    /// serialized after those in `rounds`; see [`ParserOutput::load_rounds`]
+    #[serde(skip)]
+    pub spilled: Option<crate::spool::SpilledRounds>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Serialize for ParserOutput {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Repr<'a> {
+            mjlog_version: &'a str,
+            game_id: &'a str,
+            rules: &'a Rules,
+            players: &'a [Player],
+            rounds: Rounds<'a>,
+            overtime: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            end_reason: Option<EndReason>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            result: Option<&'a GameResult>,
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            warnings: &'a [ParseWarning],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            score_warnings: &'a [ScoreWarning],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            wall_mismatches: &'a [WallMismatch],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            connections: &'a [Connection],
+        }
+
+        /// The rounds in memory, then the spilled ones read back one at a
+        /// time
+        struct Rounds<'a>(&'a ParserOutput);
+
+        impl Serialize for Rounds<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::{Error, SerializeSeq};
+
+                let output = self.0;
+                let spilled = output.spilled.as_ref().map_or(0, |s| s.len());
+                let mut seq = serializer.serialize_seq(Some(output.rounds.len() + spilled))?;
+                for round in &output.rounds {
+                    seq.serialize_element(round)?;
+                }
+                if let Some(spilled) = &output.spilled {
+                    for round in spilled.rounds().map_err(S::Error::custom)? {
+                        seq.serialize_element(&round.map_err(S::Error::custom)?)?;
+                    }
+                }
+                seq.end()
+            }
+        }
+
+        Repr {
+            mjlog_version: &self.mjlog_version,
+            game_id: &self.game_id,
+            rules: &self.rules,
+            players: &self.players,
+            rounds: Rounds(self),
+            overtime: self.overtime,
+            end_reason: self.end_reason,
+            result: self.result.as_ref(),
+            warnings: &self.warnings,
+            score_warnings: &self.score_warnings,
+            wall_mismatches: &self.wall_mismatches,
+            connections: &self.connections,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl ParserOutput {
+    /// Read any spilled rounds back into `rounds`, for code that needs them
+    /// all at hand rather than streamed
+    pub fn load_rounds(&mut self) -> crate::error::Result<()> {
+        if let Some(spilled) = self.spilled.take() {
+            self.rounds.reserve(spilled.len());
+            for round in spilled.rounds()? {
+                self.rounds.push(round?);
+            }
+        }
+        Ok(())
+    }
+
+    /// The JSON Schema this type serializes to; see [`crate::schema`]
+    pub fn json_schema() -> serde_json::Value {
+        crate::schema::output_schema()
+    }
+
+    /// Whether any round was played in a wind beyond the game's length
+    pub fn played_overtime(&self) -> bool {
+        let last = self.rules.game_length().last_wind();
+        self.rounds.iter().any(|r| r.init.wind() > last)
+    }
+
+    /// Sort every round's starting hands in tile order
+    pub fn sort_hands(&mut self) {
+        self.rounds.iter_mut().for_each(Round::sort_hands);
+        if let Some(spilled) = &mut self.spilled {
+            spilled.sort_hands();
+        }
+    }
+
+    /// Hide everything `seat` could not see at the table
+    ///
+    /// Other players' starting hands and draws become `Tile::HIDDEN`, while
+    /// their discards, calls and kans stay as the log reveals them. The
+    /// shanten and waits annotated on their draws and discards go too. The
+    /// wall, its mismatches, raw source tags, table states and event
+    /// counters are dropped, since they give away tiles.
+    pub fn redact_for_seat(&mut self, seat: u8) {
+        for round in &mut self.rounds {
+            round.redact_for_seat(seat);
+        }
+        if let Some(spilled) = &mut self.spilled {
+            spilled.redact_for_seat(seat);
+        }
+        self.wall_mismatches.clear();
+        self.warnings.retain(|w| w.kind != WarningKind::Wall);
+    }
+}
+
+/// Game rules from the GO tag
+///
+/// Serialized with the raw `typeFlags` and, next to it, each flag decoded;
+/// the decoded fields are ignored when deserializing.
+#[derive(Debug, Clone, Deserialize)]
 pub struct Rules {
     #[serde(rename = "typeFlags")]
     pub type_flags: u32,
@@ -19,14 +186,617 @@ pub struct Rules {
     pub lobby_id: Option<u32>,
 }
 
+impl Serialize for Rules {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Decoded {
+            type_flags: u32,
+            lobby_id: Option<u32>,
+            vs_human: bool,
+            red_fives: bool,
+            kuitan: bool,
+            players: u8,
+            length: GameLength,
+            fast: bool,
+            room: Room,
+        }
+
+        Decoded {
+            type_flags: self.type_flags,
+            lobby_id: self.lobby_id,
+            vs_human: self.is_vs_human(),
+            red_fives: self.has_aka(),
+            kuitan: self.is_open_tanyao_allowed(),
+            players: self.players(),
+            length: self.game_length(),
+            fast: self.is_fast(),
+            room: self.room(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Rules {
+    /// Against human players rather than bots
+    pub const VS_HUMAN: u32 = 0x01;
+    /// Red fives are not used
+    pub const NO_AKA: u32 = 0x02;
+    /// Open tanyao is not allowed
+    pub const NO_KUITAN: u32 = 0x04;
+    /// East-South game rather than East only
+    pub const HANCHAN: u32 = 0x08;
+    /// Three-player game
+    pub const SANMA: u32 = 0x10;
+    pub const TOKUJOU: u32 = 0x20;
+    /// Shortened time limits
+    pub const FAST: u32 = 0x40;
+    pub const JOUKYUU: u32 = 0x80;
+
+    fn flag(&self, bit: u32) -> bool {
+        self.type_flags & bit != 0
+    }
+
+    pub fn is_vs_human(&self) -> bool {
+        self.flag(Self::VS_HUMAN)
+    }
+
+    pub fn has_aka(&self) -> bool {
+        !self.flag(Self::NO_AKA)
+    }
+
+    pub fn is_open_tanyao_allowed(&self) -> bool {
+        !self.flag(Self::NO_KUITAN)
+    }
+
+    pub fn game_length(&self) -> GameLength {
+        if self.flag(Self::HANCHAN) {
+            GameLength::Hanchan
+        } else {
+            GameLength::Tonpuu
+        }
+    }
+
+    pub fn is_sanma(&self) -> bool {
+        self.flag(Self::SANMA)
+    }
+
+    /// Number of players, 3 or 4
+    pub fn players(&self) -> u8 {
+        if self.is_sanma() {
+            3
+        } else {
+            4
+        }
+    }
+
+    pub fn is_fast(&self) -> bool {
+        self.flag(Self::FAST)
+    }
+
+    pub fn room(&self) -> Room {
+        match (self.flag(Self::TOKUJOU), self.flag(Self::JOUKYUU)) {
+            (false, false) => Room::Ippan,
+            (false, true) => Room::Joukyuu,
+            (true, false) => Room::Tokujou,
+            (true, true) => Room::Houou,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameLength {
+    Tonpuu,  // 東風戦
+    Hanchan, // 東南戦
+}
+
+impl GameLength {
+    /// Prevailing wind of the scheduled last round
+    pub fn last_wind(self) -> RoundWind {
+        match self {
+            GameLength::Tonpuu => RoundWind::East,
+            GameLength::Hanchan => RoundWind::South,
+        }
+    }
+}
+
+/// How a game came to an end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndReason {
+    /// Played to its scheduled end
+    Completed,
+    /// Ended early because a player's score fell below zero (飛び)
+    Bust,
+    /// The log stops before the game's final result, e.g. at a disconnect
+    Abandoned,
+}
+
+impl EndReason {
+    /// End reason from the final scores, or `None` if the log never got to
+    /// the final result
+    pub fn from_final_scores(scores: Option<&[i32; 4]>) -> Self {
+        match scores {
+            None => EndReason::Abandoned,
+            Some(scores) if scores.iter().any(|&s| s < 0) => EndReason::Bust,
+            Some(_) => EndReason::Completed,
+        }
+    }
+}
+
+/// The final result of a game, from the `owari` attribute of its last
+/// settlement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameResult {
+    /// Final scores by seat, in units of 100 points like `Init::initial_scores`
+    pub scores: [i32; 4],
+    /// Points by seat after oka and uma, as Tenhou computed them
+    pub points: [f64; 4],
+    /// Place of each seat, 1 for first; 0 for the empty seat of a
+    /// three-player game
+    pub placements: [u8; 4],
+}
+
+impl GameResult {
+    /// Result for the first `seats` seats, placing ties by seat order as
+    /// Tenhou does
+    pub fn new(scores: [i32; 4], points: [f64; 4], seats: usize) -> Self {
+        let mut order: Vec<usize> = (0..seats.min(4)).collect();
+        order.sort_by_key(|&seat| (std::cmp::Reverse(scores[seat]), seat));
+        let mut placements = [0u8; 4];
+        for (place, seat) in order.into_iter().enumerate() {
+            placements[seat] = place as u8 + 1;
+        }
+        Self {
+            scores,
+            points,
+            placements,
+        }
+    }
+}
+
+/// A seat whose score at the start of the next round (or in the final
+/// result) differs from the one the round's score changes add up to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreWarning {
+    /// Round whose score changes were replayed
+    pub round_id: String,
+    pub seat: u8,
+    /// Score after the round from its start and score changes, in units of
+    /// 100 points
+    pub expected: i32,
+    /// Score the next INIT (or the final result) records
+    pub actual: i32,
+}
+
+/// A `BYE` or reconnecting `UN` tag that came outside a round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    /// Rounds played before it, so 0 in the lobby
+    pub after_rounds: usize,
+    /// `Event::Disconnect` or `Event::Reconnect`
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Tiles of a round that differ from those the wall rebuilt from the
+/// SHUFFLE seed gives
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WallMismatch {
+    pub round_id: String,
+    pub kind: WallMismatchKind,
+    /// The seat dealt the hand or drawing the tile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seat: Option<u8>,
+    /// The draw or dora event, for mismatches in the round's events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<EventId>,
+    /// Tile IDs the wall gives; for a replacement draw, any of these
+    pub expected: Vec<u32>,
+    /// Tile IDs the log has
+    pub actual: Vec<u32>,
+}
+
+/// What a `WallMismatch` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WallMismatchKind {
+    /// A starting hand, compared regardless of order
+    InitialHand,
+    /// The first dora indicator, or one revealed for a kan
+    DoraIndicator,
+    /// A draw from the live wall
+    Draw,
+    /// A draw from the dead wall after a kan
+    ReplacementDraw,
+}
+
+impl From<&WallMismatch> for ParseWarning {
+    fn from(mismatch: &WallMismatch) -> Self {
+        let what = match mismatch.kind {
+            WallMismatchKind::InitialHand => "starting hand",
+            WallMismatchKind::DoraIndicator => "dora indicator",
+            WallMismatchKind::Draw => "draw",
+            WallMismatchKind::ReplacementDraw => "replacement draw",
+        };
+        let seat = mismatch
+            .seat
+            .map_or_else(String::new, |seat| format!(" of seat {}", seat));
+        Self::new(
+            WarningKind::Wall,
+            format!(
+                "{}: {}{} is {:?} but the wall gives {:?}",
+                mismatch.round_id, what, seat, mismatch.actual, mismatch.expected
+            ),
+        )
+    }
+}
+
+/// Something in the input that the parser worked around, or that makes the
+/// output less reliable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseWarning {
+    pub kind: WarningKind,
+    /// Byte offset into the log, decoded to UTF-8, when the warning is
+    /// about one place in it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// Name of the tag at `offset`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    pub message: String,
+}
+
+impl ParseWarning {
+    /// A warning about the game as a whole rather than a place in the log
+    pub fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            offset: None,
+            tag: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.tag, self.offset) {
+            (Some(tag), Some(offset)) => {
+                write!(f, "{} (<{}> at byte {})", self.message, tag, offset)
+            }
+            (None, Some(offset)) => write!(f, "{} (at byte {})", self.message, offset),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl From<&ScoreWarning> for ParseWarning {
+    fn from(warning: &ScoreWarning) -> Self {
+        Self::new(
+            WarningKind::Scores,
+            format!(
+                "{}: seat {} should have {} but has {}",
+                warning.round_id, warning.seat, warning.expected, warning.actual
+            ),
+        )
+    }
+}
+
+/// What a `ParseWarning` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WarningKind {
+    /// A tag the parser does not know, kept as `Event::Unknown` inside a
+    /// round
+    UnknownTag,
+    /// Bytes invalid in the log's encoding, replaced with U+FFFD
+    Encoding,
+    /// A tag or attribute that could not be read and was skipped, or a tile
+    /// ID past the last tile
+    Malformed,
+    /// A missing or repeated player list
+    Players,
+    /// A wall that could not be rebuilt from the SHUFFLE seed, or tiles
+    /// that differ from it; the details are in `wall_mismatches`
+    Wall,
+    /// Scores that do not add up; the details are in `score_warnings`
+    Scores,
+    /// A player lookup that failed while enriching the output
+    Enrich,
+}
+
+/// Prevailing wind of a round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundWind {
+    East,  // 東場
+    South, // 南場
+    West,  // 西場
+    North, // 北場
+}
+
+impl RoundWind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RoundWind::East => "東",
+            RoundWind::South => "南",
+            RoundWind::West => "西",
+            RoundWind::North => "北",
+        }
+    }
+
+    pub fn english(self) -> &'static str {
+        match self {
+            RoundWind::East => "East",
+            RoundWind::South => "South",
+            RoundWind::West => "West",
+            RoundWind::North => "North",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Room {
+    Ippan,   // 一般
+    Joukyuu, // 上級
+    Tokujou, // 特上
+    Houou,   // 鳳凰
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub seat: u8,
     #[serde(rename = "playerId")]
     pub player_id: String,
-    pub rank: u32,
+    /// Serialized like `Dan`, followed by its name as `rankName`
+    #[serde(flatten, with = "rank_fields")]
+    pub rank: Dan,
     pub rate: u32,
-    pub gender: String,
+    pub gender: Gender,
+    /// Data looked up outside the log (`enrich` feature)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<PlayerProfile>,
+}
+
+/// Player data from a log-index service rather than the log itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerProfile {
+    /// Games the service has recorded for the player
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub games: Option<u32>,
+    /// Recent rates, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rate_history: Vec<f64>,
+}
+
+impl Player {
+    /// Name Tenhou itself uses for anonymized players
+    pub const PLACEHOLDER_ID: &'static str = "NoName";
+
+    /// Create a placeholder player for logs without player information
+    pub fn placeholder(seat: u8) -> Self {
+        Self {
+            seat,
+            player_id: Self::PLACEHOLDER_ID.to_string(),
+            rank: Dan::Shinjin,
+            rate: 0,
+            gender: Gender::Unknown,
+            profile: None,
+        }
+    }
+}
+
+/// `Player::rank` as the `rank` and `rankName` fields; only `rank` is read
+/// back
+mod rank_fields {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Dan;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Fields {
+        rank: Dan,
+        rank_name: &'static str,
+    }
+
+    #[derive(Deserialize)]
+    struct Rank {
+        rank: Dan,
+    }
+
+    pub fn serialize<S: Serializer>(rank: &Dan, serializer: S) -> Result<S::Ok, S::Error> {
+        Fields {
+            rank: *rank,
+            rank_name: rank.name(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Dan, D::Error> {
+        Rank::deserialize(deserializer).map(|fields| fields.rank)
+    }
+}
+
+/// Player gender from the `sx` attribute of `<UN>`
+///
+/// Serialized as the mjlog code: "M", "F", "C" for a computer player, or an
+/// empty string when the log does not say.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Gender {
+    Male,
+    Female,
+    Computer,
+    #[default]
+    Unknown,
+}
+
+impl Gender {
+    /// Gender for an mjlog code; `None` for codes Tenhou does not use
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "M" => Some(Gender::Male),
+            "F" => Some(Gender::Female),
+            "C" => Some(Gender::Computer),
+            "" => Some(Gender::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Gender::Male => "M",
+            Gender::Female => "F",
+            Gender::Computer => "C",
+            Gender::Unknown => "",
+        }
+    }
+}
+
+impl Serialize for Gender {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Gender {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Gender::from_code(&code)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid gender: {:?}", code)))
+    }
+}
+
+/// Tenhou dan/kyu rank, as recorded in the `dan` attribute of `<UN>`
+///
+/// Serialized as the numeric rank, e.g. `16` for 七段.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dan {
+    Shinjin,
+    Kyu9,
+    Kyu8,
+    Kyu7,
+    Kyu6,
+    Kyu5,
+    Kyu4,
+    Kyu3,
+    Kyu2,
+    Kyu1,
+    Shodan,
+    Nidan,
+    Sandan,
+    Yondan,
+    Godan,
+    Rokudan,
+    Nanadan,
+    Hachidan,
+    Kudan,
+    Judan,
+    Tenhoui,
+}
+
+impl Dan {
+    const ALL: [Dan; 21] = [
+        Dan::Shinjin,
+        Dan::Kyu9,
+        Dan::Kyu8,
+        Dan::Kyu7,
+        Dan::Kyu6,
+        Dan::Kyu5,
+        Dan::Kyu4,
+        Dan::Kyu3,
+        Dan::Kyu2,
+        Dan::Kyu1,
+        Dan::Shodan,
+        Dan::Nidan,
+        Dan::Sandan,
+        Dan::Yondan,
+        Dan::Godan,
+        Dan::Rokudan,
+        Dan::Nanadan,
+        Dan::Hachidan,
+        Dan::Kudan,
+        Dan::Judan,
+        Dan::Tenhoui,
+    ];
+
+    const NAMES: [&'static str; 21] = [
+        "新人",
+        "９級",
+        "８級",
+        "７級",
+        "６級",
+        "５級",
+        "４級",
+        "３級",
+        "２級",
+        "１級",
+        "初段",
+        "二段",
+        "三段",
+        "四段",
+        "五段",
+        "六段",
+        "七段",
+        "八段",
+        "九段",
+        "十段",
+        "天鳳位",
+    ];
+
+    /// Convert the numeric rank used in mjlog
+    pub fn from_rank(rank: u32) -> Option<Self> {
+        Self::ALL.get(rank as usize).copied()
+    }
+
+    /// The numeric rank used in mjlog
+    pub fn rank(self) -> u32 {
+        self as u32
+    }
+
+    /// Japanese name as shown by Tenhou
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+
+    /// English name, e.g. "9 kyu", "1 dan", "Tenhoui"
+    pub fn english(self) -> String {
+        match self {
+            Dan::Shinjin => "Novice".to_string(),
+            Dan::Tenhoui => "Tenhoui".to_string(),
+            dan if dan < Dan::Shodan => format!("{} kyu", Dan::Shodan.rank() - dan.rank()),
+            dan => format!("{} dan", dan.rank() - Dan::Kyu1.rank()),
+        }
+    }
+}
+
+impl std::fmt::Display for Dan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Dan {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.rank())
+    }
+}
+
+impl<'de> Deserialize<'de> for Dan {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rank = u32::deserialize(deserializer)?;
+        Dan::from_rank(rank)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid dan rank: {}", rank)))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +807,226 @@ pub struct Round {
     pub dealer_seat: u8,
     pub init: Init,
     pub events: Vec<Event>,
+    /// The shuffled wall, when reconstructed from the SHUFFLE seed with
+    /// `ParserOptions::annotate_wall`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall: Option<Wall>,
+    /// Source tag of each event, by event ID; only kept when parsing with
+    /// `ParserOptions::include_raw`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub raw: Vec<RawTag>,
+    /// The table after each event; only kept when parsing with
+    /// `ParserOptions::track_state`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<crate::state::RoundState>,
+    /// Sequence number, turn and live wall count of each event, by event
+    /// ID; only kept when parsing with `ParserOptions::annotate_counters`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub counters: Vec<crate::state::EventCounters>,
+}
+
+/// A round's wall as tile IDs by wall position (see `crate::wall`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wall {
+    pub tiles: Vec<u32>,
+    /// Dice as recorded in the INIT seed, 0 to 5
+    pub dice: [u32; 2],
+}
+
+/// An mjlog tag as it appeared in the source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawTag {
+    pub tag: String,
+    pub attributes: BTreeMap<String, String>,
+    /// The tag exactly as written, with `ParserOptions::raw_source`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Byte range of `source` in the decoded log text, which matches the
+    /// file for the plain ASCII tags of a Tenhou log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Range<usize>>,
+}
+
+impl Round {
+    /// Sort the starting hands in tile order
+    pub fn sort_hands(&mut self) {
+        for hand in &mut self.init.initial_hands {
+            hand.sort();
+        }
+    }
+
+    /// Hide everything `seat` could not see; see
+    /// [`ParserOutput::redact_for_seat`]
+    pub fn redact_for_seat(&mut self, seat: u8) {
+        for (other, hand) in self.init.initial_hands.iter_mut().enumerate() {
+            if other != seat as usize {
+                hand.fill(Tile::HIDDEN);
+            }
+        }
+        for event in &mut self.events {
+            match event {
+                Event::Draw {
+                    seat: drawer,
+                    tile,
+                    shanten,
+                    ..
+                } if *drawer != seat => {
+                    *tile = Tile::HIDDEN;
+                    *shanten = None;
+                }
+                Event::Discard {
+                    seat: discarder,
+                    shanten,
+                    waits,
+                    furiten,
+                    ..
+                } if *discarder != seat => {
+                    *shanten = None;
+                    waits.clear();
+                    *furiten = false;
+                }
+                _ => {}
+            }
+        }
+        self.wall = None;
+        self.raw.clear();
+        self.state = None;
+        self.counters.clear();
+    }
+
+    pub fn event(&self, id: EventId) -> Option<&Event> {
+        self.events.get(id)
+    }
+
+    /// Fill in the cross-references between events
+    ///
+    /// Calls get the discard they claimed, reach step 2 gets its step 1, and
+    /// kans get their dora reveal and replacement draw, and dora reveals get
+    /// the kan that triggered them, its seat and whether a discard came in
+    /// between. A reveal before any kan is taken for the starting indicator.
+    /// Links already set are overwritten.
+    pub fn link_events(&mut self) {
+        let mut last_discard: Option<EventId> = None;
+        let mut declarations: [Option<EventId>; 4] = [None; 4];
+        // Kans waiting for their replacement draw, per seat
+        let mut awaiting_rinshan: [Option<EventId>; 4] = [None; 4];
+        // Kans waiting for their dora reveal, oldest first, with their seat
+        // and whether a discard has happened since
+        let mut awaiting_dora: std::collections::VecDeque<(EventId, u8, bool)> =
+            std::collections::VecDeque::new();
+        let mut kans_seen = false;
+        let mut links: Vec<(EventId, Link)> = Vec::new();
+
+        for (id, event) in self.events.iter().enumerate() {
+            match event {
+                Event::Discard { .. } => {
+                    last_discard = Some(id);
+                    for (_, _, discarded) in awaiting_dora.iter_mut() {
+                        *discarded = true;
+                    }
+                }
+                Event::Draw { seat, .. } => {
+                    if let Some(kan) = awaiting_rinshan[*seat as usize % 4].take() {
+                        links.push((kan, Link::Rinshan(id)));
+                    }
+                    last_discard = None;
+                }
+                Event::Chi { .. } | Event::Pon { .. } => {
+                    links.push((id, Link::Claimed(last_discard.take())));
+                }
+                Event::Kan { who, kan_type, .. } => {
+                    let claimed = match kan_type {
+                        KanType::Minkan => last_discard.take(),
+                        _ => None,
+                    };
+                    links.push((id, Link::Claimed(claimed)));
+                    awaiting_rinshan[*who as usize % 4] = Some(id);
+                    awaiting_dora.push_back((id, *who, false));
+                    kans_seen = true;
+                }
+                Event::Dora { .. } => {
+                    let reveal = match awaiting_dora.pop_front() {
+                        Some((kan, who, discarded)) => {
+                            links.push((kan, Link::Dora(id)));
+                            Reveal {
+                                source: Some(DoraSource::Kan),
+                                kan: Some(kan),
+                                who: Some(who),
+                                timing: Some(if discarded {
+                                    DoraTiming::AfterDiscard
+                                } else {
+                                    DoraTiming::Immediate
+                                }),
+                            }
+                        }
+                        None if !kans_seen => Reveal {
+                            source: Some(DoraSource::Initial),
+                            ..Default::default()
+                        },
+                        None => Reveal::default(),
+                    };
+                    links.push((id, Link::Reveal(reveal)));
+                }
+                Event::Reach { who, step: 1, .. } => declarations[*who as usize % 4] = Some(id),
+                Event::Reach { who, .. } => {
+                    links.push((
+                        id,
+                        Link::Declaration(declarations[*who as usize % 4].take()),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        for (id, link) in links {
+            match (&mut self.events[id], link) {
+                (
+                    Event::Chi { claimed, .. }
+                    | Event::Pon { claimed, .. }
+                    | Event::Kan { claimed, .. },
+                    Link::Claimed(target),
+                ) => *claimed = target,
+                (Event::Kan { dora, .. }, Link::Dora(target)) => *dora = Some(target),
+                (Event::Kan { rinshan, .. }, Link::Rinshan(target)) => *rinshan = Some(target),
+                (
+                    Event::Dora {
+                        source,
+                        kan,
+                        who,
+                        timing,
+                        ..
+                    },
+                    Link::Reveal(reveal),
+                ) => {
+                    *source = reveal.source;
+                    *kan = reveal.kan;
+                    *who = reveal.who;
+                    *timing = reveal.timing;
+                }
+                (Event::Reach { declaration, .. }, Link::Declaration(target)) => {
+                    *declaration = target
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+enum Link {
+    Claimed(Option<EventId>),
+    Dora(EventId),
+    Rinshan(EventId),
+    Reveal(Reveal),
+    Declaration(Option<EventId>),
+}
+
+/// What a dora reveal is linked to
+#[derive(Default)]
+struct Reveal {
+    source: Option<DoraSource>,
+    kan: Option<EventId>,
+    who: Option<u8>,
+    timing: Option<DoraTiming>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,45 +1041,143 @@ pub struct Init {
     #[serde(rename = "initialScores")]
     pub initial_scores: [i32; 4],
     #[serde(rename = "initialHands")]
-    pub initial_hands: Vec<Vec<String>>,
+    pub initial_hands: Vec<Vec<Tile>>,
 }
 
+impl Init {
+    /// Prevailing wind; rounds past North wrap around to East
+    pub fn wind(&self) -> RoundWind {
+        match self.round_number / 4 % 4 {
+            0 => RoundWind::East,
+            1 => RoundWind::South,
+            2 => RoundWind::West,
+            _ => RoundWind::North,
+        }
+    }
+
+    /// Hand within the wind, 1 to 4
+    pub fn hand_number(&self) -> u32 {
+        self.round_number % 4 + 1
+    }
+
+    /// Japanese round name such as "西1局"
+    pub fn round_name(&self) -> String {
+        format!("{}{}局", self.wind().name(), self.hand_number())
+    }
+
+    /// English round name such as "West 1"
+    pub fn round_name_english(&self) -> String {
+        format!("{} {}", self.wind().english(), self.hand_number())
+    }
+}
+
+/// Identifies an event by its position in `Round::events`
+pub type EventId = usize;
+
+/// An event within a round
+///
+/// New variants may be added as more of the mjlog format is understood, so
+/// matches outside this crate need a wildcard arm.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
+#[non_exhaustive]
 pub enum Event {
     #[serde(rename = "draw")]
-    Draw { seat: u8, tile: String },
+    Draw {
+        seat: u8,
+        tile: Tile,
+        /// Where the tile sat in `Round::wall`, when the wall is known
+        #[serde(
+            rename = "wallPosition",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        wall_position: Option<usize>,
+        /// Shanten of the drawer's hand after the draw, with
+        /// `ParserOptions::annotate_shanten`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shanten: Option<i8>,
+    },
     #[serde(rename = "discard")]
     Discard {
         seat: u8,
-        tile: String,
+        tile: Tile,
         #[serde(rename = "isRiichi")]
         is_riichi: bool,
+        /// Shanten of the discarder's hand after the discard, with
+        /// `ParserOptions::annotate_shanten`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shanten: Option<i8>,
+        /// Tiles that complete the discarder's hand when it is tenpai after
+        /// the discard, with `ParserOptions::annotate_waits`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        waits: Vec<Tile>,
+        /// One of `waits` is in the discarder's own river
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        furiten: bool,
     },
     #[serde(rename = "chi")]
     Chi {
         who: u8,
-        tiles: [String; 3],
+        tiles: [Tile; 3],
         from: u8,
+        /// The discard this call claimed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        claimed: Option<EventId>,
     },
     #[serde(rename = "pon")]
     Pon {
         who: u8,
-        tiles: [String; 3],
+        tiles: [Tile; 3],
         from: u8,
+        /// The discard this call claimed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        claimed: Option<EventId>,
     },
     #[serde(rename = "kan")]
     Kan {
         who: u8,
-        tiles: Vec<String>,
+        tiles: Vec<Tile>,
         #[serde(rename = "kanType")]
         kan_type: KanType,
         from: Option<u8>,
+        /// The discard an open kan claimed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        claimed: Option<EventId>,
+        /// The new dora indicator revealed for this kan
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dora: Option<EventId>,
+        /// The replacement draw from the dead wall
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rinshan: Option<EventId>,
     },
     #[serde(rename = "dora")]
-    Dora { indicator: String },
+    Dora {
+        indicator: Tile,
+        /// Whether a kan or the deal revealed the indicator
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<DoraSource>,
+        /// The kan that triggered this reveal
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        kan: Option<EventId>,
+        /// Seat that called the kan
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        who: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timing: Option<DoraTiming>,
+    },
     #[serde(rename = "reach")]
-    Reach { who: u8, step: u8, scores: [i32; 4] },
+    Reach {
+        who: u8,
+        step: u8,
+        scores: [i32; 4],
+        /// On step 2, the step 1 declaration it completes
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        declaration: Option<EventId>,
+        /// On step 2, the deposit of the riichi stick
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        transfers: Vec<Transfer>,
+    },
     #[serde(rename = "agari")]
     Agari {
         who: u8,
@@ -97,24 +1185,437 @@ pub enum Event {
         han: u32,
         fu: u32,
         yakus: Vec<Yaku>,
+        /// Dora, ura dora and red fives in the winning hand together
         #[serde(rename = "doraCount")]
         dora_count: u32,
+        #[serde(default)]
+        dora: u32,
+        #[serde(rename = "uraDora", default)]
+        ura_dora: u32,
+        #[serde(rename = "akaDora", default)]
+        aka_dora: u32,
+        /// Concealed tiles of the winning hand, winning tile included
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        hand: Vec<Tile>,
+        /// Called melds and closed kans of the winner
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        melds: Vec<Vec<Tile>>,
+        #[serde(
+            rename = "winningTile",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        winning_tile: Option<Tile>,
+        #[serde(
+            rename = "doraIndicators",
+            default,
+            skip_serializing_if = "Vec::is_empty"
+        )]
+        dora_indicators: Vec<Tile>,
+        /// Revealed only for a riichi win
+        #[serde(
+            rename = "uraDoraIndicators",
+            default,
+            skip_serializing_if = "Vec::is_empty"
+        )]
+        ura_dora_indicators: Vec<Tile>,
         scores: [i32; 4],
+        /// What the winner collected, when the log records it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payout: Option<Payout>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        transfers: Vec<Transfer>,
     },
     #[serde(rename = "ryuukyoku")]
     Ryuukyoku {
         reason: RyuukyokuReason,
         scores: [i32; 4],
+        /// Hands shown at the draw by seat, `None` for hands kept hidden
+        /// (noten at an exhaustive draw)
+        #[serde(default, skip_serializing_if = "no_hands_shown")]
+        tenpai: [Option<Vec<Tile>>; 4],
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        transfers: Vec<Transfer>,
     },
+    /// A player lost the connection (`BYE`)
+    #[serde(rename = "disconnect")]
+    Disconnect { who: u8 },
+    /// A disconnected player came back (a `UN` tag naming only them)
+    #[serde(rename = "reconnect")]
+    Reconnect { who: u8 },
+    /// A tag this parser does not recognize, kept with its raw attributes
+    #[serde(rename = "unknown")]
+    Unknown {
+        tag: String,
+        attributes: BTreeMap<String, String>,
+    },
+}
+
+impl Event {
+    /// The seat that acted; none for dora reveals, draws of the round and
+    /// unknown tags
+    pub fn seat(&self) -> Option<u8> {
+        match self {
+            Event::Draw { seat, .. } | Event::Discard { seat, .. } => Some(*seat),
+            Event::Chi { who, .. }
+            | Event::Pon { who, .. }
+            | Event::Kan { who, .. }
+            | Event::Reach { who, .. }
+            | Event::Agari { who, .. }
+            | Event::Disconnect { who }
+            | Event::Reconnect { who } => Some(*who),
+            Event::Dora { .. } | Event::Ryuukyoku { .. } | Event::Unknown { .. } => None,
+        }
+    }
+}
+
+fn no_hands_shown(hands: &[Option<Vec<Tile>>; 4]) -> bool {
+    hands.iter().all(Option::is_none)
+}
+
+/// A winner's income split by source, in units of 100 points like `scores`
+///
+/// The three parts add up to the winner's score change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payout {
+    /// Value of the hand itself
+    pub hand: i32,
+    /// 300 points per honba counter
+    pub honba: i32,
+    /// 1000 points per riichi stick on the table
+    pub riichi_sticks: i32,
+}
+
+/// Points moving from one party to another, in units of 100 points
+///
+/// A missing `from` or `to` is the table, where riichi sticks are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<u8>,
+    pub amount: i32,
+    pub reason: TransferReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferReason {
+    /// Payment for the winning hand
+    Hand,
+    /// Payment for the honba counters
+    Honba,
+    /// A riichi stick put on the table
+    RiichiDeposit,
+    /// Riichi sticks collected from the table by the winner
+    RiichiSticks,
+    /// Settlement at an exhaustive draw (noten penalties, nagashi mangan)
+    NotenPenalty,
+}
+
+impl Transfer {
+    /// The stick a riichi declaration puts on the table
+    pub fn riichi_deposit(who: u8) -> Self {
+        Self {
+            from: Some(who),
+            to: None,
+            amount: 10,
+            reason: TransferReason::RiichiDeposit,
+        }
+    }
+
+    /// Payments for one win, from its score changes
+    ///
+    /// Without a payout breakdown, losers' payments all count as hand value
+    /// and anything more the winner gained came off the table.
+    pub fn agari(who: u8, from: u8, scores: &[i32; 4], payout: Option<&Payout>) -> Vec<Self> {
+        let winner = who as usize % 4;
+        let losers: Vec<usize> = (0..4).filter(|&s| s != winner && scores[s] < 0).collect();
+        let mut transfers = Vec::new();
+        let mut paid = 0;
+        for &loser in &losers {
+            let honba = match payout {
+                Some(p) if who == from => p.honba / losers.len() as i32,
+                Some(p) => p.honba,
+                None => 0,
+            };
+            let amount = -scores[loser];
+            paid += amount;
+            let mut push = |amount: i32, reason| {
+                if amount > 0 {
+                    transfers.push(Self {
+                        from: Some(loser as u8),
+                        to: Some(who),
+                        amount,
+                        reason,
+                    });
+                }
+            };
+            push(amount - honba, TransferReason::Hand);
+            push(honba.min(amount), TransferReason::Honba);
+        }
+        let sticks = payout.map_or(scores[winner] - paid, |p| p.riichi_sticks);
+        if sticks > 0 {
+            transfers.push(Self {
+                from: None,
+                to: Some(who),
+                amount: sticks,
+                reason: TransferReason::RiichiSticks,
+            });
+        }
+        transfers
+    }
+
+    /// Payments at a draw, matching each payer to receivers in seat order
+    pub fn draw(scores: &[i32; 4]) -> Vec<Self> {
+        let mut due = scores.map(|s| s.max(0));
+        let mut transfers = Vec::new();
+        for (from, score) in scores.iter().enumerate() {
+            let mut owed = (-score).max(0);
+            for (to, due) in due.iter_mut().enumerate() {
+                let amount = owed.min(*due);
+                if amount > 0 {
+                    owed -= amount;
+                    *due -= amount;
+                    transfers.push(Self {
+                        from: Some(from as u8),
+                        to: Some(to as u8),
+                        amount,
+                        reason: TransferReason::NotenPenalty,
+                    });
+                }
+            }
+        }
+        transfers
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Yaku {
     pub name: String,
+    /// Han, or 13 for each yakuman
     pub value: u32,
+    /// Which yaku this is, absent for IDs this crate does not know
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<YakuKind>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Yaku {
+    /// Han of a yakuman in the `yakuman` attribute
+    pub const YAKUMAN: u32 = 13;
+
+    /// The yaku with Tenhou's ID `id` worth `value`
+    pub fn from_id(id: u32, value: u32) -> Self {
+        let kind = YakuKind::from_id(id);
+        Self {
+            name: kind.map_or_else(|| format!("Yaku {}", id), |k| k.name().to_string()),
+            value,
+            kind,
+        }
+    }
+}
+
+/// A yaku (or dora) by Tenhou's ID, as listed in the AGARI `yaku` and
+/// `yakuman` attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum YakuKind {
+    MenzenTsumo = 0,
+    Riichi = 1,
+    Ippatsu = 2,
+    Chankan = 3,
+    RinshanKaihou = 4,
+    Haitei = 5,
+    Houtei = 6,
+    Pinfu = 7,
+    Tanyao = 8,
+    Iipeikou = 9,
+    SeatWindEast = 10,
+    SeatWindSouth = 11,
+    SeatWindWest = 12,
+    SeatWindNorth = 13,
+    RoundWindEast = 14,
+    RoundWindSouth = 15,
+    RoundWindWest = 16,
+    RoundWindNorth = 17,
+    Haku = 18,
+    Hatsu = 19,
+    Chun = 20,
+    DoubleRiichi = 21,
+    Chiitoitsu = 22,
+    Chanta = 23,
+    Ittsu = 24,
+    SanshokuDoujun = 25,
+    SanshokuDoukou = 26,
+    Sankantsu = 27,
+    Toitoi = 28,
+    Sanankou = 29,
+    Shousangen = 30,
+    Honroutou = 31,
+    Ryanpeikou = 32,
+    Junchan = 33,
+    Honitsu = 34,
+    Chinitsu = 35,
+    Renhou = 36,
+    Tenhou = 37,
+    Chiihou = 38,
+    Daisangen = 39,
+    Suuankou = 40,
+    SuuankouTanki = 41,
+    Tsuuiisou = 42,
+    Ryuuiisou = 43,
+    Chinroutou = 44,
+    ChuurenPoutou = 45,
+    JunseiChuurenPoutou = 46,
+    Kokushi = 47,
+    Kokushi13 = 48,
+    Daisuushii = 49,
+    Shousuushii = 50,
+    Suukantsu = 51,
+    Dora = 52,
+    UraDora = 53,
+    AkaDora = 54,
+}
+
+impl YakuKind {
+    /// Every yaku in ID order
+    pub const ALL: [YakuKind; 55] = [
+        YakuKind::MenzenTsumo,
+        YakuKind::Riichi,
+        YakuKind::Ippatsu,
+        YakuKind::Chankan,
+        YakuKind::RinshanKaihou,
+        YakuKind::Haitei,
+        YakuKind::Houtei,
+        YakuKind::Pinfu,
+        YakuKind::Tanyao,
+        YakuKind::Iipeikou,
+        YakuKind::SeatWindEast,
+        YakuKind::SeatWindSouth,
+        YakuKind::SeatWindWest,
+        YakuKind::SeatWindNorth,
+        YakuKind::RoundWindEast,
+        YakuKind::RoundWindSouth,
+        YakuKind::RoundWindWest,
+        YakuKind::RoundWindNorth,
+        YakuKind::Haku,
+        YakuKind::Hatsu,
+        YakuKind::Chun,
+        YakuKind::DoubleRiichi,
+        YakuKind::Chiitoitsu,
+        YakuKind::Chanta,
+        YakuKind::Ittsu,
+        YakuKind::SanshokuDoujun,
+        YakuKind::SanshokuDoukou,
+        YakuKind::Sankantsu,
+        YakuKind::Toitoi,
+        YakuKind::Sanankou,
+        YakuKind::Shousangen,
+        YakuKind::Honroutou,
+        YakuKind::Ryanpeikou,
+        YakuKind::Junchan,
+        YakuKind::Honitsu,
+        YakuKind::Chinitsu,
+        YakuKind::Renhou,
+        YakuKind::Tenhou,
+        YakuKind::Chiihou,
+        YakuKind::Daisangen,
+        YakuKind::Suuankou,
+        YakuKind::SuuankouTanki,
+        YakuKind::Tsuuiisou,
+        YakuKind::Ryuuiisou,
+        YakuKind::Chinroutou,
+        YakuKind::ChuurenPoutou,
+        YakuKind::JunseiChuurenPoutou,
+        YakuKind::Kokushi,
+        YakuKind::Kokushi13,
+        YakuKind::Daisuushii,
+        YakuKind::Shousuushii,
+        YakuKind::Suukantsu,
+        YakuKind::Dora,
+        YakuKind::UraDora,
+        YakuKind::AkaDora,
+    ];
+
+    pub fn from_id(id: u32) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Tenhou's ID of the yaku
+    pub fn id(self) -> u32 {
+        self as u32
+    }
+
+    /// Romanized name, as used for `Yaku::name`
+    pub fn name(self) -> &'static str {
+        match self {
+            YakuKind::MenzenTsumo => "Tsumo",
+            YakuKind::Riichi => "Riichi",
+            YakuKind::Ippatsu => "Ippatsu",
+            YakuKind::Chankan => "Chankan",
+            YakuKind::RinshanKaihou => "Rinshan Kaihou",
+            YakuKind::Haitei => "Haitei Raoyue",
+            YakuKind::Houtei => "Houtei Raoyui",
+            YakuKind::Pinfu => "Pinfu",
+            YakuKind::Tanyao => "Tanyao",
+            YakuKind::Iipeikou => "Iipeikou",
+            YakuKind::SeatWindEast => "Seat Wind East",
+            YakuKind::SeatWindSouth => "Seat Wind South",
+            YakuKind::SeatWindWest => "Seat Wind West",
+            YakuKind::SeatWindNorth => "Seat Wind North",
+            YakuKind::RoundWindEast => "Round Wind East",
+            YakuKind::RoundWindSouth => "Round Wind South",
+            YakuKind::RoundWindWest => "Round Wind West",
+            YakuKind::RoundWindNorth => "Round Wind North",
+            YakuKind::Haku => "Haku",
+            YakuKind::Hatsu => "Hatsu",
+            YakuKind::Chun => "Chun",
+            YakuKind::DoubleRiichi => "Double Riichi",
+            YakuKind::Chiitoitsu => "Chiitoitsu",
+            YakuKind::Chanta => "Chanta",
+            YakuKind::Ittsu => "Ittsu",
+            YakuKind::SanshokuDoujun => "Sanshoku Doujun",
+            YakuKind::SanshokuDoukou => "Sanshoku Doukou",
+            YakuKind::Sankantsu => "Sankantsu",
+            YakuKind::Toitoi => "Toitoi",
+            YakuKind::Sanankou => "Sanankou",
+            YakuKind::Shousangen => "Shousangen",
+            YakuKind::Honroutou => "Honroutou",
+            YakuKind::Ryanpeikou => "Ryanpeikou",
+            YakuKind::Junchan => "Junchan",
+            YakuKind::Honitsu => "Honitsu",
+            YakuKind::Chinitsu => "Chinitsu",
+            YakuKind::Renhou => "Renhou",
+            YakuKind::Tenhou => "Tenhou",
+            YakuKind::Chiihou => "Chiihou",
+            YakuKind::Daisangen => "Daisangen",
+            YakuKind::Suuankou => "Suuankou",
+            YakuKind::SuuankouTanki => "Suuankou Tanki",
+            YakuKind::Tsuuiisou => "Tsuuiisou",
+            YakuKind::Ryuuiisou => "Ryuuiisou",
+            YakuKind::Chinroutou => "Chinroutou",
+            YakuKind::ChuurenPoutou => "Chuuren Poutou",
+            YakuKind::JunseiChuurenPoutou => "Junsei Chuuren Poutou",
+            YakuKind::Kokushi => "Kokushi Musou",
+            YakuKind::Kokushi13 => "Kokushi Musou 13-sided",
+            YakuKind::Daisuushii => "Daisuushii",
+            YakuKind::Shousuushii => "Shousuushii",
+            YakuKind::Suukantsu => "Suukantsu",
+            YakuKind::Dora => "Dora",
+            YakuKind::UraDora => "Ura Dora",
+            YakuKind::AkaDora => "Aka Dora",
+        }
+    }
+
+    /// Whether the yaku counts dora rather than being a yaku of its own
+    pub fn is_dora(self) -> bool {
+        matches!(self, YakuKind::Dora | YakuKind::UraDora | YakuKind::AkaDora)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum KanType {
     Ankan,  // 暗槓
@@ -122,6 +1623,33 @@ pub enum KanType {
     Kakan,  // 加槓
 }
 
+/// What revealed a dora indicator
+///
+/// Tenhou logs give the starting indicator in INIT, so their reveals all
+/// come from kans; other formats may list the starting one as an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DoraSource {
+    /// The indicator turned over at the deal
+    Initial,
+    /// A new indicator for a kan
+    Kan,
+}
+
+/// When a kan dora was revealed relative to its kan
+///
+/// Closed kans reveal at once; open and added kans reveal only after the
+/// kan player's next discard, so a ron on that discard (or a chankan) does
+/// not count the new dora.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DoraTiming {
+    /// Right after the kan, before any discard
+    Immediate,
+    /// After the discard following the kan
+    AfterDiscard,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum RyuukyokuReason {
@@ -138,3 +1666,372 @@ pub enum RyuukyokuReason {
     #[serde(rename = "kan4")]
     Kan4, // 四槓散了
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(type_flags: u32) -> Rules {
+        Rules {
+            type_flags,
+            lobby_id: None,
+        }
+    }
+
+    #[test]
+    fn test_rules_accessors() {
+        // 鳳凰東南喰赤
+        let houou = rules(169);
+        assert!(houou.is_vs_human());
+        assert!(houou.has_aka());
+        assert!(houou.is_open_tanyao_allowed());
+        assert_eq!(houou.game_length(), GameLength::Hanchan);
+        assert!(!houou.is_sanma());
+        assert!(!houou.is_fast());
+        assert_eq!(houou.room(), Room::Houou);
+
+        // 一般三東喰赤速
+        let sanma = rules(0x51);
+        assert!(sanma.is_sanma());
+        assert!(sanma.is_fast());
+        assert_eq!(sanma.game_length(), GameLength::Tonpuu);
+        assert_eq!(sanma.room(), Room::Ippan);
+
+        // 上級東南喰なし赤なし
+        let plain = rules(0x8f);
+        assert!(!plain.has_aka());
+        assert!(!plain.is_open_tanyao_allowed());
+        assert_eq!(plain.room(), Room::Joukyuu);
+        assert_eq!(rules(0x21).room(), Room::Tokujou);
+        assert_eq!(sanma.players(), 3);
+    }
+
+    #[test]
+    fn test_rules_serialization() {
+        let json = serde_json::to_value(rules(169)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "typeFlags": 169,
+                "lobbyId": null,
+                "vsHuman": true,
+                "redFives": true,
+                "kuitan": true,
+                "players": 4,
+                "length": "hanchan",
+                "fast": false,
+                "room": "houou",
+            })
+        );
+        let back: Rules = serde_json::from_value(json).unwrap();
+        assert_eq!(back.type_flags, 169);
+    }
+
+    #[test]
+    fn test_dan() {
+        assert_eq!(Dan::from_rank(0), Some(Dan::Shinjin));
+        assert_eq!(Dan::from_rank(16), Some(Dan::Nanadan));
+        assert_eq!(Dan::from_rank(20), Some(Dan::Tenhoui));
+        assert_eq!(Dan::from_rank(21), None);
+        assert_eq!(Dan::Nanadan.rank(), 16);
+
+        assert_eq!(Dan::Kyu9.to_string(), "９級");
+        assert_eq!(Dan::Shodan.to_string(), "初段");
+        assert_eq!(Dan::Judan.to_string(), "十段");
+        assert_eq!(Dan::Tenhoui.to_string(), "天鳳位");
+        assert_eq!(Dan::Kyu9.english(), "9 kyu");
+        assert_eq!(Dan::Kyu1.english(), "1 kyu");
+        assert_eq!(Dan::Shodan.english(), "1 dan");
+        assert_eq!(Dan::Judan.english(), "10 dan");
+
+        let json = serde_json::to_value(Dan::Nanadan).unwrap();
+        assert_eq!(json, serde_json::json!(16));
+        assert_eq!(serde_json::from_value::<Dan>(json).unwrap(), Dan::Nanadan);
+        assert_eq!(serde_json::from_str::<Dan>("10").unwrap(), Dan::Shodan);
+        assert!(serde_json::from_str::<Dan>("30").is_err());
+
+        let mut player = Player::placeholder(0);
+        player.rank = Dan::Sandan;
+        let json = serde_json::to_value(&player).unwrap();
+        assert_eq!(json["rank"], 12);
+        assert_eq!(json["rankName"], "三段");
+        assert_eq!(
+            serde_json::from_value::<Player>(json).unwrap().rank,
+            Dan::Sandan
+        );
+    }
+
+    #[test]
+    fn test_gender() {
+        assert_eq!(Gender::from_code("F"), Some(Gender::Female));
+        assert_eq!(Gender::from_code("C"), Some(Gender::Computer));
+        assert_eq!(Gender::from_code(""), Some(Gender::Unknown));
+        assert_eq!(Gender::from_code("X"), None);
+
+        assert_eq!(serde_json::to_value(Gender::Male).unwrap(), "M");
+        assert_eq!(serde_json::to_value(Gender::Unknown).unwrap(), "");
+        assert_eq!(
+            serde_json::from_str::<Gender>("\"C\"").unwrap(),
+            Gender::Computer
+        );
+        assert!(serde_json::from_str::<Gender>("\"male\"").is_err());
+    }
+
+    #[test]
+    fn test_link_events() {
+        let draw = |seat: u8| Event::Draw {
+            seat,
+            tile: "1m".parse().unwrap(),
+            wall_position: None,
+            shanten: None,
+        };
+        let discard = |seat: u8| Event::Discard {
+            seat,
+            tile: "9p".parse().unwrap(),
+            is_riichi: false,
+            shanten: None,
+            waits: Vec::new(),
+            furiten: false,
+        };
+        let reach = |step: u8| Event::Reach {
+            who: 0,
+            step,
+            scores: [250; 4],
+            declaration: None,
+            transfers: Vec::new(),
+        };
+        let kan = |who: u8, kan_type: KanType| Event::Kan {
+            who,
+            tiles: vec!["9p".parse().unwrap(); 4],
+            kan_type,
+            from: None,
+            claimed: None,
+            dora: None,
+            rinshan: None,
+        };
+        let mut round = Round {
+            round_id: "Round 1".into(),
+            dealer_seat: 0,
+            init: Init {
+                round_number: 0,
+                honba: 0,
+                kyoutaku: 0,
+                dice: [1, 1],
+                dora_indicator: 0,
+                initial_scores: [250; 4],
+                initial_hands: vec![Vec::new(); 4],
+            },
+            wall: None,
+            raw: Vec::new(),
+            state: None,
+            counters: Vec::new(),
+            events: vec![
+                draw(0),
+                reach(1),
+                discard(0), // 2
+                reach(2),
+                Event::Pon {
+                    who: 2,
+                    tiles: [
+                        "9p".parse().unwrap(),
+                        "9p".parse().unwrap(),
+                        "9p".parse().unwrap(),
+                    ],
+                    from: 0,
+                    claimed: None,
+                },
+                discard(2), // 5
+                kan(3, KanType::Minkan),
+                draw(3), // 7
+                discard(3),
+                Event::Dora {
+                    indicator: "2m".parse().unwrap(),
+                    source: None,
+                    kan: None,
+                    who: None,
+                    timing: None,
+                }, // 9
+                draw(0),
+                kan(0, KanType::Ankan), // 11
+                Event::Dora {
+                    indicator: "3m".parse().unwrap(),
+                    source: None,
+                    kan: None,
+                    who: None,
+                    timing: None,
+                },
+                draw(0),
+            ],
+        };
+        round.link_events();
+
+        assert!(matches!(
+            round.events[3],
+            Event::Reach {
+                declaration: Some(1),
+                ..
+            }
+        ));
+        assert!(matches!(
+            round.events[4],
+            Event::Pon {
+                claimed: Some(2),
+                ..
+            }
+        ));
+        assert!(matches!(
+            round.events[6],
+            Event::Kan {
+                claimed: Some(5),
+                dora: Some(9),
+                rinshan: Some(7),
+                ..
+            }
+        ));
+        assert!(matches!(
+            round.events[11],
+            Event::Kan {
+                claimed: None,
+                dora: Some(12),
+                rinshan: Some(13),
+                ..
+            }
+        ));
+        assert!(matches!(
+            round.event(9),
+            Some(Event::Dora {
+                source: Some(DoraSource::Kan),
+                kan: Some(6),
+                who: Some(3),
+                timing: Some(DoraTiming::AfterDiscard),
+                ..
+            })
+        ));
+        assert!(matches!(
+            round.events[12],
+            Event::Dora {
+                kan: Some(11),
+                who: Some(0),
+                timing: Some(DoraTiming::Immediate),
+                ..
+            }
+        ));
+        assert!(round.event(14).is_none());
+
+        let json = serde_json::to_value(&round.events[4]).unwrap();
+        assert_eq!(json["claimed"], 2);
+        assert!(serde_json::to_value(&round.events[0])
+            .unwrap()
+            .get("claimed")
+            .is_none());
+
+        // A reveal before any kan is the starting indicator
+        round.events.insert(
+            0,
+            Event::Dora {
+                indicator: "1m".parse().unwrap(),
+                source: None,
+                kan: None,
+                who: None,
+                timing: None,
+            },
+        );
+        round.link_events();
+        assert!(matches!(
+            round.events[0],
+            Event::Dora {
+                source: Some(DoraSource::Initial),
+                kan: None,
+                who: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            round.events[13],
+            Event::Dora {
+                source: Some(DoraSource::Kan),
+                kan: Some(12),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_redact_for_seat() {
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4" hai1="1,5" hai2="2,6" hai3="3,7"/>
+<T108/><D108/><U20/><E20/>
+</mjloggm>"#;
+        let options = crate::parser::ParserOptions {
+            include_raw: true,
+            ..Default::default()
+        };
+        let mut output =
+            crate::parser::parse_mjlog_with_options(std::io::Cursor::new(xml), &options).unwrap();
+        output.redact_for_seat(1);
+        let round = &output.rounds[0];
+
+        assert_eq!(round.init.initial_hands[0], vec!["?", "?"]);
+        assert_eq!(round.init.initial_hands[1], vec!["1m", "2m"]);
+        assert!(round.raw.is_empty());
+        let tiles: Vec<&str> = round
+            .events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Draw { tile, .. } | Event::Discard { tile, .. } => Some(tile.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tiles, vec!["?", "east", "6m", "6m"]);
+    }
+
+    #[test]
+    fn test_transfers() {
+        let t = |from: Option<u8>, to: Option<u8>, amount, reason| Transfer {
+            from,
+            to,
+            amount,
+            reason,
+        };
+
+        // Dealer tsumo for 2600 all with one honba and a stick on the table
+        let payout = Payout {
+            hand: 78,
+            honba: 3,
+            riichi_sticks: 10,
+        };
+        let transfers = Transfer::agari(0, 0, &[91, -27, -27, -27], Some(&payout));
+        assert_eq!(transfers.len(), 7);
+        assert_eq!(transfers[0], t(Some(1), Some(0), 26, TransferReason::Hand));
+        assert_eq!(transfers[1], t(Some(1), Some(0), 1, TransferReason::Honba));
+        assert_eq!(
+            transfers[6],
+            t(None, Some(0), 10, TransferReason::RiichiSticks)
+        );
+
+        // Ron without a breakdown
+        assert_eq!(
+            Transfer::agari(2, 3, &[0, 0, 90, -80], None),
+            vec![
+                t(Some(3), Some(2), 80, TransferReason::Hand),
+                t(None, Some(2), 10, TransferReason::RiichiSticks),
+            ]
+        );
+
+        // Two tenpai, two noten
+        assert_eq!(
+            Transfer::draw(&[15, -15, 15, -15]),
+            vec![
+                t(Some(1), Some(0), 15, TransferReason::NotenPenalty),
+                t(Some(3), Some(2), 15, TransferReason::NotenPenalty),
+            ]
+        );
+        assert_eq!(Transfer::draw(&[-10, 30, -10, -10]).len(), 3);
+
+        let json = serde_json::to_value(Transfer::riichi_deposit(1)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"from": 1, "amount": 10, "reason": "riichiDeposit"})
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Clean, indented UTF-8 copies of mjlog files
+//!
+//! Tenhou serves logs as Shift_JIS XML on a single line, often gzipped and
+//! sometimes cut off. `normalize` rewrites a log as UTF-8 with one tag per
+//! line, closing any element the log left open, so that logs can be diffed
+//! and searched with ordinary text tools without converting them to JSON.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::warn;
+use quick_xml::events::{BytesDecl, BytesEnd, Event as XmlEvent};
+use quick_xml::{Reader, Writer};
+
+use crate::encoding::detect_encoding;
+use crate::error::{ParserError, Result};
+use crate::parser::open_input;
+
+/// Rewrite the mjlog read from `reader` as indented UTF-8 XML
+pub fn normalize<R: Read, W: Write>(mut reader: R, writer: W) -> Result<()> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let encoding = detect_encoding(&bytes);
+    let (content, _, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        warn!(
+            "Invalid {} sequences replaced while normalizing",
+            encoding.name()
+        );
+    }
+
+    let mut xml_reader = Reader::from_str(&content);
+    xml_reader.trim_text(true);
+    let mut xml_writer = Writer::new_with_indent(writer, b' ', 2);
+    xml_writer.write_event(XmlEvent::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    // Elements still open, to close if the log stops early
+    let mut open: Vec<String> = Vec::new();
+    loop {
+        let event = match xml_reader.read_event() {
+            Ok(event) => event,
+            Err(quick_xml::Error::UnexpectedEof(_)) => {
+                warn!("Log ends in the middle of a tag; closing open elements");
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        match event {
+            XmlEvent::Eof => break,
+            // Replaced by the UTF-8 declaration above
+            XmlEvent::Decl(_) => {}
+            XmlEvent::Start(e) => {
+                open.push(std::str::from_utf8(e.name().as_ref())?.to_string());
+                xml_writer.write_event(XmlEvent::Start(e))?;
+            }
+            XmlEvent::End(e) => {
+                open.pop();
+                xml_writer.write_event(XmlEvent::End(e))?;
+            }
+            event => xml_writer.write_event(event)?,
+        }
+    }
+    while let Some(name) = open.pop() {
+        xml_writer.write_event(XmlEvent::End(BytesEnd::new(name)))?;
+    }
+
+    let mut writer = xml_writer.into_inner();
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Normalize the mjlog file at `input` into `output`, gzipping the result
+/// when `gzip` is set
+pub fn normalize_file(input: &Path, output: &Path, gzip: bool) -> Result<()> {
+    let file = std::fs::File::create(output).map_err(ParserError::Io)?;
+    let writer = std::io::BufWriter::new(file);
+    if gzip {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        normalize(open_input(input)?, &mut encoder)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    } else {
+        normalize(open_input(input)?, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use encoding_rs::SHIFT_JIS;
+    use std::io::Cursor;
+
+    fn normalized(input: &[u8]) -> String {
+        let mut out = Vec::new();
+        normalize(Cursor::new(input), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_indents_and_reencodes() {
+        let (sjis, _, _) = SHIFT_JIS.encode(
+            r#"<?xml version="1.0" encoding="Shift_JIS"?><mjloggm ver="2.3"><GO type="169" lobby="0" note="東風"/><INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/><T52/><D52/></mjloggm>"#,
+        );
+        let out = normalized(&sjis);
+        assert_eq!(
+            out,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<mjloggm ver="2.3">
+  <GO type="169" lobby="0" note="東風"/>
+  <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+  <T52/>
+  <D52/>
+</mjloggm>
+"#
+        );
+
+        // The result is still an mjlog, and normalizing again changes nothing
+        assert_eq!(
+            parse_mjlog(Cursor::new(out.as_bytes()))
+                .unwrap()
+                .rounds
+                .len(),
+            1
+        );
+        assert_eq!(normalized(out.as_bytes()), out);
+    }
+
+    #[test]
+    fn test_normalize_closes_truncated_log() {
+        let out = normalized(br#"<mjloggm ver="2.3"><T52/><D5"#);
+        assert!(out.ends_with("  <T52/>\n</mjloggm>\n"));
+    }
+}
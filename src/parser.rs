@@ -1,101 +1,695 @@
-use std::io::{Read, Write};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-use encoding_rs::SHIFT_JIS;
 use flate2::read::GzDecoder;
-use log::{debug, info};
-use quick_xml::events::Event as XmlEvent;
+use log::{debug, info, warn};
+use quick_xml::events::{BytesStart, Event as XmlEvent};
 use quick_xml::Reader;
-
-use crate::error::{ParserError, Result};
-use crate::models::{Event, Init, ParserOutput, Player, Round, Rules, RyuukyokuReason, Yaku};
-use crate::tile::{parse_tile_list, tile_id_to_string};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::{DecodingReader, InputEncoding};
+use crate::error::{ErrorLocation, Limit, ParserError, Result};
+use crate::filter::RoundFilter;
+use crate::meld::Meld;
+use crate::models::{
+    Connection, Dan, EndReason, Event, GameResult, Gender, Init, ParseWarning, ParserOutput,
+    Payout, Player, RawTag, Round, RoundWind, Rules, RyuukyokuReason, Transfer, Wall, WallMismatch,
+    WarningKind, Yaku, YakuKind,
+};
+use crate::spool::Spool;
+use crate::state::{event_counters, RoundState};
+use crate::tile::{
+    is_red_five, parse_tile_list, tile_id_to_type, with_tile_format, Tile, TileFormat,
+};
+use crate::wall::WallGenerator;
 
 #[derive(Debug, Clone, Default)]
 pub struct ParserOptions {
     pub verbose: bool,
     pub validate_schema: Option<std::path::PathBuf>,
+    /// Keep the source tag and attributes of every event in `Round::raw`
+    pub include_raw: bool,
+    /// Also keep the exact text and byte range of each source tag; implies
+    /// `include_raw`
+    pub raw_source: bool,
+    /// Reconstruct each round's wall from the SHUFFLE seed and record where
+    /// every draw came from
+    pub annotate_wall: bool,
+    /// Check each round's starting hands, draws and dora indicators against
+    /// the wall rebuilt from the SHUFFLE seed, listing the tiles that differ
+    /// in `ParserOutput::wall_mismatches`
+    pub verify_wall: bool,
+    /// Emit starting hands in tile order rather than the order logged
+    pub sort_hands: bool,
+    /// Write each completed round to a temporary spool file instead of
+    /// keeping it in memory, and stream the rounds back in when the output
+    /// is serialized; see `ParserOutput::spilled`
+    pub spill_rounds: bool,
+    /// Parse the rounds of the log on several threads; takes precedence
+    /// over `spill_rounds`
+    pub parallel: bool,
+    /// Derive `game_id` from the input bytes instead of generating a random
+    /// one, so that the same input always serializes to the same bytes
+    ///
+    /// The game ID is the only source of randomness: fields serialize in
+    /// declaration order, maps are sorted by key and numbers use
+    /// `serde_json`'s shortest round-trip formatting in every mode. The same
+    /// holds for `tenhou.net/6` input read with
+    /// [`crate::tenhou6::parse_tenhou6_with_options`].
+    pub deterministic: bool,
+    /// Use this, usually the Tenhou log ID, as `game_id` instead of a random
+    /// or derived one; further games of a log holding several get `-2`,
+    /// `-3`, ... appended. The functions that read a file default it to the
+    /// log ID in the file name, if there is one
+    pub game_id: Option<String>,
+    /// Keep only the rounds this filter matches; round IDs still count
+    /// every round of the game
+    pub round_filter: Option<RoundFilter>,
+    /// Abort with `ParserError::Cancelled` once this token is cancelled
+    pub cancellation: Option<CancellationToken>,
+    /// Abort with `ParserError::DeadlineExceeded` past this instant
+    pub deadline: Option<Instant>,
+    /// Replay each round's score changes and list the seats whose next
+    /// score disagrees in `ParserOutput::score_warnings`
+    pub validate_scores: bool,
+    /// Replay every round and keep the table after each event in
+    /// `Round::state`
+    pub track_state: bool,
+    /// Record the acting seat's shanten on every draw and discard
+    pub annotate_shanten: bool,
+    /// Record the waits, and furiten on them, of every discard that leaves
+    /// the discarder tenpai
+    pub annotate_waits: bool,
+    /// Record each event's sequence number, the acting seat's turn and the
+    /// tiles left in the live wall in `Round::counters`
+    pub annotate_counters: bool,
+    /// Have `parse_stream` and `parse_file` write minified JSON rather than
+    /// indented JSON
+    pub compact: bool,
+    /// Have `parse_stream` and `parse_file` gzip the JSON they write
+    pub gzip: bool,
+    /// Have `parse_stream` and `parse_file` write every tile as an
+    /// `{id, name}` object (`TileFormat::Object`) rather than a tile string
+    pub tile_ids: bool,
+    /// What to do about malformed input the parser can work around
+    pub strictness: Strictness,
+    /// Encoding of the input; detected from the log by default
+    pub encoding: InputEncoding,
+    /// Fail with `ParserError::LimitExceeded` on input longer than this many
+    /// bytes, before decoding; unlimited by default
+    pub max_input_bytes: Option<u64>,
+    /// Fail with `ParserError::LimitExceeded` at the INIT tag of the round
+    /// after this many
+    pub max_rounds: Option<usize>,
+    /// Fail with `ParserError::LimitExceeded` at the tag that takes a round
+    /// past this many events
+    pub max_events_per_round: Option<usize>,
+}
+
+/// How the parser treats malformed input it can work around: tile IDs out
+/// of range, draws and discards without a tile, tags outside a round,
+/// repeated player lists, walls that do not match their seed and bytes
+/// that are invalid in the log's encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Fail with `ParserError::Parse` at the first anomaly
+    Strict,
+    /// Skip or keep what cannot be read, with a warning in
+    /// `ParserOutput::warnings`
+    #[default]
+    Lenient,
+    /// As lenient, and also list the anomalies for
+    /// [`parse_mjlog_with_warnings`] to return
+    Collect,
+}
+
+/// A handle for aborting parses from another thread
+///
+/// Clones share the same flag, so a service can keep one clone and hand
+/// another to the parse through `ParserOptions::cancellation`. The parser
+/// checks the flag between tags and returns `ParserError::Cancelled`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 /// Parse mjlog file and write JSON to output
 pub fn parse_file(input_path: &Path, output_path: &Path, options: &ParserOptions) -> Result<()> {
     info!("Parsing mjlog file: {:?}", input_path);
 
-    let file = std::fs::File::open(input_path).map_err(ParserError::Io)?;
-
-    let reader: Box<dyn Read> = if input_path.extension().and_then(|s| s.to_str()) == Some("gz") {
-        Box::new(GzDecoder::new(file))
-    } else {
-        Box::new(file)
-    };
+    let reader = open_input(input_path)?;
 
     let output_file = std::fs::File::create(output_path).map_err(ParserError::Io)?;
 
-    parse_stream(reader, output_file, options)?;
+    parse_stream(reader, output_file, &file_options(input_path, options))?;
 
     info!("Successfully parsed mjlog and wrote to: {:?}", output_path);
     Ok(())
 }
 
+/// Open an mjlog file for reading, decompressing `.gz` input
+pub fn open_input(input_path: &Path) -> Result<Box<dyn Read>> {
+    let file = std::fs::File::open(input_path).map_err(ParserError::Io)?;
+
+    if input_path.extension().and_then(|s| s.to_str()) == Some("gz") {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// `options` with the Tenhou log ID in the name of `input_path` as
+/// `game_id`, unless one is given
+pub fn file_options<'a>(input_path: &Path, options: &'a ParserOptions) -> Cow<'a, ParserOptions> {
+    match crate::replay::file_log_id(input_path) {
+        Some(id) if options.game_id.is_none() => Cow::Owned(ParserOptions {
+            game_id: Some(id.to_string()),
+            ..options.clone()
+        }),
+        _ => Cow::Borrowed(options),
+    }
+}
+
+/// Parse mjlog file and return ParserOutput
+pub fn parse_mjlog_file(input_path: &Path) -> Result<ParserOutput> {
+    parse_mjlog_with_options(
+        open_input(input_path)?,
+        &file_options(input_path, &ParserOptions::default()),
+    )
+}
+
+/// Parse many mjlog files on `jobs` threads
+///
+/// Workers take the next unparsed file as they become free, so a few large
+/// logs do not hold up the rest. Results come back in the order of `inputs`,
+/// and each is the same as [`parse_mjlog_with_options`] would give for that
+/// file alone. A `jobs` of 0 uses one thread per available core.
+pub fn parse_files_parallel<P: AsRef<Path> + Sync>(
+    inputs: &[P],
+    options: &ParserOptions,
+    jobs: usize,
+) -> Vec<Result<ParserOutput>> {
+    let jobs = match jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<ParserOutput>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..jobs.min(inputs.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut parsed = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(input) = inputs.get(index) else {
+                            break parsed;
+                        };
+                        let output = open_input(input.as_ref()).and_then(|reader| {
+                            parse_mjlog_with_options(reader, &file_options(input.as_ref(), options))
+                        });
+                        parsed.push((index, output));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("file parser panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, output)| output).collect()
+}
+
 /// Parse mjlog from reader and write JSON to writer
 pub fn parse_stream<R: Read, W: Write>(
     reader: R,
     mut writer: W,
-    _options: &ParserOptions,
+    options: &ParserOptions,
 ) -> Result<()> {
-    let parser_output = parse_mjlog(reader)?;
+    let parser_output = parse_mjlog_with_options(reader, options)?;
 
-    serde_json::to_writer_pretty(&mut writer, &parser_output)
-        .map_err(|e| ParserError::Io(std::io::Error::other(e)))?;
+    if options.gzip {
+        let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+        write_json(&mut encoder, &parser_output, options)?;
+        encoder.finish().map_err(ParserError::Io)?;
+    } else {
+        write_json(&mut writer, &parser_output, options)?;
+    }
 
     Ok(())
 }
 
-/// Maximum file size limit (100MB) to prevent memory exhaustion
-const MAX_FILE_SIZE: usize = 100 * 1024 * 1024;
+fn write_json<W: Write>(writer: W, output: &ParserOutput, options: &ParserOptions) -> Result<()> {
+    let tile_format = if options.tile_ids {
+        TileFormat::Object
+    } else {
+        TileFormat::Name
+    };
+    with_tile_format(tile_format, || {
+        if options.compact {
+            serde_json::to_writer(writer, output)
+        } else {
+            serde_json::to_writer_pretty(writer, output)
+        }
+    })
+    .map_err(|e| ParserError::Io(std::io::Error::other(e)))
+}
 
 /// Parse mjlog from reader and return ParserOutput
 pub fn parse_mjlog<R: Read>(reader: R) -> Result<ParserOutput> {
-    let reader = std::io::BufReader::new(reader);
-    let mut buf = Vec::new();
+    parse_mjlog_with_options(reader, &ParserOptions::default())
+}
 
-    // Read with size limit to prevent memory exhaustion
-    let mut limited_reader = reader.take(MAX_FILE_SIZE as u64);
-    limited_reader.read_to_end(&mut buf)?;
+/// Parse mjlog from reader with the given options
+pub fn parse_mjlog_with_options<R: Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<ParserOutput> {
+    parse_mjlog_with_warnings(reader, options).map(|(output, _)| output)
+}
 
-    // Check if we hit the size limit
-    if buf.len() >= MAX_FILE_SIZE {
-        return Err(ParserError::parse(
-            format!(
-                "File too large (>{} bytes). Maximum allowed size is {} bytes",
-                buf.len(),
-                MAX_FILE_SIZE
-            ),
-            "file size validation",
-        ));
+/// Parse mjlog from reader with the given options, along with the anomalies
+/// worked around in the order they appear in the log
+///
+/// The list is only filled in with `Strictness::Collect`. Of a log holding
+/// several games, only the first is read, with a warning; see
+/// [`parse_mjlog_games`].
+pub fn parse_mjlog_with_warnings<R: Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<(ParserOutput, Vec<ParseWarning>)> {
+    let mut games = parse_games(reader, options, false)?;
+    Ok(games.swap_remove(0))
+}
+
+/// Parse every game of a log, for archives that concatenate several
+/// `<mjloggm>` documents in one file
+///
+/// Each game is what [`parse_mjlog_with_options`] gives for its document
+/// alone, except that byte offsets count from the start of the log and, with
+/// `deterministic`, games after the first have their index hashed into their
+/// ID. A log of one game gives a single game.
+pub fn parse_mjlog_games<R: Read>(reader: R, options: &ParserOptions) -> Result<Vec<ParserOutput>> {
+    let games = parse_games(reader, options, true)?;
+    Ok(games.into_iter().map(|(output, _)| output).collect())
+}
+
+/// The games of a log with their anomalies; only the first unless `all`
+fn parse_games<R: Read>(
+    reader: R,
+    options: &ParserOptions,
+    all: bool,
+) -> Result<Vec<(ParserOutput, Vec<ParseWarning>)>> {
+    // The log is decoded and parsed as it is read, so only the game being
+    // assembled is held in memory
+    let source = SourceReader {
+        inner: reader,
+        hasher: (options.deterministic && options.game_id.is_none()).then(Sha256::new),
+        read: 0,
+        max: options.max_input_bytes,
+    };
+    let mut decoding = DecodingReader::new(source, options.encoding);
+
+    let (parsed, decoding) = if options.parallel {
+        // Splitting the rounds between threads needs the whole document
+        let mut content = String::new();
+        decoding.read_to_string(&mut content)?;
+        decoding.get_ref().check_size()?;
+        (parse_documents_parallel(&content, options, all), decoding)
+    } else {
+        let mut xml_reader = Reader::from_reader(BufReader::new(decoding));
+        xml_reader.trim_text(true);
+        let parsed = parse_documents(&mut xml_reader, options, all);
+        (parsed, xml_reader.into_inner().into_inner())
+    };
+    // Ahead of the parse result, since input cut off at the limit reads as
+    // a truncated or malformed log
+    decoding.get_ref().check_size()?;
+    let parsers = parsed?;
+
+    // Encoding warnings go to the game whose document they fall in
+    let starts: Vec<usize> = parsers.iter().map(|(start, _)| *start).collect();
+    let mut encoding_warnings = vec![Vec::new(); parsers.len()];
+    if let Some(encoding) = decoding.encoding() {
+        if !decoding.replacements().is_empty() {
+            debug!(
+                "Encoding errors detected during {} to UTF-8 conversion, but continuing",
+                encoding.name()
+            );
+        }
+        for &offset in decoding.replacements() {
+            if options.strictness == Strictness::Strict {
+                return Err(ParserError::encoding(format!(
+                    "Invalid {} sequence at byte {}",
+                    encoding.name(),
+                    offset
+                )));
+            }
+            let game = starts.partition_point(|&start| start <= offset).max(1) - 1;
+            let Some(warnings) = encoding_warnings.get_mut(game) else {
+                continue;
+            };
+            warnings.push(ParseWarning {
+                kind: WarningKind::Encoding,
+                offset: Some(offset),
+                tag: None,
+                message: format!("Invalid {} sequence replaced", encoding.name()),
+            });
+        }
     }
+    let hasher = decoding.into_inner().finish()?;
+
+    parsers
+        .into_iter()
+        .zip(encoding_warnings)
+        .enumerate()
+        .map(|(index, ((_, parser), encoding_warnings))| {
+            let game_id = match (&options.game_id, &hasher) {
+                (Some(id), _) if index == 0 => Some(id.clone()),
+                (Some(id), _) => Some(format!("{}-{}", id, index + 1)),
+                (None, Some(hasher)) if index == 0 => Some(digest_game_id(hasher.clone())),
+                (None, Some(hasher)) => Some(digest_game_id(
+                    hasher.clone().chain_update(index.to_string()),
+                )),
+                (None, None) => None,
+            };
+            finish_game(parser, encoding_warnings, game_id, options)
+        })
+        .collect()
+}
 
-    // Convert from Shift_JIS to UTF-8
-    let (content, encoding_used, had_errors) = SHIFT_JIS.decode(&buf);
-    if had_errors {
-        // Log warning but continue processing
-        // Only fail if the encoding is completely wrong or data is severely corrupted
-        if encoding_used != encoding_rs::UTF_8 && content.is_empty() {
-            return Err(ParserError::encoding(
-                "Critical encoding error: Unable to decode any content from Shift_JIS",
-            ));
+/// Parse the `<mjloggm>` documents of `reader` one after another, each with
+/// its own parser, along with the offset each starts at; only the first
+/// unless `all`
+fn parse_documents<R: BufRead>(
+    reader: &mut Reader<R>,
+    options: &ParserOptions,
+    all: bool,
+) -> Result<Vec<(usize, MjlogParser)>> {
+    let mut parsers = Vec::new();
+    let mut next: Option<(BytesStart<'static>, bool, usize)> = None;
+    loop {
+        let mut parser = MjlogParser::with_options(options);
+        if options.spill_rounds {
+            parser.spool = Some(Spool::create()?);
+            parser.spill_options = Some(options.clone());
+        }
+        let start = match &next {
+            Some((e, empty, end)) => {
+                parser.start_tag(e, *empty, *end)?;
+                parser.offset
+            }
+            None => 0,
+        };
+        parser.parse(reader)?;
+        next = parser.next_game.take();
+        if !all {
+            if let Some((e, empty, end)) = next.take() {
+                parser.warn_more_games(tag_start(&e, empty, end));
+            }
         }
-        // For partial errors, log and continue
-        debug!("Encoding errors detected during Shift_JIS to UTF-8 conversion, but continuing");
+        parsers.push((start, parser));
+        if next.is_none() {
+            return Ok(parsers);
+        }
+    }
+}
+
+/// [`parse_documents`] with the rounds of each document parsed on several
+/// threads by [`parse_parallel`]
+fn parse_documents_parallel(
+    content: &str,
+    options: &ParserOptions,
+    all: bool,
+) -> Result<Vec<(usize, MjlogParser)>> {
+    let mut starts = tag_offsets(content, "mjloggm");
+    match starts.first_mut() {
+        Some(first) => *first = 0,
+        None => starts.push(0),
+    }
+    let mut parsers = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(content.len());
+        let mut parser =
+            parse_parallel(&content[start..end], options).map_err(|err| err.shifted(start))?;
+        parser.shift(start);
+        if !all && end < content.len() {
+            parser.warn_more_games(end);
+        }
+        parsers.push((start, parser));
+        if !all {
+            break;
+        }
+    }
+    Ok(parsers)
+}
+
+/// Assemble a parsed game and apply the options that work on the whole of it
+fn finish_game(
+    mut parser: MjlogParser,
+    mut encoding_warnings: Vec<ParseWarning>,
+    game_id: Option<String>,
+    options: &ParserOptions,
+) -> Result<(ParserOutput, Vec<ParseWarning>)> {
+    let truncated_at = parser.truncated_at;
+    let mut warnings = Vec::new();
+    if options.strictness == Strictness::Collect {
+        warnings.extend(encoding_warnings.iter().cloned());
+        warnings.append(&mut parser.anomalies);
+    }
+    encoding_warnings.append(&mut parser.warnings);
+    parser.warnings = encoding_warnings;
+    let mut output = parser.into_output()?;
+    if let Some(game_id) = game_id {
+        output.game_id = game_id;
+    }
+    let players = output.rules.players();
+    for round in &mut output.rounds {
+        annotate_round(round, options, players);
+    }
+    if options.validate_scores {
+        output.score_warnings = match &output.spilled {
+            // Spilled rounds were annotated before they were written out
+            Some(spilled) => {
+                let mut error = None;
+                let rounds = spilled
+                    .rounds()?
+                    .map_while(|round| round.map_err(|err| error = Some(err)).ok());
+                let warnings = crate::validation::check_round_scores(&output, rounds);
+                if let Some(err) = error {
+                    return Err(err);
+                }
+                warnings
+            }
+            None => crate::validation::check_scores(&output),
+        };
+        output
+            .warnings
+            .extend(output.score_warnings.iter().map(ParseWarning::from));
+    }
+    match truncated_at {
+        Some(offset) => Err(ParserError::TruncatedLog {
+            offset,
+            partial: Box::new(output),
+        }),
+        None => Ok((output, warnings)),
+    }
+}
+
+/// Apply the options that annotate rounds one at a time to `round`
+fn annotate_round(round: &mut Round, options: &ParserOptions, players: u8) {
+    if options.sort_hands {
+        round.sort_hands();
+    }
+    if options.annotate_shanten {
+        crate::analysis::annotate_shanten(round);
+    }
+    if options.annotate_waits {
+        crate::analysis::annotate_waits(round);
+    }
+    if options.track_state {
+        round.state = Some(RoundState::replay(round, players));
+    }
+    if options.annotate_counters {
+        round.counters = event_counters(round, players);
+    }
+}
+
+/// An item of the stream produced by [`parse_events`]
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    /// `<mjloggm ver>`
+    Version(String),
+    Rules(Rules),
+    Players(Vec<Player>),
+    /// The start of a round, before any of its events
+    RoundStart {
+        round_id: String,
+        dealer_seat: u8,
+        init: Init,
+    },
+    /// An event of the round last started
+    Event(Event),
+    /// Final result from the `owari` attribute of the last settlement
+    GameEnd(GameResult),
+}
+
+/// Parse an mjlog lazily, one tag at a time
+///
+/// Unlike [`parse_mjlog`], nothing is kept once it has been yielded, so
+/// archives of any size can be processed in constant memory. The input is
+/// the decompressed log, as returned by [`open_input`].
+///
+/// Events refer to one another only through IDs resolved once a round is
+/// complete, so the links (`claimed`, `dora`, `rinshan`, `declaration` and
+/// `kan`) are left empty here. A log that stops in the middle of a round
+/// ends the stream with [`ParserError::TruncatedLog`]; the events yielded
+/// before it are the partial log.
+pub fn parse_events<R: Read>(reader: R) -> LogEvents<R> {
+    let mut reader = Reader::from_reader(BufReader::new(DecodingReader::new(
+        reader,
+        InputEncoding::Auto,
+    )));
+    reader.trim_text(true);
+    LogEvents {
+        reader,
+        buf: Vec::new(),
+        parser: MjlogParser::new(),
+        pending: VecDeque::new(),
+        closed: false,
+        settled: true,
+        done: false,
+    }
+}
+
+/// Iterator returned by [`parse_events`]
+pub struct LogEvents<R: Read> {
+    reader: Reader<BufReader<DecodingReader<R>>>,
+    buf: Vec<u8>,
+    parser: MjlogParser,
+    pending: VecDeque<LogEvent>,
+    closed: bool,
+    /// Whether the last event yielded ended a round
+    settled: bool,
+    done: bool,
+}
+
+impl<R: Read> LogEvents<R> {
+    /// Read the next tag, queueing whatever it produced; false at the end
+    /// of the input
+    fn step(&mut self) -> Result<bool> {
+        self.buf.clear();
+        let (e, empty) = match self.reader.read_event_into(&mut self.buf) {
+            Ok(XmlEvent::Start(e)) => (e, false),
+            Ok(XmlEvent::Empty(e)) => (e, true),
+            Ok(XmlEvent::End(e)) => {
+                self.closed |= e.name().as_ref() == b"mjloggm";
+                return Ok(true);
+            }
+            Ok(XmlEvent::Eof) => return self.finish().map(|_| false),
+            Err(quick_xml::Error::UnexpectedEof(_)) => {
+                self.closed = false;
+                self.settled = false;
+                return self.finish().map(|_| false);
+            }
+            Err(e) => return Err(e.into()),
+            Ok(_) => return Ok(true),
+        };
+
+        let had_players = !self.parser.players.is_empty();
+        self.parser
+            .parse_located(&e, empty, self.reader.buffer_position())?;
+        match e.name().as_ref() {
+            b"mjloggm" => self
+                .pending
+                .push_back(LogEvent::Version(self.parser.mjlog_version.clone())),
+            b"GO" => {
+                if let Some(rules) = &self.parser.rules {
+                    self.pending.push_back(LogEvent::Rules(rules.clone()));
+                }
+            }
+            b"UN" if !had_players && !self.parser.players.is_empty() => self
+                .pending
+                .push_back(LogEvent::Players(self.parser.players.clone())),
+            b"INIT" => {
+                if let Some(round) = &self.parser.current_round {
+                    self.settled = false;
+                    self.pending.push_back(LogEvent::RoundStart {
+                        round_id: round.round_id.clone(),
+                        dealer_seat: round.dealer_seat,
+                        init: round.init.clone(),
+                    });
+                }
+            }
+            _ => {}
+        }
+        if let Some(round) = &mut self.parser.current_round {
+            for event in round.events.drain(..) {
+                self.settled = matches!(event, Event::Agari { .. } | Event::Ryuukyoku { .. });
+                self.pending.push_back(LogEvent::Event(event));
+            }
+        }
+        if let Some(result) = self.parser.result.take() {
+            self.pending.push_back(LogEvent::GameEnd(result));
+        }
+        // Completed rounds hold no events by now, but are not kept either
+        self.parser.round_offset += self.parser.rounds.len();
+        self.parser.rounds.clear();
+        Ok(true)
     }
 
-    let mut xml_reader = Reader::from_str(&content);
-    xml_reader.trim_text(true);
+    fn finish(&mut self) -> Result<()> {
+        if self.closed || self.settled {
+            return Ok(());
+        }
+        let parser = std::mem::replace(&mut self.parser, MjlogParser::new());
+        Err(ParserError::TruncatedLog {
+            offset: self.reader.buffer_position(),
+            partial: Box::new(parser.into_output()?),
+        })
+    }
+}
 
-    let mut parser = MjlogParser::new();
-    parser.parse(&mut xml_reader)?;
+impl<R: Read> Iterator for LogEvents<R> {
+    type Item = Result<LogEvent>;
 
-    Ok(parser.into_output())
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+            match self.step() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
 }
 
 struct MjlogParser {
@@ -104,7 +698,54 @@ struct MjlogParser {
     rules: Option<Rules>,
     players: Vec<Player>,
     rounds: Vec<Round>,
+    /// Where completed rounds go instead of `rounds` when spilling
+    spool: Option<Spool>,
+    /// Options applied to each round before it is spilled
+    spill_options: Option<ParserOptions>,
+    /// Rounds before the first one this parser sees, when it parses one
+    /// segment of a log
+    round_offset: usize,
+    round_filter: Option<RoundFilter>,
+    /// Rounds left out by `round_filter`
+    skipped_rounds: usize,
+    /// Latest wind among the skipped and spilled rounds, to tell whether the
+    /// game went into overtime
+    skipped_wind: Option<RoundWind>,
     current_round: Option<Round>,
+    /// Seats that declared riichi and have yet to make the riichi discard
+    riichi_pending: [bool; 4],
+    warnings: Vec<ParseWarning>,
+    strictness: Strictness,
+    /// Anomalies recorded in `Strictness::Collect` mode
+    anomalies: Vec<ParseWarning>,
+    max_rounds: Option<usize>,
+    max_events_per_round: Option<usize>,
+    /// Whether the `<mjloggm>` tag has been read
+    opened: bool,
+    /// The `<mjloggm>` tag of a game concatenated after this one, which
+    /// ended the parse, and the offset it ends at
+    next_game: Option<(BytesStart<'static>, bool, usize)>,
+    /// Name and byte offset of the tag being parsed
+    tag: String,
+    offset: usize,
+    include_raw: bool,
+    raw_source: bool,
+    annotate_wall: bool,
+    verify_wall: bool,
+    /// The wall generated for the current round, kept for `verify_wall`
+    round_wall: Option<Wall>,
+    wall_mismatches: Vec<WallMismatch>,
+    /// Disconnects and reconnects outside a round
+    connections: Vec<Connection>,
+    walls: Option<WallGenerator>,
+    /// Wall of the next round, generated ahead of time
+    next_wall: Option<Wall>,
+    /// Final result from the `owari` attribute of the last settlement
+    result: Option<GameResult>,
+    /// Where the input ran out, if it stopped in the middle of a round
+    truncated_at: Option<usize>,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
 }
 
 impl MjlogParser {
@@ -115,46 +756,204 @@ impl MjlogParser {
             rules: None,
             players: Vec::new(),
             rounds: Vec::new(),
+            spool: None,
+            spill_options: None,
+            round_offset: 0,
+            round_filter: None,
+            skipped_rounds: 0,
+            skipped_wind: None,
             current_round: None,
+            riichi_pending: [false; 4],
+            warnings: Vec::new(),
+            strictness: Strictness::Lenient,
+            anomalies: Vec::new(),
+            max_rounds: None,
+            max_events_per_round: None,
+            opened: false,
+            next_game: None,
+            tag: String::new(),
+            offset: 0,
+            include_raw: false,
+            raw_source: false,
+            annotate_wall: false,
+            verify_wall: false,
+            round_wall: None,
+            wall_mismatches: Vec::new(),
+            connections: Vec::new(),
+            walls: None,
+            next_wall: None,
+            result: None,
+            truncated_at: None,
+            cancellation: None,
+            deadline: None,
+        }
+    }
+
+    fn with_options(options: &ParserOptions) -> Self {
+        let mut parser = Self::new();
+        parser.include_raw = options.include_raw || options.raw_source;
+        parser.raw_source = options.raw_source;
+        parser.annotate_wall = options.annotate_wall;
+        parser.verify_wall = options.verify_wall;
+        parser.round_filter = options.round_filter.clone();
+        parser.cancellation = options.cancellation.clone();
+        parser.deadline = options.deadline;
+        parser.strictness = options.strictness;
+        parser.max_rounds = options.max_rounds;
+        parser.max_events_per_round = options.max_events_per_round;
+        parser
+    }
+
+    /// Fail if the caller has given up on this parse
+    fn check_interrupted(&self) -> Result<()> {
+        if self.cancellation.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(ParserError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(ParserError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    /// Record a warning about the tag being parsed
+    fn warn(&mut self, kind: WarningKind, message: impl Into<String>) {
+        let warning = ParseWarning {
+            kind,
+            offset: Some(self.offset),
+            tag: Some(self.tag.clone()),
+            message: message.into(),
+        };
+        warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    /// Deal with malformed input in the tag being parsed as `strictness`
+    /// says: fail in strict mode, otherwise record a warning, and in collect
+    /// mode list it as an anomaly too
+    fn anomaly(&mut self, kind: WarningKind, message: impl Into<String>) -> Result<()> {
+        let message = message.into();
+        if self.strictness == Strictness::Strict {
+            return Err(ParserError::parse(message, "strict parse").with_location(self.location()));
+        }
+        self.warn(kind, message);
+        if self.strictness == Strictness::Collect {
+            self.anomalies.extend(self.warnings.last().cloned());
+        }
+        Ok(())
+    }
+
+    /// Where the tag being parsed is
+    fn location(&self) -> ErrorLocation {
+        let round = if self.tag == "INIT" {
+            Some(self.round_count() + 1 + usize::from(self.current_round.is_some()))
+        } else {
+            self.current_round.as_ref().map(|_| self.round_count() + 1)
+        };
+        ErrorLocation {
+            offset: self.offset,
+            tag: self.tag.clone(),
+            round,
+        }
+    }
+
+    /// Parse one start or empty tag that ends at byte `end`, giving errors
+    /// its location
+    fn parse_located(
+        &mut self,
+        e: &quick_xml::events::BytesStart,
+        empty: bool,
+        end: usize,
+    ) -> Result<()> {
+        self.tag = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+        self.offset = tag_start(e, empty, end);
+        self.parse_tag(e)
+            .and_then(|()| self.check_limits())
+            .map_err(|err| err.with_location(self.location()))
+    }
+
+    /// Fail once the tag just parsed has taken the log past `max_rounds` or
+    /// its round past `max_events_per_round`
+    fn check_limits(&self) -> Result<()> {
+        let exceeded = |limit, max: usize| ParserError::LimitExceeded {
+            limit,
+            max: max as u64,
+            location: None,
+        };
+        if let Some(max) = self.max_rounds {
+            if self.current_round.is_some() && self.round_count() >= max {
+                return Err(exceeded(Limit::Rounds, max));
+            }
+        }
+        if let Some(max) = self.max_events_per_round {
+            if self
+                .current_round
+                .as_ref()
+                .is_some_and(|round| round.events.len() > max)
+            {
+                return Err(exceeded(Limit::EventsPerRound, max));
+            }
+        }
+        Ok(())
+    }
+
+    /// The round the tag being parsed belongs to; none, after an anomaly,
+    /// before the first INIT
+    fn open_round(&mut self) -> Result<Option<&mut Round>> {
+        if self.current_round.is_none() {
+            let message = format!("{} tag outside a round ignored", self.tag);
+            self.anomaly(WarningKind::Malformed, message)?;
+        }
+        Ok(self.current_round.as_mut())
+    }
+
+    /// `Tile::from_id`, noting IDs past the last tile as an anomaly
+    fn tile(&mut self, id: u32) -> Result<Tile> {
+        let tile = Tile::from_id(id);
+        if !tile.is_known() {
+            self.anomaly(WarningKind::Malformed, format!("Invalid tile ID: {}", id))?;
         }
+        Ok(tile)
     }
 
     fn parse<R: std::io::BufRead>(&mut self, reader: &mut Reader<R>) -> Result<()> {
         let mut buf = Vec::new();
+        let mut closed = false;
 
         loop {
-            match reader.read_event_into(&mut buf)? {
+            self.check_interrupted()?;
+            let event = match reader.read_event_into(&mut buf) {
+                Ok(event) => event,
+                Err(quick_xml::Error::UnexpectedEof(_)) => {
+                    self.truncated_at = Some(reader.buffer_position());
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            match event {
                 XmlEvent::Start(ref e) | XmlEvent::Empty(ref e) => {
-                    let tag_name = e.name();
-                    let tag_bytes = tag_name.as_ref();
-                    match tag_bytes {
-                        b"mjloggm" => self.parse_mjloggm(e)?,
-                        b"GO" => self.parse_go(e)?,
-                        b"UN" => self.parse_un(e)?,
-                        b"TAIKYOKU" => self.parse_taikyoku(e)?,
-                        b"INIT" => self.parse_init(e)?,
-                        b"N" => self.parse_naki(e)?,
-                        b"DORA" => self.parse_dora(e)?,
-                        b"REACH" => self.parse_reach(e)?,
-                        b"AGARI" => self.parse_agari(e)?,
-                        b"RYUUKYOKU" => self.parse_ryuukyoku(e)?,
-                        _ => {
-                            // Check if it's a draw or discard tag
-                            if !tag_bytes.is_empty() {
-                                let first_byte = tag_bytes[0];
-                                match first_byte {
-                                    b'T' | b'U' | b'V' | b'W' => self.parse_draw(e)?,
-                                    b'D' | b'E' | b'F' | b'G' => self.parse_discard(e)?,
-                                    _ => {
-                                        debug!("Unknown tag: {:?}", std::str::from_utf8(tag_bytes));
-                                    }
-                                }
-                            }
+                    let empty = matches!(event, XmlEvent::Empty(_));
+                    let end = reader.buffer_position();
+                    if self.opened && e.name().as_ref() == b"mjloggm" {
+                        // Another game follows, which is left to a parser
+                        // of its own
+                        if !closed && self.unsettled() {
+                            self.truncated_at = Some(tag_start(e, empty, end));
                         }
+                        self.next_game = Some((e.to_owned(), empty, end));
+                        break;
+                    }
+                    self.start_tag(e, empty, end)?;
+                }
+                XmlEvent::End(ref e) => closed |= e.name().as_ref() == b"mjloggm",
+                XmlEvent::Eof => {
+                    if !closed && self.unsettled() {
+                        self.truncated_at = Some(reader.buffer_position());
                     }
+                    break;
                 }
-                XmlEvent::End(_) => {}
-                XmlEvent::Eof => break,
                 _ => {}
             }
             buf.clear();
@@ -162,13 +961,99 @@ impl MjlogParser {
 
         // Finish current round if any
         if let Some(round) = self.current_round.take() {
-            self.rounds.push(round);
+            self.finish_round(round)?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse one start or empty tag that ends at byte `end`, keeping its raw
+    /// form when asked to
+    fn start_tag(&mut self, e: &BytesStart, empty: bool, end: usize) -> Result<()> {
+        let emitted = self.current_round.as_ref().map(|r| r.events.len());
+        self.parse_located(e, empty, end)?;
+        if self.include_raw {
+            let source = self
+                .raw_source
+                .then(|| source_text(e, empty, end))
+                .transpose()?;
+            self.capture_raw(e, emitted, source)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the round being parsed has yet to end in a win or draw
+    fn unsettled(&self) -> bool {
+        self.current_round.as_ref().is_some_and(|round| {
+            !matches!(
+                round.events.last(),
+                Some(Event::Agari { .. } | Event::Ryuukyoku { .. })
+            )
+        })
+    }
+
+    /// Note that the log goes on with another game from `offset`, which is
+    /// not read
+    fn warn_more_games(&mut self, offset: usize) {
+        self.tag = "mjloggm".to_string();
+        self.offset = offset;
+        self.warn(
+            WarningKind::Malformed,
+            "More games follow in the log; only the first is read",
+        );
+    }
+
+    /// Move every byte offset `by` bytes later, for a part of a log parsed
+    /// on its own; rounds spilled to disk are left as they are
+    fn shift(&mut self, by: usize) {
+        for span in self
+            .rounds
+            .iter_mut()
+            .flat_map(|round| &mut round.raw)
+            .filter_map(|raw| raw.span.as_mut())
+        {
+            *span = by + span.start..by + span.end;
         }
+        for warning in self.warnings.iter_mut().chain(&mut self.anomalies) {
+            warning.offset = warning.offset.map(|offset| by + offset);
+        }
+        self.truncated_at = self.truncated_at.map(|offset| by + offset);
+    }
 
+    /// Dispatch one start or empty tag to its parser
+    fn parse_tag(&mut self, e: &quick_xml::events::BytesStart) -> Result<()> {
+        let tag_name = e.name();
+        let tag_bytes = tag_name.as_ref();
+        match tag_bytes {
+            b"mjloggm" => self.parse_mjloggm(e)?,
+            b"GO" => self.parse_go(e)?,
+            b"UN" => self.parse_un(e)?,
+            b"TAIKYOKU" => self.parse_taikyoku(e)?,
+            b"INIT" => self.parse_init(e)?,
+            b"N" => self.parse_naki(e)?,
+            b"DORA" => self.parse_dora(e)?,
+            b"REACH" => self.parse_reach(e)?,
+            b"AGARI" => self.parse_agari(e)?,
+            b"RYUUKYOKU" => self.parse_ryuukyoku(e)?,
+            b"SHUFFLE" => self.parse_shuffle(e)?,
+            b"BYE" => self.parse_bye(e)?,
+            _ => {
+                // Check if it's a draw or discard tag
+                if !tag_bytes.is_empty() {
+                    let first_byte = tag_bytes[0];
+                    match first_byte {
+                        b'T' | b'U' | b'V' | b'W' => self.parse_draw(e)?,
+                        b'D' | b'E' | b'F' | b'G' => self.parse_discard(e)?,
+                        _ => self.parse_unknown(e)?,
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
     fn parse_mjloggm(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        self.opened = true;
         for attr in element.attributes() {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
             if attr.key.as_ref() == b"ver" {
@@ -204,10 +1089,14 @@ impl MjlogParser {
     }
 
     fn parse_un(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        if !self.players.is_empty() {
+            return self.parse_reconnect(element);
+        }
+
         let mut names = vec![String::new(); 4];
-        let mut dans = [0u32; 4];
+        let mut dans = [Dan::Shinjin; 4];
         let mut rates = [0u32; 4];
-        let mut genders = vec![String::new(); 4];
+        let mut genders = [Gender::Unknown; 4];
 
         for attr in element.attributes() {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
@@ -222,7 +1111,14 @@ impl MjlogParser {
                 "dan" => {
                     let parts: Vec<&str> = value.split(',').collect();
                     for (i, &part) in parts.iter().enumerate().take(4) {
-                        dans[i] = part.parse()?;
+                        match Dan::from_rank(part.parse()?) {
+                            Some(dan) => dans[i] = dan,
+                            // Left as 新人
+                            None => self.anomaly(
+                                WarningKind::Malformed,
+                                format!("Invalid dan rank: {}", part),
+                            )?,
+                        }
                     }
                 }
                 "rate" => {
@@ -234,26 +1130,65 @@ impl MjlogParser {
                 "sx" => {
                     let parts: Vec<&str> = value.split(',').collect();
                     for (i, &part) in parts.iter().enumerate().take(4) {
-                        genders[i] = part.to_string();
+                        match Gender::from_code(part) {
+                            Some(gender) => genders[i] = gender,
+                            // Left unknown
+                            None => self.anomaly(
+                                WarningKind::Malformed,
+                                format!("Invalid gender: {}", part),
+                            )?,
+                        }
                     }
                 }
                 _ => {}
             }
         }
 
+        // The fourth seat of a three-player game is empty; any other seat
+        // without a name is taken by a player the log does not name
+        let seats = self.rules.as_ref().map_or(4, Rules::players) as usize;
+        for (seat, name) in names.iter_mut().enumerate().take(seats) {
+            if name.is_empty() {
+                self.warn(
+                    WarningKind::Players,
+                    format!("Seat {} has no name; using placeholder", seat),
+                );
+                *name = Player::PLACEHOLDER_ID.to_string();
+            }
+        }
+
         for i in 0..4 {
             self.players.push(Player {
                 seat: i as u8,
                 player_id: names[i].clone(),
                 rank: dans[i],
                 rate: rates[i],
-                gender: genders[i].clone(),
+                gender: genders[i],
+                profile: None,
             });
         }
 
         Ok(())
     }
 
+    fn parse_shuffle(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        if !self.annotate_wall && !self.verify_wall {
+            return Ok(());
+        }
+        for attr in element.attributes() {
+            let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
+            if attr.key.as_ref() == b"seed" {
+                match WallGenerator::from_seed(std::str::from_utf8(&attr.value)?) {
+                    Ok(walls) => self.walls = Some(walls),
+                    Err(e) => {
+                        self.warn(WarningKind::Wall, format!("Wall not reconstructed: {}", e))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn parse_taikyoku(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
         for attr in element.attributes() {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
@@ -265,6 +1200,57 @@ impl MjlogParser {
         Ok(())
     }
 
+    /// A UN tag after the first one announces players coming back; a full
+    /// one with ranks repeats the player list
+    fn parse_reconnect(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        let mut seats = Vec::new();
+        for attr in element.attributes() {
+            let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
+            match attr.key.as_ref() {
+                key @ (b"n0" | b"n1" | b"n2" | b"n3") => seats.push(key[1] - b'0'),
+                b"dan" => {
+                    self.anomaly(WarningKind::Players, "Duplicate UN tag ignored")?;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        for who in seats {
+            self.connection(Event::Reconnect { who });
+        }
+        Ok(())
+    }
+
+    fn parse_bye(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        let mut who = None;
+        for attr in element.attributes() {
+            let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
+            if attr.key.as_ref() == b"who" {
+                who = Some(std::str::from_utf8(&attr.value)?.parse()?);
+            }
+        }
+        let who = who.ok_or_else(|| ParserError::invalid_format("BYE tag without who"))?;
+        self.connection(Event::Disconnect { who });
+        Ok(())
+    }
+
+    /// Add a disconnect or reconnect to the round being played, or to the
+    /// game's `connections` when it comes before the first round or after a
+    /// round has ended
+    fn connection(&mut self, event: Event) {
+        if self.unsettled() {
+            if let Some(round) = &mut self.current_round {
+                round.events.push(event);
+            }
+        } else {
+            let after_rounds = self.round_count() + usize::from(self.current_round.is_some());
+            self.connections.push(Connection {
+                after_rounds,
+                event,
+            });
+        }
+    }
+
     fn parse_init(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
         let mut seed = String::new();
         let mut ten = String::new();
@@ -318,6 +1304,10 @@ impl MjlogParser {
         let mut initial_hands = Vec::new();
         for hand in &hands {
             let tiles = parse_tile_list(hand)?;
+            if let Some(tile) = tiles.iter().find(|tile| !tile.is_known()) {
+                let message = format!("Invalid tile ID: {}", tile.id());
+                self.anomaly(WarningKind::Malformed, message)?;
+            }
             initial_hands.push(tiles);
         }
 
@@ -333,15 +1323,49 @@ impl MjlogParser {
 
         // Finish previous round if any
         if let Some(round) = self.current_round.take() {
-            self.rounds.push(round);
+            self.finish_round(round)?;
+        }
+
+        let generated = self
+            .next_wall
+            .take()
+            .or_else(|| self.walls.as_mut().map(WallGenerator::next_wall));
+        if self.verify_wall {
+            self.round_wall = generated.clone();
         }
+        let wall = match generated {
+            Some(wall) if self.annotate_wall => {
+                let dealt = hands
+                    .iter()
+                    .map(|hand| parse_id_list(hand))
+                    .collect::<Result<Vec<_>>>()?;
+                if wall.matches(oya, &dealt, dora_indicator) {
+                    Some(wall)
+                } else {
+                    self.warn(
+                        WarningKind::Wall,
+                        format!(
+                            "Reconstructed wall does not match round {}; wall not annotated",
+                            self.round_count() + 1
+                        ),
+                    );
+                    None
+                }
+            }
+            _ => None,
+        };
 
-        let round_id = format!("Round {}", self.rounds.len() + 1);
+        let round_id = format!("Round {}", self.round_count() + 1);
+        self.riichi_pending = [false; 4];
         self.current_round = Some(Round {
             round_id,
             dealer_seat: oya,
             init,
             events: Vec::new(),
+            wall,
+            raw: Vec::new(),
+            state: None,
+            counters: Vec::new(),
         });
 
         Ok(())
@@ -358,34 +1382,21 @@ impl MjlogParser {
             _ => return Err(ParserError::invalid_format("Invalid draw tag")),
         };
 
-        // Parse tile ID from element content/attributes
+        if self.open_round()?.is_none() {
+            return Ok(());
+        }
+        let Some(id) = tile_id(element)? else {
+            return self.anomaly(WarningKind::Malformed, "Draw without a tile ID ignored");
+        };
+        let tile = self.tile(id)?;
         if let Some(round) = &mut self.current_round {
-            let mut tile_id = None;
-
-            // Try to get tile ID from attributes first
-            for attr in element.attributes() {
-                let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
-                if attr.key.as_ref().is_empty() {
-                    tile_id = Some(std::str::from_utf8(&attr.value)?.parse()?);
-                    break;
-                }
-            }
-
-            // If no attribute, try to parse from tag name (e.g., T52 -> 52)
-            if tile_id.is_none() {
-                let name = element.name();
-                let tag_name = std::str::from_utf8(name.as_ref())?;
-                if tag_name.len() > 1 {
-                    if let Ok(id) = tag_name[1..].parse() {
-                        tile_id = Some(id);
-                    }
-                }
-            }
-
-            if let Some(id) = tile_id {
-                let tile = tile_id_to_string(id).into_owned();
-                round.events.push(Event::Draw { seat, tile });
-            }
+            let wall_position = round.wall.as_ref().and_then(|wall| wall.position(id));
+            round.events.push(Event::Draw {
+                seat,
+                tile,
+                wall_position,
+                shanten: None,
+            });
         }
 
         Ok(())
@@ -402,37 +1413,23 @@ impl MjlogParser {
             _ => return Err(ParserError::invalid_format("Invalid discard tag")),
         };
 
+        if self.open_round()?.is_none() {
+            return Ok(());
+        }
+        let Some(id) = tile_id(element)? else {
+            return self.anomaly(WarningKind::Malformed, "Discard without a tile ID ignored");
+        };
+        let tile = self.tile(id)?;
+        let is_riichi = std::mem::take(&mut self.riichi_pending[seat as usize]);
         if let Some(round) = &mut self.current_round {
-            let mut tile_id = None;
-
-            // Try to get tile ID from attributes first
-            for attr in element.attributes() {
-                let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
-                if attr.key.as_ref().is_empty() {
-                    tile_id = Some(std::str::from_utf8(&attr.value)?.parse()?);
-                    break;
-                }
-            }
-
-            // If no attribute, try to parse from tag name (e.g., D52 -> 52)
-            if tile_id.is_none() {
-                let name = element.name();
-                let tag_name = std::str::from_utf8(name.as_ref())?;
-                if tag_name.len() > 1 {
-                    if let Ok(id) = tag_name[1..].parse() {
-                        tile_id = Some(id);
-                    }
-                }
-            }
-
-            if let Some(id) = tile_id {
-                let tile = tile_id_to_string(id).into_owned();
-                round.events.push(Event::Discard {
-                    seat,
-                    tile,
-                    is_riichi: false, // TODO: Detect riichi discard
-                });
-            }
+            round.events.push(Event::Discard {
+                seat,
+                tile,
+                is_riichi,
+                shanten: None,
+                waits: Vec::new(),
+                furiten: false,
+            });
         }
 
         Ok(())
@@ -440,26 +1437,25 @@ impl MjlogParser {
 
     fn parse_naki(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
         let mut who = 0u8;
-        let mut _meld = String::new();
+        let mut code = None;
 
         for attr in element.attributes() {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
             match attr.key.as_ref() {
                 b"who" => who = std::str::from_utf8(&attr.value)?.parse()?,
-                b"meld" => _meld = std::str::from_utf8(&attr.value)?.to_string(),
+                b"m" => code = Some(std::str::from_utf8(&attr.value)?.parse()?),
                 _ => {}
             }
         }
 
-        // TODO: Parse meld data to determine chi/pon/kan type and tiles
-        // For now, create a generic pon event
-        if let Some(round) = &mut self.current_round {
-            let tiles = ["1m".to_string(), "1m".to_string(), "1m".to_string()];
-            round.events.push(Event::Pon {
-                who,
-                tiles,
-                from: 0, // TODO: Determine from meld data
-            });
+        let code = code.ok_or_else(|| ParserError::invalid_format("N tag without m"))?;
+        let meld = Meld::decode(code)?;
+        let Some(event) = meld.to_event(who) else {
+            // A north set aside in three-player games has no event of its own
+            return self.keep_unknown(element);
+        };
+        if let Some(round) = self.open_round()? {
+            round.events.push(event);
         }
 
         Ok(())
@@ -470,15 +1466,70 @@ impl MjlogParser {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
             if attr.key.as_ref() == b"hai" {
                 let tile_id: u32 = std::str::from_utf8(&attr.value)?.parse()?;
-                let indicator = tile_id_to_string(tile_id).into_owned();
-                if let Some(round) = &mut self.current_round {
-                    round.events.push(Event::Dora { indicator });
+                let indicator = self.tile(tile_id)?;
+                if let Some(round) = self.open_round()? {
+                    round.events.push(Event::Dora {
+                        indicator,
+                        source: None,
+                        kan: None,
+                        who: None,
+                        timing: None,
+                    });
                 }
             }
         }
         Ok(())
     }
 
+    /// Keep an unrecognized tag inside a round as `Event::Unknown`
+    fn parse_unknown(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        if self.current_round.is_none() {
+            let message = format!("Unknown tag {} outside a round ignored", self.tag);
+            return self.anomaly(WarningKind::UnknownTag, message);
+        }
+        self.warn(WarningKind::UnknownTag, format!("Unknown tag {}", self.tag));
+        self.keep_unknown(element)
+    }
+
+    /// Add `element` to the round as `Event::Unknown`
+    fn keep_unknown(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
+        let tag = std::str::from_utf8(element.name().as_ref())?.to_string();
+        let Some(round) = self.open_round()? else {
+            return Ok(());
+        };
+
+        let attributes = attribute_map(element)?;
+        round.events.push(Event::Unknown { tag, attributes });
+        Ok(())
+    }
+
+    /// Record `element` as the source of the events it added to the round,
+    /// given the event count before it was parsed and, with `raw_source`,
+    /// the tag's text and byte range
+    fn capture_raw(
+        &mut self,
+        element: &quick_xml::events::BytesStart,
+        emitted: Option<usize>,
+        source: Option<(String, Range<usize>)>,
+    ) -> Result<()> {
+        let Some(round) = &mut self.current_round else {
+            return Ok(());
+        };
+        let added = round.events.len().saturating_sub(emitted.unwrap_or(0));
+        if added == 0 {
+            return Ok(());
+        }
+        let (source, span) = source.unzip();
+        let raw = RawTag {
+            tag: std::str::from_utf8(element.name().as_ref())?.to_string(),
+            attributes: attribute_map(element)?,
+            source,
+            span,
+        };
+        round.raw.extend(std::iter::repeat_n(raw, added));
+        Ok(())
+    }
+
     fn parse_reach(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
         let mut who = 0u8;
         let mut step = 1u8;
@@ -500,8 +1551,21 @@ impl MjlogParser {
             }
         }
 
-        if let Some(round) = &mut self.current_round {
-            round.events.push(Event::Reach { who, step, scores });
+        if step == 1 {
+            self.riichi_pending[who as usize % 4] = true;
+        }
+        if let Some(round) = self.open_round()? {
+            round.events.push(Event::Reach {
+                who,
+                step,
+                scores,
+                declaration: None,
+                transfers: if step == 2 {
+                    vec![Transfer::riichi_deposit(who)]
+                } else {
+                    Vec::new()
+                },
+            });
         }
 
         Ok(())
@@ -510,33 +1574,66 @@ impl MjlogParser {
     fn parse_agari(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
         let mut who = 0u8;
         let mut from = 0u8;
-        let mut han = 0u32;
         let mut fu = 0u32;
         let mut yakus = Vec::new();
-        let dora_count = 0u32;
         let mut scores = [0i32; 4];
+        let mut hand_value = None;
+        let mut ba = None;
+        let mut hand = Vec::new();
+        let mut melds = Vec::new();
+        let mut winning_tile = None;
+        let mut dora_indicators = Vec::new();
+        let mut ura_dora_indicators = Vec::new();
 
         for attr in element.attributes() {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
             match attr.key.as_ref() {
                 b"who" => who = std::str::from_utf8(&attr.value)?.parse()?,
                 b"fromWho" => from = std::str::from_utf8(&attr.value)?.parse()?,
+                b"hai" => hand = parse_id_list(std::str::from_utf8(&attr.value)?)?,
+                b"m" => {
+                    for code in parse_id_list(std::str::from_utf8(&attr.value)?)? {
+                        melds.push(Meld::decode(code)?);
+                    }
+                }
+                b"machi" => {
+                    let id = std::str::from_utf8(&attr.value)?.parse()?;
+                    winning_tile = Some(Tile::from_id(id));
+                }
+                b"doraHai" => dora_indicators = parse_id_list(std::str::from_utf8(&attr.value)?)?,
+                b"doraHaiUra" => {
+                    ura_dora_indicators = parse_id_list(std::str::from_utf8(&attr.value)?)?
+                }
                 b"ten" => {
                     let ten_str = std::str::from_utf8(&attr.value)?;
                     let parts: Vec<&str> = ten_str.split(',').collect();
+                    // The third part is the limit (mangan and up), not the han
                     if parts.len() >= 3 {
                         fu = parts[0].parse()?;
-                        let _score = parts[1].parse::<i32>()?; // Total score
-                        han = parts[2].parse()?;
+                        hand_value = Some(parts[1].parse::<i32>()? / 100);
                     }
                 }
                 b"yaku" => {
-                    // TODO: Parse yaku list
-                    yakus.push(Yaku {
-                        name: "Unknown".to_string(),
-                        value: 1,
-                    });
+                    let values = parse_id_list(std::str::from_utf8(&attr.value)?)?;
+                    if values.len() % 2 != 0 {
+                        return Err(ParserError::invalid_format(
+                            "AGARI yaku is not a list of id,han pairs",
+                        ));
+                    }
+                    yakus.extend(values.chunks(2).map(|pair| Yaku::from_id(pair[0], pair[1])));
                 }
+                b"yakuman" => yakus.extend(
+                    parse_id_list(std::str::from_utf8(&attr.value)?)?
+                        .into_iter()
+                        .map(|id| Yaku::from_id(id, Yaku::YAKUMAN)),
+                ),
+                b"ba" => {
+                    let ba_str = std::str::from_utf8(&attr.value)?;
+                    if let Some((honba, kyoutaku)) = ba_str.split_once(',') {
+                        ba = Some((honba.parse::<i32>()?, kyoutaku.parse::<i32>()?));
+                    }
+                }
+                b"owari" => self.result = Some(parse_owari(&attr.value)?),
                 b"sc" => {
                     let sc_str = std::str::from_utf8(&attr.value)?;
                     let parts: Vec<&str> = sc_str.split(',').collect();
@@ -552,15 +1649,78 @@ impl MjlogParser {
             }
         }
 
-        if let Some(round) = &mut self.current_round {
+        let han = yakus.iter().map(|y| y.value).sum();
+        let tiles: Vec<u32> = hand
+            .iter()
+            .chain(melds.iter().flat_map(|m| &m.tiles))
+            .copied()
+            .collect();
+        let (dora, ura_dora, aka_dora) = if tiles.is_empty() {
+            // Without the hand, fall back to the dora Tenhou listed as yaku
+            let listed = |kind: YakuKind| {
+                yakus
+                    .iter()
+                    .filter(|y| y.kind == Some(kind))
+                    .map(|y| y.value)
+                    .sum()
+            };
+            (
+                listed(YakuKind::Dora),
+                listed(YakuKind::UraDora),
+                listed(YakuKind::AkaDora),
+            )
+        } else {
+            let has_aka = self.rules.as_ref().is_none_or(Rules::has_aka);
+            (
+                count_dora(&tiles, &dora_indicators)?,
+                count_dora(&tiles, &ura_dora_indicators)?,
+                if has_aka {
+                    tiles.iter().filter(|&&id| is_red_five(id)).count() as u32
+                } else {
+                    0
+                },
+            )
+        };
+        let ids_to_tiles =
+            |ids: &[u32]| -> Vec<Tile> { ids.iter().map(|&id| Tile::from_id(id)).collect() };
+        let payout = hand_value.zip(ba).map(|(hand, (honba, kyoutaku))| {
+            let payout = Payout {
+                hand,
+                honba: honba * 3,
+                riichi_sticks: kyoutaku * 10,
+            };
+            // In a double ron only the winner nearest the discarder collects
+            // the honba and the sticks
+            if scores[who as usize % 4] == hand {
+                Payout {
+                    hand,
+                    ..Default::default()
+                }
+            } else {
+                payout
+            }
+        });
+
+        let transfers = Transfer::agari(who, from, &scores, payout.as_ref());
+        if let Some(round) = self.open_round()? {
             round.events.push(Event::Agari {
                 who,
                 from,
                 han,
                 fu,
                 yakus,
-                dora_count,
+                dora_count: dora + ura_dora + aka_dora,
+                dora,
+                ura_dora,
+                aka_dora,
+                hand: ids_to_tiles(&hand),
+                melds: melds.iter().map(Meld::to_tiles).collect(),
+                winning_tile,
+                dora_indicators: ids_to_tiles(&dora_indicators),
+                ura_dora_indicators: ids_to_tiles(&ura_dora_indicators),
                 scores,
+                payout,
+                transfers,
             });
         }
 
@@ -570,6 +1730,7 @@ impl MjlogParser {
     fn parse_ryuukyoku(&mut self, element: &quick_xml::events::BytesStart) -> Result<()> {
         let mut reason = RyuukyokuReason::Normal;
         let mut scores = [0i32; 4];
+        let mut tenpai: [Option<Vec<Tile>>; 4] = Default::default();
 
         for attr in element.attributes() {
             let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
@@ -586,6 +1747,7 @@ impl MjlogParser {
                         _ => RyuukyokuReason::Normal,
                     };
                 }
+                b"owari" => self.result = Some(parse_owari(&attr.value)?),
                 b"sc" => {
                     let sc_str = std::str::from_utf8(&attr.value)?;
                     let parts: Vec<&str> = sc_str.split(',').collect();
@@ -597,19 +1759,77 @@ impl MjlogParser {
                         }
                     }
                 }
+                key @ (b"hai0" | b"hai1" | b"hai2" | b"hai3") => {
+                    let seat = (key[3] - b'0') as usize;
+                    tenpai[seat] = Some(parse_tile_list(std::str::from_utf8(&attr.value)?)?);
+                }
                 _ => {}
             }
         }
 
-        if let Some(round) = &mut self.current_round {
-            round.events.push(Event::Ryuukyoku { reason, scores });
+        if let Some(round) = self.open_round()? {
+            round.events.push(Event::Ryuukyoku {
+                reason,
+                tenpai,
+                transfers: Transfer::draw(&scores),
+                scores,
+            });
         }
 
         Ok(())
     }
 
-    fn into_output(self) -> ParserOutput {
-        ParserOutput {
+    fn finish_round(&mut self, mut round: Round) -> Result<()> {
+        round.link_events();
+        if let Some(wall) = self.round_wall.take() {
+            self.wall_mismatches
+                .extend(crate::wall::check_round(&wall, &round));
+        }
+        if self
+            .round_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.matches(&round))
+        {
+            self.skipped_rounds += 1;
+            self.skipped_wind = self.skipped_wind.max(Some(round.init.wind()));
+            return Ok(());
+        }
+        match &mut self.spool {
+            Some(spool) => {
+                if let Some(options) = &self.spill_options {
+                    let players = self.rules.as_ref().map_or(4, Rules::players);
+                    annotate_round(&mut round, options, players);
+                }
+                self.skipped_wind = self.skipped_wind.max(Some(round.init.wind()));
+                spool.push(&round)
+            }
+            None => {
+                self.rounds.push(round);
+                Ok(())
+            }
+        }
+    }
+
+    /// Number of completed rounds
+    fn round_count(&self) -> usize {
+        self.round_offset
+            + self.skipped_rounds
+            + self.spool.as_ref().map_or(self.rounds.len(), Spool::len)
+    }
+
+    fn into_output(mut self) -> Result<ParserOutput> {
+        let spilled = self.spool.take().map(Spool::finish).transpose()?;
+        if self.players.is_empty() {
+            let warning = ParseWarning::new(
+                WarningKind::Players,
+                "UN tag missing; synthesized placeholder players",
+            );
+            warn!("{}", warning);
+            self.warnings.push(warning);
+            self.players = (0..4).map(Player::placeholder).collect();
+        }
+
+        let mut output = ParserOutput {
             mjlog_version: self.mjlog_version,
             game_id: self.game_id,
             rules: self.rules.unwrap_or(Rules {
@@ -618,8 +1838,283 @@ impl MjlogParser {
             }),
             players: self.players,
             rounds: self.rounds,
+            spilled,
+            overtime: false,
+            end_reason: Some(EndReason::from_final_scores(
+                self.result.as_ref().map(|r| &r.scores),
+            )),
+            result: self.result,
+            warnings: self.warnings,
+            score_warnings: Vec::new(),
+            wall_mismatches: self.wall_mismatches,
+            connections: self.connections,
+        };
+        output
+            .warnings
+            .extend(output.wall_mismatches.iter().map(ParseWarning::from));
+        output.overtime = output.played_overtime()
+            || self
+                .skipped_wind
+                .is_some_and(|wind| wind > output.rules.game_length().last_wind());
+        Ok(output)
+    }
+}
+
+/// Game ID derived from the raw bytes of a log
+///
+/// A version 8 UUID built from the first 16 bytes of the content's SHA-256,
+/// so it keeps the shape of the random IDs while staying the same for the
+/// same input.
+pub fn content_game_id(content: &[u8]) -> String {
+    digest_game_id(Sha256::new_with_prefix(content))
+}
+
+fn digest_game_id(hasher: Sha256) -> String {
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes)
+        .into_uuid()
+        .to_string()
+}
+
+/// The raw input, hashed for [`content_game_id`] when `hasher` is set and
+/// ending after the first byte past `max`
+struct SourceReader<R> {
+    inner: R,
+    hasher: Option<Sha256>,
+    read: u64,
+    max: Option<u64>,
+}
+
+impl<R: Read> SourceReader<R> {
+    /// Fail if the input went past `max`
+    fn check_size(&self) -> Result<()> {
+        match self.max {
+            Some(max) if self.read > max => Err(ParserError::LimitExceeded {
+                limit: Limit::InputBytes,
+                max,
+                location: None,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The hash of the whole input, reading whatever the parser left
+    fn finish(mut self) -> Result<Option<Sha256>> {
+        if self.hasher.is_none() {
+            return Ok(None);
+        }
+        std::io::copy(&mut self, &mut std::io::sink())?;
+        Ok(self.hasher)
+    }
+}
+
+impl<R: Read> Read for SourceReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let buf = match self.max {
+            Some(max) => {
+                let left = (max + 1).saturating_sub(self.read);
+                let len = usize::try_from(left).map_or(buf.len(), |left| left.min(buf.len()));
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Offsets of the `tag` start and empty tags in `content`
+fn tag_offsets(content: &str, tag: &str) -> Vec<usize> {
+    let open = format!("<{}", tag);
+    content
+        .match_indices(&open)
+        .map(|(i, _)| i)
+        .filter(|&i| {
+            content[i + open.len()..]
+                .starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        })
+        .collect()
+}
+
+/// Parse the rounds of `content` on several threads
+///
+/// A pre-scan splits the document before every INIT tag. The header is
+/// parsed first; every round is then parsed by its own parser seeded with the
+/// header's rules and players and its pre-generated wall, and the results are merged
+/// back in document order.
+fn parse_parallel(content: &str, options: &ParserOptions) -> Result<MjlogParser> {
+    let starts = tag_offsets(content, "INIT");
+
+    let mut parser = MjlogParser::with_options(options);
+    let header_end = starts.first().copied().unwrap_or(content.len());
+    parser.parse(&mut segment_reader(&content[..header_end]))?;
+    if starts.is_empty() {
+        return Ok(parser);
+    }
+
+    // Walls come from one generator in round order
+    let segments: Vec<(usize, &str, Option<Wall>)> = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(content.len());
+            let wall = parser.walls.as_mut().map(WallGenerator::next_wall);
+            (i, &content[start..end], wall)
+        })
+        .collect();
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let per_thread = segments.len().div_ceil(threads.min(segments.len()));
+    let header = &parser;
+    let results: Vec<Result<MjlogParser>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = segments
+            .chunks(per_thread)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(index, text, wall)| {
+                            let mut segment = MjlogParser::with_options(options);
+                            segment.rules = header.rules.clone();
+                            segment.players = header.players.clone();
+                            segment.round_offset = *index;
+                            segment.next_wall = wall.clone();
+                            segment.parse(&mut segment_reader(text))?;
+                            Ok(segment)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("round parser panicked"))
+            .collect()
+    });
+
+    let last = starts.len() - 1;
+    for (i, result) in results.into_iter().enumerate() {
+        let mut segment = result.map_err(|err| err.shifted(starts[i]))?;
+        segment.shift(starts[i]);
+        parser.rounds.append(&mut segment.rounds);
+        parser.skipped_wind = parser.skipped_wind.max(segment.skipped_wind);
+        parser.warnings.append(&mut segment.warnings);
+        parser.wall_mismatches.append(&mut segment.wall_mismatches);
+        parser.connections.append(&mut segment.connections);
+        parser.anomalies.append(&mut segment.anomalies);
+        if parser.players.is_empty() {
+            parser.players = segment.players;
+        }
+        if segment.result.is_some() {
+            parser.result = segment.result;
+        }
+        // Only the last round can be cut off; earlier ones end at the next INIT
+        if i == last {
+            parser.truncated_at = segment.truncated_at;
+        }
+    }
+    Ok(parser)
+}
+
+/// Reader for one piece of a document, which may close tags it never opened
+fn segment_reader(text: &str) -> Reader<&[u8]> {
+    let mut reader = Reader::from_str(text);
+    reader.trim_text(true);
+    reader.check_end_names(false);
+    reader
+}
+
+/// `owari="s0,p0,s1,p1,..."`: final score (in units of 100) and points
+/// after uma by seat
+fn parse_owari(value: &[u8]) -> Result<GameResult> {
+    let parts: Vec<&str> = std::str::from_utf8(value)?.split(',').collect();
+    let mut scores = [0i32; 4];
+    let mut points = [0f64; 4];
+    for (i, chunk) in parts.chunks(2).enumerate().take(4) {
+        scores[i] = chunk[0].parse()?;
+        if let Some(point) = chunk.get(1) {
+            points[i] = point.trim().parse().map_err(|_| {
+                ParserError::invalid_format(format!("Invalid owari points: {}", point))
+            })?;
+        }
+    }
+    let seats = (parts.len() / 2).min(4);
+    Ok(GameResult::new(scores, points, seats))
+}
+
+/// Tiles among `tiles` that are dora for the given indicators, counting a
+/// tile once per indicator that points at it
+fn count_dora(tiles: &[u32], indicators: &[u32]) -> Result<u32> {
+    let mut count = 0;
+    for &indicator in indicators {
+        let dora = tile_id_to_type(indicator)?.dora();
+        for &id in tiles {
+            if tile_id_to_type(id)? == dora {
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Comma-separated numbers, as in INIT's hai attributes and AGARI's yaku
+fn parse_id_list(list: &str) -> Result<Vec<u32>> {
+    list.split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| Ok(id.trim().parse()?))
+        .collect()
+}
+
+/// The text of a tag that ends at byte `end`, rebuilt from the element,
+/// which holds everything between the angle brackets but the closing slash
+fn source_text(
+    element: &quick_xml::events::BytesStart,
+    empty: bool,
+    end: usize,
+) -> Result<(String, Range<usize>)> {
+    let inner = std::str::from_utf8(element)?;
+    let text = format!("<{}{}>", inner, if empty { "/" } else { "" });
+    let start = end.saturating_sub(text.len());
+    Ok((text, start..end))
+}
+
+/// Byte offset of the opening bracket of a tag that ends at byte `end`
+fn tag_start(element: &quick_xml::events::BytesStart, empty: bool, end: usize) -> usize {
+    let brackets = if empty { 3 } else { 2 };
+    end.saturating_sub(element.len() + brackets)
+}
+
+/// Tile ID of a draw or discard tag: a nameless attribute, or else the
+/// digits after the seat letter (T52 -> 52)
+fn tile_id(element: &quick_xml::events::BytesStart) -> Result<Option<u32>> {
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
+        if attr.key.as_ref().is_empty() {
+            return Ok(Some(std::str::from_utf8(&attr.value)?.parse()?));
         }
     }
+    let name = element.name();
+    let tag_name = std::str::from_utf8(name.as_ref())?;
+    Ok(tag_name.get(1..).and_then(|id| id.parse().ok()))
+}
+
+/// Attributes of a tag as strings, keyed by name
+fn attribute_map(element: &quick_xml::events::BytesStart) -> Result<BTreeMap<String, String>> {
+    let mut attributes = BTreeMap::new();
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| ParserError::Attr(e.to_string()))?;
+        attributes.insert(
+            std::str::from_utf8(attr.key.as_ref())?.to_string(),
+            std::str::from_utf8(&attr.value)?.to_string(),
+        );
+    }
+    Ok(attributes)
 }
 
 // Helper function to decode percent-encoded strings
@@ -659,6 +2154,45 @@ mod tests {
         assert_eq!(output.rounds.len(), 1);
     }
 
+    #[test]
+    fn test_unknown_tag_in_round() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <SHUFFLE seed="mt19937ar" ref=""/>
+    <UN n0="Player1" n1="Player2" n2="Player3" n3="Player4"/>
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <CUSTOM who="2"/>
+    <T0/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let events = &output.rounds[0].events;
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            Event::Unknown { tag, attributes } => {
+                assert_eq!(tag, "CUSTOM");
+                assert_eq!(attributes["who"], "2");
+            }
+            other => panic!("Expected unknown event, got {:?}", other),
+        }
+
+        let json = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(json["type"], "unknown");
+        assert_eq!(json["attributes"]["who"], "2");
+
+        let warning = &output.warnings[0];
+        assert_eq!(warning.kind, WarningKind::UnknownTag);
+        assert_eq!(warning.offset, mjlog_content.find("<CUSTOM"));
+        assert_eq!(
+            serde_json::to_value(warning).unwrap(),
+            serde_json::json!({
+                "kind": "unknownTag",
+                "offset": warning.offset,
+                "tag": "CUSTOM",
+                "message": "Unknown tag CUSTOM",
+            })
+        );
+    }
+
     #[test]
     fn test_parse_with_gzip() {
         let mjlog_content = r#"<?xml version="1.0" encoding="Shift_JIS"?>
@@ -712,6 +2246,25 @@ mod tests {
         assert!(parsed.get("mjlogVersion").is_some());
     }
 
+    #[test]
+    fn test_parse_stream_compact_gzip() {
+        let mjlog_content = r#"<mjloggm ver="2.3"><GO type="169" lobby="0"/></mjloggm>"#;
+        let options = ParserOptions {
+            compact: true,
+            gzip: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        parse_stream(Cursor::new(mjlog_content), &mut output, &options).unwrap();
+
+        let mut json = String::new();
+        GzDecoder::new(output.as_slice())
+            .read_to_string(&mut json)
+            .unwrap();
+        assert!(json.starts_with(r#"{"mjlogVersion":"2.3","#));
+        assert!(!json.contains('\n'));
+    }
+
     #[test]
     fn test_parse_with_encoding_errors() {
         // Create content with invalid Shift_JIS sequence
@@ -726,6 +2279,27 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_invalid_player_attributes() {
+        // Out-of-range dan ranks are covered by test_invalid_dan
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D" sx="M,F,X,M"/></mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(xml)).unwrap();
+        assert_eq!(output.players[2].gender, Gender::Unknown);
+        assert_eq!(output.players[3].gender, Gender::Male);
+        assert!(output
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Malformed && w.message == "Invalid gender: X"));
+
+        let options = ParserOptions {
+            strictness: Strictness::Strict,
+            ..Default::default()
+        };
+        let err = parse_mjlog_with_options(Cursor::new(xml), &options).unwrap_err();
+        assert!(err.to_string().contains("Invalid gender: X"));
+    }
+
     #[test]
     fn test_parse_complex_mjlog() {
         let mjlog_content = r#"<?xml version="1.0" encoding="Shift_JIS"?>
@@ -766,6 +2340,8 @@ mod tests {
 
         // Check that percent-encoded name was decoded
         assert_eq!(output.players[0].player_id, "テスト");
+        assert_eq!(output.players[1].gender, Gender::Female);
+        assert_eq!(output.players[3].rank, Dan::Kyu6);
 
         // Check events were parsed
         let round = &output.rounds[0];
@@ -889,6 +2465,7 @@ mod tests {
         let options = ParserOptions {
             verbose: false,
             validate_schema: None,
+            ..Default::default()
         };
 
         // This should test the gz branch in parse_file
@@ -915,6 +2492,7 @@ mod tests {
         let options = ParserOptions {
             verbose: false,
             validate_schema: None,
+            ..Default::default()
         };
 
         // This should test the non-gz branch in parse_file
@@ -935,6 +2513,32 @@ mod tests {
         let result = parse_mjlog(cursor);
         // Should still succeed but with encoding warnings
         assert!(result.is_ok());
+        let warnings = result.unwrap().warnings;
+        assert!(warnings.iter().any(|w| w.kind == WarningKind::Encoding));
+    }
+
+    #[test]
+    fn test_utf8_input() {
+        // Converted to UTF-8 by a mirror, declaration and all
+        let xml = r#"<?xml version="1.0" encoding="Shift_JIS"?>
+<mjloggm ver="2.3"><UN n0="天鳳" n1="B" n2="C" n3="D"/></mjloggm>"#;
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode(xml);
+        for bytes in [xml.as_bytes(), &sjis] {
+            let output = parse_mjlog(Cursor::new(bytes)).unwrap();
+            assert_eq!(output.players[0].player_id, "天鳳");
+            assert!(output.warnings.is_empty());
+            let events: Vec<LogEvent> = parse_events(Cursor::new(bytes))
+                .collect::<Result<_>>()
+                .unwrap();
+            assert!(matches!(&events[1], LogEvent::Players(p) if p[0].player_id == "天鳳"));
+        }
+
+        let options = ParserOptions {
+            encoding: InputEncoding::ShiftJis,
+            ..Default::default()
+        };
+        let output = parse_mjlog_with_options(Cursor::new(xml), &options).unwrap();
+        assert_ne!(output.players[0].player_id, "天鳳");
     }
 
     #[test]
@@ -978,6 +2582,258 @@ mod tests {
         assert!(!round.events.is_empty());
     }
 
+    #[test]
+    fn test_strictness() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/><T0/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T/><D200/><T4/><D4/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U8 extra="attr"/><DORA hai="136"/>
+</mjloggm>"#;
+        let options = |strictness| ParserOptions {
+            strictness,
+            ..Default::default()
+        };
+
+        let (lenient, warnings) =
+            parse_mjlog_with_warnings(Cursor::new(xml), &ParserOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(lenient.rounds[0].events.len(), 3);
+
+        let err =
+            parse_mjlog_with_options(Cursor::new(xml), &options(Strictness::Strict)).unwrap_err();
+        assert!(matches!(err, ParserError::Parse { .. }));
+        assert!(err.to_string().contains("T0 tag outside a round"));
+
+        for parallel in [false, true] {
+            let options = ParserOptions {
+                parallel,
+                ..options(Strictness::Collect)
+            };
+            let (output, warnings) = parse_mjlog_with_warnings(Cursor::new(xml), &options).unwrap();
+            assert_eq!(
+                serde_json::to_value(&output.rounds).unwrap(),
+                serde_json::to_value(&lenient.rounds).unwrap()
+            );
+            let found: Vec<(&str, &str, Option<&str>)> = warnings
+                .iter()
+                .map(|w| {
+                    (
+                        &xml[w.offset.unwrap()..],
+                        w.message.as_str(),
+                        w.tag.as_deref(),
+                    )
+                })
+                .map(|(at, message, tag)| (&at[..at.find('>').unwrap() + 1], message, tag))
+                .collect();
+            assert_eq!(
+                found,
+                vec![
+                    ("<T0/>", "T0 tag outside a round ignored", Some("T0")),
+                    ("<T/>", "Draw without a tile ID ignored", Some("T")),
+                    ("<D200/>", "Invalid tile ID: 200", Some("D200")),
+                    ("<DORA hai=\"136\"/>", "Invalid tile ID: 136", Some("DORA")),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_dan() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D" dan="16,21,0,20"/>
+</mjloggm>"#;
+
+        let (output, warnings) =
+            parse_mjlog_with_warnings(Cursor::new(xml), &ParserOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        let ranks: Vec<Dan> = output.players.iter().map(|p| p.rank).collect();
+        assert_eq!(
+            ranks,
+            vec![Dan::Nanadan, Dan::Shinjin, Dan::Shinjin, Dan::Tenhoui]
+        );
+        assert!(output
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Malformed && w.message == "Invalid dan rank: 21"));
+
+        let options = ParserOptions {
+            strictness: Strictness::Strict,
+            ..Default::default()
+        };
+        let err = parse_mjlog_with_options(Cursor::new(xml), &options).unwrap_err();
+        assert!(err.to_string().contains("Invalid dan rank: 21"));
+    }
+
+    #[test]
+    fn test_error_location() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/><D0/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U4/><N who="1"/>
+</mjloggm>"#;
+        let expected = ErrorLocation {
+            offset: xml.find("<N ").unwrap(),
+            tag: "N".to_string(),
+            round: Some(2),
+        };
+        for parallel in [false, true] {
+            let options = ParserOptions {
+                parallel,
+                ..Default::default()
+            };
+            let err = parse_mjlog_with_options(Cursor::new(xml), &options).unwrap_err();
+            assert!(matches!(err, ParserError::InvalidFormat { .. }));
+            assert_eq!(err.location(), Some(&expected));
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "Invalid format: N tag without m (<N> at byte {} in round 2)",
+                    expected.offset
+                )
+            );
+        }
+        let events: Vec<Result<LogEvent>> = parse_events(Cursor::new(xml)).collect();
+        let err = events.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(err.location(), Some(&expected));
+
+        // An INIT belongs to the round it starts
+        let xml = xml.replace(r#"seed="1,0,0,1,2,52""#, r#"seed="1,0,0""#);
+        let err = parse_mjlog(Cursor::new(xml.as_str())).unwrap_err();
+        let location = err.location().unwrap();
+        assert_eq!((location.tag.as_str(), location.round), ("INIT", Some(2)));
+        assert_eq!(location.offset, xml.rfind("<INIT").unwrap());
+    }
+
+    #[test]
+    fn test_multiple_games() {
+        let game = |name: &str, tile: u32| {
+            format!(
+                r#"<mjloggm ver="2.3"><UN n0="{}" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T{}/><D{}/><RYUUKYOKU ba="0,0" sc="250,0,250,0,250,0,250,0"/>
+</mjloggm>
+"#,
+                name, tile, tile
+            )
+        };
+        let second = game("Y", 200);
+        let log = format!("{}{}", game("X", 0), second);
+        let start = log.rfind("<mjloggm").unwrap();
+
+        let options = ParserOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        let games = parse_mjlog_games(Cursor::new(&log), &options).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].players[0].player_id, "X");
+        assert_eq!(games[1].players[0].player_id, "Y");
+        assert_eq!(games[0].rounds.len(), 1);
+        assert_eq!(games[0].game_id, content_game_id(log.as_bytes()));
+        assert_ne!(games[1].game_id, games[0].game_id);
+        // Offsets count from the start of the log
+        let invalid = &games[1].warnings[0];
+        assert_eq!(invalid.offset, Some(start + second.find("<T200").unwrap()));
+
+        let parallel = ParserOptions {
+            parallel: true,
+            ..options.clone()
+        };
+        let json = |games: Vec<ParserOutput>| serde_json::to_value(games).unwrap();
+        assert_eq!(
+            json(parse_mjlog_games(Cursor::new(&log), &parallel).unwrap()),
+            json(games)
+        );
+
+        // Reading a single game stops at the next one
+        for options in [options, parallel] {
+            let output = parse_mjlog_with_options(Cursor::new(&log), &options).unwrap();
+            assert_eq!(output.players[0].player_id, "X");
+            let warning = output.warnings.last().unwrap();
+            assert_eq!(
+                (warning.tag.as_deref(), warning.offset),
+                (Some("mjloggm"), Some(start))
+            );
+        }
+        let games = parse_mjlog_games(Cursor::new(game("X", 0)), &Default::default()).unwrap();
+        assert_eq!(games.len(), 1);
+        assert!(games[0].warnings.is_empty());
+    }
+
+    #[test]
+    fn test_limits() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/><D0/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U4/><E4/><V8/>
+</mjloggm>"#;
+        let parse = |options: ParserOptions| {
+            parse_mjlog_with_options(Cursor::new(xml), &options).map(|output| output.rounds.len())
+        };
+        let exceeded = |options: ParserOptions| match parse(options).unwrap_err() {
+            ParserError::LimitExceeded {
+                limit,
+                max,
+                location,
+            } => (limit, max, location.map(|l| (l.tag, l.offset))),
+            err => panic!("unexpected error {}", err),
+        };
+
+        let size = xml.len() as u64;
+        assert_eq!(
+            exceeded(ParserOptions {
+                max_input_bytes: Some(size - 1),
+                ..Default::default()
+            }),
+            (Limit::InputBytes, size - 1, None)
+        );
+        for parallel in [false, true] {
+            assert_eq!(
+                exceeded(ParserOptions {
+                    max_rounds: Some(1),
+                    parallel,
+                    ..Default::default()
+                }),
+                (
+                    Limit::Rounds,
+                    1,
+                    Some(("INIT".into(), xml.rfind("<INIT").unwrap()))
+                )
+            );
+        }
+        assert_eq!(
+            exceeded(ParserOptions {
+                max_events_per_round: Some(2),
+                ..Default::default()
+            }),
+            (
+                Limit::EventsPerRound,
+                2,
+                Some(("V8".into(), xml.find("<V8").unwrap()))
+            )
+        );
+
+        // Limits the log stays within
+        let within = ParserOptions {
+            max_input_bytes: Some(size),
+            max_rounds: Some(2),
+            max_events_per_round: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(parse(within).unwrap(), 2);
+        let err = parse(ParserOptions {
+            max_rounds: Some(1),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(err.code(), "limit-exceeded");
+        assert!(err
+            .to_string()
+            .starts_with("Limit exceeded: round count over 1 (<INIT>"));
+    }
+
     #[test]
     fn test_parse_stream_error() {
         // Test invalid JSON serialization by using a mock writer that always fails
@@ -1007,6 +2863,7 @@ mod tests {
         let options = ParserOptions {
             verbose: false,
             validate_schema: None,
+            ..Default::default()
         };
 
         let result = parse_stream(cursor, failing_writer, &options);
@@ -1028,6 +2885,139 @@ mod tests {
         assert!(output.rounds.is_empty());
     }
 
+    #[test]
+    fn test_missing_un_synthesizes_placeholders() {
+        let mjlog_content = r#"<?xml version="1.0" encoding="Shift_JIS"?>
+<mjloggm ver="2.3">
+    <GO type="169" lobby="0"/>
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0" hai1="1" hai2="2" hai3="3"/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        assert_eq!(output.players.len(), 4);
+        for (i, player) in output.players.iter().enumerate() {
+            assert_eq!(player.seat, i as u8);
+            assert_eq!(player.player_id, Player::PLACEHOLDER_ID);
+        }
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].kind, WarningKind::Players);
+        assert!(output.warnings[0].message.contains("UN tag missing"));
+    }
+
+    #[test]
+    fn test_unnamed_players_get_placeholders() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <GO type="169" lobby="0"/>
+    <UN n0="Player1" n1="" n2="Player3" n3=""/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let names: Vec<&str> = output
+            .players
+            .iter()
+            .map(|p| p.player_id.as_str())
+            .collect();
+        assert_eq!(names, vec!["Player1", "NoName", "Player3", "NoName"]);
+        assert_eq!(output.warnings.len(), 2);
+        assert_eq!(output.warnings[0].kind, WarningKind::Players);
+        assert_eq!(
+            output.warnings[0].message,
+            "Seat 1 has no name; using placeholder"
+        );
+
+        // The empty fourth seat of a three-player game stays empty
+        let sanma = r#"<mjloggm ver="2.3">
+    <GO type="25" lobby="0"/>
+    <UN n0="Player1" n1="Player2" n2="Player3" n3=""/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(sanma.as_bytes())).unwrap();
+        assert_eq!(output.players[3].player_id, "");
+        assert!(output.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_un_is_ignored() {
+        let mjlog_content = r#"<?xml version="1.0" encoding="Shift_JIS"?>
+<mjloggm ver="2.3">
+    <UN n0="Player1" n1="Player2" n2="Player3" n3="Player4" dan="1,2,3,4" rate="1500,1600,1700,1800" sx="M,M,M,M"/>
+    <UN n0="Other1" n1="Other2" n2="Other3" n3="Other4" dan="1,2,3,4" rate="1500,1600,1700,1800" sx="M,M,M,M"/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        assert_eq!(output.players.len(), 4);
+        assert_eq!(output.players[0].player_id, "Player1");
+        assert_eq!(
+            output.warnings,
+            vec![ParseWarning {
+                kind: WarningKind::Players,
+                offset: Some(mjlog_content.find("<UN n0=\"Other1\"").unwrap()),
+                tag: Some("UN".to_string()),
+                message: "Duplicate UN tag ignored".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_disconnect_and_reconnect() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <UN n0="A" n1="B" n2="C" n3="D" dan="1,2,3,4" rate="1500,1600,1700,1800" sx="M,M,M,M"/>
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <T0/><D0/><BYE who="1"/><U4/><E4/><UN n1="B"/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let events = &output.rounds[0].events;
+        assert!(matches!(events[2], Event::Disconnect { who: 1 }));
+        assert!(matches!(events[5], Event::Reconnect { who: 1 }));
+        assert_eq!(output.players[1].player_id, "B");
+        assert!(output.warnings.is_empty());
+        assert_eq!(
+            serde_json::to_value(&events[2]).unwrap(),
+            serde_json::json!({ "type": "disconnect", "who": 1 })
+        );
+    }
+
+    #[test]
+    fn test_connections_outside_rounds() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <UN n0="A" n1="B" n2="C" n3="D" dan="1,2,3,4" rate="1500,1600,1700,1800" sx="M,M,M,M"/>
+    <TAIKYOKU oya="0"/><BYE who="2"/><UN n2="C"/>
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <T0/><D0/><RYUUKYOKU type="nm" sc="250,0,250,0,250,0,250,0"/><BYE who="3"/>
+    <INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+    <U4/><E4/>
+</mjloggm>"#;
+
+        for parallel in [false, true] {
+            let options = ParserOptions {
+                strictness: Strictness::Strict,
+                parallel,
+                ..Default::default()
+            };
+            let output =
+                parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+            let connections: Vec<(usize, Option<u8>)> = output
+                .connections
+                .iter()
+                .map(|c| (c.after_rounds, c.event.seat()))
+                .collect();
+            assert_eq!(connections, vec![(0, Some(2)), (0, Some(2)), (1, Some(3))]);
+            assert!(matches!(
+                output.connections[1].event,
+                Event::Reconnect { who: 2 }
+            ));
+            assert!(output.rounds.iter().all(|round| !round
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::Disconnect { .. } | Event::Reconnect { .. }))));
+            assert!(output.warnings.is_empty());
+            assert_eq!(
+                serde_json::to_value(&output.connections[2]).unwrap(),
+                serde_json::json!({ "afterRounds": 1, "type": "disconnect", "who": 3 })
+            );
+        }
+    }
+
     #[test]
     fn test_invalid_tile_parsing() {
         let mjlog_content = r#"<?xml version="1.0" encoding="Shift_JIS"?>
@@ -1040,4 +3030,782 @@ mod tests {
         // Should fail due to invalid tile parsing
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_agari_payout() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,1,2,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <AGARI ba="1,2" who="1" fromWho="3" ten="30,7700,0" sc="250,0,250,100,250,0,250,-80"/>
+    <AGARI ba="1,2" who="2" fromWho="3" ten="30,3900,0" sc="250,0,250,0,250,39,250,-39"/>
+    <AGARI who="0" fromWho="0" ten="30,1000,0" sc="250,10,250,-4,250,-3,250,-3"/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let payouts: Vec<_> = output.rounds[0]
+            .events
+            .iter()
+            .map(|e| match e {
+                Event::Agari { payout, .. } => *payout,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            payouts,
+            vec![
+                Some(Payout {
+                    hand: 77,
+                    honba: 3,
+                    riichi_sticks: 20,
+                }),
+                Some(Payout {
+                    hand: 39,
+                    ..Default::default()
+                }),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_riichi_discard() {
+        // Seat 0 declares double riichi on the first discard; seat 1's
+        // riichi discard is called by seat 2
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <T0/><REACH who="0" step="1"/><D0/><REACH who="0" step="2" ten="240,250,250,250"/>
+    <U4/><REACH who="1" step="1"/><E108/><N who="2" m="41483"/>
+    <REACH who="1" step="2" ten="240,240,250,250"/><F8/>
+    <T12/><D12/><U16/><E16/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let riichi: Vec<(u8, bool)> = output.rounds[0]
+            .events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Discard {
+                    seat, is_riichi, ..
+                } => Some((*seat, *is_riichi)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            riichi,
+            vec![(0, true), (1, true), (2, false), (0, false), (1, false)]
+        );
+    }
+
+    #[test]
+    fn test_agari_yaku() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <AGARI who="1" fromWho="3" ten="40,8000,1" yaku="1,1,7,1,54,1,53,0,52,2" sc="250,0,250,80,250,0,250,-80"/>
+    <AGARI who="2" fromWho="2" ten="0,48000,5" yaku="" yakuman="39,42" sc="250,-160,250,-160,250,480,250,-160"/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let events = &output.rounds[0].events;
+        let Event::Agari { han, yakus, .. } = &events[0] else {
+            panic!("expected agari");
+        };
+        assert_eq!(*han, 5);
+        let names: Vec<&str> = yakus.iter().map(|y| y.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Riichi", "Pinfu", "Aka Dora", "Ura Dora", "Dora"]
+        );
+        assert_eq!(yakus[1].kind, Some(YakuKind::Pinfu));
+        assert!(yakus[4].kind.is_some_and(YakuKind::is_dora));
+
+        let Event::Agari { han, yakus, .. } = &events[1] else {
+            panic!("expected agari");
+        };
+        assert_eq!(*han, 26);
+        assert_eq!(yakus[0].kind, Some(YakuKind::Daisangen));
+        assert_eq!(yakus[1].name, "Tsuuiisou");
+        assert_eq!(YakuKind::from_id(99), None);
+        assert_eq!(Yaku::from_id(99, 1).name, "Yaku 99");
+
+        let odd = mjlog_content.replace("52,2", "52");
+        assert!(parse_mjlog(Cursor::new(odd.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_agari_hand() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <AGARI who="3" fromWho="1" hai="0,4,8,44,48,52,72,76,80,81" m="41483" machi="8" doraHai="100,104" doraHaiUra="120" ten="30,2000,0" yaku="1,1,53,0" sc="250,0,250,-20,250,0,250,20"/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let Event::Agari {
+            hand,
+            melds,
+            winning_tile,
+            dora_indicators,
+            ura_dora_indicators,
+            dora_count,
+            dora,
+            ura_dora,
+            aka_dora,
+            ..
+        } = &output.rounds[0].events[0]
+        else {
+            panic!("expected agari");
+        };
+        assert_eq!(
+            hand,
+            &["1m", "2m", "3m", "3p", "4p", "5p", "1s", "2s", "3s", "3s"]
+        );
+        assert_eq!(melds, &[vec!["east", "east", "east"]]);
+        assert_eq!(winning_tile.as_deref(), Some("3m"));
+        assert_eq!(dora_indicators, &["8s", "9s"]);
+        assert_eq!(ura_dora_indicators, &["north"]);
+        // 1s for the 9s indicator, the pon of east for north, the red 5p
+        assert_eq!((*dora, *ura_dora, *aka_dora, *dora_count), (1, 3, 1, 5));
+
+        // Without the hand, the counts come from the dora listed as yaku
+        let listed = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <AGARI who="3" fromWho="1" ten="30,2000,0" yaku="1,1,52,2,53,0,54,1" sc="250,0,250,-20,250,0,250,20"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(listed.as_bytes())).unwrap();
+        let json = serde_json::to_value(&output.rounds[0].events[0]).unwrap();
+        assert_eq!(json["dora"], 2);
+        assert_eq!(json["uraDora"], 0);
+        assert_eq!(json["akaDora"], 1);
+        assert_eq!(json["doraCount"], 3);
+    }
+
+    #[test]
+    fn test_include_raw() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <T52/><REACH who="0" step="1"/><D52/><DORA hai="56"/>
+</mjloggm>"#;
+
+        let options = ParserOptions {
+            include_raw: true,
+            ..Default::default()
+        };
+        let output =
+            parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+        let round = &output.rounds[0];
+        assert_eq!(round.raw.len(), round.events.len());
+        let tags: Vec<&str> = round.raw.iter().map(|r| r.tag.as_str()).collect();
+        assert_eq!(tags, vec!["T52", "REACH", "D52", "DORA"]);
+        assert_eq!(round.raw[1].attributes["step"], "1");
+        assert_eq!(round.raw[3].attributes["hai"], "56");
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        assert!(output.rounds[0].raw.is_empty());
+        let json = serde_json::to_value(&output.rounds[0]).unwrap();
+        assert!(json.get("raw").is_none());
+    }
+
+    #[test]
+    fn test_raw_source() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <T52/><REACH who="0"  step="1" /><D52/>
+</mjloggm>"#;
+
+        for parallel in [false, true] {
+            let options = ParserOptions {
+                raw_source: true,
+                parallel,
+                ..Default::default()
+            };
+            let output =
+                parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+            let round = &output.rounds[0];
+            assert_eq!(round.raw.len(), round.events.len());
+            for raw in &round.raw {
+                let span = raw.span.clone().unwrap();
+                assert_eq!(&mjlog_content[span], raw.source.as_deref().unwrap());
+            }
+            assert_eq!(
+                round.raw[1].source.as_deref(),
+                Some(r#"<REACH who="0"  step="1" />"#)
+            );
+        }
+    }
+
+    #[test]
+    fn test_deterministic_output() {
+        let content = std::fs::read("tests/data/sample_complex.xml").unwrap();
+        let options = ParserOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        let json = || {
+            let output = parse_mjlog_with_options(Cursor::new(&content), &options).unwrap();
+            serde_json::to_vec_pretty(&output).unwrap()
+        };
+        assert_eq!(json(), json());
+
+        let output = parse_mjlog_with_options(Cursor::new(&content), &options).unwrap();
+        assert_eq!(output.game_id, content_game_id(&content));
+        let id = uuid::Uuid::parse_str(&output.game_id).unwrap();
+        assert_eq!(id.get_version_num(), 8);
+        assert_ne!(content_game_id(b"<mjloggm/>"), output.game_id);
+
+        // Bytes after the log, which the parser never needs, are hashed too
+        let trailing = [content.as_slice(), b"\n\n"].concat();
+        let output = parse_mjlog_with_options(Cursor::new(&trailing), &options).unwrap();
+        assert_eq!(output.game_id, content_game_id(&trailing));
+
+        let parallel = ParserOptions {
+            parallel: true,
+            ..options.clone()
+        };
+        let output = parse_mjlog_with_options(Cursor::new(&content), &parallel).unwrap();
+        assert_eq!(serde_json::to_vec_pretty(&output).unwrap(), json());
+    }
+
+    #[test]
+    fn test_game_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let id = "2024061500gm-00a9-0000-1a2b3c4d";
+        let path = dir.path().join(format!("{}.mjlog", id));
+        std::fs::copy("tests/data/sample.xml", &path).unwrap();
+        assert_eq!(parse_mjlog_file(&path).unwrap().game_id, id);
+
+        // A given ID wins, and numbers the games after the first
+        let options = ParserOptions {
+            game_id: Some("custom".into()),
+            deterministic: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            file_options(&path, &options).game_id.as_deref(),
+            Some("custom")
+        );
+        let log = std::fs::read_to_string(&path).unwrap();
+        let games = parse_mjlog_games(Cursor::new(format!("{}{}", log, log)), &options).unwrap();
+        let ids: Vec<&str> = games.iter().map(|g| g.game_id.as_str()).collect();
+        assert_eq!(ids, ["custom", "custom-2"]);
+
+        // Otherwise a file not named after a log keeps a random ID
+        let other = dir.path().join("game.mjlog");
+        std::fs::copy("tests/data/sample.xml", &other).unwrap();
+        assert!(uuid::Uuid::parse_str(&parse_mjlog_file(&other).unwrap().game_id).is_ok());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let content = std::fs::read("tests/data/sample_complex.xml").unwrap();
+        let token = CancellationToken::new();
+        let options = ParserOptions {
+            cancellation: Some(token.clone()),
+            ..Default::default()
+        };
+        assert!(parse_mjlog_with_options(Cursor::new(&content), &options).is_ok());
+
+        token.cancel();
+        for parallel in [false, true] {
+            let options = ParserOptions {
+                parallel,
+                ..options.clone()
+            };
+            let result = parse_mjlog_with_options(Cursor::new(&content), &options);
+            assert!(matches!(result, Err(ParserError::Cancelled)));
+        }
+
+        let options = ParserOptions {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let result = parse_mjlog_with_options(Cursor::new(&content), &options);
+        assert_eq!(result.unwrap_err().code(), "deadline-exceeded");
+    }
+
+    #[test]
+    fn test_spill_rounds() {
+        let content = std::fs::read("tests/data/sample_complex.xml").unwrap();
+        let in_memory = parse_mjlog(Cursor::new(&content)).unwrap();
+
+        let options = ParserOptions {
+            spill_rounds: true,
+            ..Default::default()
+        };
+        let mut spilled = parse_mjlog_with_options(Cursor::new(&content), &options).unwrap();
+        assert!(spilled.rounds.is_empty());
+        assert_eq!(
+            spilled
+                .spilled
+                .as_ref()
+                .map(crate::spool::SpilledRounds::len),
+            Some(in_memory.rounds.len())
+        );
+        assert_eq!(spilled.overtime, in_memory.overtime);
+        spilled.game_id = in_memory.game_id.clone();
+        assert_eq!(
+            serde_json::to_value(&spilled).unwrap(),
+            serde_json::to_value(&in_memory).unwrap()
+        );
+
+        spilled.load_rounds().unwrap();
+        assert!(spilled.spilled.is_none());
+        assert_eq!(
+            serde_json::to_value(&spilled.rounds).unwrap(),
+            serde_json::to_value(&in_memory.rounds).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_spill_rounds_annotated() {
+        let content = std::fs::read("tests/data/sample_complex.xml").unwrap();
+        let options = ParserOptions {
+            sort_hands: true,
+            track_state: true,
+            annotate_counters: true,
+            validate_scores: true,
+            ..Default::default()
+        };
+        let mut in_memory = parse_mjlog_with_options(Cursor::new(&content), &options).unwrap();
+
+        let options = ParserOptions {
+            spill_rounds: true,
+            ..options
+        };
+        let mut spilled = parse_mjlog_with_options(Cursor::new(&content), &options).unwrap();
+        assert_eq!(spilled.score_warnings, in_memory.score_warnings);
+        spilled.redact_for_seat(1);
+        in_memory.redact_for_seat(1);
+        spilled.game_id = in_memory.game_id.clone();
+        assert_eq!(
+            serde_json::to_value(&spilled).unwrap(),
+            serde_json::to_value(&in_memory).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sort_hands() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="132,72,0,40" hai1="" hai2="" hai3=""/>
+</mjloggm>"#;
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        assert_eq!(output.rounds[0].init.initial_hands[0][0], "red");
+
+        let options = ParserOptions {
+            sort_hands: true,
+            ..Default::default()
+        };
+        let output =
+            parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+        assert_eq!(
+            output.rounds[0].init.initial_hands[0],
+            vec!["1m", "2p", "1s", "red"]
+        );
+    }
+
+    #[test]
+    fn test_annotate_wall() {
+        use base64::Engine;
+
+        let seed = format!(
+            "{}{}",
+            crate::wall::SEED_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode([0x5au8; 2496])
+        );
+        let mut walls = WallGenerator::from_seed(&seed).unwrap();
+        let wall = walls.next_wall();
+        let hai: Vec<String> = wall
+            .deal(0)
+            .iter()
+            .map(|hand| {
+                let ids: Vec<String> = hand.iter().map(u32::to_string).collect();
+                ids.join(",")
+            })
+            .collect();
+        let mjlog_content = format!(
+            r#"<mjloggm ver="2.3"><SHUFFLE seed="{seed}" ref=""/>
+<INIT seed="0,0,0,{d0},{d1},{dora}" ten="250,250,250,250" oya="0" hai0="{h0}" hai1="{h1}" hai2="{h2}" hai3="{h3}"/>
+<T{draw}/>
+<INIT seed="1,0,0,0,0,{dora}" ten="250,250,250,250" oya="1" hai0="{h0}" hai1="{h1}" hai2="{h2}" hai3="{h3}"/>
+<T{draw}/>
+</mjloggm>"#,
+            d0 = wall.dice[0],
+            d1 = wall.dice[1],
+            dora = wall.tiles[5],
+            h0 = hai[0],
+            h1 = hai[1],
+            h2 = hai[2],
+            h3 = hai[3],
+            draw = wall.tiles[83],
+        );
+
+        let options = ParserOptions {
+            annotate_wall: true,
+            ..Default::default()
+        };
+        let output =
+            parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+        assert_eq!(output.rounds[0].wall.as_ref(), Some(&wall));
+        assert!(matches!(
+            output.rounds[0].events[0],
+            Event::Draw {
+                wall_position: Some(83),
+                ..
+            }
+        ));
+        // The second round's wall differs from the hands it claims to deal
+        assert!(output.rounds[1].wall.is_none());
+        assert!(matches!(
+            output.rounds[1].events[0],
+            Event::Draw {
+                wall_position: None,
+                ..
+            }
+        ));
+        let wall_warnings: Vec<&ParseWarning> = output
+            .warnings
+            .iter()
+            .filter(|w| w.kind == WarningKind::Wall)
+            .collect();
+        assert_eq!(wall_warnings.len(), 1);
+        assert!(wall_warnings[0].message.contains("round 2"));
+        assert_eq!(wall_warnings[0].tag.as_deref(), Some("INIT"));
+
+        // Walls follow round order when rounds are parsed in parallel
+        let options = ParserOptions {
+            parallel: true,
+            ..options
+        };
+        let parallel =
+            parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+        assert_eq!(parallel.rounds[0].wall.as_ref(), Some(&wall));
+        assert_eq!(parallel.warnings, output.warnings);
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        assert!(output.rounds[0].wall.is_none());
+        assert!(!output.warnings.iter().any(|w| w.kind == WarningKind::Wall));
+    }
+
+    #[test]
+    fn test_verify_wall() {
+        use crate::models::WallMismatchKind;
+        use base64::Engine;
+
+        let seed = format!(
+            "{}{}",
+            crate::wall::SEED_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode([0x5au8; 2496])
+        );
+        let wall = WallGenerator::from_seed(&seed).unwrap().next_wall();
+        let hai: Vec<String> = wall
+            .deal(0)
+            .iter()
+            .map(|hand| {
+                let ids: Vec<String> = hand.iter().map(u32::to_string).collect();
+                ids.join(",")
+            })
+            .collect();
+        // The second draw is not the next tile of the wall, and the second
+        // round deals the first round's hands again
+        let mjlog_content = format!(
+            r#"<mjloggm ver="2.3"><SHUFFLE seed="{seed}" ref=""/>
+<INIT seed="0,0,0,{d0},{d1},{dora}" ten="250,250,250,250" oya="0" hai0="{h0}" hai1="{h1}" hai2="{h2}" hai3="{h3}"/>
+<T{draw}/><D{draw}/><U{wrong}/>
+<INIT seed="1,0,0,0,0,{dora}" ten="250,250,250,250" oya="1" hai0="{h0}" hai1="{h1}" hai2="{h2}" hai3="{h3}"/>
+</mjloggm>"#,
+            d0 = wall.dice[0],
+            d1 = wall.dice[1],
+            dora = wall.tiles[5],
+            h0 = hai[0],
+            h1 = hai[1],
+            h2 = hai[2],
+            h3 = hai[3],
+            draw = wall.tiles[83],
+            wrong = wall.tiles[20],
+        );
+
+        let options = ParserOptions {
+            verify_wall: true,
+            ..Default::default()
+        };
+        let output =
+            parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &options).unwrap();
+        let first = &output.wall_mismatches[0];
+        assert_eq!(
+            (first.round_id.as_str(), first.kind, first.seat, first.event),
+            ("Round 1", WallMismatchKind::Draw, Some(1), Some(2))
+        );
+        assert_eq!(
+            (first.expected.as_slice(), first.actual.as_slice()),
+            ([wall.tiles[82]].as_slice(), [wall.tiles[20]].as_slice())
+        );
+        let second = &output.wall_mismatches[1..];
+        assert!(second.iter().all(|m| m.round_id == "Round 2"));
+        assert!(second
+            .iter()
+            .any(|m| m.kind == WallMismatchKind::InitialHand && m.seat.is_some()));
+        assert!(output.warnings.iter().any(
+            |w| w.kind == WarningKind::Wall && w.message.starts_with("Round 1: draw of seat 1")
+        ));
+        // Verifying alone leaves the rounds without walls
+        assert!(output.rounds.iter().all(|round| round.wall.is_none()));
+
+        let parallel = ParserOptions {
+            parallel: true,
+            ..options
+        };
+        let output_parallel =
+            parse_mjlog_with_options(Cursor::new(mjlog_content.as_bytes()), &parallel).unwrap();
+        assert_eq!(output_parallel.wall_mismatches, output.wall_mismatches);
+
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        assert!(output.wall_mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_west_round_overtime() {
+        let mjlog = |go: u32| {
+            format!(
+                r#"<mjloggm ver="2.3"><GO type="{go}" lobby="0"/>
+    <INIT seed="7,0,0,1,2,52" ten="250,250,250,250" oya="3" hai0="" hai1="" hai2="" hai3=""/>
+    <INIT seed="8,1,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+</mjloggm>"#
+            )
+        };
+
+        let output = parse_mjlog(Cursor::new(mjlog(169).into_bytes())).unwrap();
+        let names: Vec<String> = output.rounds.iter().map(|r| r.init.round_name()).collect();
+        assert_eq!(names, vec!["南4局", "西1局"]);
+        assert_eq!(output.rounds[1].init.round_name_english(), "West 1");
+        assert!(output.overtime);
+
+        // Past East 4 in an East-only game is already overtime
+        let output = parse_mjlog(Cursor::new(mjlog(161).into_bytes())).unwrap();
+        assert!(output.overtime);
+        assert_eq!(
+            output.rounds[0].init.wind(),
+            crate::models::RoundWind::South
+        );
+
+        let output = parse_mjlog(Cursor::new(
+            r#"<mjloggm ver="2.3"><GO type="169" lobby="0"/>
+    <INIT seed="7,0,0,1,2,52" ten="250,250,250,250" oya="3" hai0="" hai1="" hai2="" hai3=""/>
+</mjloggm>"#,
+        ))
+        .unwrap();
+        assert!(!output.overtime);
+    }
+
+    #[test]
+    fn test_end_reason() {
+        let mjlog = |settlement: &str| {
+            format!(
+                r#"<mjloggm ver="2.3">
+    <INIT seed="7,0,0,1,2,52" ten="250,250,20,330" oya="3" hai0="" hai1="" hai2="" hai3=""/>
+    {settlement}
+</mjloggm>"#
+            )
+        };
+        let end_reason = |settlement: &str| {
+            parse_mjlog(Cursor::new(mjlog(settlement).into_bytes()))
+                .unwrap()
+                .end_reason
+        };
+
+        assert_eq!(
+            end_reason(
+                r#"<RYUUKYOKU ba="0,0" sc="250,0,250,0,20,0,330,0" owari="250,5.0,250,-15.0,20,-38.0,330,48.0"/>"#
+            ),
+            Some(EndReason::Completed)
+        );
+        assert_eq!(
+            end_reason(
+                r#"<AGARI ba="0,0" who="3" fromWho="2" ten="30,3900,0" sc="250,0,250,0,20,-39,330,39" owari="250,5.0,250,-15.0,-19,-62.0,369,72.0"/>"#
+            ),
+            Some(EndReason::Bust)
+        );
+        assert_eq!(end_reason("<T0/>"), Some(EndReason::Abandoned));
+    }
+
+    #[test]
+    fn test_ryuukyoku_tenpai() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <RYUUKYOKU ba="0,0" sc="250,-15,250,15,250,-15,250,15" hai1="0,4,8,12" hai3="108,109"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let event = &output.rounds[0].events[0];
+        let Event::Ryuukyoku { tenpai, .. } = event else {
+            panic!("expected ryuukyoku");
+        };
+        assert_eq!(
+            tenpai,
+            &[
+                None,
+                Some(vec![
+                    "1m".parse().unwrap(),
+                    "2m".parse().unwrap(),
+                    "3m".parse().unwrap(),
+                    "4m".parse().unwrap()
+                ]),
+                None,
+                Some(vec!["east".parse().unwrap(), "east".parse().unwrap()]),
+            ]
+        );
+        let json = serde_json::to_value(event).unwrap();
+        assert_eq!(json["tenpai"][0], serde_json::Value::Null);
+        assert_eq!(json["tenpai"][3][0], "east");
+    }
+
+    #[test]
+    fn test_game_result() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+    <INIT seed="7,0,0,1,2,52" ten="250,250,20,330" oya="3" hai0="" hai1="" hai2="" hai3=""/>
+    <RYUUKYOKU ba="0,0" sc="250,0,250,0,20,0,330,0" owari="250,5.0,250,-15.0,20,-38.0,330,48.0"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let result = output.result.unwrap();
+        assert_eq!(result.scores, [250, 250, 20, 330]);
+        assert_eq!(result.points, [5.0, -15.0, -38.0, 48.0]);
+        // The tie at 25000 goes to seat 0
+        assert_eq!(result.placements, [2, 3, 4, 1]);
+
+        let unfinished =
+            mjlog_content.replace(r#" owari="250,5.0,250,-15.0,20,-38.0,330,48.0""#, "");
+        assert!(parse_mjlog(Cursor::new(unfinished.as_bytes()))
+            .unwrap()
+            .result
+            .is_none());
+
+        let sanma = GameResult::new([400, 350, 300, 0], [50.0, -5.0, -45.0, 0.0], 3);
+        assert_eq!(sanma.placements, [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_parse_events_truncated() {
+        let mjlog_content = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/><D0/><RYUUKYOKU ba="0,0" sc="250,0,250,0,250,0,250,0" owari="250,0.0,250,0.0,250,0.0,250,0.0"/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U4/><E4/>"#;
+        let events: Vec<_> = parse_events(Cursor::new(mjlog_content.as_bytes())).collect();
+        assert!(matches!(events[0], Ok(LogEvent::Version(_))));
+        assert!(matches!(
+            events[1],
+            Ok(LogEvent::RoundStart { dealer_seat: 0, .. })
+        ));
+        assert!(matches!(events[5], Ok(LogEvent::GameEnd(_))));
+        assert!(matches!(
+            events[6],
+            Ok(LogEvent::RoundStart { dealer_seat: 1, .. })
+        ));
+        assert_eq!(events.len(), 10);
+        assert!(matches!(
+            events.last(),
+            Some(Err(ParserError::TruncatedLog { .. }))
+        ));
+
+        let complete = mjlog_content.replace("<U4/><E4/>", "<U4/><E4/></mjloggm>");
+        assert!(parse_events(Cursor::new(complete.as_bytes())).all(|e| e.is_ok()));
+    }
+
+    #[test]
+    fn test_truncated_log() {
+        let complete = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/><D0/><RYUUKYOKU ba="0,0" sc="250,0,250,0,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U4/><E4/>"#;
+
+        // Cut after a tag, and in the middle of one
+        for input in [complete.to_string(), format!("{complete}<V8")] {
+            match parse_mjlog(Cursor::new(input.as_bytes())) {
+                Err(ParserError::TruncatedLog { offset, partial }) => {
+                    assert!(offset >= complete.len() - 1);
+                    assert_eq!(partial.rounds.len(), 2);
+                    assert!(partial.rounds[1].events.len() >= 2);
+                    assert_eq!(partial.end_reason, Some(EndReason::Abandoned));
+                }
+                other => panic!("unexpected {:?}", other.map(|_| ())),
+            }
+        }
+
+        // A settled round at the end of input is not a truncation
+        let settled = &complete[..complete.find("<INIT seed=\"1").unwrap()];
+        assert!(parse_mjlog(Cursor::new(settled.as_bytes())).is_ok());
+
+        let options = ParserOptions {
+            parallel: true,
+            ..Default::default()
+        };
+        match parse_mjlog_with_options(Cursor::new(complete.as_bytes()), &options) {
+            Err(ParserError::TruncatedLog { offset, partial }) => {
+                assert!(offset >= complete.len() - 1);
+                assert_eq!(partial.rounds.len(), 2);
+            }
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_parallel_rounds() {
+        let content = std::fs::read("tests/data/sample_complex.xml").unwrap();
+        let sequential = parse_mjlog(Cursor::new(&content)).unwrap();
+
+        let options = ParserOptions {
+            parallel: true,
+            include_raw: true,
+            ..Default::default()
+        };
+        let parallel = parse_mjlog_with_options(Cursor::new(&content), &options).unwrap();
+        assert!(parallel.rounds.len() > 1);
+        assert_eq!(parallel.rounds[1].round_id, "Round 2");
+        assert_eq!(
+            parallel.rounds[0].raw.len(),
+            parallel.rounds[0].events.len()
+        );
+
+        let without_raw = |output: &ParserOutput| {
+            let mut value = serde_json::to_value(output).unwrap();
+            value.as_object_mut().unwrap().remove("gameId");
+            for round in value["rounds"].as_array_mut().unwrap() {
+                round.as_object_mut().unwrap().remove("raw");
+            }
+            value
+        };
+        assert_eq!(without_raw(&parallel), without_raw(&sequential));
+
+        // Errors surface as in a sequential parse
+        let broken = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<INIT seed="1,0,0,1,2" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+</mjloggm>"#;
+        let error = parse_mjlog_with_options(Cursor::new(broken.as_bytes()), &options).unwrap_err();
+        assert_eq!(error.code(), "invalid-format");
+    }
+
+    #[test]
+    fn test_parallel_rounds_without_aka() {
+        // GO type 3: no red fives, so the red 5p in the winning hand is no dora
+        let content = r#"<mjloggm ver="2.3"><GO type="3" lobby="0"/><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU type="nm" sc="250,0,250,0,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="3" fromWho="1" hai="0,4,8,44,48,52,72,76,80,81" m="41483" machi="8" doraHai="100" ten="30,2000,0" yaku="1,1" sc="250,0,250,-20,250,0,250,20"/>
+</mjloggm>"#;
+        let sequential = parse_mjlog(Cursor::new(content)).unwrap();
+        let options = ParserOptions {
+            parallel: true,
+            ..Default::default()
+        };
+        let mut parallel = parse_mjlog_with_options(Cursor::new(content), &options).unwrap();
+        parallel.game_id = sequential.game_id.clone();
+
+        let Some(Event::Agari { aka_dora, .. }) = parallel.rounds[1].events.last() else {
+            panic!("expected agari");
+        };
+        assert_eq!(*aka_dora, 0);
+        assert_eq!(
+            serde_json::to_value(&parallel).unwrap(),
+            serde_json::to_value(&sequential).unwrap()
+        );
+    }
 }
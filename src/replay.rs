@@ -0,0 +1,142 @@
+//! Links into Tenhou's replay viewer
+//!
+//! `https://tenhou.net/0/?log=<id>&tw=<seat>&ts=<round>` opens log `<id>` at
+//! the start of a round, counting the log's rounds from 0, as seen from a
+//! seat.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::ParserOutput;
+
+/// Tenhou's replay viewer
+pub const TENHOU_VIEWER_URL: &str = "https://tenhou.net/0/";
+
+/// The log ID in a Tenhou replay URL (`https://tenhou.net/0/?log=<id>&tw=1`,
+/// `https://tenhou.net/0/log/?<id>`), or `input` itself if it is a log ID
+///
+/// A log ID looks like `2023010100gm-00a9-0000-1a2b3c4d`: the hour the game
+/// started, the rule flags in hex, the lobby and a hash.
+pub fn tenhou_log_id(input: &str) -> Option<&str> {
+    let input = input.trim();
+    let id = match input.split_once('?') {
+        Some((_, query)) => query
+            .split('&')
+            .find_map(|pair| match pair.split_once('=') {
+                Some(("log", id)) => Some(id),
+                Some(_) => None,
+                None => Some(pair),
+            })?,
+        None => input,
+    };
+    let parts: Vec<&str> = id.split('-').collect();
+    let valid = match parts.as_slice() {
+        [time, flags, lobby, hash] => {
+            time.len() == 12
+                && time.ends_with("gm")
+                && time[..10].bytes().all(|b| b.is_ascii_digit())
+                && flags.len() == 4
+                && lobby.len() == 4
+                && [*flags, *lobby, *hash]
+                    .iter()
+                    .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_hexdigit()))
+        }
+        _ => false,
+    };
+    valid.then_some(id)
+}
+
+/// The log ID a file is named after, as in
+/// `2023010100gm-00a9-0000-1a2b3c4d.mjlog`
+pub fn file_log_id(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    tenhou_log_id(name.split('.').next()?)
+}
+
+/// Viewer link to round `round` of log `log_id`, seen from `seat`
+///
+/// `round` counts every round of the log, so it is only an index into
+/// `ParserOutput::rounds` when no round filter dropped any.
+pub fn replay_url(log_id: &str, round: usize, seat: u8) -> String {
+    format!(
+        "{}?log={}&tw={}&ts={}",
+        TENHOU_VIEWER_URL, log_id, seat, round
+    )
+}
+
+/// Viewer link to one round of a game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundLink {
+    /// Index into `ParserOutput::rounds`
+    pub round: usize,
+    pub round_id: String,
+    pub url: String,
+}
+
+/// Links to every round of `game`, the parse of log `log_id`, seen from
+/// `seat`
+pub fn round_links(log_id: &str, game: &ParserOutput, seat: u8) -> Vec<RoundLink> {
+    game.rounds
+        .iter()
+        .enumerate()
+        .map(|(r, round)| RoundLink {
+            round: r,
+            round_id: round.round_id.clone(),
+            url: replay_url(log_id, r, seat),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenhou_log_id() {
+        let id = "2023010100gm-00a9-0000-1a2b3c4d";
+        assert_eq!(tenhou_log_id(id), Some(id));
+        assert_eq!(
+            tenhou_log_id(&format!("https://tenhou.net/0/?log={}&tw=2", id)),
+            Some(id)
+        );
+        assert_eq!(
+            tenhou_log_id(&format!("https://tenhou.net/0/log/?{}", id)),
+            Some(id)
+        );
+        assert_eq!(tenhou_log_id("https://tenhou.net/0/?tw=2"), None);
+        assert_eq!(tenhou_log_id("2023010100gm-00a9-0000"), None);
+        assert_eq!(tenhou_log_id("game.mjlog"), None);
+    }
+
+    #[test]
+    fn test_replay_url() {
+        let id = "2023010100gm-00a9-0000-1a2b3c4d";
+        let url = replay_url(id, 3, 2);
+        assert_eq!(
+            url,
+            "https://tenhou.net/0/?log=2023010100gm-00a9-0000-1a2b3c4d&tw=2&ts=3"
+        );
+        assert_eq!(tenhou_log_id(&url), Some(id));
+
+        let path = format!("logs/{}.mjlog.gz", id);
+        assert_eq!(file_log_id(Path::new(&path)), Some(id));
+        assert_eq!(file_log_id(Path::new("logs/game.xml")), None);
+    }
+
+    #[test]
+    fn test_round_links() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU type="yao9" sc="250,0,250,0,250,0,250,0"/>
+<INIT seed="0,1,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+</mjloggm>"#;
+        let game = crate::parser::parse_mjlog(std::io::Cursor::new(xml)).unwrap();
+        let links = round_links("2023010100gm-00a9-0000-1a2b3c4d", &game, 1);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1].round, 1);
+        assert_eq!(links[1].round_id, game.rounds[1].round_id);
+        assert!(links[1].url.ends_with("&tw=1&ts=1"));
+    }
+}
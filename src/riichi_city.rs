@@ -0,0 +1,492 @@
+//! RiichiCity game record input (feature `riichi-city`)
+//!
+//! Reads the JSON returned by RiichiCity's game record API and normalizes it
+//! into the same models as Tenhou logs. Only the fields below are used:
+//!
+//! ```text
+//! data.keyValue                      game id
+//! data.players[] {userId, nickname, position}
+//! data.handRecord[].handEventRecord[] {eventType, userId, data}
+//! ```
+//!
+//! `data` holds an event payload, either as an object or a JSON-encoded string:
+//!
+//! | eventType | payload |
+//! |-----------|---------|
+//! | 1 deal    | `hand_cards`, `hand_points`, `dealer_pos`, `quan_feng`, `chang_ci`, `ben_chang_num`, `li_zhi_bang_num`, `bao_pai_card` |
+//! | 2 action  | `action` (2 chi, 3 pon, 4 open kan, 5 closed kan, 6 added kan, 11 discard), `card`, `group_cards`, `is_li_zhi` |
+//! | 3 draw    | `card` |
+//! | 4 end     | `win_info[] {user_id, fang_num, fu, is_zi_mo}`, `user_profit[] {user_id, point_profit}` |
+//! | 5 dora    | `card` |
+//!
+//! Cards are encoded as `suit << 4 | number` with suits 1 man, 2 pin, 3 sou
+//! and 4 honors (east … red); higher bits (red five flags) are ignored.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::{ParserError, Result};
+use crate::models::{
+    Event, Init, KanType, ParserOutput, Player, Round, Rules, RyuukyokuReason, Transfer,
+};
+use crate::stats::SCORE_UNIT;
+use crate::tile::Tile;
+
+const EVENT_DEAL: u32 = 1;
+const EVENT_ACTION: u32 = 2;
+const EVENT_DRAW: u32 = 3;
+const EVENT_END: u32 = 4;
+const EVENT_DORA: u32 = 5;
+
+const ACTION_CHI: u32 = 2;
+const ACTION_PON: u32 = 3;
+const ACTION_MINKAN: u32 = 4;
+const ACTION_ANKAN: u32 = 5;
+const ACTION_KAKAN: u32 = 6;
+const ACTION_DISCARD: u32 = 11;
+
+#[derive(Deserialize)]
+struct Record {
+    data: RecordData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordData {
+    #[serde(default)]
+    key_value: String,
+    players: Vec<RecordPlayer>,
+    #[serde(default)]
+    hand_record: Vec<HandRecord>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordPlayer {
+    user_id: u64,
+    #[serde(default)]
+    nickname: String,
+    position: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HandRecord {
+    #[serde(default)]
+    hand_event_record: Vec<HandEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HandEvent {
+    event_type: u32,
+    #[serde(default)]
+    user_id: u64,
+    #[serde(default)]
+    data: Value,
+}
+
+/// Convert a RiichiCity card code into a tile ID (first copy of its type)
+fn card_to_tile_id(card: u64) -> Result<u32> {
+    let suit = (card >> 4) & 0xF;
+    let number = (card & 0xF) as u32;
+    let tile_type = match (suit, number) {
+        (1..=3, 1..=9) => (suit as u32 - 1) * 9 + number - 1,
+        (4, 1..=7) => 27 + number - 1,
+        _ => {
+            return Err(ParserError::invalid_format(format!(
+                "Invalid RiichiCity card: {}",
+                card
+            )))
+        }
+    };
+    Ok(tile_type * 4)
+}
+
+fn card_to_tile(card: u64) -> Result<Tile> {
+    Ok(Tile::from_index(card_to_tile_id(card)? as usize / 4))
+}
+
+fn payload(event: &HandEvent) -> Result<Value> {
+    match &event.data {
+        Value::String(s) => serde_json::from_str(s)
+            .map_err(|e| ParserError::parse(e.to_string(), "RiichiCity event data")),
+        other => Ok(other.clone()),
+    }
+}
+
+fn field_u64(data: &Value, key: &str) -> u64 {
+    data.get(key).and_then(Value::as_u64).unwrap_or(0)
+}
+
+fn field_i64(data: &Value, key: &str) -> i64 {
+    data.get(key).and_then(Value::as_i64).unwrap_or(0)
+}
+
+fn cards(data: &Value, key: &str) -> Result<Vec<Tile>> {
+    data.get(key)
+        .and_then(Value::as_array)
+        .map(|cards| {
+            cards
+                .iter()
+                .filter_map(Value::as_u64)
+                .map(card_to_tile)
+                .collect()
+        })
+        .unwrap_or_else(|| Ok(Vec::new()))
+}
+
+/// Parse a RiichiCity game record into `ParserOutput`
+pub fn parse_riichi_city<R: Read>(reader: R) -> Result<ParserOutput> {
+    let record: Record = serde_json::from_reader(reader)
+        .map_err(|e| ParserError::parse(e.to_string(), "RiichiCity record"))?;
+    let data = record.data;
+
+    let seats: HashMap<u64, u8> = data
+        .players
+        .iter()
+        .map(|p| (p.user_id, p.position))
+        .collect();
+    let seat_of = |user_id: u64| -> Result<u8> {
+        seats.get(&user_id).copied().ok_or_else(|| {
+            ParserError::invalid_format(format!("Unknown RiichiCity user: {}", user_id))
+        })
+    };
+
+    let mut players: Vec<Player> = data
+        .players
+        .iter()
+        .map(|p| Player {
+            player_id: p.nickname.clone(),
+            ..Player::placeholder(p.position)
+        })
+        .collect();
+    players.sort_by_key(|p| p.seat);
+
+    let mut rounds = Vec::new();
+    for hand in &data.hand_record {
+        let mut init: Option<Init> = None;
+        let mut dealer_seat = 0;
+        let mut events = Vec::new();
+        let mut scores = [0i32; 4];
+        let mut last_discard: Option<u8> = None;
+
+        for event in &hand.hand_event_record {
+            let body = payload(event)?;
+            match event.event_type {
+                EVENT_DEAL => {
+                    let seat = seat_of(event.user_id)? as usize % 4;
+                    scores[seat] = (field_i64(&body, "hand_points") / i64::from(SCORE_UNIT)) as i32;
+                    let init = init.get_or_insert_with(|| {
+                        dealer_seat = field_u64(&body, "dealer_pos") as u8;
+                        let wind = field_u64(&body, "quan_feng").saturating_sub(0x41) as u32;
+                        let hand_number = field_u64(&body, "chang_ci").max(1) as u32 - 1;
+                        Init {
+                            round_number: wind * 4 + hand_number,
+                            honba: field_u64(&body, "ben_chang_num") as u32,
+                            kyoutaku: field_u64(&body, "li_zhi_bang_num") as u32,
+                            dice: [0, 0],
+                            dora_indicator: 0,
+                            initial_scores: [0; 4],
+                            initial_hands: vec![Vec::new(); 4],
+                        }
+                    });
+                    if let Some(card) = body.get("bao_pai_card").and_then(Value::as_u64) {
+                        init.dora_indicator = card_to_tile_id(card)?;
+                    }
+                    init.initial_hands[seat] = cards(&body, "hand_cards")?;
+                    init.initial_scores = scores;
+                }
+                EVENT_DRAW => events.push(Event::Draw {
+                    seat: seat_of(event.user_id)?,
+                    tile: card_to_tile(field_u64(&body, "card"))?,
+                    wall_position: None,
+                    shanten: None,
+                }),
+                EVENT_DORA => events.push(Event::Dora {
+                    indicator: card_to_tile(field_u64(&body, "card"))?,
+                    source: None,
+                    kan: None,
+                    who: None,
+                    timing: None,
+                }),
+                EVENT_ACTION => {
+                    let who = seat_of(event.user_id)?;
+                    let card = field_u64(&body, "card");
+                    let mut meld = cards(&body, "group_cards")?;
+                    match field_u64(&body, "action") as u32 {
+                        ACTION_DISCARD => {
+                            let riichi = body
+                                .get("is_li_zhi")
+                                .and_then(Value::as_bool)
+                                .unwrap_or(false);
+                            if riichi {
+                                events.push(Event::Reach {
+                                    who,
+                                    step: 1,
+                                    scores: [0; 4],
+                                    declaration: None,
+                                    transfers: Vec::new(),
+                                });
+                            }
+                            events.push(Event::Discard {
+                                seat: who,
+                                tile: card_to_tile(card)?,
+                                is_riichi: riichi,
+                                shanten: None,
+                                waits: Vec::new(),
+                                furiten: false,
+                            });
+                            if riichi {
+                                scores[who as usize % 4] -= 10;
+                                events.push(Event::Reach {
+                                    who,
+                                    step: 2,
+                                    scores,
+                                    declaration: None,
+                                    transfers: vec![Transfer::riichi_deposit(who)],
+                                });
+                            }
+                            last_discard = Some(who);
+                        }
+                        action @ (ACTION_CHI | ACTION_PON) => {
+                            meld.push(card_to_tile(card)?);
+                            let tiles: [Tile; 3] = meld.try_into().map_err(|_| {
+                                ParserError::invalid_format("RiichiCity chi/pon needs three cards")
+                            })?;
+                            let from = last_discard.unwrap_or(who);
+                            events.push(if action == ACTION_CHI {
+                                Event::Chi {
+                                    who,
+                                    tiles,
+                                    from,
+                                    claimed: None,
+                                }
+                            } else {
+                                Event::Pon {
+                                    who,
+                                    tiles,
+                                    from,
+                                    claimed: None,
+                                }
+                            });
+                        }
+                        action @ (ACTION_MINKAN | ACTION_ANKAN | ACTION_KAKAN) => {
+                            let (kan_type, from) = match action {
+                                ACTION_MINKAN => (KanType::Minkan, last_discard),
+                                ACTION_ANKAN => (KanType::Ankan, None),
+                                _ => (KanType::Kakan, None),
+                            };
+                            if meld.len() < 4 {
+                                meld.push(card_to_tile(card)?);
+                            }
+                            events.push(Event::Kan {
+                                who,
+                                tiles: meld,
+                                kan_type,
+                                from,
+                                claimed: None,
+                                dora: None,
+                                rinshan: None,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                EVENT_END => {
+                    let mut deltas = [0i32; 4];
+                    for profit in body
+                        .get("user_profit")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                    {
+                        let seat = seat_of(field_u64(profit, "user_id"))? as usize % 4;
+                        deltas[seat] =
+                            (field_i64(profit, "point_profit") / i64::from(SCORE_UNIT)) as i32;
+                    }
+                    // The discarder of a ron is the seat that lost the most
+                    let loser = (0..4u8).min_by_key(|&s| deltas[s as usize]).unwrap_or(0);
+
+                    let wins = body
+                        .get("win_info")
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default();
+                    if wins.is_empty() {
+                        events.push(Event::Ryuukyoku {
+                            reason: RyuukyokuReason::Normal,
+                            scores: deltas,
+                            tenpai: Default::default(),
+                            transfers: Transfer::draw(&deltas),
+                        });
+                    }
+                    for win in &wins {
+                        let who = seat_of(field_u64(win, "user_id"))?;
+                        let tsumo = win
+                            .get("is_zi_mo")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false);
+                        let from = if tsumo { who } else { loser };
+                        // Deltas are only known for the hand as a whole
+                        let scores = if wins.len() == 1 { deltas } else { [0; 4] };
+                        events.push(Event::Agari {
+                            who,
+                            from,
+                            han: field_u64(win, "fang_num") as u32,
+                            fu: field_u64(win, "fu") as u32,
+                            yakus: Vec::new(),
+                            dora_count: 0,
+                            dora: 0,
+                            ura_dora: 0,
+                            aka_dora: 0,
+                            hand: Vec::new(),
+                            melds: Vec::new(),
+                            winning_tile: None,
+                            dora_indicators: Vec::new(),
+                            ura_dora_indicators: Vec::new(),
+                            scores,
+                            payout: None,
+                            transfers: Transfer::agari(who, from, &scores, None),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(init) = init else {
+            continue;
+        };
+        let mut round = Round {
+            round_id: format!("Round {}", rounds.len() + 1),
+            dealer_seat,
+            init,
+            events,
+            wall: None,
+            raw: Vec::new(),
+            state: None,
+            counters: Vec::new(),
+        };
+        round.link_events();
+        rounds.push(round);
+    }
+
+    Ok(ParserOutput {
+        mjlog_version: "riichi-city".to_string(),
+        game_id: data.key_value,
+        rules: Rules {
+            type_flags: 0,
+            lobby_id: None,
+        },
+        players,
+        rounds,
+        spilled: None,
+        // The record does not say how long the game was meant to be
+        overtime: false,
+        end_reason: None,
+        result: None,
+        warnings: Vec::new(),
+        score_warnings: Vec::new(),
+        wall_mismatches: Vec::new(),
+        connections: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const RECORD: &str = r#"{
+  "data": {
+    "keyValue": "rc-123",
+    "players": [
+      {"userId": 10, "nickname": "A", "position": 0},
+      {"userId": 11, "nickname": "B", "position": 1},
+      {"userId": 12, "nickname": "C", "position": 2},
+      {"userId": 13, "nickname": "D", "position": 3}
+    ],
+    "handRecord": [{
+      "handEventRecord": [
+        {"eventType": 1, "userId": 10, "data": "{\"dealer_pos\":0,\"quan_feng\":65,\"chang_ci\":1,\"ben_chang_num\":0,\"li_zhi_bang_num\":0,\"bao_pai_card\":65,\"hand_points\":25000,\"hand_cards\":[17,18,19,20,21,22,23,24,25,33,34,35,36]}"},
+        {"eventType": 1, "userId": 11, "data": {"hand_points": 25000, "hand_cards": [49,49,50]}},
+        {"eventType": 3, "userId": 10, "data": {"card": 37}},
+        {"eventType": 2, "userId": 10, "data": {"action": 11, "card": 37, "is_li_zhi": true}},
+        {"eventType": 2, "userId": 11, "data": {"action": 3, "card": 37, "group_cards": [37, 37]}},
+        {"eventType": 4, "userId": 0, "data": {"win_info": [{"user_id": 10, "fang_num": 2, "fu": 40, "is_zi_mo": false}], "user_profit": [{"user_id": 10, "point_profit": 3600}, {"user_id": 11, "point_profit": -2600}]}}
+      ]
+    }]
+  }
+}"#;
+
+    #[test]
+    fn test_parse_riichi_city() {
+        let output = parse_riichi_city(Cursor::new(RECORD)).unwrap();
+        assert_eq!(output.game_id, "rc-123");
+        assert_eq!(output.players.len(), 4);
+        assert_eq!(output.players[1].player_id, "B");
+
+        let round = &output.rounds[0];
+        assert_eq!(round.init.round_number, 0);
+        assert_eq!(round.init.initial_scores[..2], [250, 250]);
+        assert_eq!(Tile::from_id(round.init.dora_indicator), "east");
+        assert_eq!(round.init.initial_hands[0][0], "1m");
+        assert_eq!(round.init.initial_hands[0][12], "4p");
+        assert_eq!(round.init.initial_hands[1], vec!["1s", "1s", "2s"]);
+
+        assert!(matches!(&round.events[0], Event::Draw { seat: 0, tile, .. } if tile == "5p"));
+        assert!(matches!(
+            round.events[1],
+            Event::Reach {
+                who: 0,
+                step: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &round.events[2],
+            Event::Discard {
+                seat: 0,
+                is_riichi: true,
+                ..
+            }
+        ));
+        assert!(
+            matches!(round.events[3], Event::Reach { who: 0, step: 2, scores, .. } if scores[0] == 240)
+        );
+        assert!(matches!(
+            &round.events[4],
+            Event::Pon {
+                who: 1,
+                from: 0,
+                ..
+            }
+        ));
+        match &round.events[5] {
+            Event::Agari {
+                who,
+                from,
+                han,
+                fu,
+                scores,
+                ..
+            } => {
+                assert_eq!((*who, *from, *han, *fu), (0, 1, 2, 40));
+                assert_eq!(scores[..2], [36, -26]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_card() {
+        assert!(card_to_tile_id(0x4A).is_err());
+        assert!(card_to_tile_id(0x10).is_err());
+        assert_eq!(card_to_tile_id(0x19).unwrap(), 8 * 4);
+        assert_eq!(card_to_tile_id(0x147).unwrap(), 33 * 4);
+    }
+}
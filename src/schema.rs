@@ -0,0 +1,173 @@
+//! JSON Schema of the parser output
+//!
+//! The schema is maintained by hand next to the models in
+//! `schema/parser_output.schema.json` and embedded at build time, so
+//! consumers in other languages can validate output or generate types from
+//! it. A test walks parsed sample logs against it to catch the two drifting
+//! apart.
+
+/// The JSON Schema (draft 2020-12) of [`ParserOutput`](crate::ParserOutput)
+pub const OUTPUT_SCHEMA: &str = include_str!("../schema/parser_output.schema.json");
+
+/// [`OUTPUT_SCHEMA`] as a JSON value
+pub fn output_schema() -> serde_json::Value {
+    serde_json::from_str(OUTPUT_SCHEMA).expect("embedded schema is valid JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::YakuKind;
+    use serde_json::Value;
+
+    /// The subset of JSON Schema the output schema uses: `$ref`, `oneOf`,
+    /// `const`, `enum`, `type`, `properties`, `required`,
+    /// `additionalProperties` and `items`
+    fn check(value: &Value, schema: &Value, root: &Value, path: &str, errors: &mut Vec<String>) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/$defs/");
+            return check(value, &root["$defs"][name], root, path, errors);
+        }
+        if let Some(options) = schema["oneOf"].as_array() {
+            let matching = options
+                .iter()
+                .filter(|option| {
+                    let mut option_errors = Vec::new();
+                    check(value, option, root, path, &mut option_errors);
+                    option_errors.is_empty()
+                })
+                .count();
+            if matching != 1 {
+                errors.push(format!("{}: matches {} of oneOf", path, matching));
+            }
+            return;
+        }
+        if let Some(expected) = schema.get("const") {
+            if value != expected {
+                errors.push(format!("{}: expected {}", path, expected));
+            }
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                errors.push(format!("{}: {} not in enum", path, value));
+            }
+        }
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let type_matches = |t: &&str| match *t {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        };
+        if !types.is_empty() && !types.iter().any(type_matches) {
+            errors.push(format!("{}: {} is not {:?}", path, value, types));
+            return;
+        }
+        if let Some(object) = value.as_object() {
+            for key in schema["required"].as_array().into_iter().flatten() {
+                let key = key.as_str().unwrap();
+                if !object.contains_key(key) {
+                    errors.push(format!("{}: missing {}", path, key));
+                }
+            }
+            for (key, item) in object {
+                let item_path = format!("{}.{}", path, key);
+                match (&schema["properties"][key], &schema["additionalProperties"]) {
+                    (Value::Null, Value::Bool(false)) => {
+                        errors.push(format!("{}: not in schema", item_path))
+                    }
+                    (Value::Null, Value::Object(_)) => check(
+                        item,
+                        &schema["additionalProperties"],
+                        root,
+                        &item_path,
+                        errors,
+                    ),
+                    (Value::Null, _) => {}
+                    (property, _) => check(item, property, root, &item_path, errors),
+                }
+            }
+        }
+        if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+            for (i, item) in items.iter().enumerate() {
+                check(item, item_schema, root, &format!("{}[{}]", path, i), errors);
+            }
+        }
+    }
+
+    fn errors(value: &Value) -> Vec<String> {
+        let schema = output_schema();
+        let mut errors = Vec::new();
+        check(value, &schema, &schema, "$", &mut errors);
+        errors
+    }
+
+    #[test]
+    fn test_sample_logs_match_schema() {
+        for name in ["sample.xml", "sample_complex.xml"] {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/data")
+                .join(name);
+            let options = crate::ParserOptions {
+                raw_source: true,
+                annotate_wall: true,
+                verify_wall: true,
+                validate_scores: true,
+                track_state: true,
+                annotate_shanten: true,
+                annotate_waits: true,
+                annotate_counters: true,
+                ..Default::default()
+            };
+            let output = crate::open_input(&path)
+                .and_then(|reader| crate::parse_mjlog_with_options(reader, &options))
+                .unwrap();
+            let value = serde_json::to_value(&output).unwrap();
+            assert_eq!(errors(&value), Vec::<String>::new(), "{}", name);
+
+            let value = crate::with_tile_format(crate::TileFormat::Object, || {
+                serde_json::to_value(&output).unwrap()
+            });
+            assert_eq!(
+                errors(&value),
+                Vec::<String>::new(),
+                "{} with tile IDs",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_catches_drift() {
+        let mut value = serde_json::to_value(
+            crate::parse_mjlog_file(
+                &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/sample.xml"),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        value["rules"]["newField"] = Value::Bool(true);
+        value["rounds"][0]["events"][0]["type"] = "teleport".into();
+        assert_eq!(errors(&value).len(), 2);
+    }
+
+    #[test]
+    fn test_yaku_kinds_match_schema() {
+        let kinds: Vec<Value> = YakuKind::ALL
+            .iter()
+            .map(|kind| serde_json::to_value(kind).unwrap())
+            .collect();
+        assert_eq!(
+            output_schema()["$defs"]["yaku"]["properties"]["kind"]["enum"],
+            Value::Array(kinds)
+        );
+    }
+}
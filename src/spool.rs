@@ -0,0 +1,120 @@
+//! Rounds kept in a temporary file rather than in memory
+//!
+//! With `ParserOptions::spill_rounds`, each round is written to a spool file
+//! once complete, one JSON object per line with tiles as `{id, name}`
+//! objects so that no tile loses its copy or red five. The parsed game then
+//! holds a [`SpilledRounds`] in `ParserOutput::spilled` instead of its
+//! rounds, and serializing it reads them back one at a time, so a log of any
+//! length is written out without ever holding all its rounds.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::models::Round;
+use crate::tile::{with_tile_format, TileFormat};
+
+/// A spool file being written, removed again unless it is finished
+pub(crate) struct Spool {
+    file: SpoolFile,
+    writer: BufWriter<File>,
+    len: usize,
+}
+
+impl Spool {
+    pub(crate) fn create() -> Result<Self> {
+        let path =
+            std::env::temp_dir().join(format!("tenhou-log-parser-{}.jsonl", uuid::Uuid::new_v4()));
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(Self {
+            file: SpoolFile(path),
+            writer,
+            len: 0,
+        })
+    }
+
+    pub(crate) fn push(&mut self, round: &Round) -> Result<()> {
+        // Tile strings would lose the copy and red five of each tile
+        with_tile_format(TileFormat::Object, || {
+            serde_json::to_writer(&mut self.writer, round)
+        })
+        .map_err(std::io::Error::other)?;
+        self.writer.write_all(b"\n")?;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Flush the rounds written so far and hand them over for reading
+    pub(crate) fn finish(mut self) -> Result<SpilledRounds> {
+        self.writer.flush()?;
+        Ok(SpilledRounds {
+            file: Arc::new(self.file),
+            len: self.len,
+            sort_hands: false,
+            redactions: Vec::new(),
+        })
+    }
+}
+
+/// Path of a spool file, which is removed when this is dropped
+#[derive(Debug)]
+struct SpoolFile(PathBuf);
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// The rounds of a game left in a spool file
+///
+/// Clones share the file, which is removed once the last of them is
+/// dropped. Edits made through `ParserOutput::sort_hands` and
+/// `ParserOutput::redact_for_seat` are applied as the rounds are read.
+#[derive(Debug, Clone)]
+pub struct SpilledRounds {
+    file: Arc<SpoolFile>,
+    len: usize,
+    sort_hands: bool,
+    /// Seats redacted for, in order
+    redactions: Vec<u8>,
+}
+
+impl SpilledRounds {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read the rounds back one at a time, in order
+    pub fn rounds(&self) -> Result<impl Iterator<Item = Result<Round>> + '_> {
+        let lines = BufReader::new(File::open(&self.file.0)?).lines();
+        Ok(lines.map(move |line| {
+            let mut round: Round = serde_json::from_str(&line?).map_err(std::io::Error::other)?;
+            if self.sort_hands {
+                round.sort_hands();
+            }
+            for &seat in &self.redactions {
+                round.redact_for_seat(seat);
+            }
+            Ok(round)
+        }))
+    }
+
+    pub(crate) fn sort_hands(&mut self) {
+        self.sort_hands = true;
+    }
+
+    pub(crate) fn redact_for_seat(&mut self, seat: u8) {
+        self.redactions.push(seat);
+    }
+}
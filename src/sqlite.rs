@@ -0,0 +1,265 @@
+//! Games written into a normalized SQLite database
+//!
+//! One database holds any number of games in four tables keyed by `game_id`:
+//! `games`, `players` (one row per seat), `rounds` and `events`. Rounds and
+//! events have the columns of the round and event datasets, and every event
+//! also keeps its full JSON in `events.data` for what the columns leave out.
+//!
+//! Writing a game whose ID is already in the database replaces it, so the
+//! same logs can be appended again without duplicating rows.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::dataset::{event_rows, round_rows};
+use crate::models::ParserOutput;
+
+/// Tables and indexes, created when missing
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS games (
+    game_id TEXT PRIMARY KEY,
+    mjlog_version TEXT NOT NULL,
+    type_flags INTEGER NOT NULL,
+    lobby_id INTEGER,
+    player_count INTEGER NOT NULL,
+    rules TEXT NOT NULL,
+    overtime INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS players (
+    game_id TEXT NOT NULL REFERENCES games(game_id),
+    seat INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    rate INTEGER NOT NULL,
+    gender TEXT NOT NULL,
+    final_score INTEGER,
+    points REAL,
+    placement INTEGER,
+    PRIMARY KEY (game_id, seat)
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    game_id TEXT NOT NULL REFERENCES games(game_id),
+    round INTEGER NOT NULL,
+    round_id TEXT NOT NULL,
+    round_number INTEGER NOT NULL,
+    honba INTEGER NOT NULL,
+    kyoutaku INTEGER NOT NULL,
+    dealer INTEGER NOT NULL,
+    events INTEGER NOT NULL,
+    outcome TEXT NOT NULL,
+    winner INTEGER,
+    from_seat INTEGER,
+    han INTEGER,
+    fu INTEGER,
+    delta0 INTEGER NOT NULL,
+    delta1 INTEGER NOT NULL,
+    delta2 INTEGER NOT NULL,
+    delta3 INTEGER NOT NULL,
+    PRIMARY KEY (game_id, round)
+);
+CREATE TABLE IF NOT EXISTS events (
+    game_id TEXT NOT NULL REFERENCES games(game_id),
+    round INTEGER NOT NULL,
+    event INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    seat INTEGER,
+    tile TEXT NOT NULL,
+    tiles TEXT NOT NULL,
+    from_seat INTEGER,
+    riichi INTEGER,
+    detail TEXT NOT NULL,
+    han INTEGER,
+    fu INTEGER,
+    scores TEXT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (game_id, round, event)
+);
+CREATE INDEX IF NOT EXISTS players_name ON players(name);
+CREATE INDEX IF NOT EXISTS events_type ON events(type);
+";
+
+/// A database open for writing games
+pub struct SqliteWriter {
+    connection: Connection,
+}
+
+impl SqliteWriter {
+    /// Open or create the database at `path`, creating missing tables
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// A database that lives only in memory
+    pub fn in_memory() -> rusqlite::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Write one game in a single transaction, replacing any game with the
+    /// same ID
+    pub fn insert(&mut self, game: &ParserOutput) -> rusqlite::Result<()> {
+        let tx = self.connection.transaction()?;
+        for table in ["events", "rounds", "players", "games"] {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE game_id = ?1", table),
+                params![game.game_id],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT INTO games VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                game.game_id,
+                game.mjlog_version,
+                game.rules.type_flags,
+                game.rules.lobby_id,
+                game.rules.players(),
+                serde_json::to_string(&game.rules).unwrap_or_default(),
+                game.overtime,
+            ],
+        )?;
+
+        let mut players =
+            tx.prepare("INSERT INTO players VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")?;
+        for player in &game.players {
+            let seat = player.seat as usize % 4;
+            let result = game.result.as_ref();
+            players.execute(params![
+                game.game_id,
+                player.seat,
+                player.player_id,
+                player.rank.rank(),
+                player.rate,
+                player.gender.code(),
+                result.map(|r| r.scores[seat]),
+                result.map(|r| r.points[seat]),
+                result.map(|r| r.placements[seat]).filter(|&p| p > 0),
+            ])?;
+        }
+        drop(players);
+
+        let mut rounds = tx.prepare(
+            "INSERT INTO rounds VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        )?;
+        for row in round_rows(game) {
+            rounds.execute(params![
+                row.game_id,
+                row.round,
+                row.round_id,
+                row.round_number,
+                row.honba,
+                row.kyoutaku,
+                row.dealer,
+                row.events,
+                row.outcome,
+                row.winner,
+                row.from,
+                row.han,
+                row.fu,
+                row.deltas[0],
+                row.deltas[1],
+                row.deltas[2],
+                row.deltas[3],
+            ])?;
+        }
+        drop(rounds);
+
+        let mut events = tx.prepare(
+            "INSERT INTO events VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        )?;
+        for row in event_rows(game) {
+            let event = &game.rounds[row.round].events[row.event];
+            events.execute(params![
+                row.game_id,
+                row.round,
+                row.event,
+                row.kind,
+                row.seat,
+                row.tile,
+                row.tiles,
+                row.from,
+                row.riichi,
+                row.detail,
+                row.han,
+                row.fu,
+                row.scores,
+                serde_json::to_string(event).unwrap_or_default(),
+            ])?;
+        }
+        drop(events);
+
+        tx.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_insert() {
+        let xml = r#"<mjloggm ver="2.3"><GO type="169"/><UN n0="A" n1="B" n2="C" n3="D" dan="16,15,14,13" rate="2000,1900,1800,1700" sx="M,F,M,F"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/><D0/>
+<AGARI who="0" fromWho="1" ten="30,3900,0" sc="250,39,250,-39,250,0,250,0" owari="289,38.9,211,-8.9,250,-5.0,250,-25.0"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let mut db = SqliteWriter::in_memory().unwrap();
+        db.insert(&game).unwrap();
+        // Writing the game again replaces it
+        db.insert(&game).unwrap();
+
+        let count = |table: &str| -> i64 {
+            db.connection()
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(
+            (
+                count("games"),
+                count("players"),
+                count("rounds"),
+                count("events")
+            ),
+            (1, 4, 1, 3)
+        );
+
+        let (name, placement): (String, i64) = db
+            .connection()
+            .query_row(
+                "SELECT name, placement FROM players WHERE seat = 1",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((name.as_str(), placement), ("B", 4));
+
+        let (kind, tile, data): (String, String, String) = db
+            .connection()
+            .query_row(
+                "SELECT type, tile, data FROM events WHERE event = 1",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((kind.as_str(), tile.as_str()), ("discard", "1m"));
+        assert!(data.contains(r#""type":"discard""#));
+
+        let delta: i64 = db
+            .connection()
+            .query_row("SELECT delta0 FROM rounds", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(delta, 3900);
+    }
+}
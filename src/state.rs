@@ -0,0 +1,436 @@
+//! Replaying a round into the state of the table after every event
+//!
+//! [`RoundState::replay`] plays a round's events from the deal and keeps a
+//! [`TableState`] snapshot after each of them: every seat's concealed hand,
+//! melds and discard river, the dora indicators shown and the tiles left in
+//! the live wall. Tiles the log does not account for (a damaged log, or
+//! `Tile::HIDDEN` after `ParserOutput::redact_for_seat`) are skipped rather
+//! than treated as errors, so the state is a best effort.
+//!
+//! [`event_counters`] keeps only a few counts per event: its sequence number,
+//! the acting seat's turn and the tiles left in the live wall.
+
+use serde::{Deserialize, Serialize};
+
+use crate::meld::MeldKind;
+use crate::models::{Event, KanType, Round};
+use crate::tile::{Tile, TileType, TILE_TYPES};
+
+/// Tiles in the dead wall, which draws never reach
+const DEAD_WALL: u32 = 14;
+
+/// One seat at one point of a round
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerState {
+    /// Concealed tiles, in tile order
+    pub hand: Vec<Tile>,
+    /// Calls and closed kans, in the order they were made
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub melds: Vec<MeldState>,
+    /// Discards, including those other players called
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub river: Vec<RiverTile>,
+    /// Riichi accepted (REACH step 2)
+    #[serde(default)]
+    pub riichi: bool,
+    /// Norths set aside in three-player games
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub nukidora: u8,
+}
+
+fn is_zero(n: &u8) -> bool {
+    *n == 0
+}
+
+impl PlayerState {
+    /// Tile counts per tile type of the concealed hand
+    pub fn counts(&self) -> [u8; TILE_TYPES] {
+        let mut counts = [0u8; TILE_TYPES];
+        for tile_type in self.hand.iter().filter_map(|t| t.tile_type()) {
+            counts[tile_type.index()] += 1;
+        }
+        counts
+    }
+
+    /// Remove `tile` from the hand, or a hidden tile when the hand does not
+    /// show it
+    fn take(&mut self, tile: Tile) {
+        let index = self
+            .hand
+            .iter()
+            .position(|&t| t == tile)
+            .or_else(|| self.hand.iter().position(|t| t.is_hidden()));
+        if let Some(index) = index {
+            self.hand.remove(index);
+        }
+    }
+}
+
+/// A call or closed kan on the table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeldState {
+    pub kind: MeldKind,
+    pub tiles: Vec<Tile>,
+    /// Seat the called tile came from; none for a closed kan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<u8>,
+}
+
+/// A discard in a river
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiverTile {
+    pub tile: Tile,
+    /// Discarded to declare riichi
+    #[serde(default)]
+    pub riichi: bool,
+    /// Claimed by another player's call
+    #[serde(default)]
+    pub called: bool,
+}
+
+/// The table at one point of a round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableState {
+    /// By seat; three entries in three-player games
+    pub players: Vec<PlayerState>,
+    /// Dora indicators revealed so far
+    pub dora_indicators: Vec<Tile>,
+    /// Tiles still to be drawn from the live wall
+    pub wall_remaining: u32,
+}
+
+impl TableState {
+    /// The table right after the deal of `round`
+    pub fn dealt(round: &Round, players: u8) -> Self {
+        let seats = players.clamp(1, 4) as usize;
+        let tiles = if seats == 3 { 108 } else { 136 };
+        let players = round.init.initial_hands[..seats]
+            .iter()
+            .map(|hand| {
+                let mut hand = hand.clone();
+                hand.sort();
+                PlayerState {
+                    hand,
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        let dealt: u32 = players.iter().map(|p| p.hand.len() as u32).sum();
+        Self {
+            players,
+            dora_indicators: vec![Tile::from_id(round.init.dora_indicator)],
+            wall_remaining: (tiles - DEAD_WALL).saturating_sub(dealt),
+        }
+    }
+
+    /// Update the table for one event
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Draw { seat, tile, .. } => {
+                // Replacement draws come from the dead wall, which takes the
+                // last tile of the live wall in exchange
+                self.wall_remaining = self.wall_remaining.saturating_sub(1);
+                if let Some(player) = self.players.get_mut(*seat as usize) {
+                    player.hand.push(*tile);
+                    player.hand.sort();
+                }
+            }
+            Event::Discard {
+                seat,
+                tile,
+                is_riichi,
+                ..
+            } => {
+                if let Some(player) = self.players.get_mut(*seat as usize) {
+                    player.take(*tile);
+                    player.river.push(RiverTile {
+                        tile: *tile,
+                        riichi: *is_riichi,
+                        called: false,
+                    });
+                }
+            }
+            Event::Chi {
+                who, tiles, from, ..
+            } => self.call(*who, MeldKind::Chi, tiles, Some(*from)),
+            Event::Pon {
+                who, tiles, from, ..
+            } => self.call(*who, MeldKind::Pon, tiles, Some(*from)),
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                from,
+                ..
+            } => match kan_type {
+                KanType::Minkan => self.call(*who, MeldKind::Minkan, tiles, *from),
+                KanType::Ankan => self.call(*who, MeldKind::Ankan, tiles, None),
+                KanType::Kakan => self.add_to_pon(*who, tiles),
+            },
+            Event::Dora { indicator, .. } => self.dora_indicators.push(*indicator),
+            Event::Reach { who, step: 2, .. } => {
+                if let Some(player) = self.players.get_mut(*who as usize) {
+                    player.riichi = true;
+                }
+            }
+            Event::Unknown { tag, attributes } if tag == "N" => {
+                // A north set aside, the only call without an event
+                let who = attributes.get("who").and_then(|w| w.parse::<usize>().ok());
+                if let Some(player) = who.and_then(|who| self.players.get_mut(who)) {
+                    player.take(Tile::from_type(TileType::North));
+                    player.nukidora += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A meld made from the hand of `who`, plus the discard it claimed
+    fn call(&mut self, who: u8, kind: MeldKind, tiles: &[Tile], from: Option<u8>) {
+        let claimed = from.and_then(|from| {
+            let river = &mut self.players.get_mut(from as usize)?.river;
+            let discard = river.last_mut()?;
+            discard.called = true;
+            Some(discard.tile)
+        });
+        let Some(player) = self.players.get_mut(who as usize) else {
+            return;
+        };
+        let mut claimed = claimed.filter(|tile| tiles.contains(tile));
+        for &tile in tiles {
+            if claimed == Some(tile) {
+                claimed = None;
+            } else {
+                player.take(tile);
+            }
+        }
+        player.melds.push(MeldState {
+            kind,
+            tiles: tiles.to_vec(),
+            from,
+        });
+    }
+
+    /// An added kan: the pon of the same tile becomes a kan
+    fn add_to_pon(&mut self, who: u8, tiles: &[Tile]) {
+        let Some(player) = self.players.get_mut(who as usize) else {
+            return;
+        };
+        let Some(&added) = tiles.first() else {
+            return;
+        };
+        player.take(added);
+        match player
+            .melds
+            .iter_mut()
+            .find(|m| m.kind == MeldKind::Pon && m.tiles.first() == Some(&added))
+        {
+            Some(pon) => {
+                pon.kind = MeldKind::Kakan;
+                pon.tiles = tiles.to_vec();
+            }
+            None => player.melds.push(MeldState {
+                kind: MeldKind::Kakan,
+                tiles: tiles.to_vec(),
+                from: None,
+            }),
+        }
+    }
+}
+
+/// Snapshots of the table through one round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundState {
+    /// Right after the deal
+    pub dealt: TableState,
+    /// After each event, by event ID
+    pub after_events: Vec<TableState>,
+}
+
+impl RoundState {
+    /// Play `round` through from the deal
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Cursor;
+    /// use tenhou_log_parser::{parse_mjlog, state::RoundState};
+    ///
+    /// let xml = r#"<mjloggm ver="2.3"><INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,44,48" hai1="" hai2="" hai3=""/><T52/><D0/></mjloggm>"#;
+    /// let output = parse_mjlog(Cursor::new(xml)).unwrap();
+    /// let state = RoundState::replay(&output.rounds[0], 4);
+    /// let after_discard = state.at_event(1).unwrap();
+    /// assert_eq!(after_discard.players[0].hand.len(), 13);
+    /// assert_eq!(after_discard.players[0].river[0].tile, "1m");
+    /// ```
+    pub fn replay(round: &Round, players: u8) -> Self {
+        let dealt = TableState::dealt(round, players);
+        let mut state = dealt.clone();
+        let after_events = round
+            .events
+            .iter()
+            .map(|event| {
+                state.apply(event);
+                state.clone()
+            })
+            .collect();
+        Self {
+            dealt,
+            after_events,
+        }
+    }
+
+    /// The table right after `events[index]`
+    pub fn at_event(&self, index: usize) -> Option<&TableState> {
+        self.after_events.get(index)
+    }
+
+    /// The table once every event has been played
+    pub fn last(&self) -> &TableState {
+        self.after_events.last().unwrap_or(&self.dealt)
+    }
+}
+
+/// Where one event falls in its round
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventCounters {
+    /// The event's ID, its index in `Round::events`
+    pub event_index: usize,
+    /// Turn (junme) of the seat that acted: the turns it has taken so far,
+    /// each started by a draw or by calling a discard, so 0 before its
+    /// first; none for events of no seat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn: Option<u32>,
+    /// Tiles still to be drawn from the live wall after the event
+    pub wall_tiles_remaining: u32,
+}
+
+/// The counters of every event of `round`, by event ID
+///
+/// The replacement draw after a kan or a north set aside stays in the turn
+/// of the kan; it still takes a tile off the live wall.
+pub fn event_counters(round: &Round, players: u8) -> Vec<EventCounters> {
+    let mut table = TableState::dealt(round, players);
+    let mut turns = [0u32; 4];
+    // Seats whose next draw replaces a kan or a north
+    let mut replacing = [false; 4];
+    round
+        .events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| {
+            table.apply(event);
+            let seat = match event {
+                Event::Unknown { tag, attributes } if tag == "N" => {
+                    attributes.get("who").and_then(|w| w.parse::<u8>().ok())
+                }
+                _ => event.seat(),
+            };
+            let turn = seat.map(|seat| {
+                let seat = seat as usize % 4;
+                match event {
+                    Event::Draw { .. } if !std::mem::take(&mut replacing[seat]) => turns[seat] += 1,
+                    Event::Chi { .. } | Event::Pon { .. } => turns[seat] += 1,
+                    Event::Kan { kan_type, .. } => {
+                        if *kan_type == KanType::Minkan {
+                            turns[seat] += 1;
+                        }
+                        replacing[seat] = true;
+                    }
+                    Event::Unknown { .. } => replacing[seat] = true,
+                    _ => {}
+                }
+                turns[seat]
+            });
+            EventCounters {
+                event_index: index,
+                turn,
+                wall_tiles_remaining: table.wall_remaining,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_replay_calls_and_kans() {
+        // Seat 0 discards east and seat 1 pons it; seat 2 calls a closed
+        // kan of 1m, seat 1 adds the fourth east and declares riichi
+        let xml = r#"<mjloggm ver="2.3">
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="108,4,8,12,16,20,24,28,32,36,40,44,48" hai1="109,110,5,9,13,17,21,25,29,33,37,41,45" hai2="0,1,2,3,53,57,61,65,69,73,77,81,85" hai3=""/>
+<T100/><D108/>
+<N who="1" m="41483"/><E5/>
+<V124/><N who="2" m="768"/><DORA hai="60"/><V125/><F124/>
+<U111/><N who="1" m="41491"/><DORA hai="64"/><U96/><E96/>
+<U92/><REACH who="1" step="1"/><E92/><REACH who="1" step="2" ten="250,240,250,250"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(xml)).unwrap();
+        let round = &output.rounds[0];
+        let state = RoundState::replay(round, 4);
+
+        assert_eq!(state.dealt.wall_remaining, 136 - 14 - 39);
+        assert_eq!(state.dealt.players[1].hand.len(), 13);
+
+        // After the pon
+        let pon = state.at_event(2).unwrap();
+        assert!(pon.players[0].river[0].called);
+        assert_eq!(pon.players[1].hand.len(), 11);
+        assert_eq!(pon.players[1].melds[0].kind, MeldKind::Pon);
+        assert_eq!(pon.players[1].melds[0].from, Some(0));
+
+        // The replacement draws after the kans stay in the kans' turns
+        let counters = event_counters(round, 4);
+        assert_eq!(counters.len(), round.events.len());
+        let turns: Vec<_> = counters.iter().map(|c| c.turn).collect();
+        assert_eq!(
+            turns[..9],
+            [
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(1),
+                None,
+                Some(1),
+                Some(1),
+            ]
+        );
+        assert_eq!(counters[12].turn, Some(2));
+        assert_eq!(counters[12].event_index, 12);
+        let last_counters = counters.last().unwrap();
+        assert_eq!(
+            last_counters.wall_tiles_remaining,
+            state.last().wall_remaining
+        );
+        assert_eq!(last_counters.wall_tiles_remaining, 136 - 14 - 39 - 6);
+
+        let last = state.last();
+        let seat1 = &last.players[1];
+        assert_eq!(seat1.melds.len(), 1);
+        assert_eq!(seat1.melds[0].kind, MeldKind::Kakan);
+        assert_eq!(seat1.melds[0].tiles.len(), 4);
+        assert!(seat1.riichi);
+        assert!(seat1.river.last().unwrap().riichi);
+        assert_eq!(seat1.hand.len() + 3 * seat1.melds.len(), 13);
+        assert!(!seat1.hand.iter().any(|t| t == "east"));
+
+        let seat2 = &last.players[2];
+        assert_eq!(seat2.melds[0].kind, MeldKind::Ankan);
+        assert_eq!(seat2.hand.len(), 10);
+        assert_eq!(last.dora_indicators, ["5p", "7p", "8p"]);
+        // Six draws, replacement draws included
+        assert_eq!(last.wall_remaining, state.dealt.wall_remaining - 6);
+        assert_eq!(state.after_events.len(), round.events.len());
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio, Outcome, RoundResult};
+use crate::models::{Event, KanType, ParserOutput};
+
+/// Calls (naki) made by a player and how the called hands ended
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStats {
+    pub rounds: u32,
+    /// Rounds in which the player opened their hand
+    pub called_rounds: u32,
+    pub chi: u32,
+    pub pon: u32,
+    pub ankan: u32,
+    pub minkan: u32,
+    pub kakan: u32,
+    /// Number of calls per meld, keyed by the meld's first tile
+    pub called_tiles: BTreeMap<String, u32>,
+    pub called_wins: u32,
+    pub called_deal_ins: u32,
+    pub called_draws: u32,
+    pub called_other_wins: u32,
+    /// Points gained in wins with an open hand
+    pub called_win_points: i64,
+    /// Wins with a closed hand (closed kans allowed)
+    pub closed_wins: u32,
+    /// Points gained in wins with a closed hand
+    pub closed_win_points: i64,
+}
+
+impl CallStats {
+    /// Fraction of rounds in which the player opened their hand
+    pub fn call_rate(&self) -> f64 {
+        ratio(self.called_rounds, self.rounds)
+    }
+
+    pub fn called_win_rate(&self) -> f64 {
+        ratio(self.called_wins, self.called_rounds)
+    }
+
+    pub fn called_deal_in_rate(&self) -> f64 {
+        ratio(self.called_deal_ins, self.called_rounds)
+    }
+
+    pub fn average_called_win(&self) -> f64 {
+        if self.called_wins == 0 {
+            0.0
+        } else {
+            self.called_win_points as f64 / self.called_wins as f64
+        }
+    }
+
+    /// Win rate over the rounds in which the hand stayed closed
+    pub fn closed_win_rate(&self) -> f64 {
+        ratio(self.closed_wins, self.rounds - self.called_rounds)
+    }
+
+    pub fn average_closed_win(&self) -> f64 {
+        if self.closed_wins == 0 {
+            0.0
+        } else {
+            self.closed_win_points as f64 / self.closed_wins as f64
+        }
+    }
+}
+
+/// Collect call statistics per player name
+pub fn call_stats(games: &[ParserOutput]) -> BTreeMap<String, CallStats> {
+    let mut stats: BTreeMap<String, CallStats> = BTreeMap::new();
+
+    for game in games {
+        for round in &game.rounds {
+            let result = RoundResult::from_round(round);
+            let mut opened = [false; 4];
+
+            for event in &round.events {
+                let (who, tiles) = match event {
+                    Event::Chi { who, tiles, .. } | Event::Pon { who, tiles, .. } => {
+                        (*who, tiles.as_slice())
+                    }
+                    Event::Kan { who, tiles, .. } => (*who, tiles.as_slice()),
+                    _ => continue,
+                };
+                let Some(name) = player_name(game, who) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+
+                match event {
+                    Event::Chi { .. } => entry.chi += 1,
+                    Event::Pon { .. } => entry.pon += 1,
+                    Event::Kan { kan_type, .. } => match kan_type {
+                        KanType::Ankan => entry.ankan += 1,
+                        KanType::Minkan => entry.minkan += 1,
+                        KanType::Kakan => entry.kakan += 1,
+                    },
+                    _ => {}
+                }
+                if let Some(tile) = tiles.first() {
+                    *entry.called_tiles.entry(tile.to_string()).or_default() += 1;
+                }
+                // A closed kan keeps the hand closed
+                if !matches!(
+                    event,
+                    Event::Kan {
+                        kan_type: KanType::Ankan,
+                        ..
+                    }
+                ) {
+                    opened[who as usize % 4] = true;
+                }
+            }
+
+            for seat in 0..4u8 {
+                let Some(name) = player_name(game, seat) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+                entry.rounds += 1;
+                if !opened[seat as usize] {
+                    if result.won(seat) {
+                        entry.closed_wins += 1;
+                        entry.closed_win_points += result.deltas[seat as usize] as i64;
+                    }
+                    continue;
+                }
+
+                entry.called_rounds += 1;
+                match result.outcome(seat) {
+                    Outcome::Win => {
+                        entry.called_wins += 1;
+                        entry.called_win_points += result.deltas[seat as usize] as i64;
+                    }
+                    Outcome::DealIn => entry.called_deal_ins += 1,
+                    Outcome::Draw => entry.called_draws += 1,
+                    Outcome::OtherWin => entry.called_other_wins += 1,
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use crate::tile::Tile;
+    use std::io::Cursor;
+
+    fn tiles(t: &[&str]) -> Vec<Tile> {
+        t.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_call_stats() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" sc="250,20,250,-20,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="270,230,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU type="nm" sc="270,0,230,0,250,0,250,0"/>
+</mjloggm>"#;
+        let mut game = parse_mjlog(Cursor::new(xml)).unwrap();
+
+        // Round 1: A chis and pons, then wins off B; B only makes a closed kan
+        game.rounds[0].events.splice(
+            0..0,
+            [
+                Event::Chi {
+                    who: 0,
+                    tiles: [
+                        "3m".parse().unwrap(),
+                        "4m".parse().unwrap(),
+                        "5m".parse().unwrap(),
+                    ],
+                    from: 3,
+                    claimed: None,
+                },
+                Event::Pon {
+                    who: 0,
+                    tiles: [
+                        "east".parse().unwrap(),
+                        "east".parse().unwrap(),
+                        "east".parse().unwrap(),
+                    ],
+                    from: 2,
+                    claimed: None,
+                },
+                Event::Kan {
+                    who: 1,
+                    tiles: tiles(&["9p", "9p", "9p", "9p"]),
+                    kan_type: KanType::Ankan,
+                    from: None,
+                    claimed: None,
+                    dora: None,
+                    rinshan: None,
+                },
+            ],
+        );
+        // Round 2: C calls a minkan and the round is drawn
+        game.rounds[1].events.insert(
+            0,
+            Event::Kan {
+                who: 2,
+                tiles: tiles(&["white", "white", "white", "white"]),
+                kan_type: KanType::Minkan,
+                from: Some(0),
+                claimed: None,
+                dora: None,
+                rinshan: None,
+            },
+        );
+
+        let stats = call_stats(&[game]);
+
+        let a = &stats["A"];
+        assert_eq!(a.rounds, 2);
+        assert_eq!((a.chi, a.pon), (1, 1));
+        assert_eq!(a.called_rounds, 1);
+        assert_eq!(a.called_wins, 1);
+        assert_eq!(a.called_win_points, 2000);
+        assert_eq!(a.called_tiles["3m"], 1);
+        assert_eq!(a.called_tiles["east"], 1);
+        assert_eq!(a.call_rate(), 0.5);
+
+        let b = &stats["B"];
+        assert_eq!(b.ankan, 1);
+        assert_eq!(b.called_rounds, 0);
+
+        let c = &stats["C"];
+        assert_eq!(c.minkan, 1);
+        assert_eq!(c.called_draws, 1);
+        assert_eq!(c.called_win_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_open_vs_closed_wins() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" sc="250,10,250,-10,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="260,240,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="2" sc="260,39,240,0,250,-39,250,0"/>
+<INIT seed="2,0,0,1,2,52" ten="299,240,211,250" oya="2" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU type="nm" sc="299,0,240,0,211,0,250,0"/>
+</mjloggm>"#;
+        let mut game = parse_mjlog(Cursor::new(xml)).unwrap();
+        game.rounds[0].events.insert(
+            0,
+            Event::Chi {
+                who: 0,
+                tiles: [
+                    "1s".parse().unwrap(),
+                    "2s".parse().unwrap(),
+                    "3s".parse().unwrap(),
+                ],
+                from: 3,
+                claimed: None,
+            },
+        );
+
+        let a = &call_stats(&[game])["A"];
+        assert_eq!(a.called_wins, 1);
+        assert_eq!(a.average_called_win(), 1000.0);
+        assert_eq!(a.closed_wins, 1);
+        assert_eq!(a.closed_win_points, 3900);
+        assert_eq!(a.average_closed_win(), 3900.0);
+        assert_eq!(a.closed_win_rate(), 0.5);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio, RoundResult};
+use crate::models::ParserOutput;
+
+/// How often and how expensively a player dealt into another's ron (houjuu)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DealInStats {
+    pub rounds: u32,
+    /// Rounds in which the player dealt in (a double ron counts once)
+    pub deal_ins: u32,
+    /// Points paid in deal-ins, as a positive number
+    pub deal_in_points: i64,
+}
+
+impl DealInStats {
+    /// Houjuu rate: fraction of rounds ending in a deal-in by this player
+    pub fn deal_in_rate(&self) -> f64 {
+        ratio(self.deal_ins, self.rounds)
+    }
+
+    pub fn average_deal_in(&self) -> f64 {
+        if self.deal_ins == 0 {
+            0.0
+        } else {
+            self.deal_in_points as f64 / self.deal_ins as f64
+        }
+    }
+}
+
+/// Collect deal-in statistics per player name
+///
+/// The discarder is identified by the `from` seat of each ron `Agari` event.
+pub fn deal_in_stats(games: &[ParserOutput]) -> BTreeMap<String, DealInStats> {
+    let mut stats: BTreeMap<String, DealInStats> = BTreeMap::new();
+
+    for game in games {
+        for round in &game.rounds {
+            let result = RoundResult::from_round(round);
+
+            for seat in 0..4u8 {
+                let Some(name) = player_name(game, seat) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+                entry.rounds += 1;
+
+                if result.dealt_in(seat) {
+                    let paid: i32 = result
+                        .deal_ins(seat)
+                        .map(|w| -w.deltas[seat as usize])
+                        .sum();
+                    entry.deal_ins += 1;
+                    entry.deal_in_points += paid as i64;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_deal_in_stats() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" sc="250,39,250,-39,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="289,211,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="2" fromWho="1" sc="289,0,211,-20,250,20,250,0"/>
+<AGARI who="3" fromWho="1" sc="289,0,191,-80,270,0,250,80"/>
+<INIT seed="2,0,0,1,2,52" ten="289,111,270,330" oya="2" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="0" sc="289,30,111,-10,270,-10,330,-10"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let stats = deal_in_stats(&[game]);
+
+        let b = &stats["B"];
+        assert_eq!(b.rounds, 3);
+        assert_eq!(b.deal_ins, 2);
+        // 3900 + double ron of 2000 and 8000; tsumo payments don't count
+        assert_eq!(b.deal_in_points, 3900 + 2000 + 8000);
+        assert_eq!(b.average_deal_in(), 6950.0);
+        assert_eq!(b.deal_in_rate(), 2.0 / 3.0);
+
+        assert_eq!(stats["A"].deal_ins, 0);
+        assert_eq!(stats["A"].average_deal_in(), 0.0);
+    }
+}
@@ -0,0 +1,277 @@
+//! Common CSV/JSON output for statistics
+//!
+//! JSON is the serde representation. CSV is a tidy (long) table by default:
+//! every numeric or string leaf of the serialized value becomes one row, with
+//! the leading path segments split into key columns and the rest joined with
+//! `.` into a `metric` column.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{DiscardHeatmap, HeadToHead, Session, StatsReport, StatsSummary, YakuStats};
+
+/// Output format, usually chosen from a file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// CSV for `.csv` paths, JSON for anything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
+/// A statistics value that can be written as JSON or CSV
+pub trait Export: Serialize {
+    /// Key columns that precede `metric` and `value` in the tidy CSV
+    const KEY_COLUMNS: &'static [&'static str] = &[];
+
+    fn write_csv<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        write_tidy_csv(self, Self::KEY_COLUMNS, writer)
+    }
+
+    fn write_json<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(writer, self).map_err(std::io::Error::other)
+    }
+
+    fn export<W: Write>(&self, format: ExportFormat, writer: W) -> std::io::Result<()> {
+        match format {
+            ExportFormat::Json => self.write_json(writer),
+            ExportFormat::Csv => self.write_csv(writer),
+        }
+    }
+}
+
+impl Export for StatsReport {
+    const KEY_COLUMNS: &'static [&'static str] = &["section", "key"];
+}
+
+impl<T: Serialize> Export for BTreeMap<String, T> {
+    const KEY_COLUMNS: &'static [&'static str] = &["player"];
+}
+
+impl Export for HeadToHead {}
+
+impl Export for YakuStats {
+    /// One `player,yaku,count,frequency` row per yaku
+    ///
+    /// Corpus-wide rows come first and have an empty player column.
+    fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "player,yaku,count,frequency")?;
+        let sections = std::iter::once(("", &self.overall))
+            .chain(self.players.iter().map(|(n, f)| (n.as_str(), f)));
+        for (player, frequency) in sections {
+            for (yaku, count) in &frequency.counts {
+                writeln!(
+                    writer,
+                    "{},{},{},{}",
+                    escape(player, ','),
+                    escape(yaku, ','),
+                    count,
+                    frequency.frequency(yaku)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Export for DiscardHeatmap {
+    /// The matrix with a `turn` column followed by one column per tile
+    fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "turn,{}", self.tiles.join(","))?;
+        for (i, row) in self.turns.iter().enumerate() {
+            let cells: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            writeln!(writer, "{},{}", i + 1, cells.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl Export for Session {
+    /// The standings, one row per player
+    fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "player,games,netPoints,averagePlacement,first,second,third,fourth,rounds,wins,dealIns"
+        )?;
+        for s in &self.standings {
+            let [first, second, third, fourth] = s.placements;
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                escape(&s.player, ','),
+                s.games,
+                s.net_points,
+                s.average_placement(),
+                first,
+                second,
+                third,
+                fourth,
+                s.rounds,
+                s.wins,
+                s.deal_ins
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Export for StatsSummary {
+    /// One row per player
+    fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "player,games,rounds,winRate,dealInRate,riichiRate,callRate,averageWin,averagePlacement"
+        )?;
+        for p in &self.players {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{}",
+                escape(&p.player, ','),
+                p.games,
+                p.rounds,
+                p.win_rate,
+                p.deal_in_rate,
+                p.riichi_rate,
+                p.call_rate,
+                p.average_win,
+                p.average_placement
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `value` as a tidy CSV table with `keys` as leading key columns
+pub fn write_tidy_csv<T: Serialize + ?Sized, W: Write>(
+    value: &T,
+    keys: &[&str],
+    mut writer: W,
+) -> std::io::Result<()> {
+    let value = serde_json::to_value(value).map_err(std::io::Error::other)?;
+    let mut rows = Vec::new();
+    flatten(&value, &mut Vec::new(), &mut rows);
+
+    let header: Vec<&str> = keys.iter().copied().chain(["metric", "value"]).collect();
+    writeln!(writer, "{}", header.join(","))?;
+
+    for (path, leaf) in rows {
+        let split = keys.len().min(path.len().saturating_sub(1));
+        let mut cells: Vec<String> = path[..split].iter().map(|p| escape(p, ',')).collect();
+        cells.resize(keys.len(), String::new());
+        cells.push(escape(&path[split..].join("."), ','));
+        cells.push(escape(&leaf, ','));
+        writeln!(writer, "{}", cells.join(","))?;
+    }
+    Ok(())
+}
+
+fn flatten(value: &Value, path: &mut Vec<String>, rows: &mut Vec<(Vec<String>, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                path.push(key.clone());
+                flatten(child, path, rows);
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                path.push(i.to_string());
+                flatten(child, path, rows);
+                path.pop();
+            }
+        }
+        Value::Null => {}
+        Value::String(s) => rows.push((path.clone(), s.clone())),
+        other => rows.push((path.clone(), other.to_string())),
+    }
+}
+
+/// Quote a CSV field when it contains `separator`, a quote or a line break
+pub(crate) fn escape(field: &str, separator: char) -> String {
+    if field.contains([separator, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{DealInStats, PlacementStats};
+
+    fn csv<T: Export>(value: &T) -> Vec<String> {
+        let mut out = Vec::new();
+        value.write_csv(&mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_report_tidy_csv() {
+        let mut report = StatsReport {
+            games: 1,
+            ..Default::default()
+        };
+        report.deal_in.insert(
+            "Smith, J".to_string(),
+            DealInStats {
+                rounds: 8,
+                deal_ins: 1,
+                deal_in_points: 3900,
+            },
+        );
+        report.placement.insert(
+            "A".to_string(),
+            PlacementStats {
+                games: 1,
+                placements: [0, 1, 0, 0],
+            },
+        );
+
+        let lines = csv(&report);
+        assert_eq!(lines[0], "section,key,metric,value");
+        assert!(lines.contains(&",,games,1".to_string()));
+        assert!(lines.contains(&"dealIn,\"Smith, J\",dealInPoints,3900".to_string()));
+        assert!(lines.contains(&"placement,A,placements.1,1".to_string()));
+    }
+
+    #[test]
+    fn test_player_map_csv_and_format() {
+        let mut map = BTreeMap::new();
+        map.insert("A".to_string(), DealInStats::default());
+        let lines = csv(&map);
+        assert_eq!(lines[0], "player,metric,value");
+        assert!(lines.contains(&"A,rounds,0".to_string()));
+
+        let mut json = Vec::new();
+        map.export(ExportFormat::Json, &mut json).unwrap();
+        let value: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["A"]["dealIns"], 0);
+
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out.CSV")),
+            ExportFormat::Csv
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out.json")),
+            ExportFormat::Json
+        );
+    }
+}
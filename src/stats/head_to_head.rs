@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, standings, RoundResult};
+use crate::models::ParserOutput;
+
+/// Direct results between two named players over the games both took part in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadToHead {
+    pub player: String,
+    pub opponent: String,
+    /// Games containing both players
+    pub games: u32,
+    /// Games in which `player` finished above `opponent`
+    pub player_ahead: u32,
+    /// Games in which `opponent` finished above `player`
+    pub opponent_ahead: u32,
+    /// Times `player` dealt into `opponent`
+    pub player_deal_ins: u32,
+    /// Times `opponent` dealt into `player`
+    pub opponent_deal_ins: u32,
+    /// Points `player` received from `opponent` in wins, by ron or tsumo
+    pub points_won: i64,
+    /// Points `player` paid to `opponent` in wins, by ron or tsumo
+    pub points_lost: i64,
+}
+
+impl HeadToHead {
+    /// Net points that moved from `opponent` to `player`
+    pub fn net_transfer(&self) -> i64 {
+        self.points_won - self.points_lost
+    }
+}
+
+/// Compare `player` against `opponent` in every game where both sat
+pub fn head_to_head(games: &[ParserOutput], player: &str, opponent: &str) -> HeadToHead {
+    let mut stats = HeadToHead {
+        player: player.to_string(),
+        opponent: opponent.to_string(),
+        ..Default::default()
+    };
+
+    for game in games {
+        let seat_of = |name: &str| (0..4u8).find(|&seat| player_name(game, seat) == Some(name));
+        let (Some(a), Some(b)) = (seat_of(player), seat_of(opponent)) else {
+            continue;
+        };
+        stats.games += 1;
+
+        if let Some(order) = standings(game) {
+            let place = |seat: u8| order.iter().position(|&s| s == seat);
+            match (place(a), place(b)) {
+                (Some(pa), Some(pb)) if pa < pb => stats.player_ahead += 1,
+                (Some(pa), Some(pb)) if pb < pa => stats.opponent_ahead += 1,
+                _ => {}
+            }
+        }
+
+        for round in &game.rounds {
+            let result = RoundResult::from_round(round);
+            for win in &result.wins {
+                if win.who == a && (win.from == b || win.is_tsumo()) {
+                    stats.points_won += -win.deltas[b as usize] as i64;
+                } else if win.who == b && (win.from == a || win.is_tsumo()) {
+                    stats.points_lost += -win.deltas[a as usize] as i64;
+                }
+                if win.who == b && win.from == a {
+                    stats.player_deal_ins += 1;
+                } else if win.who == a && win.from == b {
+                    stats.opponent_deal_ins += 1;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::game;
+
+    #[test]
+    fn test_head_to_head() {
+        let games = vec![
+            // A rons B for 3900, then C tsumos and B pays 1000
+            game(
+                ["A", "B", "C", "D"],
+                r#"<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" sc="250,39,250,-39,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="289,211,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="2" fromWho="2" sc="289,-10,211,-20,250,40,250,-10"/>"#,
+            ),
+            // B tsumos with A paying 2000 and B finishes ahead
+            game(
+                ["B", "X", "A", "Y"],
+                r#"<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="0" sc="250,60,250,-20,250,-20,250,-20"/>"#,
+            ),
+            // Only A plays
+            game(
+                ["A", "X", "Y", "Z"],
+                r#"<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" sc="250,80,250,-80,250,0,250,0"/>"#,
+            ),
+        ];
+
+        let h2h = head_to_head(&games, "A", "B");
+        assert_eq!(h2h.games, 2);
+        assert_eq!((h2h.player_ahead, h2h.opponent_ahead), (1, 1));
+        assert_eq!((h2h.player_deal_ins, h2h.opponent_deal_ins), (0, 1));
+        assert_eq!(h2h.points_won, 3900);
+        assert_eq!(h2h.points_lost, 2000);
+        assert_eq!(h2h.net_transfer(), 1900);
+
+        let none = head_to_head(&games, "A", "nobody");
+        assert_eq!(none.games, 0);
+    }
+}
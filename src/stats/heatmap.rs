@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use super::player_name;
+use crate::analysis::{tile_index, TILE_TYPES};
+use crate::models::{Event, ParserOutput};
+use crate::tile::tile_id_to_string;
+
+/// Discard counts by turn (rows) and tile type (columns)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscardHeatmap {
+    /// Column labels, in tile type order (1m … red)
+    pub tiles: Vec<String>,
+    /// `turns[n]` counts the discards made as a player's (n+1)-th discard
+    pub turns: Vec<Vec<u32>>,
+}
+
+impl Default for DiscardHeatmap {
+    fn default() -> Self {
+        Self {
+            tiles: (0..TILE_TYPES as u32)
+                .map(|t| tile_id_to_string(t * 4).into_owned())
+                .collect(),
+            turns: Vec::new(),
+        }
+    }
+}
+
+impl DiscardHeatmap {
+    pub fn count(&self, turn: usize, tile: &str) -> u32 {
+        match (turn.checked_sub(1), tile_index(tile)) {
+            (Some(row), Some(col)) => self.turns.get(row).map_or(0, |r| r[col]),
+            _ => 0,
+        }
+    }
+
+    fn add(&mut self, turn: usize, tile: usize) {
+        if self.turns.len() < turn {
+            self.turns.resize(turn, vec![0; TILE_TYPES]);
+        }
+        self.turns[turn - 1][tile] += 1;
+    }
+}
+
+/// Build a discard heatmap for one player, or for everyone when `player` is `None`
+pub fn discard_heatmap(games: &[ParserOutput], player: Option<&str>) -> DiscardHeatmap {
+    let mut heatmap = DiscardHeatmap::default();
+
+    for game in games {
+        for round in &game.rounds {
+            let mut turns = [0usize; 4];
+            for event in &round.events {
+                let Event::Discard { seat, tile, .. } = event else {
+                    continue;
+                };
+                let seat = *seat as usize % 4;
+                turns[seat] += 1;
+
+                if player.is_some() && player != player_name(game, seat as u8) {
+                    continue;
+                }
+                if let Some(t) = tile_index(tile) {
+                    heatmap.add(turns[seat], t);
+                }
+            }
+        }
+    }
+
+    heatmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use crate::stats::Export;
+    use std::io::Cursor;
+
+    fn game() -> ParserOutput {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<D108/><E109/><F0/><G1/><D2/><E132/>
+</mjloggm>"#;
+        parse_mjlog(Cursor::new(xml)).unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_heatmap() {
+        let heatmap = discard_heatmap(&[game()], None);
+        assert_eq!(heatmap.tiles.len(), TILE_TYPES);
+        assert_eq!(heatmap.turns.len(), 2);
+        assert_eq!(heatmap.count(1, "east"), 2);
+        assert_eq!(heatmap.count(1, "1m"), 2);
+        assert_eq!(heatmap.count(2, "1m"), 1);
+        assert_eq!(heatmap.count(2, "red"), 1);
+        assert_eq!(heatmap.count(3, "1m"), 0);
+        assert_eq!(heatmap.count(0, "1m"), 0);
+    }
+
+    #[test]
+    fn test_player_heatmap_and_csv() {
+        let heatmap = discard_heatmap(&[game()], Some("A"));
+        assert_eq!(heatmap.count(1, "east"), 1);
+        assert_eq!(heatmap.count(2, "1m"), 1);
+        assert_eq!(heatmap.count(1, "1m"), 0);
+
+        let mut csv = Vec::new();
+        heatmap.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("turn,1m,2m,"));
+        assert!(lines[0].ends_with(",white,green,red"));
+        assert!(lines[2].starts_with("2,1,0,"));
+    }
+}
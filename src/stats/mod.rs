@@ -0,0 +1,256 @@
+//! Per-player statistics aggregated over one or many parsed logs
+//!
+//! Players are identified by their Tenhou ID (`Player::player_id`) so that
+//! numbers accumulate across games where they sit in different seats.
+
+pub mod calls;
+pub mod deal_in;
+pub mod export;
+pub mod head_to_head;
+pub mod heatmap;
+pub mod placement;
+pub mod riichi;
+pub mod round_length;
+pub mod safety;
+pub mod scores;
+pub mod session;
+pub mod summary;
+pub mod tenpai;
+pub mod wins;
+pub mod yaku;
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Event, ParserOutput, Player, Round, RyuukyokuReason};
+
+pub use calls::{call_stats, CallStats};
+pub use deal_in::{deal_in_stats, DealInStats};
+pub use export::{Export, ExportFormat};
+pub use head_to_head::{head_to_head, HeadToHead};
+pub use heatmap::{discard_heatmap, DiscardHeatmap};
+pub use placement::{placement_stats, PlacementStats};
+pub use riichi::{riichi_stats, RiichiStats};
+pub use round_length::{round_length_stats, RoundLengthStats};
+pub use safety::{safety_stats, SafetyStats};
+pub use scores::{score_stats, ScoreStats};
+pub use session::{session, Session, SessionGame, SessionStanding};
+pub use summary::{summary, PlayerSummary, StatsSummary};
+pub use tenpai::{tenpai_stats, TenpaiStats};
+pub use wins::{win_stats, WinStats};
+pub use yaku::{yaku_stats, YakuFrequency, YakuStats};
+
+/// All statistics sections, keyed by player name unless noted otherwise
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsReport {
+    pub games: usize,
+    pub riichi: BTreeMap<String, RiichiStats>,
+    pub calls: BTreeMap<String, CallStats>,
+    pub deal_in: BTreeMap<String, DealInStats>,
+    pub wins: BTreeMap<String, WinStats>,
+    pub placement: BTreeMap<String, PlacementStats>,
+    pub scores: BTreeMap<String, ScoreStats>,
+    pub safety: BTreeMap<String, SafetyStats>,
+    pub tenpai: BTreeMap<String, TenpaiStats>,
+    pub yaku: YakuStats,
+    /// Keyed by rules type (`Rules::type_flags`)
+    pub round_length: BTreeMap<String, RoundLengthStats>,
+    /// Set when a pair of players was given to compare
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_to_head: Option<HeadToHead>,
+}
+
+/// Compute every statistics section over `games`
+pub fn collect(games: &[ParserOutput]) -> StatsReport {
+    StatsReport {
+        games: games.len(),
+        riichi: riichi_stats(games),
+        calls: call_stats(games),
+        deal_in: deal_in_stats(games),
+        wins: win_stats(games),
+        placement: placement_stats(games),
+        scores: score_stats(games),
+        safety: safety_stats(games),
+        tenpai: tenpai_stats(games),
+        yaku: yaku_stats(games),
+        round_length: round_length_stats(games),
+        head_to_head: None,
+    }
+}
+
+/// How a round ended from one seat's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Win,
+    DealIn,
+    Draw,
+    /// Someone else won without this seat dealing in
+    OtherWin,
+}
+
+/// A single win (AGARI) within a round
+#[derive(Debug, Clone)]
+pub(crate) struct Win {
+    pub who: u8,
+    pub from: u8,
+    pub han: u32,
+    pub fu: u32,
+    /// Score change per seat for this win, in points
+    pub deltas: [i32; 4],
+}
+
+impl Win {
+    pub fn is_tsumo(&self) -> bool {
+        self.who == self.from
+    }
+}
+
+/// Summary of how a round ended
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RoundResult {
+    /// Every win in log order (double ron yields two)
+    pub wins: Vec<Win>,
+    /// True when the round ended in an exhaustive or abortive draw
+    pub draw: bool,
+    /// True when the draw was an exhaustive draw (wall ran out)
+    pub exhaustive_draw: bool,
+    /// Net score change per seat over all settlements, in points
+    pub deltas: [i32; 4],
+}
+
+impl RoundResult {
+    pub fn from_round(round: &Round) -> Self {
+        let mut result = Self::default();
+        for event in &round.events {
+            match event {
+                Event::Agari {
+                    who,
+                    from,
+                    han,
+                    fu,
+                    scores,
+                    ..
+                } => {
+                    let mut deltas = [0; 4];
+                    add_deltas(&mut deltas, scores);
+                    add_deltas(&mut result.deltas, scores);
+                    result.wins.push(Win {
+                        who: *who,
+                        from: *from,
+                        han: *han,
+                        fu: *fu,
+                        deltas,
+                    });
+                }
+                Event::Ryuukyoku { reason, scores, .. } => {
+                    result.draw = true;
+                    result.exhaustive_draw = matches!(reason, RyuukyokuReason::Normal);
+                    add_deltas(&mut result.deltas, scores);
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    pub fn won(&self, seat: u8) -> bool {
+        self.wins.iter().any(|w| w.who == seat)
+    }
+
+    pub fn dealt_in(&self, seat: u8) -> bool {
+        self.deal_ins(seat).next().is_some()
+    }
+
+    /// Wins that `seat` dealt into
+    pub fn deal_ins(&self, seat: u8) -> impl Iterator<Item = &Win> {
+        self.wins
+            .iter()
+            .filter(move |w| w.from == seat && w.who != seat)
+    }
+
+    /// True when someone other than `seat` won by self-draw
+    pub fn tsumo_by_other(&self, seat: u8) -> bool {
+        self.wins.iter().any(|w| w.is_tsumo() && w.who != seat)
+    }
+
+    pub fn outcome(&self, seat: u8) -> Outcome {
+        if self.won(seat) {
+            Outcome::Win
+        } else if self.dealt_in(seat) {
+            Outcome::DealIn
+        } else if self.draw {
+            Outcome::Draw
+        } else {
+            Outcome::OtherWin
+        }
+    }
+}
+
+/// Tenhou records scores in units of 100 points
+pub(crate) const SCORE_UNIT: i32 = 100;
+
+fn add_deltas(deltas: &mut [i32; 4], scores: &[i32; 4]) {
+    for (delta, score) in deltas.iter_mut().zip(scores) {
+        *delta += score * SCORE_UNIT;
+    }
+}
+
+/// Name of the player in `seat` to key statistics on, if the log has one
+///
+/// Anonymous players are left out: every one of them is named "NoName",
+/// which would merge them all into a single player.
+pub(crate) fn player_name(game: &ParserOutput, seat: u8) -> Option<&str> {
+    seat_name(game, seat).filter(|&name| name != Player::PLACEHOLDER_ID)
+}
+
+/// Name of the player in `seat`, anonymous or not, if the seat is taken
+pub(crate) fn seat_name(game: &ParserOutput, seat: u8) -> Option<&str> {
+    game.players
+        .get(seat as usize)
+        .map(|p| p.player_id.as_str())
+        .filter(|name| !name.is_empty())
+}
+
+/// Final score per seat in points
+///
+/// Taken from the `owari` result when the log has one. Otherwise it is
+/// derived from the last round, without riichi sticks left on the table.
+pub(crate) fn final_scores(game: &ParserOutput) -> Option<[i32; 4]> {
+    if let Some(result) = &game.result {
+        return Some(result.scores.map(|s| s * SCORE_UNIT));
+    }
+    let last = game.rounds.last()?;
+    let mut scores = last.init.initial_scores.map(|s| s * SCORE_UNIT);
+    for event in &last.events {
+        if let Event::Reach { who, step: 2, .. } = event {
+            scores[*who as usize % 4] -= 10 * SCORE_UNIT;
+        }
+    }
+    let result = RoundResult::from_round(last);
+    for (score, delta) in scores.iter_mut().zip(result.deltas) {
+        *score += delta;
+    }
+    Some(scores)
+}
+
+/// Seats that have a player, ordered from first to last place
+///
+/// Ties go to the seat closer to the starting dealer, as on Tenhou.
+pub(crate) fn standings(game: &ParserOutput) -> Option<Vec<u8>> {
+    let scores = final_scores(game)?;
+    let mut seats: Vec<u8> = (0..4u8)
+        .filter(|&seat| seat_name(game, seat).is_some())
+        .collect();
+    seats.sort_by_key(|&seat| (std::cmp::Reverse(scores[seat as usize]), seat));
+    Some(seats)
+}
+
+pub(crate) fn ratio(count: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio, standings};
+use crate::models::ParserOutput;
+
+/// Finishing positions of a player over many games
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementStats {
+    pub games: u32,
+    /// Number of 1st, 2nd, 3rd and 4th place finishes
+    pub placements: [u32; 4],
+}
+
+impl PlacementStats {
+    /// Fraction of games finished in `place` (1-based)
+    pub fn rate(&self, place: usize) -> f64 {
+        match place {
+            1..=4 => ratio(self.placements[place - 1], self.games),
+            _ => 0.0,
+        }
+    }
+
+    pub fn average_placement(&self) -> f64 {
+        let total: u32 = self
+            .placements
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (i as u32 + 1) * count)
+            .sum();
+        ratio(total, self.games)
+    }
+}
+
+/// Collect finishing positions per player name
+pub fn placement_stats(games: &[ParserOutput]) -> BTreeMap<String, PlacementStats> {
+    let mut stats: BTreeMap<String, PlacementStats> = BTreeMap::new();
+
+    for game in games {
+        let Some(order) = standings(game) else {
+            continue;
+        };
+        for (place, seat) in order.into_iter().enumerate() {
+            let Some(name) = player_name(game, seat) else {
+                continue;
+            };
+            let entry = stats.entry(name.to_string()).or_default();
+            entry.games += 1;
+            entry.placements[place] += 1;
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{all_last, game};
+
+    #[test]
+    fn test_placement_stats() {
+        let games = vec![
+            game(
+                ["A", "B", "C", "D"],
+                &all_last(
+                    "300,250,250,200",
+                    r#"<RYUUKYOKU type="nm" sc="300,-15,250,-15,250,15,200,15"/>"#,
+                ),
+            ),
+            // Seats rotate between games; ties go to the lower seat
+            game(
+                ["D", "C", "B", "A"],
+                &all_last(
+                    "250,250,250,250",
+                    r#"<RYUUKYOKU type="nm" sc="250,0,250,0,250,0,250,0"/>"#,
+                ),
+            ),
+        ];
+        let stats = placement_stats(&games);
+
+        // Game 1 final: A 28500, B 23500, C 26500, D 21500
+        assert_eq!(stats["A"].placements, [1, 0, 0, 1]);
+        assert_eq!(stats["C"].placements, [0, 2, 0, 0]);
+        assert_eq!(stats["B"].placements, [0, 0, 2, 0]);
+        assert_eq!(stats["D"].placements, [1, 0, 0, 1]);
+        assert_eq!(stats["A"].games, 2);
+        assert_eq!(stats["A"].average_placement(), 2.5);
+        assert_eq!(stats["A"].rate(1), 0.5);
+        assert_eq!(stats["A"].rate(5), 0.0);
+    }
+
+    #[test]
+    fn test_anonymous_players() {
+        let games = vec![
+            game(
+                ["A", "NoName", "NoName", "B"],
+                &all_last(
+                    "250,250,250,250",
+                    r#"<RYUUKYOKU type="nm" sc="300,0,250,0,250,0,200,0"/>"#,
+                ),
+            ),
+            game(
+                ["NoName", "A", "B", "NoName"],
+                &all_last(
+                    "250,250,250,250",
+                    r#"<RYUUKYOKU type="nm" sc="200,0,250,0,250,0,300,0"/>"#,
+                ),
+            ),
+        ];
+        let stats = placement_stats(&games);
+
+        // Anonymous players are not merged into one, yet still take places
+        assert!(!stats.contains_key("NoName"));
+        assert_eq!(stats["A"].placements, [1, 1, 0, 0]);
+        assert_eq!(stats["B"].placements, [0, 0, 1, 1]);
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio, Outcome, RoundResult, SCORE_UNIT};
+use crate::models::{Event, ParserOutput};
+
+/// Riichi declarations and what came of them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiichiStats {
+    pub riichi_count: u32,
+    /// Declaration turn (the declarer's n-th discard) → number of riichi
+    pub turn_distribution: BTreeMap<u32, u32>,
+    pub wins: u32,
+    pub deal_ins: u32,
+    pub draws: u32,
+    /// Draws in which the wall ran out, a subset of `draws`
+    pub exhaustive_draws: u32,
+    /// Net tenpai/noten payments received in exhaustive draws, in points
+    pub tenpai_payments: i64,
+    /// Rounds won by another player without the declarer dealing in
+    pub other_wins: u32,
+    /// Wins by another player's tsumo, a subset of `other_wins`
+    pub tsumo_by_others: u32,
+    /// Sum of round results for the declarer, including the 1000 point deposit
+    pub total_gain: i64,
+}
+
+impl RiichiStats {
+    pub fn average_turn(&self) -> f64 {
+        let total: u32 = self
+            .turn_distribution
+            .iter()
+            .map(|(turn, count)| turn * count)
+            .sum();
+        ratio(total, self.riichi_count)
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.wins, self.riichi_count)
+    }
+
+    pub fn deal_in_rate(&self) -> f64 {
+        ratio(self.deal_ins, self.riichi_count)
+    }
+
+    pub fn draw_rate(&self) -> f64 {
+        ratio(self.draws, self.riichi_count)
+    }
+
+    pub fn exhaustive_draw_rate(&self) -> f64 {
+        ratio(self.exhaustive_draws, self.riichi_count)
+    }
+
+    pub fn other_win_rate(&self) -> f64 {
+        ratio(self.other_wins, self.riichi_count)
+    }
+
+    pub fn tsumo_by_others_rate(&self) -> f64 {
+        ratio(self.tsumo_by_others, self.riichi_count)
+    }
+
+    /// Average points gained per riichi
+    pub fn average_gain(&self) -> f64 {
+        if self.riichi_count == 0 {
+            0.0
+        } else {
+            self.total_gain as f64 / self.riichi_count as f64
+        }
+    }
+}
+
+/// Collect riichi statistics per player name
+///
+/// # Examples
+/// ```
+/// use std::io::Cursor;
+/// use tenhou_log_parser::parse_mjlog;
+/// use tenhou_log_parser::stats::riichi_stats;
+///
+/// let xml = r#"<mjloggm ver="2.3">
+/// <UN n0="A" n1="B" n2="C" n3="D"/>
+/// <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+/// <D0/><REACH who="0" step="1"/><REACH who="0" step="2"/>
+/// <AGARI who="0" fromWho="0" ten="30,3900,0" sc="240,49,250,-13,250,-13,250,-13"/>
+/// </mjloggm>"#;
+/// let games = vec![parse_mjlog(Cursor::new(xml)).unwrap()];
+/// let stats = riichi_stats(&games);
+/// assert_eq!(stats["A"].wins, 1);
+/// assert_eq!(stats["A"].average_gain(), 3900.0);
+/// ```
+pub fn riichi_stats(games: &[ParserOutput]) -> BTreeMap<String, RiichiStats> {
+    let mut stats: BTreeMap<String, RiichiStats> = BTreeMap::new();
+
+    for game in games {
+        for round in &game.rounds {
+            let result = RoundResult::from_round(round);
+            let mut discards = [0u32; 4];
+            // (seat, turn, accepted)
+            let mut declarations: Vec<(u8, u32, bool)> = Vec::new();
+
+            for event in &round.events {
+                match event {
+                    Event::Discard { seat, .. } => discards[*seat as usize % 4] += 1,
+                    Event::Reach { who, step: 1, .. } => {
+                        // The declaration tile is the next discard
+                        declarations.push((*who, discards[*who as usize % 4] + 1, false));
+                    }
+                    Event::Reach { who, step: 2, .. } => {
+                        if let Some(d) = declarations.iter_mut().find(|d| d.0 == *who) {
+                            d.2 = true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            for (seat, turn, accepted) in declarations {
+                let Some(name) = player_name(game, seat) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+                entry.riichi_count += 1;
+                *entry.turn_distribution.entry(turn).or_default() += 1;
+
+                match result.outcome(seat) {
+                    Outcome::Win => entry.wins += 1,
+                    Outcome::DealIn => entry.deal_ins += 1,
+                    Outcome::Draw => entry.draws += 1,
+                    Outcome::OtherWin => entry.other_wins += 1,
+                }
+                if result.tsumo_by_other(seat) {
+                    entry.tsumo_by_others += 1;
+                }
+                if result.exhaustive_draw {
+                    entry.exhaustive_draws += 1;
+                    entry.tenpai_payments += result.deltas[seat as usize % 4] as i64;
+                }
+
+                let deposit = if accepted { 10 * SCORE_UNIT } else { 0 };
+                entry.total_gain += (result.deltas[seat as usize % 4] - deposit) as i64;
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    fn parse(rounds: &str) -> ParserOutput {
+        let xml = format!(
+            r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>{}</mjloggm>"#,
+            rounds
+        );
+        parse_mjlog(Cursor::new(xml.into_bytes())).unwrap()
+    }
+
+    const INIT: &str = r#"<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>"#;
+
+    #[test]
+    fn test_riichi_outcomes() {
+        let game = parse(&format!(
+            concat!(
+                // A riichi on the 3rd discard and wins by tsumo
+                "{init}<D0/><D4/><REACH who=\"0\" step=\"1\"/><REACH who=\"0\" step=\"2\"/>",
+                "<AGARI who=\"0\" fromWho=\"0\" sc=\"240,49,250,-13,250,-13,250,-13\"/>",
+                // A riichi on the 2nd discard and deals into B
+                "{init}<D0/><REACH who=\"0\" step=\"1\"/><REACH who=\"0\" step=\"2\"/>",
+                "<AGARI who=\"1\" fromWho=\"0\" sc=\"240,-39,250,49,250,0,250,0\"/>",
+                // A riichi declared but never accepted, B riichi accepted, then a draw
+                "{init}<REACH who=\"0\" step=\"1\"/><REACH who=\"1\" step=\"1\"/>",
+                "<REACH who=\"1\" step=\"2\"/>",
+                "<RYUUKYOKU type=\"nm\" sc=\"250,-15,240,15,250,-15,250,15\"/>",
+            ),
+            init = INIT
+        ));
+
+        let stats = riichi_stats(&[game]);
+        let a = &stats["A"];
+        assert_eq!(a.riichi_count, 3);
+        assert_eq!(a.wins, 1);
+        assert_eq!(a.deal_ins, 1);
+        assert_eq!(a.draws, 1);
+        let turns: Vec<(u32, u32)> = a.turn_distribution.iter().map(|(t, c)| (*t, *c)).collect();
+        assert_eq!(turns, vec![(1, 1), (2, 1), (3, 1)]);
+        // +3900, -4900, -1500 (no deposit for the unaccepted riichi)
+        assert_eq!(a.total_gain, 3900 - 4900 - 1500);
+        assert_eq!(a.average_turn(), 2.0);
+
+        let b = &stats["B"];
+        assert_eq!(b.riichi_count, 1);
+        assert_eq!(b.draws, 1);
+        assert_eq!(b.total_gain, 1500 - 1000);
+        assert_eq!(b.exhaustive_draws, 1);
+        assert_eq!(b.tenpai_payments, 1500);
+    }
+
+    #[test]
+    fn test_tsumo_by_others() {
+        let game = parse(&format!(
+            concat!(
+                // A riichi, C wins by tsumo
+                "{init}<D0/><REACH who=\"0\" step=\"1\"/><D4/><REACH who=\"0\" step=\"2\"/>",
+                "<AGARI who=\"2\" fromWho=\"2\" sc=\"240,-10,250,-10,250,40,250,-20\"/>",
+                // A riichi, C ronned by D
+                "{init}<D0/><REACH who=\"0\" step=\"1\"/><D4/><REACH who=\"0\" step=\"2\"/>",
+                "<AGARI who=\"3\" fromWho=\"2\" sc=\"240,10,250,0,250,-20,250,20\"/>",
+                // A riichi, abortive draw
+                "{init}<REACH who=\"0\" step=\"1\"/><D0/><REACH who=\"0\" step=\"2\"/>",
+                "<RYUUKYOKU type=\"reach4\" sc=\"240,0,250,0,250,0,250,0\"/>",
+            ),
+            init = INIT
+        ));
+
+        let a = &riichi_stats(&[game])["A"];
+        assert_eq!(a.riichi_count, 3);
+        assert_eq!(a.other_wins, 2);
+        assert_eq!(a.tsumo_by_others, 1);
+        assert_eq!(a.tsumo_by_others_rate(), 1.0 / 3.0);
+        assert_eq!(a.draws, 1);
+        assert_eq!(a.exhaustive_draws, 0);
+        assert_eq!(a.tenpai_payments, 0);
+    }
+
+    #[test]
+    fn test_empty_stats() {
+        let stats = RiichiStats::default();
+        assert_eq!(stats.win_rate(), 0.0);
+        assert_eq!(stats.average_gain(), 0.0);
+        assert!(riichi_stats(&[]).is_empty());
+    }
+}
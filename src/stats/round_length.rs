@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ratio, RoundResult};
+use crate::models::{Event, ParserOutput};
+
+/// How long rounds lasted and how they ended
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundLengthStats {
+    pub rounds: u32,
+    /// Turns (the most discards made by any one player) → number of rounds
+    pub turns: BTreeMap<u32, u32>,
+    /// Tiles drawn from the wall, including replacement draws after a kan
+    pub total_draws: u64,
+    pub ron: u32,
+    pub tsumo: u32,
+    pub exhaustive_draws: u32,
+    pub abortive_draws: u32,
+}
+
+impl RoundLengthStats {
+    pub fn average_turns(&self) -> f64 {
+        let total: u32 = self.turns.iter().map(|(turn, count)| turn * count).sum();
+        ratio(total, self.rounds)
+    }
+
+    pub fn average_draws(&self) -> f64 {
+        if self.rounds == 0 {
+            0.0
+        } else {
+            self.total_draws as f64 / self.rounds as f64
+        }
+    }
+}
+
+/// Collect round lengths keyed by the game's rules type (`Rules::type_flags`)
+pub fn round_length_stats(games: &[ParserOutput]) -> BTreeMap<String, RoundLengthStats> {
+    let mut stats: BTreeMap<String, RoundLengthStats> = BTreeMap::new();
+
+    for game in games {
+        let entry = stats.entry(game.rules.type_flags.to_string()).or_default();
+
+        for round in &game.rounds {
+            let mut discards = [0u32; 4];
+            for event in &round.events {
+                match event {
+                    Event::Draw { .. } => entry.total_draws += 1,
+                    Event::Discard { seat, .. } => discards[*seat as usize % 4] += 1,
+                    _ => {}
+                }
+            }
+            entry.rounds += 1;
+            *entry
+                .turns
+                .entry(discards.into_iter().max().unwrap_or(0))
+                .or_default() += 1;
+
+            let result = RoundResult::from_round(round);
+            match result.wins.first() {
+                Some(win) if win.is_tsumo() => entry.tsumo += 1,
+                Some(_) => entry.ron += 1,
+                None if result.exhaustive_draw => entry.exhaustive_draws += 1,
+                None if result.draw => entry.abortive_draws += 1,
+                None => {}
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_length_stats() {
+        let xml = r#"<mjloggm ver="2.3"><GO type="169" lobby="0"/><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<T0/><D0/><U1/><E1/><V2/><F2/><W3/><G3/><T4/>
+<AGARI who="0" fromWho="0" sc="250,30,250,-10,250,-10,250,-10"/>
+<INIT seed="1,0,0,1,2,52" ten="280,240,240,240" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U5/><E5/>
+<AGARI who="2" fromWho="1" sc="280,0,240,-20,240,20,240,0"/>
+<INIT seed="2,0,0,1,2,52" ten="280,220,260,240" oya="2" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU type="yao9" sc="280,0,220,0,260,0,240,0"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let stats = round_length_stats(&[game]);
+
+        let hanchan = &stats["169"];
+        assert_eq!(hanchan.rounds, 3);
+        assert_eq!(hanchan.turns, BTreeMap::from([(0, 1), (1, 2)]));
+        assert_eq!(hanchan.total_draws, 6);
+        assert_eq!(hanchan.average_draws(), 2.0);
+        assert_eq!((hanchan.tsumo, hanchan.ron), (1, 1));
+        assert_eq!((hanchan.exhaustive_draws, hanchan.abortive_draws), (0, 1));
+        assert_eq!(hanchan.average_turns(), 2.0 / 3.0);
+    }
+}
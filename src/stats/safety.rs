@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio};
+use crate::analysis::{discard_safety, SafetyClass};
+use crate::models::ParserOutput;
+
+/// Safety classes of a player's discards against riichi
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetyStats {
+    /// Discards classified, one per riichi player they were made against
+    pub discards: u32,
+    pub genbutsu: u32,
+    pub suji: u32,
+    pub one_chance: u32,
+    pub dangerous: u32,
+}
+
+impl SafetyStats {
+    /// Fraction of discards against riichi that were not known safe
+    pub fn dangerous_rate(&self) -> f64 {
+        ratio(self.dangerous, self.discards)
+    }
+}
+
+/// Collect discard safety against riichi per player name
+pub fn safety_stats(games: &[ParserOutput]) -> BTreeMap<String, SafetyStats> {
+    let mut stats: BTreeMap<String, SafetyStats> = BTreeMap::new();
+
+    for game in games {
+        for round in &game.rounds {
+            for discard in discard_safety(round) {
+                let Some(name) = player_name(game, discard.seat) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+                entry.discards += 1;
+                match discard.class {
+                    SafetyClass::Genbutsu => entry.genbutsu += 1,
+                    SafetyClass::Suji => entry.suji += 1,
+                    SafetyClass::OneChance => entry.one_chance += 1,
+                    SafetyClass::Dangerous => entry.dangerous += 1,
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_safety_stats() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,135" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<D12/><REACH who="0" step="1"/><D68/><E13/><F0/><G28/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let stats = safety_stats(&[game]);
+
+        assert_eq!(stats["B"].genbutsu, 1);
+        assert_eq!(stats["C"].suji, 1);
+        assert_eq!(stats["D"].dangerous, 1);
+        assert_eq!(stats["D"].dangerous_rate(), 1.0);
+        assert!(!stats.contains_key("A"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{final_scores, player_name, ratio};
+use crate::models::ParserOutput;
+
+/// Width of a histogram bucket, in points
+pub const SCORE_BUCKET: i32 = 10000;
+
+/// Distribution of a player's final scores over many games
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreStats {
+    pub games: u32,
+    /// Sum of final scores, in points
+    pub total: i64,
+    /// Sum of squared final scores, for the variance
+    pub total_squared: i64,
+    /// Games finished below zero points
+    pub busts: u32,
+    /// Lower bound of each `SCORE_BUCKET`-wide bucket → number of games
+    pub histogram: BTreeMap<i32, u32>,
+}
+
+impl ScoreStats {
+    pub fn mean(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.total as f64 / self.games as f64
+        }
+    }
+
+    /// Population variance of the final scores
+    pub fn variance(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        self.total_squared as f64 / self.games as f64 - mean * mean
+    }
+
+    pub fn bust_rate(&self) -> f64 {
+        ratio(self.busts, self.games)
+    }
+
+    fn add(&mut self, score: i32) {
+        self.games += 1;
+        self.total += score as i64;
+        self.total_squared += score as i64 * score as i64;
+        if score < 0 {
+            self.busts += 1;
+        }
+        let bucket = score.div_euclid(SCORE_BUCKET) * SCORE_BUCKET;
+        *self.histogram.entry(bucket).or_default() += 1;
+    }
+}
+
+/// Collect final score distributions per player name
+pub fn score_stats(games: &[ParserOutput]) -> BTreeMap<String, ScoreStats> {
+    let mut stats: BTreeMap<String, ScoreStats> = BTreeMap::new();
+
+    for game in games {
+        let Some(scores) = final_scores(game) else {
+            continue;
+        };
+        for (seat, &score) in scores.iter().enumerate() {
+            let Some(name) = player_name(game, seat as u8) else {
+                continue;
+            };
+            stats.entry(name.to_string()).or_default().add(score);
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{all_last, game};
+
+    #[test]
+    fn test_score_stats() {
+        let games = vec![
+            game(
+                ["A", "B", "C", "D"],
+                &all_last(
+                    "250,50,350,350",
+                    r#"<AGARI who="0" fromWho="1" sc="250,80,50,-80,350,0,350,0"/>"#,
+                ),
+            ),
+            game(
+                ["A", "B", "C", "D"],
+                &all_last(
+                    "250,250,250,250",
+                    r#"<AGARI who="0" fromWho="1" sc="250,20,250,-20,250,0,250,0"/>"#,
+                ),
+            ),
+        ];
+        let stats = score_stats(&games);
+
+        let a = &stats["A"];
+        assert_eq!(a.games, 2);
+        assert_eq!(a.mean(), (33000.0 + 27000.0) / 2.0);
+        assert_eq!(a.variance(), 9_000_000.0);
+        assert_eq!(a.histogram, BTreeMap::from([(20000, 1), (30000, 1)]));
+
+        let b = &stats["B"];
+        assert_eq!(b.busts, 1);
+        assert_eq!(b.bust_rate(), 0.5);
+        assert_eq!(b.histogram, BTreeMap::from([(-10000, 1), (20000, 1)]));
+    }
+
+    #[test]
+    fn test_score_stats_prefers_owari() {
+        // The riichi stick left after the draw goes to the top, as owari has it
+        let games = vec![game(
+            ["A", "B", "C", "D"],
+            &all_last(
+                "250,250,250,250",
+                r#"<REACH who="0" step="1"/><REACH who="0" ten="240,250,250,250" step="2"/>
+<RYUUKYOKU ba="0,1" sc="240,0,250,0,250,0,250,0" owari="260,46.0,250,5.0,250,-15.0,240,-36.0"/>"#,
+            ),
+        )];
+        let stats = score_stats(&games);
+
+        assert_eq!(stats["A"].mean(), 26000.0);
+        assert_eq!(stats["D"].mean(), 24000.0);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{final_scores, player_name, seat_name, standings, RoundResult, SCORE_UNIT};
+use crate::models::ParserOutput;
+
+/// Results of several games played by the same group, in playing order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub games: Vec<SessionGame>,
+    /// Players ordered by net points, best first
+    pub standings: Vec<SessionStanding>,
+}
+
+/// One game of a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionGame {
+    pub game_id: String,
+    /// Player name per seat, empty for a seat without one
+    pub seats: Vec<String>,
+    /// Final score per seat in points
+    pub final_scores: [i32; 4],
+    /// Finishing position per seat, 1-based; 0 for a seat without a player
+    pub places: [u8; 4],
+}
+
+/// A player's running totals over a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStanding {
+    pub player: String,
+    pub games: u32,
+    /// Final score minus starting score, summed over games
+    pub net_points: i64,
+    /// Net points after each game the player took part in
+    pub cumulative: Vec<i64>,
+    /// Number of 1st, 2nd, 3rd and 4th place finishes
+    pub placements: [u32; 4],
+    /// Games played from each seat
+    pub seats: [u32; 4],
+    pub rounds: u32,
+    pub wins: u32,
+    pub deal_ins: u32,
+}
+
+impl SessionStanding {
+    pub fn average_placement(&self) -> f64 {
+        let total: u32 = self
+            .placements
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (i as u32 + 1) * count)
+            .sum();
+        super::ratio(total, self.games)
+    }
+}
+
+/// Aggregate `games` from one session, following players by name as they
+/// change seats between games
+pub fn session(games: &[ParserOutput]) -> Session {
+    let mut session = Session::default();
+    let mut players: BTreeMap<String, SessionStanding> = BTreeMap::new();
+
+    for game in games {
+        let (Some(scores), Some(order)) = (final_scores(game), standings(game)) else {
+            continue;
+        };
+        let starting = game
+            .rounds
+            .first()
+            .map_or([0; 4], |r| r.init.initial_scores.map(|s| s * SCORE_UNIT));
+        let results: Vec<RoundResult> = game.rounds.iter().map(RoundResult::from_round).collect();
+
+        let mut places = [0u8; 4];
+        for (place, &seat) in order.iter().enumerate() {
+            places[seat as usize] = place as u8 + 1;
+        }
+        let seats: Vec<String> = (0..4u8)
+            .map(|seat| seat_name(game, seat).unwrap_or_default().to_string())
+            .collect();
+
+        for &seat in &order {
+            let Some(name) = player_name(game, seat) else {
+                continue;
+            };
+            let index = seat as usize;
+            let standing = players
+                .entry(name.to_string())
+                .or_insert_with(|| SessionStanding {
+                    player: name.to_string(),
+                    ..Default::default()
+                });
+            standing.games += 1;
+            standing.net_points += (scores[index] - starting[index]) as i64;
+            standing.cumulative.push(standing.net_points);
+            standing.placements[places[index] as usize - 1] += 1;
+            standing.seats[index] += 1;
+            standing.rounds += results.len() as u32;
+            standing.wins += results.iter().filter(|r| r.won(seat)).count() as u32;
+            standing.deal_ins += results.iter().filter(|r| r.dealt_in(seat)).count() as u32;
+        }
+
+        session.games.push(SessionGame {
+            game_id: game.game_id.clone(),
+            seats,
+            final_scores: scores,
+            places,
+        });
+    }
+
+    session.standings = players.into_values().collect();
+    session.standings.sort_by(|a, b| {
+        b.net_points
+            .cmp(&a.net_points)
+            .then_with(|| a.average_placement().total_cmp(&b.average_placement()))
+            .then_with(|| a.player.cmp(&b.player))
+    });
+    session
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Export;
+    use crate::test_support::{all_last, game};
+
+    #[test]
+    fn test_session_standings() {
+        let games = vec![
+            // A rons B for 8000
+            game(
+                ["A", "B", "C", "D"],
+                &all_last(
+                    "250,250,250,250",
+                    r#"<AGARI who="0" fromWho="1" sc="250,80,250,-80,250,0,250,0"/>"#,
+                ),
+            ),
+            // Seats rotate; B (now seat 0) rons C for 3900
+            game(
+                ["B", "C", "D", "A"],
+                &all_last(
+                    "250,250,250,250",
+                    r#"<AGARI who="0" fromWho="1" sc="250,39,250,-39,250,0,250,0"/>"#,
+                ),
+            ),
+        ];
+        let session = session(&games);
+
+        assert_eq!(session.games.len(), 2);
+        assert_eq!(session.games[1].seats, vec!["B", "C", "D", "A"]);
+        assert_eq!(session.games[1].final_scores, [28900, 21100, 25000, 25000]);
+        assert_eq!(session.games[1].places, [1, 4, 2, 3]);
+
+        let order: Vec<&str> = session
+            .standings
+            .iter()
+            .map(|s| s.player.as_str())
+            .collect();
+        assert_eq!(order, vec!["A", "D", "C", "B"]);
+
+        let a = &session.standings[0];
+        assert_eq!(a.games, 2);
+        assert_eq!(a.net_points, 8000);
+        assert_eq!(a.cumulative, vec![8000, 8000]);
+        assert_eq!(a.placements, [1, 0, 1, 0]);
+        assert_eq!(a.seats, [1, 0, 0, 1]);
+        assert_eq!((a.rounds, a.wins, a.deal_ins), (2, 1, 0));
+
+        let b = &session.standings[3];
+        assert_eq!(b.net_points, -8000 + 3900);
+        assert_eq!(b.cumulative, vec![-8000, -4100]);
+        assert_eq!((b.wins, b.deal_ins), (1, 1));
+        assert_eq!(b.average_placement(), 2.5);
+
+        let mut csv = Vec::new();
+        session.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        assert_eq!(csv.lines().nth(1), Some("A,2,8000,2,1,0,1,0,2,1,0"));
+    }
+}
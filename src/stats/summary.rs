@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use super::StatsReport;
+
+/// The headline numbers of one player
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerSummary {
+    pub player: String,
+    pub games: u32,
+    pub rounds: u32,
+    /// Fraction of rounds won
+    pub win_rate: f64,
+    /// Fraction of rounds dealt into
+    pub deal_in_rate: f64,
+    /// Fraction of rounds with a riichi declaration
+    pub riichi_rate: f64,
+    /// Fraction of rounds with at least one call
+    pub call_rate: f64,
+    /// Points per win, including honba and collected riichi sticks
+    pub average_win: f64,
+    /// Average finishing position, 1 for first; 0 without finished games
+    pub average_placement: f64,
+}
+
+/// Per-player headline numbers of a statistics report
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSummary {
+    pub games: usize,
+    /// By player name
+    pub players: Vec<PlayerSummary>,
+}
+
+/// Condense `report` into one row of rates and averages per player
+pub fn summary(report: &StatsReport) -> StatsSummary {
+    let players = report
+        .wins
+        .iter()
+        .map(|(name, wins)| {
+            let placement = report.placement.get(name).cloned().unwrap_or_default();
+            let riichi = report
+                .riichi
+                .get(name)
+                .map_or(0, |riichi| riichi.riichi_count);
+            PlayerSummary {
+                player: name.clone(),
+                games: placement.games,
+                rounds: wins.rounds,
+                win_rate: wins.win_rate(),
+                deal_in_rate: report
+                    .deal_in
+                    .get(name)
+                    .map_or(0.0, |deal_in| deal_in.deal_in_rate()),
+                riichi_rate: super::ratio(riichi, wins.rounds),
+                call_rate: report
+                    .calls
+                    .get(name)
+                    .map_or(0.0, |calls| calls.call_rate()),
+                average_win: wins.average_win(),
+                average_placement: placement.average_placement(),
+            }
+        })
+        .collect();
+    StatsSummary {
+        games: report.games,
+        players,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_summary() {
+        // A riichis and wins by ron from B in the only round
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<REACH who="0" step="1"/><REACH who="0" step="2" ten="240,250,250,250"/>
+<AGARI who="0" fromWho="1" ten="30,3900,0" sc="240,49,250,-39,250,0,250,0" owari="289,38.9,211,-8.9,250,-5.0,250,-25.0"/>
+</mjloggm>"#;
+        let games = vec![parse_mjlog(Cursor::new(xml)).unwrap()];
+        let summary = summary(&super::super::collect(&games));
+
+        assert_eq!(summary.games, 1);
+        let a = &summary.players[0];
+        assert_eq!((a.player.as_str(), a.games, a.rounds), ("A", 1, 1));
+        assert_eq!((a.win_rate, a.riichi_rate, a.call_rate), (1.0, 1.0, 0.0));
+        assert_eq!(a.average_win, 4900.0);
+        assert_eq!(a.average_placement, 1.0);
+        let b = &summary.players[1];
+        assert_eq!((b.win_rate, b.deal_in_rate), (0.0, 1.0));
+        assert_eq!(b.average_placement, 4.0);
+    }
+}
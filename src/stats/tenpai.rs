@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio};
+use crate::analysis::{shanten, ConcealedHands};
+use crate::models::{Event, ParserOutput};
+
+/// How quickly a player's hand reaches tenpai
+///
+/// Turn 0 is the dealt hand; turn n is the hand after the player's n-th discard.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TenpaiStats {
+    /// Rounds with a known starting hand
+    pub rounds: u32,
+    /// Rounds in which the player reached tenpai at some point
+    pub tenpai_rounds: u32,
+    /// Turn of first tenpai → number of rounds
+    pub tenpai_turns: BTreeMap<u32, u32>,
+    /// Turn → shanten → number of hands
+    pub shanten_by_turn: BTreeMap<u32, BTreeMap<i8, u32>>,
+}
+
+impl TenpaiStats {
+    pub fn tenpai_rate(&self) -> f64 {
+        ratio(self.tenpai_rounds, self.rounds)
+    }
+
+    /// Average turn of first tenpai over rounds that reached it
+    pub fn average_tenpai_turn(&self) -> f64 {
+        let total: u32 = self
+            .tenpai_turns
+            .iter()
+            .map(|(turn, count)| turn * count)
+            .sum();
+        ratio(total, self.tenpai_rounds)
+    }
+
+    /// Average shanten of hands at `turn`
+    pub fn average_shanten(&self, turn: u32) -> f64 {
+        let Some(counts) = self.shanten_by_turn.get(&turn) else {
+            return 0.0;
+        };
+        let hands: u32 = counts.values().sum();
+        let total: i64 = counts
+            .iter()
+            .map(|(&shanten, &count)| shanten as i64 * count as i64)
+            .sum();
+        if hands == 0 {
+            0.0
+        } else {
+            total as f64 / hands as f64
+        }
+    }
+}
+
+/// Collect tenpai speed statistics per player name
+///
+/// Hands are replayed from the dealt tiles, so seats whose starting hand is
+/// missing from the log are skipped.
+pub fn tenpai_stats(games: &[ParserOutput]) -> BTreeMap<String, TenpaiStats> {
+    let mut stats: BTreeMap<String, TenpaiStats> = BTreeMap::new();
+
+    for game in games {
+        for round in &game.rounds {
+            let mut hands = ConcealedHands::new(round);
+            // Shanten per seat after each of its discards, starting with the deal
+            let mut history: [Vec<i8>; 4] = Default::default();
+
+            for (seat, shantens) in history.iter_mut().enumerate() {
+                if hands.len(seat as u8) == 13 {
+                    shantens.push(shanten(&hands.counts[seat], 0));
+                }
+            }
+
+            for event in &round.events {
+                hands.apply(event);
+                if let Event::Discard { seat, .. } = event {
+                    let seat = *seat as usize % 4;
+                    if !history[seat].is_empty() {
+                        history[seat].push(shanten(&hands.counts[seat], hands.melds[seat]));
+                    }
+                }
+            }
+
+            for (seat, shantens) in history.iter().enumerate() {
+                if shantens.is_empty() {
+                    continue;
+                }
+                let Some(name) = player_name(game, seat as u8) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+                entry.rounds += 1;
+
+                for (turn, &value) in shantens.iter().enumerate() {
+                    *entry
+                        .shanten_by_turn
+                        .entry(turn as u32)
+                        .or_default()
+                        .entry(value)
+                        .or_default() += 1;
+                }
+                if let Some(turn) = shantens.iter().position(|&s| s <= 0) {
+                    entry.tenpai_rounds += 1;
+                    *entry.tenpai_turns.entry(turn as u32).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_tenpai_stats() {
+        // A: 123m 456m 789m 12p 1s 9s, draws 3p and cuts 1s for a 9s wait
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,72,104" hai1="" hai2="" hai3=""/>
+<T44/>
+<D72/>
+<T45/>
+<D45/>
+<RYUUKYOKU sc="250,30,250,-10,250,-10,250,-10"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let stats = tenpai_stats(&[game]);
+
+        assert_eq!(stats.len(), 1);
+        let a = &stats["A"];
+        assert_eq!(a.rounds, 1);
+        assert_eq!(a.tenpai_rounds, 1);
+        assert_eq!(a.tenpai_turns, BTreeMap::from([(1, 1)]));
+        assert_eq!(a.shanten_by_turn[&0], BTreeMap::from([(1, 1)]));
+        assert_eq!(a.shanten_by_turn[&2], BTreeMap::from([(0, 1)]));
+        assert_eq!(a.average_tenpai_turn(), 1.0);
+        assert_eq!(a.average_shanten(0), 1.0);
+        assert_eq!(a.tenpai_rate(), 1.0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio, RoundResult};
+use crate::models::ParserOutput;
+
+/// Winning (hora) frequency and value
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WinStats {
+    pub rounds: u32,
+    pub wins: u32,
+    pub tsumo: u32,
+    pub ron: u32,
+    pub total_han: u64,
+    pub total_fu: u64,
+    /// Points gained in wins, including honba and collected riichi sticks
+    pub win_points: i64,
+}
+
+impl WinStats {
+    /// Hora rate: fraction of rounds won
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.wins, self.rounds)
+    }
+
+    pub fn tsumo_rate(&self) -> f64 {
+        ratio(self.tsumo, self.wins)
+    }
+
+    pub fn average_han(&self) -> f64 {
+        self.per_win(self.total_han as f64)
+    }
+
+    pub fn average_fu(&self) -> f64 {
+        self.per_win(self.total_fu as f64)
+    }
+
+    pub fn average_win(&self) -> f64 {
+        self.per_win(self.win_points as f64)
+    }
+
+    fn per_win(&self, total: f64) -> f64 {
+        if self.wins == 0 {
+            0.0
+        } else {
+            total / self.wins as f64
+        }
+    }
+}
+
+/// Collect win statistics per player name
+pub fn win_stats(games: &[ParserOutput]) -> BTreeMap<String, WinStats> {
+    let mut stats: BTreeMap<String, WinStats> = BTreeMap::new();
+
+    for game in games {
+        for round in &game.rounds {
+            let result = RoundResult::from_round(round);
+
+            for seat in 0..4u8 {
+                if let Some(name) = player_name(game, seat) {
+                    stats.entry(name.to_string()).or_default().rounds += 1;
+                }
+            }
+
+            for win in &result.wins {
+                let Some(name) = player_name(game, win.who) else {
+                    continue;
+                };
+                let entry = stats.entry(name.to_string()).or_default();
+                entry.wins += 1;
+                if win.is_tsumo() {
+                    entry.tsumo += 1;
+                } else {
+                    entry.ron += 1;
+                }
+                entry.total_han += win.han as u64;
+                entry.total_fu += win.fu as u64;
+                entry.win_points += win.deltas[win.who as usize % 4] as i64;
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_win_stats() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="1" ten="30,3900,0" sc="250,39,250,-39,250,0,250,0"/>
+<INIT seed="1,0,0,1,2,52" ten="289,211,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="0" ten="40,2600,2" sc="289,26,211,-13,250,-7,250,-6"/>
+<INIT seed="2,0,0,1,2,52" ten="315,198,243,244" oya="2" hai0="" hai1="" hai2="" hai3=""/>
+<RYUUKYOKU type="nm" sc="315,0,198,0,243,0,244,0"/>
+</mjloggm>"#;
+        let game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let stats = win_stats(&[game]);
+
+        let a = &stats["A"];
+        assert_eq!(a.rounds, 3);
+        assert_eq!(a.wins, 2);
+        assert_eq!((a.tsumo, a.ron), (1, 1));
+        assert_eq!(a.average_fu(), 35.0);
+        assert_eq!(a.average_win(), 3250.0);
+        assert_eq!(a.win_rate(), 2.0 / 3.0);
+        assert_eq!(a.tsumo_rate(), 0.5);
+
+        assert_eq!(stats["B"].wins, 0);
+        assert_eq!(stats["B"].average_win(), 0.0);
+    }
+}
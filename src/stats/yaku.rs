@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{player_name, ratio};
+use crate::models::{Event, ParserOutput};
+
+/// How often each yaku appeared among a set of wins
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YakuFrequency {
+    pub wins: u32,
+    /// Yaku name → number of wins containing it
+    pub counts: BTreeMap<String, u32>,
+}
+
+impl YakuFrequency {
+    /// Fraction of wins that contained `yaku`
+    pub fn frequency(&self, yaku: &str) -> f64 {
+        ratio(self.counts.get(yaku).copied().unwrap_or(0), self.wins)
+    }
+
+    fn add(&mut self, yakus: &[String]) {
+        self.wins += 1;
+        for yaku in yakus {
+            *self.counts.entry(yaku.clone()).or_default() += 1;
+        }
+    }
+}
+
+/// Yaku frequencies over the whole corpus and per player name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YakuStats {
+    pub overall: YakuFrequency,
+    pub players: BTreeMap<String, YakuFrequency>,
+}
+
+/// Count yaku over every win in `games`
+///
+/// A yaku is counted once per win, and only when it contributes han, so
+/// zero-valued entries such as missing ura dora are skipped.
+pub fn yaku_stats(games: &[ParserOutput]) -> YakuStats {
+    let mut stats = YakuStats::default();
+
+    for game in games {
+        for round in &game.rounds {
+            for event in &round.events {
+                let Event::Agari { who, yakus, .. } = event else {
+                    continue;
+                };
+                let mut names: Vec<String> = yakus
+                    .iter()
+                    .filter(|y| y.value > 0)
+                    .map(|y| y.name.clone())
+                    .collect();
+                names.sort();
+                names.dedup();
+
+                stats.overall.add(&names);
+                if let Some(name) = player_name(game, *who) {
+                    stats
+                        .players
+                        .entry(name.to_string())
+                        .or_default()
+                        .add(&names);
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Yaku;
+    use crate::parser::parse_mjlog;
+    use crate::stats::Export;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_yaku_stats() {
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+<AGARI who="0" fromWho="0" sc="250,0,250,0,250,0,250,0"/>
+<AGARI who="0" fromWho="1" sc="250,0,250,0,250,0,250,0"/>
+<AGARI who="1" fromWho="2" sc="250,0,250,0,250,0,250,0"/>
+</mjloggm>"#;
+        let mut game = parse_mjlog(Cursor::new(xml)).unwrap();
+        let lists: [&[(&str, u32)]; 3] = [
+            &[("Riichi", 1), ("Tsumo", 1), ("Ura Dora", 0)],
+            &[("Riichi", 1), ("Pinfu", 1)],
+            &[("Tanyao", 1)],
+        ];
+        for (event, list) in game.rounds[0].events.iter_mut().zip(lists) {
+            if let Event::Agari { yakus, .. } = event {
+                *yakus = list
+                    .iter()
+                    .map(|(name, value)| Yaku {
+                        name: name.to_string(),
+                        value: *value,
+                        kind: None,
+                    })
+                    .collect();
+            }
+        }
+        let stats = yaku_stats(&[game]);
+
+        assert_eq!(stats.overall.wins, 3);
+        assert_eq!(stats.overall.counts["Riichi"], 2);
+        assert!(!stats.overall.counts.contains_key("Ura Dora"));
+        assert_eq!(stats.players["A"].frequency("Riichi"), 1.0);
+        assert_eq!(stats.players["A"].frequency("Pinfu"), 0.5);
+        assert_eq!(stats.players["B"].wins, 1);
+
+        let mut csv = Vec::new();
+        stats.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "player,yaku,count,frequency");
+        assert!(lines.contains(&",Tanyao,1,0.3333333333333333"));
+        assert!(lines.contains(&"A,Pinfu,1,0.5"));
+    }
+}
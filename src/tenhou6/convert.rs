@@ -0,0 +1,340 @@
+//! Writing parsed games in the `tenhou.net/6` JSON format
+//!
+//! Parser output does not tell red fives from plain ones, nor a discard of
+//! the tile just drawn from one of the same tile out of the hand, so red
+//! fives are written as plain fives and every discard by its tile code.
+
+use serde_json::{json, Value};
+
+use super::{
+    tile_id_to_code, Tenhou6Action, Tenhou6Log, Tenhou6Round, Tenhou6Rule, SCORE_UNIT, YAKU_NAMES,
+};
+use crate::models::{
+    Event, GameLength, KanType, ParserOutput, Room, Round, RyuukyokuReason, TransferReason, Yaku,
+};
+use crate::tile::{tile_string_to_id, Tile};
+
+/// Convert a parsed game to the `tenhou.net/6` JSON format
+pub fn to_tenhou6(output: &ParserOutput) -> Tenhou6Log {
+    let rules = &output.rules;
+    let disp = format!(
+        "{}{}{}{}{}{}",
+        if rules.is_sanma() { "三" } else { "" },
+        match rules.room() {
+            Room::Ippan => "般",
+            Room::Joukyuu => "上",
+            Room::Tokujou => "特",
+            Room::Houou => "鳳",
+        },
+        match rules.game_length() {
+            GameLength::Tonpuu => "東",
+            GameLength::Hanchan => "南",
+        },
+        if rules.is_open_tanyao_allowed() {
+            "喰"
+        } else {
+            ""
+        },
+        if rules.has_aka() { "赤" } else { "" },
+        if rules.is_fast() { "速" } else { "" },
+    );
+    Tenhou6Log {
+        title: Vec::new(),
+        name: output.players.iter().map(|p| p.player_id.clone()).collect(),
+        rule: Tenhou6Rule {
+            disp,
+            aka: rules.has_aka() as u32,
+        },
+        log: output
+            .rounds
+            .iter()
+            .map(|round| convert_round(round, rules.players()))
+            .collect(),
+    }
+}
+
+/// Tile code of a tile string, never a red five
+fn code(tile: &str) -> u32 {
+    // Copy 1 of a tile is never red
+    tile_string_to_id(tile).map_or(0, |id| tile_id_to_code(id + 1))
+}
+
+fn codes(tiles: &[Tile]) -> Vec<u32> {
+    tiles.iter().map(|t| code(t)).collect()
+}
+
+/// Tile codes joined with `letter` inserted before the tile at `at`
+fn call(letter: char, codes: &[u32], at: usize) -> Tenhou6Action {
+    let mut text = String::new();
+    for (i, code) in codes.iter().enumerate() {
+        if i == at {
+            text.push(letter);
+        }
+        text.push_str(&code.to_string());
+    }
+    Tenhou6Action::Call(text)
+}
+
+/// Where the letter of a pon or open kan goes: before the called tile, which
+/// is written first for the previous seat, second for the seat opposite and
+/// last for the next seat
+fn called_position(who: u8, from: u8, len: usize, players: u8) -> usize {
+    match (from + players - who) % players {
+        offset if offset == players - 1 => 0,
+        2 => 1,
+        _ => len - 1,
+    }
+}
+
+fn convert_round(round: &Round, players: u8) -> Tenhou6Round {
+    let init = &round.init;
+    let mut converted = Tenhou6Round {
+        kyoku: [init.round_number, init.honba, init.kyoutaku],
+        scores: init.initial_scores.map(|s| s * SCORE_UNIT).to_vec(),
+        dora: vec![tile_id_to_code(init.dora_indicator)],
+        ..Default::default()
+    };
+    for (seat, hand) in init.initial_hands.iter().take(4).enumerate() {
+        converted.hands[seat] = codes(hand);
+    }
+
+    let mut last_discard: Option<&str> = None;
+    // Seat a pon came from, by caller and tile, for the kan that extends it
+    let mut pons: Vec<(u8, &str, u8)> = Vec::new();
+    for event in &round.events {
+        match event {
+            Event::Draw { seat, tile, .. } => {
+                converted.takes[*seat as usize % 4].push(Tenhou6Action::Tile(code(tile)))
+            }
+            Event::Discard {
+                seat,
+                tile,
+                is_riichi,
+                ..
+            } => {
+                let action = if *is_riichi {
+                    Tenhou6Action::Call(format!("r{}", code(tile)))
+                } else {
+                    Tenhou6Action::Tile(code(tile))
+                };
+                converted.discards[*seat as usize % 4].push(action);
+                last_discard = Some(tile);
+            }
+            Event::Chi { who, tiles, .. } => {
+                // The called tile comes first, then the caller's own two
+                let called = last_discard
+                    .filter(|tile| tiles.iter().any(|t| t == tile))
+                    .unwrap_or(&tiles[0]);
+                let mut own: Vec<&Tile> = tiles.iter().collect();
+                if let Some(i) = own.iter().position(|t| *t == called) {
+                    own.remove(i);
+                }
+                let mut ordered = vec![code(called)];
+                ordered.extend(own.iter().take(2).map(|t| code(t)));
+                converted.takes[*who as usize % 4].push(call('c', &ordered, 0));
+            }
+            Event::Pon {
+                who, tiles, from, ..
+            } => {
+                converted.takes[*who as usize % 4].push(call(
+                    'p',
+                    &codes(tiles),
+                    called_position(*who, *from, 3, players),
+                ));
+                pons.push((*who, &tiles[0], *from));
+            }
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                from,
+                ..
+            } => {
+                let seat = *who as usize % 4;
+                let tile_codes = codes(tiles);
+                match kan_type {
+                    KanType::Minkan => {
+                        let from = from.unwrap_or((who + players - 1) % players);
+                        converted.takes[seat].push(call(
+                            'm',
+                            &tile_codes,
+                            called_position(*who, from, 4, players),
+                        ));
+                        // The kan takes the place of a discard
+                        converted.discards[seat].push(Tenhou6Action::Tile(0));
+                    }
+                    KanType::Ankan => converted.discards[seat].push(call('a', &tile_codes, 3)),
+                    KanType::Kakan => {
+                        let pon_from = pons
+                            .iter()
+                            .rev()
+                            .find(|(w, t, _)| w == who && code(t) == tile_codes[0])
+                            .map_or((who + players - 1) % players, |(_, _, from)| *from);
+                        converted.discards[seat].push(call(
+                            'k',
+                            &tile_codes,
+                            called_position(*who, pon_from, 3, players),
+                        ));
+                    }
+                }
+            }
+            Event::Dora { indicator, .. } => converted.dora.push(code(indicator)),
+            Event::Agari {
+                ura_dora_indicators,
+                ..
+            } => {
+                if converted.ura_dora.is_empty() {
+                    converted.ura_dora = codes(ura_dora_indicators);
+                }
+                if converted.result.is_empty() {
+                    converted.result.push(json!("和了"));
+                }
+                converted.result.extend(agari_result(event));
+            }
+            Event::Ryuukyoku { reason, scores, .. } => {
+                let text = match reason {
+                    RyuukyokuReason::Normal => "流局",
+                    RyuukyokuReason::Yao9 => "九種九牌",
+                    RyuukyokuReason::Kaze4 => "四風連打",
+                    RyuukyokuReason::Reach4 => "四家立直",
+                    RyuukyokuReason::Ron3 => "三家和了",
+                    RyuukyokuReason::Kan4 => "四槓散了",
+                };
+                converted.result = vec![json!(text)];
+                if matches!(reason, RyuukyokuReason::Normal) {
+                    converted.result.push(json!(scores.map(|s| s * SCORE_UNIT)));
+                }
+            }
+            _ => {}
+        }
+    }
+    converted
+}
+
+/// Score changes and `[who, from, who, score text, yaku...]` of one win
+fn agari_result(event: &Event) -> [Value; 2] {
+    let Event::Agari {
+        who,
+        from,
+        han,
+        fu,
+        yakus,
+        scores,
+        payout,
+        transfers,
+        ..
+    } = event
+    else {
+        unreachable!("agari_result is only called for wins");
+    };
+
+    let total = payout.map_or(scores[*who as usize % 4], |p| p.hand) * SCORE_UNIT;
+    let points = if who == from {
+        let mut paid: Vec<i32> = transfers
+            .iter()
+            .filter(|t| matches!(t.reason, TransferReason::Hand) && t.to == Some(*who))
+            .map(|t| t.amount * SCORE_UNIT)
+            .collect();
+        paid.sort_unstable();
+        paid.dedup();
+        match paid.as_slice() {
+            [each] => format!("{}点∀", each),
+            [ko, oya] => format!("{}-{}点", ko, oya),
+            _ => format!("{}点", total),
+        }
+    } else {
+        format!("{}点", total)
+    };
+    let text = match limit_name(*han, *fu) {
+        Some(limit) => format!("{}{}", limit, points),
+        None => format!("{}符{}飜{}", fu, han, points),
+    };
+
+    let mut info = vec![json!(who), json!(from), json!(who), json!(text)];
+    info.extend(
+        yakus
+            .iter()
+            .filter(|yaku| yaku.value > 0)
+            .map(|yaku| json!(yaku_text(yaku))),
+    );
+    [json!(scores.map(|s| s * SCORE_UNIT)), Value::Array(info)]
+}
+
+fn limit_name(han: u32, fu: u32) -> Option<&'static str> {
+    match han {
+        13.. => Some("役満"),
+        11..=12 => Some("三倍満"),
+        8..=10 => Some("倍満"),
+        6..=7 => Some("跳満"),
+        5 => Some("満貫"),
+        4 if fu >= 40 => Some("満貫"),
+        3 if fu >= 70 => Some("満貫"),
+        _ => None,
+    }
+}
+
+/// A yaku as the replay writes it, e.g. "立直(1飜)" or "国士無双(役満)"
+fn yaku_text(yaku: &Yaku) -> String {
+    let name = yaku
+        .kind
+        .map_or(yaku.name.as_str(), |kind| YAKU_NAMES[kind.id() as usize]);
+    if yaku.value >= Yaku::YAKUMAN && !yaku.kind.is_some_and(|kind| kind.is_dora()) {
+        format!("{}(役満)", name)
+    } else {
+        format!("{}({}飜)", name, yaku.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::YakuKind;
+    use crate::parse_mjlog;
+    use crate::tenhou6::verify;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip_through_verify() {
+        for name in ["sample.xml", "sample_complex.xml"] {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/data")
+                .join(name);
+            let output = crate::parse_mjlog_file(&path).unwrap();
+            let converted = to_tenhou6(&output);
+            // Through JSON, as other tools would read it
+            let json = serde_json::to_string(&converted).unwrap();
+            let replay: Tenhou6Log = serde_json::from_str(&json).unwrap();
+            assert_eq!(verify(&output, &replay), Vec::new(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_call_notation() {
+        // Pon of east from seat 3 by seat 0, then the added kan
+        let mjlog_content = r#"<mjloggm ver="2.3">
+<GO type="169" lobby="0"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="109,110" hai1="" hai2="" hai3=""/>
+<W111/><G111/><N who="0" m="41483"/><D0/><T108/><N who="0" m="41491"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(mjlog_content.as_bytes())).unwrap();
+        let log = to_tenhou6(&output);
+        assert_eq!(log.rule.disp, "鳳南喰赤");
+        let round = &log.log[0];
+        assert_eq!(round.kyoku, [0, 0, 0]);
+        assert_eq!(round.takes[0][0], Tenhou6Action::Call("p414141".into()));
+        assert_eq!(
+            round.discards[0][1],
+            Tenhou6Action::Call("k41414141".into())
+        );
+    }
+
+    #[test]
+    fn test_yaku_text() {
+        assert_eq!(yaku_text(&Yaku::from_id(1, 1)), "立直(1飜)");
+        assert_eq!(yaku_text(&Yaku::from_id(47, 13)), "国士無双(役満)");
+        assert_eq!(yaku_text(&Yaku::from_id(52, 2)), "ドラ(2飜)");
+        assert_eq!(YAKU_NAMES.len(), YakuKind::ALL.len());
+        assert_eq!(limit_name(4, 30), None);
+        assert_eq!(limit_name(3, 70), Some("満貫"));
+    }
+}
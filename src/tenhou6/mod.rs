@@ -0,0 +1,294 @@
+//! Tenhou's JSON replay format (`tenhou.net/6`)
+//!
+//! The replay viewer at `tenhou.net/6` reads a JSON form of a game:
+//!
+//! ```text
+//! { "title": [...], "name": ["A", "B", "C", "D"], "rule": {"disp": "...", "aka": 1},
+//!   "log": [ [ [kyoku, honba, riichi sticks], [scores], [dora], [ura dora],
+//!              hand0, takes0, discards0, ..., hand3, takes3, discards3, result ] ] }
+//! ```
+//!
+//! Tiles are two-digit codes: 11-19 man, 21-29 pin, 31-39 sou, 41-47 the
+//! winds and dragons, and 51-53 the red fives. A discard of 60 throws the
+//! tile just drawn. Calls are strings such as `"c275226"` or `"41p4141"`,
+//! where the letter marks the call and the codes are its tiles.
+
+pub mod convert;
+pub mod parse;
+pub mod verify;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::stats::SCORE_UNIT;
+
+pub use convert::to_tenhou6;
+pub use parse::{from_tenhou6, parse_tenhou6, parse_tenhou6_with_options};
+pub use verify::{verify, Mismatch};
+
+/// A discard code meaning the tile just drawn
+pub const TSUMOGIRI: u32 = 60;
+
+/// Japanese yaku names by Tenhou's yaku ID, as the replay viewer shows them
+const YAKU_NAMES: [&str; 55] = [
+    "門前清自摸和",
+    "立直",
+    "一発",
+    "槍槓",
+    "嶺上開花",
+    "海底摸月",
+    "河底撈魚",
+    "平和",
+    "断幺九",
+    "一盃口",
+    "自風 東",
+    "自風 南",
+    "自風 西",
+    "自風 北",
+    "場風 東",
+    "場風 南",
+    "場風 西",
+    "場風 北",
+    "役牌 白",
+    "役牌 發",
+    "役牌 中",
+    "両立直",
+    "七対子",
+    "混全帯幺九",
+    "一気通貫",
+    "三色同順",
+    "三色同刻",
+    "三槓子",
+    "対々和",
+    "三暗刻",
+    "小三元",
+    "混老頭",
+    "二盃口",
+    "純全帯幺九",
+    "混一色",
+    "清一色",
+    "人和",
+    "天和",
+    "地和",
+    "大三元",
+    "四暗刻",
+    "四暗刻単騎",
+    "字一色",
+    "緑一色",
+    "清老頭",
+    "九蓮宝燈",
+    "純正九蓮宝燈",
+    "国士無双",
+    "国士無双１３面",
+    "大四喜",
+    "小四喜",
+    "四槓子",
+    "ドラ",
+    "裏ドラ",
+    "赤ドラ",
+];
+
+/// A game in the `tenhou.net/6` JSON format
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tenhou6Log {
+    #[serde(default)]
+    pub title: Vec<String>,
+    /// Player names by seat
+    #[serde(default)]
+    pub name: Vec<String>,
+    #[serde(default)]
+    pub rule: Tenhou6Rule,
+    pub log: Vec<Tenhou6Round>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tenhou6Rule {
+    #[serde(default)]
+    pub disp: String,
+    #[serde(default)]
+    pub aka: u32,
+}
+
+/// One round, stored by the format as a 17-element array
+#[derive(Debug, Clone, Default)]
+pub struct Tenhou6Round {
+    /// Round number (0 = East 1), honba and riichi sticks on the table
+    pub kyoku: [u32; 3],
+    /// Scores by seat at the start of the round, in points
+    pub scores: Vec<i32>,
+    pub dora: Vec<u32>,
+    pub ura_dora: Vec<u32>,
+    pub hands: [Vec<u32>; 4],
+    /// Tiles drawn and calls made, by seat
+    pub takes: [Vec<Tenhou6Action>; 4],
+    /// Tiles discarded and kans declared, by seat
+    pub discards: [Vec<Tenhou6Action>; 4],
+    /// `["和了", deltas, info, ...]`, `["流局", deltas]` or an abortive draw
+    pub result: Vec<serde_json::Value>,
+}
+
+impl Serialize for Tenhou6Round {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(Some(17))?;
+        seq.serialize_element(&self.kyoku)?;
+        seq.serialize_element(&self.scores)?;
+        seq.serialize_element(&self.dora)?;
+        seq.serialize_element(&self.ura_dora)?;
+        for seat in 0..4 {
+            seq.serialize_element(&self.hands[seat])?;
+            seq.serialize_element(&self.takes[seat])?;
+            seq.serialize_element(&self.discards[seat])?;
+        }
+        seq.serialize_element(&self.result)?;
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tenhou6Round {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let items = Vec::<serde_json::Value>::deserialize(deserializer)?;
+        if items.len() < 17 {
+            return Err(D::Error::custom(format!(
+                "round has {} elements, expected 17",
+                items.len()
+            )));
+        }
+        let mut items = items.into_iter();
+        let mut next = |what: &str| -> Result<serde_json::Value, D::Error> {
+            items
+                .next()
+                .ok_or_else(|| D::Error::custom(format!("round is missing {}", what)))
+        };
+        fn field<T: serde::de::DeserializeOwned, E: Error>(
+            value: serde_json::Value,
+            what: &str,
+        ) -> Result<T, E> {
+            serde_json::from_value(value).map_err(|e| E::custom(format!("{}: {}", what, e)))
+        }
+
+        let mut round = Tenhou6Round {
+            kyoku: field(next("kyoku")?, "kyoku")?,
+            scores: field(next("scores")?, "scores")?,
+            dora: field(next("dora")?, "dora")?,
+            ura_dora: field(next("ura dora")?, "ura dora")?,
+            ..Default::default()
+        };
+        for seat in 0..4 {
+            round.hands[seat] = field(next("hand")?, "hand")?;
+            round.takes[seat] = field(next("takes")?, "takes")?;
+            round.discards[seat] = field(next("discards")?, "discards")?;
+        }
+        round.result = field(next("result")?, "result")?;
+        Ok(round)
+    }
+}
+
+/// An entry of a takes or discards list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Tenhou6Action {
+    Tile(u32),
+    /// A call, kan or riichi discard written as a string
+    Call(String),
+}
+
+impl Tenhou6Action {
+    /// The letter of a call string and its tile codes, e.g. `('p', [41, 41, 41])`
+    pub fn call(&self) -> Option<(char, Vec<u32>)> {
+        let Tenhou6Action::Call(text) = self else {
+            return None;
+        };
+        let letter = text.chars().find(|c| c.is_ascii_alphabetic())?;
+        let digits: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+        let codes = digits
+            .as_bytes()
+            .chunks(2)
+            .filter_map(|pair| std::str::from_utf8(pair).ok()?.parse().ok())
+            .collect();
+        Some((letter, codes))
+    }
+}
+
+/// Tile code of a tile ID, with 51-53 for the red fives
+pub fn tile_id_to_code(id: u32) -> u32 {
+    match id {
+        16 => 51,
+        52 => 52,
+        88 => 53,
+        _ => {
+            let tile_type = id / 4;
+            match tile_type {
+                0..=8 => 11 + tile_type,
+                9..=17 => 21 + tile_type - 9,
+                18..=26 => 31 + tile_type - 18,
+                _ => 41 + tile_type - 27,
+            }
+        }
+    }
+}
+
+/// Tile string of a tile code, as used in parser output; red fives are
+/// written as plain fives
+pub fn code_to_tile(code: u32) -> Option<String> {
+    let tile_type = match code {
+        11..=19 => code - 11,
+        21..=29 => code - 21 + 9,
+        31..=39 => code - 31 + 18,
+        41..=47 => code - 41 + 27,
+        51 => 4,
+        52 => 13,
+        53 => 22,
+        _ => return None,
+    };
+    Some(crate::tile::tile_id_to_string(tile_type * 4).into_owned())
+}
+
+/// Han of a yaku entry such as "立直(1飜)" or "国士無双(役満)"
+fn yaku_han(entry: &str) -> u32 {
+    if entry.contains("役満") {
+        return 13;
+    }
+    entry
+        .rsplit_once('(')
+        .and_then(|(_, value)| value.split('飜').next()?.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Fu from a score text such as "30符1飜1000点"; limit hands carry none
+fn score_fu(text: &str) -> Option<u32> {
+    text.split_once('符')?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_codes() {
+        assert_eq!(tile_id_to_code(0), 11);
+        assert_eq!(tile_id_to_code(16), 51);
+        assert_eq!(tile_id_to_code(17), 15);
+        assert_eq!(tile_id_to_code(135), 47);
+        assert_eq!(code_to_tile(51).as_deref(), Some("5m"));
+        assert_eq!(code_to_tile(41).as_deref(), Some("east"));
+        assert_eq!(code_to_tile(TSUMOGIRI), None);
+        for id in 0..136 {
+            assert_eq!(
+                code_to_tile(tile_id_to_code(id)).unwrap(),
+                crate::tile::tile_id_to_string(id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_call_strings() {
+        let chi = Tenhou6Action::Call("c275226".to_string());
+        assert_eq!(chi.call(), Some(('c', vec![27, 52, 26])));
+        let pon = Tenhou6Action::Call("41p4141".to_string());
+        assert_eq!(pon.call(), Some(('p', vec![41, 41, 41])));
+        assert_eq!(Tenhou6Action::Tile(11).call(), None);
+    }
+}
@@ -0,0 +1,746 @@
+//! Reading games in the `tenhou.net/6` JSON format
+//!
+//! The format lists each seat's takes and discards separately, so the turn
+//! order is rebuilt by playing each round through: after a discard, the
+//! next take of another seat claims it if that take is a call of the
+//! discarded tile from the discarding seat, and otherwise the next seat
+//! draws. Pons and open kans take precedence over a chi.
+//!
+//! What the format leaves out stays empty: dice, the final hand and winning
+//! tile of a win, and the final scores.
+
+use std::io::Read;
+
+use super::{
+    code_to_tile, score_fu, yaku_han, Tenhou6Action, Tenhou6Log, Tenhou6Round, SCORE_UNIT,
+    TSUMOGIRI, YAKU_NAMES,
+};
+use crate::error::{ParserError, Result};
+use crate::models::{
+    Event, Init, KanType, ParserOutput, Player, Round, Rules, RyuukyokuReason, Transfer, Yaku,
+    YakuKind,
+};
+use crate::parser::{content_game_id, ParserOptions};
+use crate::tile::Tile;
+
+/// Parse a `tenhou.net/6` JSON document
+pub fn parse_tenhou6<R: Read>(reader: R) -> Result<ParserOutput> {
+    let log: Tenhou6Log = serde_json::from_reader(reader)
+        .map_err(|e| ParserError::parse(e.to_string(), "tenhou.net/6 JSON"))?;
+    from_tenhou6(&log)
+}
+
+/// Parse a `tenhou.net/6` JSON document, taking the game ID from
+/// `options.game_id`, or from the content with `options.deterministic`
+pub fn parse_tenhou6_with_options<R: Read>(
+    mut reader: R,
+    options: &ParserOptions,
+) -> Result<ParserOutput> {
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content).map_err(ParserError::Io)?;
+    let mut output = parse_tenhou6(content.as_slice())?;
+    if let Some(game_id) = &options.game_id {
+        output.game_id = game_id.clone();
+    } else if options.deterministic {
+        output.game_id = content_game_id(&content);
+    }
+    Ok(output)
+}
+
+/// Convert a `tenhou.net/6` game to the parser's model
+pub fn from_tenhou6(log: &Tenhou6Log) -> Result<ParserOutput> {
+    let rules = Rules {
+        type_flags: type_flags(&log.rule.disp, log.rule.aka),
+        lobby_id: None,
+    };
+    let players = log
+        .name
+        .iter()
+        .take(4)
+        .enumerate()
+        .map(|(seat, name)| Player {
+            player_id: name.clone(),
+            ..Player::placeholder(seat as u8)
+        })
+        .collect();
+
+    let mut rounds = Vec::new();
+    for (i, round) in log.log.iter().enumerate() {
+        let mut round = RoundBuilder::new(round, rules.players())
+            .build()
+            .map_err(|e| ParserError::invalid_format(format!("round {}: {}", i + 1, e)))?;
+        round.round_id = format!("Round {}", i + 1);
+        rounds.push(round);
+    }
+
+    let mut output = ParserOutput {
+        mjlog_version: "tenhou6".to_string(),
+        game_id: uuid::Uuid::new_v4().to_string(),
+        rules,
+        players,
+        rounds,
+        spilled: None,
+        overtime: false,
+        // The replay does not carry the final scores
+        end_reason: None,
+        result: None,
+        warnings: Vec::new(),
+        score_warnings: Vec::new(),
+        wall_mismatches: Vec::new(),
+        connections: Vec::new(),
+    };
+    output.overtime = output.played_overtime();
+    Ok(output)
+}
+
+/// Rule flags from a rule text such as "般南喰赤"
+fn type_flags(disp: &str, aka: u32) -> u32 {
+    let mut flags = 0;
+    if aka == 0 && !disp.contains('赤') {
+        flags |= Rules::NO_AKA;
+    }
+    if disp.is_empty() {
+        return flags;
+    }
+    flags |= Rules::VS_HUMAN;
+    if !disp.contains('喰') {
+        flags |= Rules::NO_KUITAN;
+    }
+    if disp.contains('南') {
+        flags |= Rules::HANCHAN;
+    }
+    if disp.contains('三') {
+        flags |= Rules::SANMA;
+    }
+    if disp.contains('速') {
+        flags |= Rules::FAST;
+    }
+    if disp.contains('上') {
+        flags |= Rules::JOUKYUU;
+    }
+    if disp.contains('特') {
+        flags |= Rules::TOKUJOU;
+    }
+    if disp.contains('鳳') {
+        flags |= Rules::TOKUJOU | Rules::JOUKYUU;
+    }
+    flags
+}
+
+fn tile(code: u32) -> Result<Tile> {
+    tile_id(code).map(Tile::from_id)
+}
+
+fn tiles(codes: &[u32]) -> Result<Vec<Tile>> {
+    codes.iter().map(|&code| tile(code)).collect()
+}
+
+/// Tile ID of a tile code, keeping red fives apart from plain ones
+fn tile_id(code: u32) -> Result<u32> {
+    let id = match code {
+        51 => 16,
+        52 => 52,
+        53 => 88,
+        _ => {
+            let tile = code_to_tile(code).ok_or_else(|| {
+                ParserError::invalid_format(format!("Unknown tile code: {}", code))
+            })?;
+            // Copy 1 of a five is never red
+            crate::tile::tile_string_to_id(&tile)? + u32::from(tile.starts_with('5'))
+        }
+    };
+    Ok(id)
+}
+
+/// A call string taken apart: its letter, the index of the tile the letter
+/// stands before, and all tile codes
+fn call_parts(action: &Tenhou6Action) -> Option<(char, usize, Vec<u32>)> {
+    let Tenhou6Action::Call(text) = action else {
+        return None;
+    };
+    let (letter, codes) = action.call()?;
+    let letter_at = text.find(letter)?;
+    Some((letter, letter_at / 2, codes))
+}
+
+/// The seat a pon or open kan by `who` came from, by where its letter
+/// stands: first for the previous seat, second for the seat opposite and
+/// last for the next seat
+fn called_from(who: u8, at: usize, players: u8) -> u8 {
+    let offset = match at {
+        0 => players - 1,
+        1 if players == 4 => 2,
+        _ => 1,
+    };
+    (who + offset) % players
+}
+
+struct RoundBuilder<'a> {
+    round: &'a Tenhou6Round,
+    players: u8,
+    takes: [usize; 4],
+    discards: [usize; 4],
+    events: Vec<Event>,
+    scores: [i32; 4],
+    /// Kan dora indicators not yet revealed
+    next_dora: usize,
+    /// Reveals owed after the next discard, for open and added kans
+    dora_after_discard: usize,
+    /// Seat whose riichi discard has yet to pass without being ronned
+    riichi_pending: Option<u8>,
+}
+
+impl<'a> RoundBuilder<'a> {
+    fn new(round: &'a Tenhou6Round, players: u8) -> Self {
+        Self {
+            round,
+            players,
+            takes: [0; 4],
+            discards: [0; 4],
+            events: Vec::new(),
+            scores: [0; 4],
+            next_dora: 1,
+            dora_after_discard: 0,
+            riichi_pending: None,
+        }
+    }
+
+    fn next_take(&self, seat: u8) -> Option<&'a Tenhou6Action> {
+        let seat = seat as usize;
+        self.round.takes[seat].get(self.takes[seat])
+    }
+
+    fn next_discard(&mut self, seat: u8) -> Option<&'a Tenhou6Action> {
+        let seat = seat as usize;
+        let action = self.round.discards[seat].get(self.discards[seat]);
+        self.discards[seat] += 1;
+        action
+    }
+
+    fn reveal_dora(&mut self) -> Result<()> {
+        if let Some(&code) = self.round.dora.get(self.next_dora) {
+            self.next_dora += 1;
+            self.events.push(Event::Dora {
+                indicator: tile(code)?,
+                source: None,
+                kan: None,
+                who: None,
+                timing: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Complete a pending riichi, now that play goes on past its discard
+    fn settle_riichi(&mut self) {
+        if let Some(who) = self.riichi_pending.take() {
+            self.scores[who as usize] -= 10;
+            self.events.push(Event::Reach {
+                who,
+                step: 2,
+                scores: self.scores,
+                declaration: None,
+                transfers: vec![Transfer::riichi_deposit(who)],
+            });
+        }
+    }
+
+    /// Draw the next take of `seat` as a tile; false once it has none left
+    fn draw(&mut self, seat: u8) -> Result<Option<u32>> {
+        let Some(action) = self.next_take(seat) else {
+            return Ok(None);
+        };
+        let Tenhou6Action::Tile(code) = action else {
+            return Err(ParserError::invalid_format(format!(
+                "seat {} calls {:?} without a discard to claim",
+                seat, action
+            )));
+        };
+        self.takes[seat as usize] += 1;
+        self.settle_riichi();
+        self.events.push(Event::Draw {
+            seat,
+            tile: tile(*code)?,
+            wall_position: None,
+            shanten: None,
+        });
+        Ok(Some(*code))
+    }
+
+    /// Play the turn of `seat` from its discard on: kans from the hand and
+    /// their replacement draws, then the discard. Returns the tile
+    /// discarded, or `None` when the round ended first.
+    fn discard(&mut self, seat: u8, mut drawn: Option<u32>) -> Result<Option<u32>> {
+        loop {
+            let Some(action) = self.next_discard(seat) else {
+                return Ok(None);
+            };
+            let (code, riichi) = match action {
+                Tenhou6Action::Tile(code) => (*code, false),
+                Tenhou6Action::Call(text) => {
+                    let Some((letter, _, codes)) = call_parts(action) else {
+                        return Err(ParserError::invalid_format(format!(
+                            "Unknown discard: {}",
+                            text
+                        )));
+                    };
+                    match letter {
+                        'r' => (codes.first().copied().unwrap_or(TSUMOGIRI), true),
+                        'a' | 'k' => {
+                            let kan_type = if letter == 'a' {
+                                KanType::Ankan
+                            } else {
+                                KanType::Kakan
+                            };
+                            self.events.push(Event::Kan {
+                                who: seat,
+                                tiles: tiles(&codes)?,
+                                kan_type,
+                                from: None,
+                                claimed: None,
+                                dora: None,
+                                rinshan: None,
+                            });
+                            if letter == 'a' {
+                                self.reveal_dora()?;
+                            } else {
+                                self.dora_after_discard += 1;
+                            }
+                            match self.draw(seat)? {
+                                Some(code) => drawn = Some(code),
+                                // Robbed, or the round ended on the kan
+                                None => return Ok(None),
+                            }
+                            continue;
+                        }
+                        _ => {
+                            return Err(ParserError::invalid_format(format!(
+                                "Unknown discard: {}",
+                                text
+                            )))
+                        }
+                    }
+                }
+            };
+            let code = match (code, drawn) {
+                (TSUMOGIRI, Some(drawn)) => drawn,
+                (TSUMOGIRI, None) => {
+                    return Err(ParserError::invalid_format(format!(
+                        "seat {} discards the drawn tile after a call",
+                        seat
+                    )))
+                }
+                (code, _) => code,
+            };
+
+            if riichi {
+                self.events.push(Event::Reach {
+                    who: seat,
+                    step: 1,
+                    scores: [0; 4],
+                    declaration: None,
+                    transfers: Vec::new(),
+                });
+            }
+            self.events.push(Event::Discard {
+                seat,
+                tile: tile(code)?,
+                is_riichi: riichi,
+                shanten: None,
+                waits: Vec::new(),
+                furiten: false,
+            });
+            if riichi {
+                self.riichi_pending = Some(seat);
+            }
+            for _ in 0..std::mem::take(&mut self.dora_after_discard) {
+                self.reveal_dora()?;
+            }
+            return Ok(Some(code));
+        }
+    }
+
+    /// The seat whose next take calls the tile `code` just discarded by
+    /// `seat`, with the call taken apart
+    fn claim(&self, seat: u8, code: u32) -> Option<(u8, char, Vec<u32>)> {
+        let same = |a: u32, b: u32| code_to_tile(a) == code_to_tile(b);
+        let mut chi = None;
+        for offset in 1..self.players {
+            let other = (seat + offset) % self.players;
+            let Some((letter, at, codes)) = self.next_take(other).and_then(call_parts) else {
+                continue;
+            };
+            if !codes.get(at).is_some_and(|&called| same(called, code)) {
+                continue;
+            }
+            match letter {
+                'p' | 'm' if called_from(other, at, self.players) == seat => {
+                    return Some((other, letter, codes))
+                }
+                'c' if offset == 1 => chi = Some((other, letter, codes)),
+                _ => {}
+            }
+        }
+        chi
+    }
+
+    fn build(mut self) -> Result<Round> {
+        let round = self.round;
+        let [round_number, honba, kyoutaku] = round.kyoku;
+        let dealer_seat = (round_number % 4) as u8;
+        for (seat, score) in round.scores.iter().take(4).enumerate() {
+            self.scores[seat] = score / SCORE_UNIT;
+        }
+        let init = Init {
+            round_number,
+            honba,
+            kyoutaku,
+            dice: [0, 0],
+            dora_indicator: tile_id(round.dora.first().copied().unwrap_or(0))?,
+            initial_scores: self.scores,
+            initial_hands: round
+                .hands
+                .iter()
+                .map(|hand| tiles(hand))
+                .collect::<Result<_>>()?,
+        };
+
+        let mut seat = dealer_seat % self.players.max(1);
+        let mut drawn = self.draw(seat)?;
+        if drawn.is_some() {
+            while let Some(code) = self.discard(seat, drawn)? {
+                match self.claim(seat, code) {
+                    Some((caller, letter, codes)) => {
+                        self.takes[caller as usize] += 1;
+                        self.settle_riichi();
+                        let event = call_event(caller, seat, letter, &codes)?;
+                        self.events.push(event);
+                        seat = caller;
+                        drawn = None;
+                        if letter == 'm' {
+                            // The kan leaves a placeholder among the discards
+                            if self.round.discards[seat as usize].get(self.discards[seat as usize])
+                                == Some(&Tenhou6Action::Tile(0))
+                            {
+                                self.discards[seat as usize] += 1;
+                            }
+                            self.dora_after_discard += 1;
+                            drawn = self.draw(seat)?;
+                            if drawn.is_none() {
+                                break;
+                            }
+                        }
+                    }
+                    None => {
+                        seat = (seat + 1) % self.players;
+                        drawn = self.draw(seat)?;
+                        if drawn.is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        for seat in 0..4 {
+            let takes_left =
+                round.takes[seat].len() - self.takes[seat].min(round.takes[seat].len());
+            let discards_left =
+                round.discards[seat].len() - self.discards[seat].min(round.discards[seat].len());
+            if takes_left + discards_left > 0 {
+                return Err(ParserError::invalid_format(format!(
+                    "seat {} has {} take(s) and {} discard(s) that no turn order reaches",
+                    seat, takes_left, discards_left
+                )));
+            }
+        }
+
+        // Reveals still owed when the round ended, such as after a kan
+        // that was robbed
+        while self.next_dora < round.dora.len() {
+            self.reveal_dora()?;
+        }
+        self.finish()?;
+        let mut built = Round {
+            round_id: String::new(),
+            dealer_seat,
+            init,
+            events: self.events,
+            wall: None,
+            raw: Vec::new(),
+            state: None,
+            counters: Vec::new(),
+        };
+        built.link_events();
+        Ok(built)
+    }
+
+    /// Add the settlement from the result array
+    fn finish(&mut self) -> Result<()> {
+        let result = &self.round.result;
+        let kind = result.first().and_then(|v| v.as_str()).unwrap_or_default();
+        let deltas = |value: Option<&serde_json::Value>| -> [i32; 4] {
+            let points: Vec<i32> = value
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default();
+            let mut scores = [0; 4];
+            for (seat, points) in points.iter().take(4).enumerate() {
+                scores[seat] = points / SCORE_UNIT;
+            }
+            scores
+        };
+
+        if kind == "和了" {
+            for pair in result[1..].chunks(2) {
+                let scores = deltas(pair.first());
+                let info = pair
+                    .get(1)
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                let seat = |i: usize| info.get(i).and_then(|v| v.as_u64()).map(|n| n as u8);
+                let (Some(who), Some(from)) = (seat(0), seat(1)) else {
+                    return Err(ParserError::invalid_format("win without a winner"));
+                };
+                // A riichi discard that was ronned never completes
+                if self.riichi_pending == Some(from) && who != from {
+                    self.riichi_pending = None;
+                }
+                self.settle_riichi();
+
+                let text = info.get(3).and_then(|v| v.as_str()).unwrap_or_default();
+                let yakus: Vec<Yaku> = info
+                    .iter()
+                    .skip(4)
+                    .filter_map(|v| v.as_str())
+                    .map(parse_yaku)
+                    .collect();
+                let count = |kind: YakuKind| -> u32 {
+                    yakus
+                        .iter()
+                        .filter(|y| y.kind == Some(kind))
+                        .map(|y| y.value)
+                        .sum()
+                };
+                let (dora, ura_dora, aka_dora) = (
+                    count(YakuKind::Dora),
+                    count(YakuKind::UraDora),
+                    count(YakuKind::AkaDora),
+                );
+                self.events.push(Event::Agari {
+                    who,
+                    from,
+                    han: yakus.iter().map(|y| y.value).sum(),
+                    fu: score_fu(text).unwrap_or(0),
+                    yakus,
+                    dora_count: dora + ura_dora + aka_dora,
+                    dora,
+                    ura_dora,
+                    aka_dora,
+                    hand: Vec::new(),
+                    melds: Vec::new(),
+                    winning_tile: None,
+                    dora_indicators: tiles(&self.round.dora)?,
+                    ura_dora_indicators: tiles(&self.round.ura_dora)?,
+                    scores,
+                    payout: None,
+                    transfers: Transfer::agari(who, from, &scores, None),
+                });
+            }
+            return Ok(());
+        }
+
+        self.settle_riichi();
+        let reason = match kind {
+            "九種九牌" => RyuukyokuReason::Yao9,
+            "四風連打" => RyuukyokuReason::Kaze4,
+            "四家立直" => RyuukyokuReason::Reach4,
+            "三家和了" => RyuukyokuReason::Ron3,
+            "四槓散了" => RyuukyokuReason::Kan4,
+            "流局" | "全員聴牌" | "全員不聴" | "流し満貫" => RyuukyokuReason::Normal,
+            // A round cut short has no settlement
+            _ => return Ok(()),
+        };
+        let scores = deltas(result.get(1));
+        self.events.push(Event::Ryuukyoku {
+            reason,
+            scores,
+            tenpai: Default::default(),
+            transfers: Transfer::draw(&scores),
+        });
+        Ok(())
+    }
+}
+
+/// The event of a call claiming a discard
+fn call_event(who: u8, from: u8, letter: char, codes: &[u32]) -> Result<Event> {
+    let mut ordered = tiles(codes)?;
+    ordered.sort();
+    Ok(match letter {
+        'c' | 'p' => {
+            let tiles: [Tile; 3] = ordered
+                .try_into()
+                .map_err(|_| ParserError::invalid_format("chi and pon need three tiles"))?;
+            if letter == 'c' {
+                Event::Chi {
+                    who,
+                    tiles,
+                    from,
+                    claimed: None,
+                }
+            } else {
+                Event::Pon {
+                    who,
+                    tiles,
+                    from,
+                    claimed: None,
+                }
+            }
+        }
+        _ => Event::Kan {
+            who,
+            tiles: ordered,
+            kan_type: KanType::Minkan,
+            from: Some(from),
+            claimed: None,
+            dora: None,
+            rinshan: None,
+        },
+    })
+}
+
+/// A yaku entry such as "立直(1飜)" or "国士無双(役満)"
+fn parse_yaku(entry: &str) -> Yaku {
+    let name = entry.split('(').next().unwrap_or(entry);
+    let value = yaku_han(entry);
+    match YAKU_NAMES.iter().position(|&known| known == name) {
+        Some(id) => Yaku::from_id(id as u32, value),
+        None => Yaku {
+            name: name.to_string(),
+            value,
+            kind: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenhou6::{to_tenhou6, verify};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        let path = |name: &str| {
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/data")
+                .join(name)
+        };
+        let output = crate::parse_mjlog_file(&path("sample.xml")).unwrap();
+        let replay = to_tenhou6(&output);
+        let read = from_tenhou6(&replay).unwrap();
+        assert_eq!(read.rules.type_flags, output.rules.type_flags);
+        // The game read back converts to the same replay
+        assert_eq!(verify(&read, &replay), Vec::new());
+
+        // A chi of a tile nobody discarded cannot be played through
+        let output = crate::parse_mjlog_file(&path("sample_complex.xml")).unwrap();
+        let error = from_tenhou6(&to_tenhou6(&output)).unwrap_err();
+        assert!(
+            error.to_string().contains("no turn order reaches"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_deterministic_game_id() {
+        let output =
+            crate::parse_mjlog(Cursor::new(std::fs::read("tests/data/sample.xml").unwrap()))
+                .unwrap();
+        let json = serde_json::to_vec(&to_tenhou6(&output)).unwrap();
+        let options = ParserOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        let parse = |options: &ParserOptions| {
+            parse_tenhou6_with_options(json.as_slice(), options)
+                .unwrap()
+                .game_id
+        };
+        assert_eq!(parse(&options), content_game_id(&json));
+        assert_ne!(
+            parse(&ParserOptions::default()),
+            parse(&ParserOptions::default())
+        );
+        let named = ParserOptions {
+            game_id: Some("2024061500gm-00a9-0000-1a2b3c4d".into()),
+            ..options
+        };
+        assert_eq!(parse(&named), "2024061500gm-00a9-0000-1a2b3c4d");
+    }
+
+    #[test]
+    fn test_parse_tenhou6() {
+        // East 1: seat 0 declares riichi on its first discard, seat 1 pons
+        // it and seat 0 wins by ron on seat 1's discard
+        let json = r#"{
+  "name": ["A", "B", "C", "D"],
+  "rule": {"disp": "般南喰赤", "aka": 1},
+  "log": [[
+    [0, 0, 0], [25000, 25000, 25000, 25000], [11], [12],
+    [41, 41, 12, 13, 14, 15, 16, 17, 18, 19, 21, 22, 23], [41], ["r60"],
+    [41, 41, 31, 32, 33, 34, 35, 36, 37, 38, 39, 21, 22], ["p414141"], [39],
+    [], [], [],
+    [], [], [],
+    ["和了", [3900, -2900, 0, 0], [0, 1, 0, "30符3飜3900点", "立直(1飜)", "ドラ(2飜)", "裏ドラ(0飜)"]]
+  ]]
+}"#;
+        let output = parse_tenhou6(Cursor::new(json)).unwrap();
+        assert_eq!(output.players[1].player_id, "B");
+        assert!(output.rules.has_aka());
+        let events = &output.rounds[0].events;
+        let kinds: Vec<String> = events
+            .iter()
+            .map(|e| {
+                serde_json::to_value(e).unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            ["draw", "reach", "discard", "reach", "pon", "discard", "agari"]
+        );
+        // The riichi completes as the pon goes on with play
+        assert!(matches!(
+            events[3],
+            Event::Reach {
+                step: 2,
+                scores: [240, 250, 250, 250],
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[4],
+            Event::Pon {
+                who: 1,
+                from: 0,
+                claimed: Some(2),
+                ..
+            }
+        ));
+        let Event::Agari {
+            han,
+            fu,
+            dora,
+            ura_dora,
+            ..
+        } = &events[6]
+        else {
+            panic!("expected a win");
+        };
+        assert_eq!((*han, *fu, *dora, *ura_dora), (3, 30, 2, 0));
+    }
+}
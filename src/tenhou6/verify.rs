@@ -0,0 +1,385 @@
+//! Cross-checking a parsed mjlog against Tenhou's own JSON replay
+//!
+//! Tenhou publishes every game both as mjlog XML and in the `tenhou.net/6`
+//! JSON format, which spells out calls, kans and yaku that the XML only
+//! encodes. Parsing the XML and comparing it with the JSON of the same game
+//! therefore checks the parser's decoding against Tenhou's.
+//!
+//! Red fives are compared as plain fives, since parser output does not
+//! distinguish them.
+
+use serde::Serialize;
+
+use super::{
+    code_to_tile, score_fu, yaku_han, Tenhou6Action, Tenhou6Log, Tenhou6Round, SCORE_UNIT,
+    TSUMOGIRI,
+};
+use crate::models::{Event, KanType, ParserOutput, Round, RyuukyokuReason};
+use crate::tile::Tile;
+
+/// A difference between the parsed mjlog and the JSON replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mismatch {
+    /// Round index, absent for game-level fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seat: Option<u8>,
+    /// What differs, such as `discards[7]` or `result.deltas`
+    pub field: String,
+    pub mjlog: String,
+    pub tenhou6: String,
+}
+
+struct Checker {
+    round: Option<usize>,
+    mismatches: Vec<Mismatch>,
+}
+
+impl Checker {
+    fn check<T: PartialEq + std::fmt::Debug>(
+        &mut self,
+        seat: Option<u8>,
+        field: impl Into<String>,
+        mjlog: T,
+        tenhou6: T,
+    ) {
+        if mjlog != tenhou6 {
+            self.mismatches.push(Mismatch {
+                round: self.round,
+                seat,
+                field: field.into(),
+                mjlog: format!("{:?}", mjlog),
+                tenhou6: format!("{:?}", tenhou6),
+            });
+        }
+    }
+
+    /// Compare two sequences, reporting only the first difference
+    fn check_sequence(&mut self, seat: u8, field: &str, mjlog: &[String], tenhou6: &[String]) {
+        let differs = mjlog.iter().zip(tenhou6).position(|(a, b)| a != b);
+        match differs {
+            Some(i) => self.check(
+                Some(seat),
+                format!("{}[{}]", field, i),
+                &mjlog[i],
+                &tenhou6[i],
+            ),
+            None => self.check(
+                Some(seat),
+                format!("{}.len", field),
+                mjlog.len(),
+                tenhou6.len(),
+            ),
+        }
+    }
+}
+
+/// Compare a parsed game with the JSON replay of the same game
+///
+/// Returns every difference found; an empty list means both describe the
+/// same game.
+pub fn verify(output: &ParserOutput, replay: &Tenhou6Log) -> Vec<Mismatch> {
+    let mut checker = Checker {
+        round: None,
+        mismatches: Vec::new(),
+    };
+    if !replay.name.is_empty() {
+        let names: Vec<&str> = output
+            .players
+            .iter()
+            .map(|p| p.player_id.as_str())
+            .collect();
+        let expected: Vec<&str> = replay.name.iter().map(String::as_str).collect();
+        checker.check(None, "names", names, expected);
+    }
+    checker.check(None, "rounds", output.rounds.len(), replay.log.len());
+
+    for (i, (round, theirs)) in output.rounds.iter().zip(&replay.log).enumerate() {
+        checker.round = Some(i);
+        verify_round(&mut checker, round, theirs);
+    }
+    checker.mismatches
+}
+
+fn tiles(codes: &[u32]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|&c| code_to_tile(c).unwrap_or_else(|| c.to_string()))
+        .collect()
+}
+
+fn sorted(mut tiles: Vec<String>) -> Vec<String> {
+    crate::tile::sort_hand(&mut tiles);
+    tiles
+}
+
+fn call<T: ToString>(kind: &str, tiles: &[T]) -> String {
+    let tiles = tiles.iter().map(T::to_string).collect();
+    format!("{} {}", kind, sorted(tiles).join(" "))
+}
+
+fn verify_round(checker: &mut Checker, round: &Round, theirs: &Tenhou6Round) {
+    let init = &round.init;
+    checker.check(
+        None,
+        "kyoku",
+        [init.round_number, init.honba, init.kyoutaku],
+        theirs.kyoku,
+    );
+    checker.check(
+        None,
+        "scores",
+        init.initial_scores.map(|s| s * SCORE_UNIT).to_vec(),
+        theirs.scores.clone(),
+    );
+
+    let mut dora = vec![crate::tile::tile_id_to_string(init.dora_indicator).into_owned()];
+    dora.extend(round.events.iter().filter_map(|e| match e {
+        Event::Dora { indicator, .. } => Some(indicator.to_string()),
+        _ => None,
+    }));
+    checker.check(None, "dora", dora, tiles(&theirs.dora));
+
+    for seat in 0..4u8 {
+        let s = seat as usize;
+        checker.check(
+            Some(seat),
+            "hand",
+            sorted(
+                init.initial_hands
+                    .get(s)
+                    .map_or_else(Vec::new, |hand| hand.iter().map(Tile::to_string).collect()),
+            ),
+            sorted(tiles(&theirs.hands[s])),
+        );
+        let (takes, discards) = our_actions(round, seat);
+        let their_takes: Vec<String> = theirs.takes[s].iter().map(their_take).collect();
+        checker.check_sequence(seat, "takes", &takes, &their_takes);
+        let their_discards: Vec<String> = theirs.discards[s]
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| **action != Tenhou6Action::Tile(0))
+            .map(|(i, action)| their_discard(action, their_takes.get(i)))
+            .collect();
+        checker.check_sequence(seat, "discards", &discards, &their_discards);
+    }
+
+    verify_result(checker, round, &theirs.result);
+}
+
+/// A seat's draws and calls, and its discards and kan declarations, in the
+/// notation used to compare with the replay
+fn our_actions(round: &Round, seat: u8) -> (Vec<String>, Vec<String>) {
+    let mut takes = Vec::new();
+    let mut discards = Vec::new();
+    for event in &round.events {
+        match event {
+            Event::Draw { seat: s, tile, .. } if *s == seat => takes.push(tile.to_string()),
+            Event::Chi { who, tiles, .. } if *who == seat => takes.push(call("chi", tiles)),
+            Event::Pon { who, tiles, .. } if *who == seat => takes.push(call("pon", tiles)),
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                ..
+            } if *who == seat => match kan_type {
+                KanType::Minkan => takes.push(call("kan", tiles)),
+                KanType::Ankan => discards.push(call("ankan", tiles)),
+                KanType::Kakan => discards.push(call("kakan", tiles)),
+            },
+            Event::Discard {
+                seat: s,
+                tile,
+                is_riichi,
+                ..
+            } if *s == seat => discards.push(if *is_riichi {
+                format!("riichi {}", tile)
+            } else {
+                tile.to_string()
+            }),
+            _ => {}
+        }
+    }
+    (takes, discards)
+}
+
+fn their_take(action: &Tenhou6Action) -> String {
+    match action {
+        Tenhou6Action::Tile(code) => tiles(&[*code]).remove(0),
+        Tenhou6Action::Call(text) => match action.call() {
+            Some(('c', codes)) => call("chi", &tiles(&codes)),
+            Some(('p', codes)) => call("pon", &tiles(&codes)),
+            Some(('m', codes)) => call("kan", &tiles(&codes)),
+            _ => text.clone(),
+        },
+    }
+}
+
+fn their_discard(action: &Tenhou6Action, drawn: Option<&String>) -> String {
+    let tile = |code: u32| match (code, drawn) {
+        (TSUMOGIRI, Some(drawn)) => drawn.clone(),
+        _ => tiles(&[code]).remove(0),
+    };
+    match action {
+        Tenhou6Action::Tile(code) => tile(*code),
+        Tenhou6Action::Call(text) => match action.call() {
+            Some(('r', codes)) if !codes.is_empty() => format!("riichi {}", tile(codes[0])),
+            Some(('a', codes)) => call("ankan", &tiles(&codes)),
+            Some(('k', codes)) => call("kakan", &tiles(&codes)),
+            _ => text.clone(),
+        },
+    }
+}
+
+fn deltas(value: &serde_json::Value) -> Option<Vec<i32>> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+fn verify_result(checker: &mut Checker, round: &Round, result: &[serde_json::Value]) {
+    let kind = result.first().and_then(|v| v.as_str()).unwrap_or_default();
+    let wins: Vec<&Event> = round
+        .events
+        .iter()
+        .filter(|e| matches!(e, Event::Agari { .. }))
+        .collect();
+    let draw = round.events.iter().find_map(|e| match e {
+        Event::Ryuukyoku { reason, scores, .. } => Some((reason, scores)),
+        _ => None,
+    });
+
+    if kind == "和了" {
+        let theirs: Vec<(&serde_json::Value, &serde_json::Value)> = result[1..]
+            .chunks(2)
+            .filter_map(|pair| Some((pair.first()?, pair.get(1)?)))
+            .collect();
+        checker.check(None, "result.wins", wins.len(), theirs.len());
+        for (i, (event, (their_deltas, info))) in wins.iter().zip(theirs).enumerate() {
+            let Event::Agari {
+                who,
+                from,
+                fu,
+                yakus,
+                scores,
+                ..
+            } = event
+            else {
+                continue;
+            };
+            let info = info.as_array().cloned().unwrap_or_default();
+            let number = |j: usize| info.get(j).and_then(|v| v.as_u64()).map(|n| n as u8);
+            checker.check(
+                Some(*who),
+                format!("result[{}].winner", i),
+                (Some(*who), Some(*from)),
+                (number(0), number(1)),
+            );
+            checker.check(
+                Some(*who),
+                format!("result[{}].deltas", i),
+                Some(scores.map(|s| s * SCORE_UNIT).to_vec()),
+                deltas(their_deltas),
+            );
+            let text = info.get(3).and_then(|v| v.as_str()).unwrap_or_default();
+            if let Some(their_fu) = score_fu(text) {
+                checker.check(Some(*who), format!("result[{}].fu", i), *fu, their_fu);
+            }
+            let their_han: u32 = info[4.min(info.len())..]
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(yaku_han)
+                .sum();
+            let han: u32 = yakus.iter().map(|y| y.value).sum();
+            checker.check(Some(*who), format!("result[{}].han", i), han, their_han);
+        }
+        return;
+    }
+
+    let reason = match kind {
+        "九種九牌" => Some(RyuukyokuReason::Yao9),
+        "四風連打" => Some(RyuukyokuReason::Kaze4),
+        "四家立直" => Some(RyuukyokuReason::Reach4),
+        "三家和了" => Some(RyuukyokuReason::Ron3),
+        "四槓散了" => Some(RyuukyokuReason::Kan4),
+        "流局" | "全員聴牌" | "全員不聴" | "流し満貫" => {
+            Some(RyuukyokuReason::Normal)
+        }
+        _ => None,
+    };
+    let ours = draw.map(|(reason, _)| format!("{:?}", reason));
+    let expected = reason.map_or_else(|| Some(kind.to_string()), |r| Some(format!("{:?}", r)));
+    checker.check(None, "result", ours, expected);
+    if let (Some((_, scores)), Some(their_deltas)) = (draw, result.get(1).and_then(deltas)) {
+        checker.check(
+            None,
+            "result.deltas",
+            scores.map(|s| s * SCORE_UNIT).to_vec(),
+            their_deltas,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    const XML: &str = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8" hai1="1,5,9" hai2="2,6,10" hai3="3,7,11"/>
+<T108/><D108/><U20/><E5/><V21/><F21/>
+<W24/><REACH who="3" step="1"/><G3/><REACH who="3" step="2" ten="250,250,250,240"/>
+<T25/><D25/>
+<AGARI ba="0,1" who="3" fromWho="0" ten="30,1000,1" yaku="1,1" sc="250,-10,250,0,250,0,240,20"/>
+</mjloggm>"#;
+
+    fn replay() -> Tenhou6Log {
+        serde_json::from_str(
+            r#"{"name": ["A", "B", "C", "D"], "log": [[
+                [0, 0, 0], [25000, 25000, 25000, 25000], [52], [],
+                [11, 12, 13], [41, 17], [60, 60],
+                [11, 12, 13], [16], [12],
+                [11, 12, 13], [16], [60],
+                [11, 12, 13], [17], ["r11"],
+                ["和了", [-1000, 0, 0, 2000], [3, 0, 3, "30符1飜1000点", "立直(1飜)"]]
+            ]]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_matching_game() {
+        let output = parse_mjlog(Cursor::new(XML)).unwrap();
+        assert_eq!(verify(&output, &replay()), Vec::new());
+    }
+
+    #[test]
+    fn test_reports_differences() {
+        let output = parse_mjlog(Cursor::new(XML)).unwrap();
+        let mut replay = replay();
+        replay.log[0].discards[1][0] = Tenhou6Action::Tile(13);
+        replay.log[0].result[2][3] = serde_json::json!("40符1飜1300点");
+
+        let mismatches = verify(&output, &replay);
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch {
+                    round: Some(0),
+                    seat: Some(1),
+                    field: "discards[0]".to_string(),
+                    mjlog: "\"2m\"".to_string(),
+                    tenhou6: "\"3m\"".to_string(),
+                },
+                Mismatch {
+                    round: Some(0),
+                    seat: Some(3),
+                    field: "result[0].fu".to_string(),
+                    mjlog: "30".to_string(),
+                    tenhou6: "40".to_string(),
+                },
+            ]
+        );
+    }
+}
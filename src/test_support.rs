@@ -0,0 +1,25 @@
+//! Fixtures shared by unit tests
+
+use std::io::Cursor;
+
+use crate::models::ParserOutput;
+use crate::parser::parse_mjlog;
+
+/// Parse a four-player log between `names` whose tags after `<UN>` are
+/// `body`
+pub(crate) fn game(names: [&str; 4], body: &str) -> ParserOutput {
+    let xml = format!(
+        r#"<mjloggm ver="2.3"><UN n0="{}" n1="{}" n2="{}" n3="{}"/>{}</mjloggm>"#,
+        names[0], names[1], names[2], names[3], body
+    );
+    parse_mjlog(Cursor::new(xml.into_bytes())).unwrap()
+}
+
+/// Tags of an all-last round (South 4, seat 3 dealing) that starts from the
+/// `ten` scores with empty hands and ends with `end`
+pub(crate) fn all_last(ten: &str, end: &str) -> String {
+    format!(
+        r#"<INIT seed="7,0,0,1,2,52" ten="{}" oya="3" hai0="" hai1="" hai2="" hai3=""/>{}"#,
+        ten, end
+    )
+}
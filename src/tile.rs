@@ -1,8 +1,21 @@
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::ser::SerializeStruct;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::error::{ParserError, Result};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Number of distinct tile types (9 man, 9 pin, 9 sou, 7 honors)
+pub const TILE_TYPES: usize = 34;
+
+/// Tile type, ordered m, p, s, then winds and dragons in table order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TileType {
     Man(u8), // 1m-9m
     Pin(u8), // 1p-9p
@@ -16,6 +29,453 @@ pub enum TileType {
     Red,     // 中
 }
 
+/// Suit of a tile, with all honors grouped together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suit {
+    Man,   // 萬子
+    Pin,   // 筒子
+    Sou,   // 索子
+    Honor, // 字牌
+}
+
+impl TileType {
+    pub fn suit(self) -> Suit {
+        match self {
+            TileType::Man(_) => Suit::Man,
+            TileType::Pin(_) => Suit::Pin,
+            TileType::Sou(_) => Suit::Sou,
+            _ => Suit::Honor,
+        }
+    }
+
+    /// Rank 1-9 of a suited tile; `None` for honors
+    pub fn number(self) -> Option<u8> {
+        match self {
+            TileType::Man(n) | TileType::Pin(n) | TileType::Sou(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// 1 or 9 of a suit (老頭牌)
+    pub fn is_terminal(self) -> bool {
+        matches!(self.number(), Some(1 | 9))
+    }
+
+    /// Wind or dragon (字牌)
+    pub fn is_honor(self) -> bool {
+        self.suit() == Suit::Honor
+    }
+
+    /// Terminal or honor (么九牌)
+    pub fn is_yaochuu(self) -> bool {
+        self.is_terminal() || self.is_honor()
+    }
+
+    /// 2 to 8 of a suit (中張牌)
+    pub fn is_simple(self) -> bool {
+        matches!(self.number(), Some(2..=8))
+    }
+
+    pub fn is_wind(self) -> bool {
+        matches!(
+            self,
+            TileType::East | TileType::South | TileType::West | TileType::North
+        )
+    }
+
+    pub fn is_dragon(self) -> bool {
+        matches!(self, TileType::White | TileType::Green | TileType::Red)
+    }
+
+    /// Tile type index 0-33 in m, p, s, honor order
+    pub fn index(self) -> usize {
+        match self {
+            TileType::Man(n) => n as usize - 1,
+            TileType::Pin(n) => n as usize + 8,
+            TileType::Sou(n) => n as usize + 17,
+            TileType::East => 27,
+            TileType::South => 28,
+            TileType::West => 29,
+            TileType::North => 30,
+            TileType::White => 31,
+            TileType::Green => 32,
+            TileType::Red => 33,
+        }
+    }
+
+    /// Unicode Mahjong Tiles glyph (U+1F000 block)
+    pub fn glyph(self) -> char {
+        TILE_ASSETS[self.index()].glyph
+    }
+
+    /// Sprite name of the tile, with the red five's name when `red` is set
+    /// for a five
+    pub fn sprite(self, red: bool) -> &'static str {
+        let index = self.index();
+        match self.number() {
+            Some(5) if red => TILE_ASSETS[TILE_TYPES + index / 9].sprite,
+            _ => TILE_ASSETS[index].sprite,
+        }
+    }
+
+    /// The dora this tile indicates: the next rank of its suit (9 wraps to
+    /// 1), the next wind (north wraps to east) or the next dragon (red wraps
+    /// to white)
+    pub fn dora(self) -> TileType {
+        match self {
+            TileType::Man(n) => TileType::Man(n % 9 + 1),
+            TileType::Pin(n) => TileType::Pin(n % 9 + 1),
+            TileType::Sou(n) => TileType::Sou(n % 9 + 1),
+            TileType::East => TileType::South,
+            TileType::South => TileType::West,
+            TileType::West => TileType::North,
+            TileType::North => TileType::East,
+            TileType::White => TileType::Green,
+            TileType::Green => TileType::Red,
+            TileType::Red => TileType::White,
+        }
+    }
+}
+
+/// Tile strings of the 34 tile types, in tile type order
+const TILE_NAMES: [&str; TILE_TYPES] = [
+    "1m", "2m", "3m", "4m", "5m", "6m", "7m", "8m", "9m", "1p", "2p", "3p", "4p", "5p", "6p", "7p",
+    "8p", "9p", "1s", "2s", "3s", "4s", "5s", "6s", "7s", "8s", "9s", "east", "south", "west",
+    "north", "white", "green", "red",
+];
+
+/// One tile of the log, identified by its tile ID (0-135)
+///
+/// The ID carries the tile type, which of the four copies of that type the
+/// tile is and, in games with aka, whether it is a red five. A `Tile`
+/// serializes to the tile string the output has always used ("5m", "east")
+/// and dereferences to it, while equality, ordering and hashing go by tile
+/// type, so a red 5m equals the other three and tiles sort in hand order.
+///
+/// Two IDs are not real tiles: [`Tile::HIDDEN`], a tile the viewer cannot
+/// see, written "?", and IDs past 135 from a malformed log, written
+/// "unknown_<id>". Both sort after every real tile.
+///
+/// Tile strings carry no copy, so a tile read back from one is the first
+/// copy of its type that is not red. To keep the ID, serialize inside
+/// [`with_tile_format`] to write every tile as an `{id, name}` object, or
+/// write a single field as a plain ID with `#[serde(with = "tile::id")]`.
+/// Deserializing takes all three forms.
+#[derive(Debug, Clone, Copy)]
+pub struct Tile(u32);
+
+impl Tile {
+    /// A tile hidden from the viewer
+    pub const HIDDEN: Tile = Tile(u32::MAX);
+
+    pub const fn from_id(id: u32) -> Self {
+        Tile(id)
+    }
+
+    /// The first copy of a tile type that is not a red five
+    pub fn from_type(tile_type: TileType) -> Self {
+        Tile::from_index(tile_type.index())
+    }
+
+    /// The first copy that is not a red five of the tile type with index
+    /// 0-33, in m, p, s, honor order
+    pub fn from_index(index: usize) -> Self {
+        let five = index < 27 && index % 9 == 4;
+        Tile(index as u32 * 4 + u32::from(five))
+    }
+
+    pub fn id(self) -> u32 {
+        self.0
+    }
+
+    /// Tile type; `None` for hidden and unknown tiles
+    pub fn tile_type(self) -> Option<TileType> {
+        tile_id_to_type(self.0).ok()
+    }
+
+    pub fn suit(self) -> Option<Suit> {
+        self.tile_type().map(TileType::suit)
+    }
+
+    /// Rank 1-9 of a suited tile
+    pub fn rank(self) -> Option<u8> {
+        self.tile_type().and_then(TileType::number)
+    }
+
+    /// Which of the four copies of its type the tile is, 0-3
+    pub fn copy(self) -> Option<u8> {
+        self.is_known().then_some((self.0 % 4) as u8)
+    }
+
+    /// Whether the tile is a red five, in games played with aka
+    pub fn is_red(self) -> bool {
+        is_red_five(self.0)
+    }
+
+    pub fn is_hidden(self) -> bool {
+        self == Tile::HIDDEN
+    }
+
+    /// Whether the ID is a real tile, 0-135
+    pub fn is_known(self) -> bool {
+        self.0 < 136
+    }
+
+    /// Tile string; "unknown" for IDs past 135, without the ID that
+    /// `Display` adds
+    pub fn as_str(&self) -> &'static str {
+        match self.0 {
+            id if id < 136 => TILE_NAMES[id as usize / 4],
+            u32::MAX => "?",
+            _ => "unknown",
+        }
+    }
+
+    /// Hand order key: tile type, with hidden and unknown tiles last
+    fn key(self) -> u32 {
+        if self.is_known() {
+            self.0 / 4
+        } else {
+            self.0
+        }
+    }
+}
+
+impl PartialEq for Tile {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Tile {}
+
+impl Hash for Tile {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl PartialOrd for Tile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Deref for Tile {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Tile {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            id if id < 136 || id == u32::MAX => f.write_str(self.as_str()),
+            id => write!(f, "unknown_{}", id),
+        }
+    }
+}
+
+impl PartialEq<str> for Tile {
+    fn eq(&self, other: &str) -> bool {
+        match self.0 {
+            id if id < 136 || id == u32::MAX => self.as_str() == other,
+            id => other.strip_prefix("unknown_") == Some(id.to_string().as_str()),
+        }
+    }
+}
+
+impl PartialEq<&str> for Tile {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Tile {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Tile> for &str {
+    fn eq(&self, other: &Tile) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<Tile> for String {
+    fn eq(&self, other: &Tile) -> bool {
+        other == self
+    }
+}
+
+impl From<TileType> for Tile {
+    fn from(tile_type: TileType) -> Self {
+        Tile::from_type(tile_type)
+    }
+}
+
+impl FromStr for Tile {
+    type Err = ParserError;
+
+    /// Parse a tile string as `Display` writes it
+    fn from_str(tile: &str) -> Result<Self> {
+        if tile == "?" {
+            return Ok(Tile::HIDDEN);
+        }
+        if let Some(id) = tile.strip_prefix("unknown_").and_then(|id| id.parse().ok()) {
+            return Ok(Tile(id));
+        }
+        Ok(Tile::from_type(tile_id_to_type(tile_string_to_id(tile)?)?))
+    }
+}
+
+/// How tiles are written when serialized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TileFormat {
+    /// The tile string, e.g. "5m"
+    #[default]
+    Name,
+    /// An object with the tile ID and the tile string, e.g.
+    /// `{"id": 16, "name": "5m"}`; a hidden tile has no ID
+    Object,
+}
+
+thread_local! {
+    static TILE_FORMAT: Cell<TileFormat> = const { Cell::new(TileFormat::Name) };
+}
+
+/// Run `f` with every tile serialized on this thread written in `format`
+///
+/// ```
+/// use tenhou_log_parser::tile::{with_tile_format, Tile, TileFormat};
+///
+/// let json = with_tile_format(TileFormat::Object, || {
+///     serde_json::to_string(&Tile::from_id(16)).unwrap()
+/// });
+/// assert_eq!(json, r#"{"id":16,"name":"5m"}"#);
+/// ```
+pub fn with_tile_format<R>(format: TileFormat, f: impl FnOnce() -> R) -> R {
+    /// Puts the previous format back, even when `f` panics
+    struct Restore(TileFormat);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            TILE_FORMAT.set(self.0);
+        }
+    }
+
+    let _restore = Restore(TILE_FORMAT.replace(format));
+    f()
+}
+
+impl Serialize for Tile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match TILE_FORMAT.get() {
+            TileFormat::Name => serializer.collect_str(self),
+            TileFormat::Object => {
+                let hidden = self.is_hidden();
+                let mut object = serializer.serialize_struct("Tile", 2 - hidden as usize)?;
+                if !hidden {
+                    object.serialize_field("id", &self.0)?;
+                }
+                object.serialize_field("name", &self.to_string())?;
+                object.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Tile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct TileVisitor;
+
+        impl<'de> de::Visitor<'de> for TileVisitor {
+            type Value = Tile;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a tile string, tile ID or tile object")
+            }
+
+            fn visit_str<E: de::Error>(self, tile: &str) -> std::result::Result<Tile, E> {
+                tile.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, id: u64) -> std::result::Result<Tile, E> {
+                u32::try_from(id).map(Tile).map_err(E::custom)
+            }
+
+            /// The object form of `TileFormat::Object`, read by ID when it
+            /// has one
+            fn visit_map<A: de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Tile, A::Error> {
+                let (mut id, mut name) = (None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "id" => id = Some(map.next_value::<u32>()?),
+                        "name" => name = Some(map.next_value::<String>()?),
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                match (id, name) {
+                    (Some(id), _) => Ok(Tile(id)),
+                    (None, Some(name)) => name.parse().map_err(de::Error::custom),
+                    (None, None) => Err(de::Error::missing_field("name")),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(TileVisitor)
+    }
+}
+
+/// Serde representation of a tile as its tile ID rather than its string,
+/// keeping the copy and red five apart
+///
+/// ```
+/// use serde::Serialize;
+/// use tenhou_log_parser::tile::{self, Tile};
+///
+/// #[derive(Serialize)]
+/// struct Draw {
+///     #[serde(with = "tile::id")]
+///     tile: Tile,
+/// }
+/// let json = serde_json::to_string(&Draw { tile: Tile::from_id(16) }).unwrap();
+/// assert_eq!(json, r#"{"tile":16}"#);
+/// ```
+pub mod id {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Tile;
+
+    pub fn serialize<S: Serializer>(tile: &Tile, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(tile.id())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Tile, D::Error> {
+        Tile::deserialize(deserializer)
+    }
+}
+
 /// Convert tile ID (0-135) to tile string representation
 ///
 /// # Examples
@@ -128,8 +588,8 @@ pub fn tile_id_to_type(id: u32) -> Result<TileType> {
     }
 }
 
-/// Parse a comma-separated list of tile IDs to tile strings
-pub fn parse_tile_list(tiles: &str) -> Result<Vec<String>> {
+/// Parse a comma-separated list of tile IDs
+pub fn parse_tile_list(tiles: &str) -> Result<Vec<Tile>> {
     if tiles.is_empty() {
         return Ok(vec![]);
     }
@@ -140,7 +600,362 @@ pub fn parse_tile_list(tiles: &str) -> Result<Vec<String>> {
             let id = s
                 .parse::<u32>()
                 .map_err(|_| ParserError::invalid_format(format!("Invalid tile ID: {}", s)))?;
-            Ok(tile_id_to_string(id).into_owned())
+            Ok(Tile::from_id(id))
+        })
+        .collect()
+}
+
+/// Tile string of the dora indicated by a dora indicator tile string
+///
+/// # Examples
+/// ```
+/// use tenhou_log_parser::indicator_to_dora;
+/// assert_eq!(indicator_to_dora("9m").unwrap(), "1m");
+/// assert_eq!(indicator_to_dora("north").unwrap(), "east");
+/// assert_eq!(indicator_to_dora("red").unwrap(), "white");
+/// ```
+pub fn indicator_to_dora(indicator: &str) -> Result<String> {
+    let dora = tile_id_to_type(tile_string_to_id(indicator)?)?.dora();
+    Ok(tile_type_to_string(dora).into_owned())
+}
+
+/// Tile string of a tile type
+pub fn tile_type_to_string(tile: TileType) -> Cow<'static, str> {
+    tile_id_to_string(tile.index() as u32 * 4)
+}
+
+/// Display assets of one tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TileAsset {
+    /// Tile string as used in parser output
+    pub tile: &'static str,
+    pub red: bool,
+    pub glyph: char,
+    /// File stem used by common riichi tile sprite sets, e.g. "Pin5-Dora"
+    pub sprite: &'static str,
+}
+
+const fn asset(tile: &'static str, red: bool, glyph: char, sprite: &'static str) -> TileAsset {
+    TileAsset {
+        tile,
+        red,
+        glyph,
+        sprite,
+    }
+}
+
+/// Display assets of the 34 tile types in tile type order, followed by the
+/// red 5m, 5p and 5s
+///
+/// Unicode has no red five glyphs, so those share the plain five's glyph.
+/// The table is part of the public interface and is only ever extended.
+pub const TILE_ASSETS: [TileAsset; TILE_TYPES + 3] = [
+    asset("1m", false, '\u{1F007}', "Man1"),
+    asset("2m", false, '\u{1F008}', "Man2"),
+    asset("3m", false, '\u{1F009}', "Man3"),
+    asset("4m", false, '\u{1F00A}', "Man4"),
+    asset("5m", false, '\u{1F00B}', "Man5"),
+    asset("6m", false, '\u{1F00C}', "Man6"),
+    asset("7m", false, '\u{1F00D}', "Man7"),
+    asset("8m", false, '\u{1F00E}', "Man8"),
+    asset("9m", false, '\u{1F00F}', "Man9"),
+    asset("1p", false, '\u{1F019}', "Pin1"),
+    asset("2p", false, '\u{1F01A}', "Pin2"),
+    asset("3p", false, '\u{1F01B}', "Pin3"),
+    asset("4p", false, '\u{1F01C}', "Pin4"),
+    asset("5p", false, '\u{1F01D}', "Pin5"),
+    asset("6p", false, '\u{1F01E}', "Pin6"),
+    asset("7p", false, '\u{1F01F}', "Pin7"),
+    asset("8p", false, '\u{1F020}', "Pin8"),
+    asset("9p", false, '\u{1F021}', "Pin9"),
+    asset("1s", false, '\u{1F010}', "Sou1"),
+    asset("2s", false, '\u{1F011}', "Sou2"),
+    asset("3s", false, '\u{1F012}', "Sou3"),
+    asset("4s", false, '\u{1F013}', "Sou4"),
+    asset("5s", false, '\u{1F014}', "Sou5"),
+    asset("6s", false, '\u{1F015}', "Sou6"),
+    asset("7s", false, '\u{1F016}', "Sou7"),
+    asset("8s", false, '\u{1F017}', "Sou8"),
+    asset("9s", false, '\u{1F018}', "Sou9"),
+    asset("east", false, '\u{1F000}', "Ton"),
+    asset("south", false, '\u{1F001}', "Nan"),
+    asset("west", false, '\u{1F002}', "Shaa"),
+    asset("north", false, '\u{1F003}', "Pei"),
+    asset("white", false, '\u{1F006}', "Haku"),
+    asset("green", false, '\u{1F005}', "Hatsu"),
+    asset("red", false, '\u{1F004}', "Chun"),
+    asset("5m", true, '\u{1F00B}', "Man5-Dora"),
+    asset("5p", true, '\u{1F01D}', "Pin5-Dora"),
+    asset("5s", true, '\u{1F014}', "Sou5-Dora"),
+];
+
+/// Display assets of a tile ID, with red fives told apart
+pub fn tile_asset(id: u32) -> Result<&'static TileAsset> {
+    let tile = tile_id_to_type(id)?;
+    let index = tile.index();
+    Ok(if is_red_five(id) {
+        &TILE_ASSETS[TILE_TYPES + index / 9]
+    } else {
+        &TILE_ASSETS[index]
+    })
+}
+
+/// Compare tile strings in hand order; unknown tiles sort last
+pub fn compare_tiles(a: &str, b: &str) -> Ordering {
+    let key = |tile: &str| tile_string_to_id(tile).unwrap_or(u32::MAX);
+    key(a).cmp(&key(b))
+}
+
+/// Sort tile strings in hand order (m, p, s, winds, dragons)
+///
+/// Tile IDs need no helper: ID order already keeps each red five next to
+/// the other fives of its suit.
+///
+/// # Examples
+/// ```
+/// use tenhou_log_parser::tile::sort_hand;
+/// let mut hand = vec!["red", "3s", "east", "1m", "9p"];
+/// sort_hand(&mut hand);
+/// assert_eq!(hand, vec!["1m", "9p", "3s", "east", "red"]);
+/// ```
+pub fn sort_hand<S: AsRef<str>>(tiles: &mut [S]) {
+    tiles.sort_by(|a, b| compare_tiles(a.as_ref(), b.as_ref()));
+}
+
+/// Whether a tile ID is a red five in games played with aka
+pub fn is_red_five(id: u32) -> bool {
+    matches!(id, 16 | 52 | 88)
+}
+
+/// Per-type tile counts of tile strings, indexed 0-33 in m, p, s, honor order
+///
+/// # Examples
+/// ```
+/// use tenhou_log_parser::tile::tiles_to_counts;
+/// let counts = tiles_to_counts(&["1m", "1m", "red"]).unwrap();
+/// assert_eq!((counts[0], counts[33]), (2, 1));
+/// ```
+pub fn tiles_to_counts<S: AsRef<str>>(tiles: &[S]) -> Result<[u8; TILE_TYPES]> {
+    let mut counts = [0u8; TILE_TYPES];
+    for tile in tiles {
+        counts[tile_string_to_id(tile.as_ref())? as usize / 4] += 1;
+    }
+    Ok(counts)
+}
+
+/// Tile strings of a count vector, in tile order
+pub fn counts_to_tiles(counts: &[u8; TILE_TYPES]) -> Vec<String> {
+    counts
+        .iter()
+        .enumerate()
+        .flat_map(|(t, &n)| {
+            std::iter::repeat_n(tile_id_to_string(t as u32 * 4).into_owned(), n as usize)
+        })
+        .collect()
+}
+
+/// Per-type counts of tile IDs, plus whether the red five of each suit
+/// (m, p, s) is among them
+pub fn ids_to_counts(ids: &[u32]) -> Result<([u8; TILE_TYPES], [bool; 3])> {
+    let mut counts = [0u8; TILE_TYPES];
+    let mut aka = [false; 3];
+    for &id in ids {
+        if id >= 136 {
+            return Err(ParserError::InvalidTileId(id));
+        }
+        counts[id as usize / 4] += 1;
+        if is_red_five(id) {
+            aka[id as usize / 36] = true;
+        }
+    }
+    Ok((counts, aka))
+}
+
+/// Distinct tile IDs for a count vector, in tile order
+///
+/// A five takes the red copy only when its suit's aka flag is set (or all
+/// four copies are held). At most four copies of a type are returned.
+pub fn counts_to_ids(counts: &[u8; TILE_TYPES], aka: &[bool; 3]) -> Vec<u32> {
+    let mut ids = Vec::new();
+    for (t, &n) in counts.iter().enumerate() {
+        let five = t < 27 && t % 9 == 4;
+        let copies: [u32; 4] = if five && !aka[t / 9] {
+            [1, 2, 3, 0]
+        } else {
+            [0, 1, 2, 3]
+        };
+        ids.extend(copies.iter().take(n as usize).map(|c| t as u32 * 4 + c));
+    }
+    ids
+}
+
+/// Sort key and compact digit of a tile type index (0-33)
+fn compact_digit(tile_type: u32) -> (char, u32) {
+    let suit = ['m', 'p', 's', 'z'][(tile_type / 9).min(3) as usize];
+    let number = if tile_type >= 27 {
+        tile_type - 27 + 1
+    } else {
+        tile_type % 9 + 1
+    };
+    (suit, number)
+}
+
+fn write_compact(mut tiles: Vec<(u32, bool)>) -> String {
+    // Red fives sort just before the other fives of their suit
+    tiles.sort_by_key(|&(tile_type, red)| (tile_type, !red));
+    let mut out = String::new();
+    let mut pending: Option<char> = None;
+    for (tile_type, red) in tiles {
+        let (suit, number) = compact_digit(tile_type);
+        if let Some(previous) = pending.filter(|&p| p != suit) {
+            out.push(previous);
+        }
+        out.push(if red {
+            '0'
+        } else {
+            char::from(b'0' + number as u8)
+        });
+        pending = Some(suit);
+    }
+    out.extend(pending);
+    out
+}
+
+/// Render tile strings as a compact hand string such as "123m456p55s11z"
+///
+/// Honors are numbered 1-7 in the order east, south, west, north, white,
+/// green, red. Tile strings carry no aka, so fives are always written as 5.
+///
+/// # Examples
+/// ```
+/// use tenhou_log_parser::tile::tiles_to_compact;
+/// assert_eq!(tiles_to_compact(&["5p", "1m", "east", "red", "2m"]).unwrap(), "12m5p17z");
+/// ```
+pub fn tiles_to_compact<S: AsRef<str>>(tiles: &[S]) -> Result<String> {
+    let tiles = tiles
+        .iter()
+        .map(|t| Ok((tile_string_to_id(t.as_ref())? / 4, false)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(write_compact(tiles))
+}
+
+/// Render tile IDs as a compact hand string, writing red fives as 0
+pub fn ids_to_compact(ids: &[u32]) -> Result<String> {
+    let tiles = ids
+        .iter()
+        .map(|&id| {
+            if id >= 136 {
+                return Err(ParserError::InvalidTileId(id));
+            }
+            Ok((id / 4, is_red_five(id)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(write_compact(tiles))
+}
+
+/// Tile type index (0-33) and red flag of each tile in a compact string
+fn parse_compact_types(hand: &str) -> Result<Vec<(u32, bool)>> {
+    let mut tiles = Vec::new();
+    let mut digits: Vec<u32> = Vec::new();
+    for c in hand.chars() {
+        match c {
+            '0'..='9' => digits.push(c.to_digit(10).unwrap()),
+            'm' | 'p' | 's' | 'z' => {
+                if digits.is_empty() {
+                    return Err(ParserError::invalid_format(format!(
+                        "Suit without tiles in hand string: {}",
+                        hand
+                    )));
+                }
+                for digit in digits.drain(..) {
+                    let tile = match (c, digit) {
+                        ('z', 1..=7) => (27 + digit - 1, false),
+                        ('z', _) => {
+                            return Err(ParserError::invalid_format(format!(
+                                "Invalid honor {}z in hand string: {}",
+                                digit, hand
+                            )))
+                        }
+                        (_, 0) => (suit_offset(c) + 4, true),
+                        (_, n) => (suit_offset(c) + n - 1, false),
+                    };
+                    tiles.push(tile);
+                }
+            }
+            _ => {
+                return Err(ParserError::invalid_format(format!(
+                    "Unexpected '{}' in hand string: {}",
+                    c, hand
+                )))
+            }
+        }
+    }
+    if !digits.is_empty() {
+        return Err(ParserError::invalid_format(format!(
+            "Hand string ends without a suit: {}",
+            hand
+        )));
+    }
+    Ok(tiles)
+}
+
+fn suit_offset(suit: char) -> u32 {
+    match suit {
+        'm' => 0,
+        'p' => 9,
+        _ => 18,
+    }
+}
+
+/// Parse a compact hand string into tile strings, in the order written
+///
+/// A red five (0) becomes an ordinary five.
+///
+/// # Examples
+/// ```
+/// use tenhou_log_parser::tile::parse_compact;
+/// assert_eq!(parse_compact("406p1z").unwrap(), vec!["4p", "5p", "6p", "east"]);
+/// ```
+pub fn parse_compact(hand: &str) -> Result<Vec<String>> {
+    Ok(parse_compact_types(hand)?
+        .into_iter()
+        .map(|(tile_type, _)| tile_id_to_string(tile_type * 4).into_owned())
+        .collect())
+}
+
+/// Parse a compact hand string into distinct tile IDs
+///
+/// Red fives get the red copy's ID and other fives skip it. Fails if the
+/// string holds more copies of a tile than exist.
+pub fn parse_compact_ids(hand: &str) -> Result<Vec<u32>> {
+    let mut used = [0u32; 34];
+    parse_compact_types(hand)?
+        .into_iter()
+        .map(|(tile_type, red)| {
+            let five = tile_type < 27 && tile_type % 9 == 4;
+            let copy = if red {
+                // The red copy is always copy 0
+                if used[tile_type as usize] & 1 != 0 {
+                    return Err(ParserError::invalid_format(format!(
+                        "More than one red five in hand string: {}",
+                        hand
+                    )));
+                }
+                0
+            } else {
+                let start = u32::from(five);
+                match (start..4).find(|c| used[tile_type as usize] & (1 << c) == 0) {
+                    Some(copy) => copy,
+                    None => {
+                        return Err(ParserError::invalid_format(format!(
+                            "Too many copies of a tile in hand string: {}",
+                            hand
+                        )))
+                    }
+                }
+            };
+            used[tile_type as usize] |= 1 << copy;
+            Ok(tile_type * 4 + copy)
         })
         .collect()
 }
@@ -246,9 +1061,205 @@ mod tests {
         assert_eq!(tile_id_to_type(132).unwrap(), TileType::Red);
     }
 
+    #[test]
+    fn test_tile_type_predicates() {
+        assert_eq!(TileType::Pin(5).suit(), Suit::Pin);
+        assert_eq!(TileType::Red.suit(), Suit::Honor);
+        assert_eq!(TileType::Sou(7).number(), Some(7));
+        assert_eq!(TileType::East.number(), None);
+
+        assert!(TileType::Man(1).is_terminal());
+        assert!(TileType::Sou(9).is_terminal());
+        assert!(!TileType::Pin(5).is_terminal());
+        assert!(!TileType::North.is_terminal());
+
+        assert!(TileType::Green.is_honor());
+        assert!(!TileType::Man(9).is_honor());
+        assert!(TileType::Man(9).is_yaochuu());
+        assert!(TileType::West.is_yaochuu());
+        assert!(!TileType::Pin(2).is_yaochuu());
+        assert!(TileType::Pin(2).is_simple());
+
+        assert!(TileType::North.is_wind());
+        assert!(!TileType::White.is_wind());
+        assert!(TileType::White.is_dragon());
+    }
+
+    #[test]
+    fn test_indicator_to_dora() {
+        for (indicator, dora) in [
+            ("1m", "2m"),
+            ("9m", "1m"),
+            ("9p", "1p"),
+            ("4s", "5s"),
+            ("9s", "1s"),
+            ("east", "south"),
+            ("north", "east"),
+            ("white", "green"),
+            ("red", "white"),
+        ] {
+            assert_eq!(indicator_to_dora(indicator).unwrap(), dora);
+        }
+        assert!(indicator_to_dora("0m").is_err());
+        assert_eq!(TileType::West.dora(), TileType::North);
+    }
+
+    #[test]
+    fn test_count_vectors() {
+        let counts = tiles_to_counts(&["9s", "1m", "east", "1m", "red"]).unwrap();
+        assert_eq!(counts.iter().sum::<u8>(), 5);
+        assert_eq!(
+            (counts[0], counts[26], counts[27], counts[33]),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(
+            counts_to_tiles(&counts),
+            vec!["1m", "1m", "9s", "east", "red"]
+        );
+        assert!(tiles_to_counts(&["10m"]).is_err());
+
+        // A red 5p and a plain 5s
+        let (counts, aka) = ids_to_counts(&[52, 89, 0]).unwrap();
+        assert_eq!((counts[0], counts[13], counts[22]), (1, 1, 1));
+        assert_eq!(aka, [false, true, false]);
+        assert_eq!(counts_to_ids(&counts, &aka), vec![0, 52, 89]);
+        assert!(ids_to_counts(&[136]).is_err());
+
+        let mut fives = [0u8; TILE_TYPES];
+        fives[4] = 4;
+        assert_eq!(counts_to_ids(&fives, &[false; 3]), vec![17, 18, 19, 16]);
+    }
+
+    #[test]
+    fn test_tile_assets() {
+        for (index, asset) in TILE_ASSETS.iter().enumerate().take(TILE_TYPES) {
+            assert_eq!(tile_string_to_id(asset.tile).unwrap() / 4, index as u32);
+            assert_eq!(tile_id_to_type(index as u32 * 4).unwrap().index(), index);
+        }
+        assert_eq!(TileType::Man(1).glyph(), '🀇');
+        assert_eq!(TileType::Red.glyph(), '🀄');
+        assert_eq!(TileType::Pin(5).sprite(true), "Pin5-Dora");
+        assert_eq!(TileType::Pin(4).sprite(true), "Pin4");
+        assert_eq!(TileType::North.sprite(false), "Pei");
+        assert_eq!(tile_asset(88).unwrap().sprite, "Sou5-Dora");
+        assert_eq!(tile_asset(89).unwrap().sprite, "Sou5");
+        assert!(tile_asset(136).is_err());
+
+        let json = serde_json::to_value(TILE_ASSETS[34]).unwrap();
+        assert_eq!(json["tile"], "5m");
+        assert_eq!(json["glyph"], "🀋");
+    }
+
+    #[test]
+    fn test_tile_ordering() {
+        assert!(TileType::Man(9) < TileType::Pin(1));
+        assert!(TileType::Pin(9) < TileType::Sou(1));
+        assert!(TileType::Sou(9) < TileType::East);
+        assert!(TileType::North < TileType::White);
+        assert!(TileType::Green < TileType::Red);
+
+        let mut hand = vec!["west", "??", "5s", "1s", "5m", "white", "5m"];
+        sort_hand(&mut hand);
+        assert_eq!(hand, vec!["5m", "5m", "1s", "5s", "west", "white", "??"]);
+
+        let mut ids = vec![53, 17, 52, 16];
+        ids.sort_unstable();
+        assert_eq!(ids_to_compact(&ids).unwrap(), "05m05p");
+    }
+
+    #[test]
+    fn test_compact_hand_strings() {
+        let hand = [
+            "3m", "1m", "2m", "5p", "4p", "6p", "5s", "5s", "east", "east",
+        ];
+        assert_eq!(tiles_to_compact(&hand).unwrap(), "123m456p55s11z");
+        assert_eq!(tiles_to_compact::<&str>(&[]).unwrap(), "");
+        assert!(tiles_to_compact(&["nope"]).is_err());
+
+        // 5p red (52) sorts before a plain 5p (53)
+        assert_eq!(ids_to_compact(&[53, 48, 52, 56, 132]).unwrap(), "4056p7z");
+        assert!(ids_to_compact(&[136]).is_err());
+
+        assert_eq!(
+            parse_compact("123m406p55s11z").unwrap(),
+            vec!["1m", "2m", "3m", "4p", "5p", "6p", "5s", "5s", "east", "east"]
+        );
+        let ids = parse_compact_ids("406p55s").unwrap();
+        assert_eq!(ids, vec![48, 52, 56, 89, 90]);
+        assert_eq!(ids_to_compact(&ids).unwrap(), "406p55s");
+        assert_eq!(
+            parse_compact_ids("5555m").unwrap_err().to_string(),
+            "Invalid format: Too many copies of a tile in hand string: 5555m"
+        );
+        assert_eq!(parse_compact_ids("0555m").unwrap(), vec![16, 17, 18, 19]);
+
+        for bad in ["123", "m", "8z", "12x", "00m"] {
+            assert!(parse_compact_ids(bad).is_err(), "{}", bad);
+        }
+    }
+
     #[test]
     fn test_tile_id_to_type_invalid() {
         assert!(tile_id_to_type(136).is_err());
         assert!(tile_id_to_type(1000).is_err());
     }
+
+    #[test]
+    fn test_tile() {
+        let red = Tile::from_id(52);
+        assert_eq!(red.tile_type(), Some(TileType::Pin(5)));
+        assert_eq!(
+            (red.suit(), red.rank(), red.copy()),
+            (Some(Suit::Pin), Some(5), Some(0))
+        );
+        assert!(red.is_red());
+        // A red five equals the plain fives and sorts with them
+        assert_eq!(red, Tile::from_id(55));
+        assert_eq!(red, "5p");
+        assert!(Tile::from_id(0) < red && red < Tile::from_id(56));
+        assert!(Tile::from_id(132) < Tile::HIDDEN && Tile::from_id(200) < Tile::HIDDEN);
+
+        assert_eq!(Tile::from_id(200).to_string(), "unknown_200");
+        assert_eq!(Tile::from_id(200), "unknown_200");
+        assert_eq!(&*Tile::from_id(200), "unknown");
+        assert_eq!(Tile::HIDDEN.to_string(), "?");
+        assert_eq!(Tile::HIDDEN.copy(), None);
+
+        let plain: Tile = "5p".parse().unwrap();
+        assert_eq!((plain.id(), plain.is_red()), (53, false));
+        assert_eq!("east".parse::<Tile>().unwrap().id(), 108);
+        assert!("?".parse::<Tile>().unwrap().is_hidden());
+        assert_eq!("unknown_200".parse::<Tile>().unwrap().id(), 200);
+        assert!("5x".parse::<Tile>().is_err());
+    }
+
+    #[test]
+    fn test_tile_serde() {
+        let tiles = [Tile::from_id(16), Tile::from_id(108), Tile::HIDDEN];
+        let json = serde_json::to_string(&tiles).unwrap();
+        assert_eq!(json, r#"["5m","east","?"]"#);
+        let back: Vec<Tile> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, tiles);
+        assert_eq!(back[0].id(), 17);
+
+        // Tile IDs read back exactly
+        let back: Vec<Tile> = serde_json::from_str("[16,108]").unwrap();
+        assert_eq!((back[0].id(), back[0].is_red()), (16, true));
+        assert!(serde_json::from_str::<Tile>(r#""10m""#).is_err());
+    }
+
+    #[test]
+    fn test_tile_format_object() {
+        let tiles = [Tile::from_id(16), Tile::HIDDEN];
+        let json = with_tile_format(TileFormat::Object, || {
+            serde_json::to_string(&tiles).unwrap()
+        });
+        assert_eq!(json, r#"[{"id":16,"name":"5m"},{"name":"?"}]"#);
+        // The format only holds inside the closure
+        assert_eq!(serde_json::to_string(&tiles).unwrap(), r#"["5m","?"]"#);
+
+        let back: Vec<Tile> = serde_json::from_str(&json).unwrap();
+        assert_eq!((back[0].id(), back[0].is_red()), (16, true));
+        assert!(back[1].is_hidden());
+    }
 }
@@ -0,0 +1,273 @@
+//! Plain-text narration of a whole game
+//!
+//! Every line starts with the round ("E1", "S3") and seats are named by
+//! their wind in that round, the way hands are discussed on forums:
+//!
+//! ```text
+//! E1: South draws 5p, discards west
+//! ```
+//!
+//! Each round opens with the players behind the winds and their scores, and
+//! the game closes with the final standings.
+
+use std::fmt::Write;
+
+use crate::models::{Event, Init, KanType, ParserOutput, Round, RyuukyokuReason};
+use crate::stats::{final_scores, standings, SCORE_UNIT};
+use crate::tile::Tile;
+
+const WINDS: [&str; 4] = ["East", "South", "West", "North"];
+
+/// Narrate `output` turn by turn
+pub fn to_transcript(output: &ParserOutput) -> String {
+    let mut text = String::new();
+    for round in &output.rounds {
+        write_round(&mut text, output, round);
+        text.push('\n');
+    }
+    if let (Some(scores), Some(order)) = (final_scores(output), standings(output)) {
+        text.push_str("Final standings:\n");
+        for (place, seat) in order.iter().enumerate() {
+            let _ = writeln!(
+                text,
+                "{}. {} {}",
+                place + 1,
+                name(output, *seat),
+                scores[*seat as usize]
+            );
+        }
+    }
+    text
+}
+
+/// Short round name such as "E1" or "S3"
+fn round_code(init: &Init) -> String {
+    format!("{}{}", &init.wind().english()[..1], init.hand_number())
+}
+
+fn name(output: &ParserOutput, seat: u8) -> &str {
+    output
+        .players
+        .get(seat as usize)
+        .map(|p| p.player_id.as_str())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("?")
+}
+
+fn ryuukyoku_name(reason: &RyuukyokuReason) -> &'static str {
+    match reason {
+        RyuukyokuReason::Normal => "Exhaustive draw",
+        RyuukyokuReason::Yao9 => "Abortive draw: nine terminals",
+        RyuukyokuReason::Kaze4 => "Abortive draw: four winds",
+        RyuukyokuReason::Reach4 => "Abortive draw: four riichi",
+        RyuukyokuReason::Ron3 => "Abortive draw: triple ron",
+        RyuukyokuReason::Kan4 => "Abortive draw: four kans",
+    }
+}
+
+/// Tiles separated by spaces
+fn joined(tiles: &[Tile]) -> String {
+    tiles
+        .iter()
+        .map(Tile::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn write_round(text: &mut String, output: &ParserOutput, round: &Round) {
+    let init = &round.init;
+    let code = round_code(init);
+    let wind = |seat: u8| WINDS[(seat as usize + 4 - round.dealer_seat as usize % 4) % 4];
+
+    let _ = writeln!(
+        text,
+        "{}: {} {}, {} honba, {} riichi stick(s), dora indicator {}",
+        code,
+        init.wind().english(),
+        init.hand_number(),
+        init.honba,
+        init.kyoutaku,
+        crate::tile::tile_id_to_string(init.dora_indicator)
+    );
+    let seats: Vec<String> = (0..4u8)
+        .map(|offset| (round.dealer_seat + offset) % 4)
+        .map(|seat| {
+            format!(
+                "{} {} ({})",
+                wind(seat),
+                name(output, seat),
+                init.initial_scores[seat as usize] * SCORE_UNIT
+            )
+        })
+        .collect();
+    let _ = writeln!(text, "{}: {}", code, seats.join(", "));
+
+    // A draw waits for the discard that follows so both share a line
+    let mut pending_draw: Option<(u8, &str)> = None;
+    for event in &round.events {
+        if !matches!(event, Event::Draw { .. } | Event::Discard { .. }) {
+            flush_draw(text, &code, pending_draw.take(), wind);
+        }
+        let line = match event {
+            Event::Draw { seat, tile, .. } => {
+                flush_draw(text, &code, pending_draw.take(), wind);
+                pending_draw = Some((*seat, tile));
+                continue;
+            }
+            Event::Discard {
+                seat,
+                tile,
+                is_riichi,
+                ..
+            } => {
+                let action = if *is_riichi {
+                    "declares riichi, discards"
+                } else {
+                    "discards"
+                };
+                match pending_draw.take() {
+                    Some((drawer, drawn)) if drawer == *seat => {
+                        format!("{} draws {}, {} {}", wind(*seat), drawn, action, tile)
+                    }
+                    other => {
+                        flush_draw(text, &code, other, wind);
+                        format!("{} {} {}", wind(*seat), action, tile)
+                    }
+                }
+            }
+            Event::Chi {
+                who, tiles, from, ..
+            } => format!(
+                "{} calls chi {} from {}",
+                wind(*who),
+                joined(tiles),
+                wind(*from)
+            ),
+            Event::Pon {
+                who, tiles, from, ..
+            } => format!(
+                "{} calls pon {} from {}",
+                wind(*who),
+                joined(tiles),
+                wind(*from)
+            ),
+            Event::Kan {
+                who,
+                tiles,
+                kan_type,
+                from,
+                ..
+            } => match (kan_type, from) {
+                (KanType::Ankan, _) => {
+                    format!("{} declares closed kan {}", wind(*who), joined(tiles))
+                }
+                (KanType::Kakan, _) => format!("{} adds to kan {}", wind(*who), joined(tiles)),
+                (KanType::Minkan, Some(from)) => format!(
+                    "{} calls kan {} from {}",
+                    wind(*who),
+                    joined(tiles),
+                    wind(*from)
+                ),
+                (KanType::Minkan, None) => format!("{} calls kan {}", wind(*who), joined(tiles)),
+            },
+            Event::Dora { indicator, .. } => format!("New dora indicator {}", indicator),
+            Event::Agari {
+                who,
+                from,
+                han,
+                fu,
+                yakus,
+                scores,
+                ..
+            } => {
+                let how = if who == from {
+                    "by tsumo".to_string()
+                } else {
+                    format!("by ron from {}", wind(*from))
+                };
+                let yaku: Vec<String> = yakus
+                    .iter()
+                    .filter(|y| y.value > 0)
+                    .map(|y| format!("{} {}", y.name, y.value))
+                    .collect();
+                format!(
+                    "{} wins {}: {} han {} fu ({}), +{}",
+                    wind(*who),
+                    how,
+                    han,
+                    fu,
+                    yaku.join(", "),
+                    scores[*who as usize % 4] * SCORE_UNIT
+                )
+            }
+            Event::Ryuukyoku { reason, scores, .. } => {
+                let changes: Vec<String> = (0..4)
+                    .filter(|&seat| scores[seat] != 0)
+                    .map(|seat| format!("{} {:+}", wind(seat as u8), scores[seat] * SCORE_UNIT))
+                    .collect();
+                if changes.is_empty() {
+                    ryuukyoku_name(reason).to_string()
+                } else {
+                    format!("{} ({})", ryuukyoku_name(reason), changes.join(", "))
+                }
+            }
+            // Riichi shows on the discard; unknown tags have nothing to say
+            _ => continue,
+        };
+        let _ = writeln!(text, "{}: {}", code, line);
+    }
+    flush_draw(text, &code, pending_draw, wind);
+}
+
+/// Write a draw that no discard followed, such as one before a tsumo
+fn flush_draw(
+    text: &mut String,
+    code: &str,
+    draw: Option<(u8, &str)>,
+    wind: impl Fn(u8) -> &'static str,
+) {
+    if let Some((seat, tile)) = draw {
+        let _ = writeln!(text, "{}: {} draws {}", code, wind(seat), tile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_transcript() {
+        // Seat 1 deals; seat 3 tsumos after a pon
+        let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/>
+<INIT seed="4,1,0,1,2,52" ten="250,250,250,250" oya="1" hai0="" hai1="" hai2="" hai3=""/>
+<U53/><E20/><V54/><F108/>
+<N who="3" m="41483"/><G124/>
+<T60/><D60/>
+<W64/><AGARI who="3" fromWho="3" ten="30,1000,1" yaku="0,1" sc="250,-3,250,-5,250,-2,250,10"/>
+</mjloggm>"#;
+        let output = parse_mjlog(Cursor::new(xml)).unwrap();
+        let text = to_transcript(&output);
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "S1: South 1, 1 honba, 0 riichi stick(s), dora indicator 5p"
+        );
+        assert_eq!(
+            lines[1],
+            "S1: East B (25000), South C (25000), West D (25000), North A (25000)"
+        );
+        assert_eq!(lines[2], "S1: East draws 5p, discards 6m");
+        assert_eq!(lines[3], "S1: South draws 5p, discards east");
+        assert_eq!(lines[4], "S1: West calls pon east east east from South");
+        assert_eq!(lines[5], "S1: West discards white");
+        assert_eq!(lines[6], "S1: North draws 7p, discards 7p");
+        assert_eq!(lines[7], "S1: West draws 8p");
+        assert!(lines[8].starts_with("S1: West wins by tsumo: 1 han 30 fu ("));
+        assert!(lines[8].ends_with("+1000"));
+        assert_eq!(lines[10], "Final standings:");
+        assert_eq!(lines[11], "1. D 26000");
+    }
+}
@@ -0,0 +1,558 @@
+use std::borrow::Borrow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Event, ParserOutput, Player, Round, ScoreWarning};
+use crate::tile::Tile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Parse,   // Warnings recorded while parsing
+    Players, // Player list
+    Rounds,  // Round structure
+    Tiles,   // Tile strings and hand sizes
+    Events,  // Event ordering and seats
+    Scores,  // Score deltas
+}
+
+/// Stable identifier of what a validation issue is about
+///
+/// Serialized in kebab-case; codes are never renamed or reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueCode {
+    ParseWarning,
+    PlayerCount,
+    SeatMismatch,
+    AnonymousPlayer,
+    NoRounds,
+    HandSize,
+    InvalidTile,
+    SeatOutOfRange,
+    RiichiWithoutDeclaration,
+    InvalidRiichiStep,
+    ScoreSum,
+    ScoreMismatch,
+    UnsettledRound,
+}
+
+impl IssueCode {
+    /// The code as serialized, e.g. "invalid-tile"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IssueCode::ParseWarning => "parse-warning",
+            IssueCode::PlayerCount => "player-count",
+            IssueCode::SeatMismatch => "seat-mismatch",
+            IssueCode::AnonymousPlayer => "anonymous-player",
+            IssueCode::NoRounds => "no-rounds",
+            IssueCode::HandSize => "hand-size",
+            IssueCode::InvalidTile => "invalid-tile",
+            IssueCode::SeatOutOfRange => "seat-out-of-range",
+            IssueCode::RiichiWithoutDeclaration => "riichi-without-declaration",
+            IssueCode::InvalidRiichiStep => "invalid-riichi-step",
+            IssueCode::ScoreSum => "score-sum",
+            IssueCode::ScoreMismatch => "score-mismatch",
+            IssueCode::UnsettledRound => "unsettled-round",
+        }
+    }
+
+    pub fn category(self) -> Category {
+        match self {
+            IssueCode::ParseWarning => Category::Parse,
+            IssueCode::PlayerCount | IssueCode::SeatMismatch | IssueCode::AnonymousPlayer => {
+                Category::Players
+            }
+            IssueCode::NoRounds | IssueCode::UnsettledRound => Category::Rounds,
+            IssueCode::HandSize | IssueCode::InvalidTile => Category::Tiles,
+            IssueCode::SeatOutOfRange
+            | IssueCode::RiichiWithoutDeclaration
+            | IssueCode::InvalidRiichiStep => Category::Events,
+            IssueCode::ScoreSum | IssueCode::ScoreMismatch => Category::Scores,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub category: Category,
+    pub code: IssueCode,
+    /// Index into `ParserOutput::rounds`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round: Option<usize>,
+    /// Index into `Round::events`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// True when no issue of `Severity::Error` was found
+    pub fn is_valid(&self) -> bool {
+        self.count(Severity::Error) == 0
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == severity)
+            .count()
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: IssueCode,
+        location: (Option<usize>, Option<usize>),
+        message: impl Into<String>,
+    ) {
+        self.issues.push(ValidationIssue {
+            severity,
+            category: code.category(),
+            code,
+            round: location.0,
+            event: location.1,
+            message: message.into(),
+        });
+    }
+}
+
+/// Run all integrity checks against a parsed log
+///
+/// # Examples
+/// ```
+/// use std::io::Cursor;
+/// use tenhou_log_parser::{parse_mjlog, validate};
+///
+/// let xml = r#"<mjloggm ver="2.3"><UN n0="A" n1="B" n2="C" n3="D"/></mjloggm>"#;
+/// let output = parse_mjlog(Cursor::new(xml)).unwrap();
+/// assert!(validate(&output).is_valid());
+/// ```
+pub fn validate(output: &ParserOutput) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    for warning in &output.warnings {
+        report.push(
+            Severity::Warning,
+            IssueCode::ParseWarning,
+            (None, None),
+            warning.to_string(),
+        );
+    }
+
+    check_players(output, &mut report);
+
+    if output.rounds.is_empty() {
+        report.push(
+            Severity::Warning,
+            IssueCode::NoRounds,
+            (None, None),
+            "Log contains no rounds",
+        );
+    }
+
+    let seats = output.players.len().max(1) as u8;
+    for (i, round) in output.rounds.iter().enumerate() {
+        check_round(i, round, seats, &mut report);
+    }
+
+    for warning in check_scores(output) {
+        let index = output
+            .rounds
+            .iter()
+            .position(|r| r.round_id == warning.round_id);
+        report.push(
+            Severity::Warning,
+            IssueCode::ScoreMismatch,
+            (index, None),
+            format!(
+                "Seat {} ends {} with {}, but {} is recorded",
+                warning.seat, warning.round_id, warning.expected, warning.actual
+            ),
+        );
+    }
+
+    report
+}
+
+/// A riichi stick, in units of 100 points
+const RIICHI_DEPOSIT: i32 = 10;
+
+/// Replay each round's score changes from its starting scores and compare
+/// the outcome with the scores the next round starts with, or with the
+/// final result after the last round
+///
+/// A round is only compared with the round numbered right after it, so
+/// rounds left out by a round filter are not reported as mismatches.
+pub fn check_scores(output: &ParserOutput) -> Vec<ScoreWarning> {
+    check_round_scores(output, output.rounds.iter())
+}
+
+/// [`check_scores`] over `rounds`, the rounds of `output` taken one at a
+/// time, as read back from a spool file
+pub(crate) fn check_round_scores<R: Borrow<Round>>(
+    output: &ParserOutput,
+    rounds: impl IntoIterator<Item = R>,
+) -> Vec<ScoreWarning> {
+    let seats = output.rules.players() as usize;
+    let mut warnings = Vec::new();
+    let mut rounds = rounds.into_iter().peekable();
+
+    while let Some(round) = rounds.next() {
+        let round = round.borrow();
+        let (mut expected, deposits) = replay_scores(round);
+        let actual = match rounds.peek().map(Borrow::borrow) {
+            Some(next) if round_number(next) == round_number(round).map(|n| n + 1) => {
+                next.init.initial_scores
+            }
+            Some(_) => continue,
+            None => match &output.result {
+                Some(result) => {
+                    // Sticks still on the table go to the winner of the game
+                    if let Some(top) = result.placements.iter().position(|&p| p == 1) {
+                        expected[top] += deposits;
+                    }
+                    result.scores
+                }
+                None => continue,
+            },
+        };
+
+        for seat in 0..seats {
+            if expected[seat] != actual[seat] {
+                warnings.push(ScoreWarning {
+                    round_id: round.round_id.clone(),
+                    seat: seat as u8,
+                    expected: expected[seat],
+                    actual: actual[seat],
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Scores at the end of a round, and the riichi sticks left on the table
+fn replay_scores(round: &Round) -> ([i32; 4], i32) {
+    let mut scores = round.init.initial_scores;
+    let mut deposits = round.init.kyoutaku as i32 * RIICHI_DEPOSIT;
+    for event in &round.events {
+        match event {
+            Event::Reach { who, step: 2, .. } => {
+                scores[*who as usize % 4] -= RIICHI_DEPOSIT;
+                deposits += RIICHI_DEPOSIT;
+            }
+            Event::Agari {
+                scores: changes, ..
+            } => {
+                for (score, change) in scores.iter_mut().zip(changes) {
+                    *score += change;
+                }
+                // The first winner collects the sticks
+                deposits = 0;
+            }
+            Event::Ryuukyoku {
+                scores: changes, ..
+            } => {
+                for (score, change) in scores.iter_mut().zip(changes) {
+                    *score += change;
+                }
+            }
+            _ => {}
+        }
+    }
+    (scores, deposits)
+}
+
+/// N of a "Round N" round ID
+fn round_number(round: &Round) -> Option<usize> {
+    round.round_id.strip_prefix("Round ")?.parse().ok()
+}
+
+fn check_players(output: &ParserOutput, report: &mut ValidationReport) {
+    if !(3..=4).contains(&output.players.len()) {
+        report.push(
+            Severity::Error,
+            IssueCode::PlayerCount,
+            (None, None),
+            format!("Expected 3 or 4 players, found {}", output.players.len()),
+        );
+    }
+
+    for (i, player) in output.players.iter().enumerate() {
+        if player.seat as usize != i {
+            report.push(
+                Severity::Error,
+                IssueCode::SeatMismatch,
+                (None, None),
+                format!("Player at index {} has seat {}", i, player.seat),
+            );
+        }
+        if player.player_id == Player::PLACEHOLDER_ID {
+            report.push(
+                Severity::Info,
+                IssueCode::AnonymousPlayer,
+                (None, None),
+                format!("Seat {} is an anonymous player", i),
+            );
+        }
+    }
+}
+
+fn check_round(index: usize, round: &Round, seats: u8, report: &mut ValidationReport) {
+    let at_round = (Some(index), None);
+
+    for (seat, hand) in round.init.initial_hands.iter().enumerate() {
+        if !hand.is_empty() && hand.len() != 13 {
+            report.push(
+                Severity::Error,
+                IssueCode::HandSize,
+                at_round,
+                format!(
+                    "Seat {} starts with {} tiles, expected 13",
+                    seat,
+                    hand.len()
+                ),
+            );
+        }
+        for tile in hand {
+            check_tile(*tile, at_round, report);
+        }
+    }
+
+    let mut declared = [false; 4];
+    let mut sticks = round.init.kyoutaku as i32;
+    let mut settled = false;
+
+    for (j, event) in round.events.iter().enumerate() {
+        let at = (Some(index), Some(j));
+
+        if let Some(seat) = event.seat() {
+            if seat >= seats {
+                report.push(
+                    Severity::Error,
+                    IssueCode::SeatOutOfRange,
+                    at,
+                    format!("Seat {} is out of range", seat),
+                );
+                continue;
+            }
+        }
+
+        match event {
+            Event::Draw { tile, .. } | Event::Discard { tile, .. } => check_tile(*tile, at, report),
+            Event::Dora { indicator, .. } => check_tile(*indicator, at, report),
+            Event::Reach { who, step, .. } => match step {
+                1 => declared[*who as usize] = true,
+                2 if declared[*who as usize] => sticks += 1,
+                2 => report.push(
+                    Severity::Error,
+                    IssueCode::RiichiWithoutDeclaration,
+                    at,
+                    format!("Riichi of seat {} accepted without declaration", who),
+                ),
+                _ => report.push(
+                    Severity::Error,
+                    IssueCode::InvalidRiichiStep,
+                    at,
+                    format!("Invalid riichi step {}", step),
+                ),
+            },
+            Event::Agari { scores, .. } => {
+                // Deposited riichi sticks go to the first winner only
+                let expected = if settled { 0 } else { sticks * 10 };
+                check_score_sum(scores, expected, at, report);
+                settled = true;
+            }
+            Event::Ryuukyoku { scores, .. } => {
+                check_score_sum(scores, 0, at, report);
+                settled = true;
+            }
+            _ => {}
+        }
+    }
+
+    if !settled {
+        report.push(
+            Severity::Warning,
+            IssueCode::UnsettledRound,
+            at_round,
+            "Round does not end with AGARI or RYUUKYOKU",
+        );
+    }
+}
+
+fn check_tile(tile: Tile, at: (Option<usize>, Option<usize>), report: &mut ValidationReport) {
+    if !tile.is_known() {
+        report.push(
+            Severity::Error,
+            IssueCode::InvalidTile,
+            at,
+            format!("Invalid tile: {}", tile),
+        );
+    }
+}
+
+fn check_score_sum(
+    scores: &[i32; 4],
+    expected: i32,
+    at: (Option<usize>, Option<usize>),
+    report: &mut ValidationReport,
+) {
+    let sum: i32 = scores.iter().sum();
+    if sum != expected {
+        report.push(
+            Severity::Warning,
+            IssueCode::ScoreSum,
+            at,
+            format!("Score changes sum to {}, expected {}", sum, expected),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mjlog;
+    use std::io::Cursor;
+
+    fn parse(xml: &str) -> ParserOutput {
+        parse_mjlog(Cursor::new(xml.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_valid_log() {
+        let output = parse(
+            r#"<mjloggm ver="2.3">
+    <UN n0="A" n1="B" n2="C" n3="D"/>
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4,8,12,16,20,24,28,32,36,40,44,48" hai1="1,5,9,13,17,21,25,29,33,37,41,45,49" hai2="2,6,10,14,18,22,26,30,34,38,42,46,50" hai3="3,7,11,15,19,23,27,31,35,39,43,47,51"/>
+    <T52/>
+    <D52/>
+    <REACH who="0" step="1"/>
+    <REACH who="0" step="2" ten="240,250,250,250"/>
+    <AGARI who="0" fromWho="1" ten="30,1000,0" sc="240,20,250,-10,250,0,250,0"/>
+</mjloggm>"#,
+        );
+
+        let report = validate(&output);
+        assert!(report.is_valid(), "{:?}", report.issues);
+        assert!(report.issues.is_empty());
+    }
+
+    #[test]
+    fn test_detects_problems() {
+        let output = parse(
+            r#"<mjloggm ver="2.3">
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0,4" hai1="1" hai2="2" hai3="3"/>
+    <T200/>
+    <REACH who="2" step="2"/>
+    <RYUUKYOKU sc="250,10,250,0,250,0,250,0" type="nm"/>
+</mjloggm>"#,
+        );
+
+        let report = validate(&output);
+        assert!(!report.is_valid());
+
+        let has = |category: Category, severity: Severity| {
+            report
+                .issues
+                .iter()
+                .any(|i| i.category == category && i.severity == severity)
+        };
+        assert!(has(Category::Parse, Severity::Warning)); // missing UN
+        assert!(has(Category::Players, Severity::Info));
+        assert!(has(Category::Tiles, Severity::Error)); // short hands and unknown tile
+        assert!(has(Category::Events, Severity::Error)); // riichi without declaration
+        assert!(has(Category::Scores, Severity::Warning));
+
+        let tile_issue = report
+            .issues
+            .iter()
+            .find(|i| i.message.contains("unknown_200"))
+            .unwrap();
+        assert_eq!(tile_issue.code, IssueCode::InvalidTile);
+        assert_eq!(tile_issue.round, Some(0));
+        assert_eq!(tile_issue.event, Some(0));
+    }
+
+    #[test]
+    fn test_check_scores() {
+        // Seat 0's riichi stick is still on the table when the game ends
+        // in a draw, and goes to seat 0 as the top of the game
+        let xml = r#"<mjloggm ver="2.3">
+    <GO type="169" lobby="0"/>
+    <UN n0="A" n1="B" n2="C" n3="D"/>
+    <INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <REACH who="0" step="1"/>
+    <REACH who="0" step="2" ten="240,250,250,250"/>
+    <RYUUKYOKU sc="240,15,250,-5,250,-5,250,-5" type="nm"/>
+    <INIT seed="0,1,1,1,2,52" ten="255,245,245,245" oya="0" hai0="" hai1="" hai2="" hai3=""/>
+    <RYUUKYOKU sc="255,0,245,0,245,0,245,0" owari="265,26.5,245,-5.5,245,-15.5,245,-25.5"/>
+</mjloggm>"#;
+        let options = crate::ParserOptions {
+            validate_scores: true,
+            ..Default::default()
+        };
+        let mut output = crate::parse_mjlog_with_options(Cursor::new(xml), &options).unwrap();
+        assert!(
+            output.score_warnings.is_empty(),
+            "{:?}",
+            output.score_warnings
+        );
+
+        output.rounds[1].init.initial_scores[3] = 250;
+        let warning = |round: &str, expected, actual| ScoreWarning {
+            round_id: round.to_string(),
+            seat: 3,
+            expected,
+            actual,
+        };
+        assert_eq!(
+            check_scores(&output),
+            vec![warning("Round 1", 245, 250), warning("Round 2", 250, 245)]
+        );
+        assert!(validate(&output)
+            .issues
+            .iter()
+            .any(|i| i.code == IssueCode::ScoreMismatch && i.round == Some(1)));
+
+        // Rounds filtered out in between break the chain
+        output.rounds[1].round_id = "Round 3".to_string();
+        output.result = None;
+        assert!(check_scores(&output).is_empty());
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let mut report = ValidationReport::default();
+        report.push(
+            Severity::Error,
+            IssueCode::InvalidTile,
+            (Some(1), None),
+            "Invalid tile: x",
+        );
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["issues"][0]["severity"], "error");
+        assert_eq!(json["issues"][0]["category"], "tiles");
+        assert_eq!(json["issues"][0]["code"], "invalid-tile");
+        assert_eq!(json["issues"][0]["code"], IssueCode::InvalidTile.as_str());
+        assert_eq!(json["issues"][0]["round"], 1);
+        assert!(json["issues"][0].get("event").is_none());
+    }
+}
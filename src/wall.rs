@@ -0,0 +1,369 @@
+//! Wall reconstruction from the SHUFFLE seed
+//!
+//! Tenhou logs carry the seed of the game's random number generator in
+//! `<SHUFFLE seed="mt19937ar-sha512-n288-base64,...">`. Each round draws 288
+//! words from an MT19937 generator seeded with the decoded key, hashes them
+//! with SHA-512 in nine 128-byte blocks, and shuffles the 136 tiles with the
+//! resulting 144 words. The published algorithm is at
+//! <https://tenhou.net/stat/rand2011/>.
+//!
+//! Wall positions follow the shuffled array: tiles are dealt and drawn from
+//! position 135 downward, and positions 0 to 13 form the dead wall with the
+//! dora indicators at 5, 7, 9, 11 and 13.
+
+use base64::Engine;
+use sha2::{Digest, Sha512};
+
+use crate::error::{ParserError, Result};
+use crate::models::{Event, Round, Wall, WallMismatch, WallMismatchKind};
+
+/// Prefix of the seeds this module understands
+pub const SEED_PREFIX: &str = "mt19937ar-sha512-n288-base64,";
+
+const TILES: usize = 136;
+
+/// Tiles in the dead wall
+pub const DEAD_WALL: usize = 14;
+
+const N: usize = 624;
+const M: usize = 397;
+
+/// MT19937 as in the reference `mt19937ar.c`
+struct Mt19937 {
+    state: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    fn new(seed: u32) -> Self {
+        let mut state = [0u32; N];
+        state[0] = seed;
+        for i in 1..N {
+            state[i] = 1_812_433_253u32
+                .wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        Self { state, index: N }
+    }
+
+    fn from_key(key: &[u32]) -> Self {
+        let mut mt = Self::new(19_650_218);
+        let s = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..N.max(key.len()) {
+            s[i] = (s[i] ^ (s[i - 1] ^ (s[i - 1] >> 30)).wrapping_mul(1_664_525))
+                .wrapping_add(key[j])
+                .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= N {
+                s[0] = s[N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N - 1 {
+            s[i] = (s[i] ^ (s[i - 1] ^ (s[i - 1] >> 30)).wrapping_mul(1_566_083_941))
+                .wrapping_sub(i as u32);
+            i += 1;
+            if i >= N {
+                s[0] = s[N - 1];
+                i = 1;
+            }
+        }
+        s[0] = 0x8000_0000;
+        mt
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            for i in 0..N {
+                let y = (self.state[i] & 0x8000_0000) | (self.state[(i + 1) % N] & 0x7fff_ffff);
+                let mag = if y & 1 == 0 { 0 } else { 0x9908_b0df };
+                self.state[i] = self.state[(i + M) % N] ^ (y >> 1) ^ mag;
+            }
+            self.index = 0;
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c_5680;
+        y ^= (y << 15) & 0xefc6_0000;
+        y ^ (y >> 18)
+    }
+}
+
+/// Generates the wall of each round in turn
+pub struct WallGenerator {
+    mt: Mt19937,
+}
+
+impl WallGenerator {
+    /// Generator for a SHUFFLE `seed` attribute
+    pub fn from_seed(seed: &str) -> Result<Self> {
+        let encoded = seed.strip_prefix(SEED_PREFIX).ok_or_else(|| {
+            ParserError::invalid_format(format!("Unsupported shuffle seed: {:.40}", seed))
+        })?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| ParserError::invalid_format(format!("Invalid shuffle seed: {}", e)))?;
+        if bytes.len() != N * 4 {
+            return Err(ParserError::invalid_format(format!(
+                "Shuffle seed is {} bytes, expected {}",
+                bytes.len(),
+                N * 4
+            )));
+        }
+        let key: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Self {
+            mt: Mt19937::from_key(&key),
+        })
+    }
+
+    /// The wall of the next round
+    pub fn next_wall(&mut self) -> Wall {
+        let source: Vec<u8> = (0..288)
+            .flat_map(|_| self.mt.next_u32().to_le_bytes())
+            .collect();
+        let rnd: Vec<u32> = source
+            .chunks_exact(128)
+            .flat_map(|block| Sha512::digest(block).to_vec())
+            .collect::<Vec<u8>>()
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let mut tiles: Vec<u32> = (0..TILES as u32).collect();
+        for (i, &r) in rnd.iter().enumerate().take(TILES - 1) {
+            tiles.swap(i, i + r as usize % (TILES - i));
+        }
+        Wall {
+            tiles,
+            dice: [rnd[135] % 6, rnd[136] % 6],
+        }
+    }
+}
+
+impl Wall {
+    /// Wall position of a tile ID
+    pub fn position(&self, tile_id: u32) -> Option<usize> {
+        self.tiles.iter().position(|&t| t == tile_id)
+    }
+
+    pub fn dead_wall(&self) -> &[u32] {
+        &self.tiles[..DEAD_WALL.min(self.tiles.len())]
+    }
+
+    /// Dora indicators in reveal order
+    pub fn dora_indicators(&self) -> Vec<u32> {
+        [5, 7, 9, 11, 13]
+            .iter()
+            .filter_map(|&i| self.tiles.get(i).copied())
+            .collect()
+    }
+
+    /// Starting hands in tile IDs for each seat, the dealer taking first
+    pub fn deal(&self, dealer: u8) -> [Vec<u32>; 4] {
+        let mut hands: [Vec<u32>; 4] = Default::default();
+        let top = TILES - 1;
+        for offset in 0..4 {
+            let hand = &mut hands[(dealer as usize + offset) % 4];
+            for block in 0..3 {
+                for k in 0..4 {
+                    hand.push(self.tiles[top - block * 16 - offset * 4 - k]);
+                }
+            }
+            hand.push(self.tiles[top - 48 - offset]);
+        }
+        hands
+    }
+
+    /// Whether this wall deals `hands` and reveals `dora_indicator` first
+    pub fn matches(&self, dealer: u8, hands: &[Vec<u32>], dora_indicator: u32) -> bool {
+        if self.tiles.len() != TILES || self.tiles.get(5) != Some(&dora_indicator) {
+            return false;
+        }
+        self.deal(dealer).iter().zip(hands).all(|(dealt, hand)| {
+            let (mut dealt, mut hand) = (dealt.clone(), hand.clone());
+            dealt.sort_unstable();
+            hand.sort_unstable();
+            dealt == hand
+        })
+    }
+}
+
+/// Compare a four-player round with the wall the seed gives for it
+///
+/// Starting hands, dora indicators and draws are checked in turn: draws from
+/// the live wall go down from position 83, and replacement draws after a kan
+/// must come from the four tiles at the start of the dead wall. Hidden
+/// tiles, and rounds not dealt to four seats, are not checked.
+pub fn check_round(wall: &Wall, round: &Round) -> Vec<WallMismatch> {
+    let mut mismatches = Vec::new();
+    if wall.tiles.len() != TILES
+        || round.init.initial_hands.len() != 4
+        || round.init.initial_hands.iter().any(|hand| hand.len() != 13)
+    {
+        return mismatches;
+    }
+    let mut mismatch = |kind, seat, event, expected: Vec<u32>, actual: Vec<u32>| {
+        mismatches.push(WallMismatch {
+            round_id: round.round_id.clone(),
+            kind,
+            seat,
+            event,
+            expected,
+            actual,
+        });
+    };
+
+    for (seat, (dealt, hand)) in wall
+        .deal(round.dealer_seat)
+        .into_iter()
+        .zip(&round.init.initial_hands)
+        .enumerate()
+    {
+        let mut dealt = dealt;
+        let mut hand: Vec<u32> = hand.iter().map(|tile| tile.id()).collect();
+        dealt.sort_unstable();
+        hand.sort_unstable();
+        if dealt != hand {
+            mismatch(
+                WallMismatchKind::InitialHand,
+                Some(seat as u8),
+                None,
+                dealt,
+                hand,
+            );
+        }
+    }
+
+    let indicators = wall.dora_indicators();
+    if round.init.dora_indicator != indicators[0] {
+        mismatch(
+            WallMismatchKind::DoraIndicator,
+            None,
+            None,
+            vec![indicators[0]],
+            vec![round.init.dora_indicator],
+        );
+    }
+
+    let replacements: Vec<usize> = round
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Kan { rinshan, .. } => *rinshan,
+            _ => None,
+        })
+        .collect();
+    let rinshan_tiles = &wall.tiles[..4];
+    let mut live = (DEAD_WALL..TILES - 52).rev();
+    let mut revealed = indicators.iter().skip(1);
+    for (id, event) in round.events.iter().enumerate() {
+        match event {
+            Event::Draw { seat, tile, .. } if replacements.contains(&id) => {
+                if tile.is_hidden() || rinshan_tiles.contains(&tile.id()) {
+                    continue;
+                }
+                mismatch(
+                    WallMismatchKind::ReplacementDraw,
+                    Some(*seat),
+                    Some(id),
+                    rinshan_tiles.to_vec(),
+                    vec![tile.id()],
+                );
+            }
+            Event::Draw { seat, tile, .. } => {
+                let Some(position) = live.next() else {
+                    continue;
+                };
+                if !tile.is_hidden() && tile.id() != wall.tiles[position] {
+                    mismatch(
+                        WallMismatchKind::Draw,
+                        Some(*seat),
+                        Some(id),
+                        vec![wall.tiles[position]],
+                        vec![tile.id()],
+                    );
+                }
+            }
+            Event::Dora { indicator, .. } => {
+                let Some(&expected) = revealed.next() else {
+                    continue;
+                };
+                if indicator.id() != expected {
+                    mismatch(
+                        WallMismatchKind::DoraIndicator,
+                        None,
+                        Some(id),
+                        vec![expected],
+                        vec![indicator.id()],
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A seed string for a key derived from `word`
+    fn seed(word: u32) -> String {
+        let bytes: Vec<u8> = (0..N)
+            .flat_map(|i| (word ^ i as u32).to_le_bytes())
+            .collect();
+        format!(
+            "{}{}",
+            SEED_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )
+    }
+
+    #[test]
+    fn test_mt19937_reference_output() {
+        // First outputs of mt19937ar.c's own test
+        let mut mt = Mt19937::from_key(&[0x123, 0x234, 0x345, 0x456]);
+        let first: Vec<u32> = (0..3).map(|_| mt.next_u32()).collect();
+        assert_eq!(first, vec![1_067_595_299, 955_945_823, 477_289_528]);
+    }
+
+    #[test]
+    fn test_wall_generation() {
+        let mut generator = WallGenerator::from_seed(&seed(7)).unwrap();
+        let first = generator.next_wall();
+        let second = generator.next_wall();
+        assert_ne!(first.tiles, second.tiles);
+        for wall in [&first, &second] {
+            let mut sorted = wall.tiles.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, (0..136).collect::<Vec<_>>());
+            assert!(wall.dice.iter().all(|&d| d < 6));
+        }
+        assert_eq!(
+            WallGenerator::from_seed(&seed(7)).unwrap().next_wall(),
+            first
+        );
+
+        let hands = first.deal(1);
+        assert!(hands.iter().all(|h| h.len() == 13));
+        assert_eq!(hands[1][0], first.tiles[135]);
+        assert!(first.matches(1, &hands, first.tiles[5]));
+        assert!(!first.matches(0, &hands, first.tiles[5]));
+        assert_eq!(first.position(first.tiles[83]), Some(83));
+        assert_eq!(first.dora_indicators()[0], first.tiles[5]);
+        assert_eq!(first.dead_wall().len(), DEAD_WALL);
+
+        assert!(WallGenerator::from_seed("mt19937ar-sha512-n288-base64,AAAA").is_err());
+        assert!(WallGenerator::from_seed("xorshift,AAAA").is_err());
+    }
+}
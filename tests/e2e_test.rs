@@ -1,3 +1,4 @@
+use std::io::Write;
 use std::process::Command;
 use tempfile::NamedTempFile;
 
@@ -36,6 +37,7 @@ fn test_e2e_sample_xml() {
     assert_eq!(player0["seat"], 0);
     assert_eq!(player0["playerId"], "テストユーザー1");
     assert_eq!(player0["rank"], 5);
+    assert_eq!(player0["rankName"], "５級");
     assert_eq!(player0["rate"], 1800);
     assert_eq!(player0["gender"], "M");
 
@@ -156,3 +158,967 @@ fn test_e2e_version() {
     let stdout = String::from_utf8(output.stdout).unwrap();
     assert!(stdout.contains("0.1.0"));
 }
+
+#[test]
+fn test_e2e_validate() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["validate", "tests/data/sample.xml", "--json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout).expect("Failed to parse validation report");
+    assert!(report["issues"].is_array());
+}
+
+#[test]
+fn test_e2e_validate_invalid_log() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    std::io::Write::write_all(
+        &mut temp_file,
+        br#"<mjloggm ver="2.3"><INIT seed="0,0,0,1,2,52" ten="250,250,250,250" oya="0" hai0="0" hai1="1" hai2="2" hai3="3"/></mjloggm>"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["validate", temp_file.path().to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("expected 13"));
+    assert!(stdout.contains("(hand-size)"));
+}
+
+#[test]
+fn test_e2e_validate_unparseable_log_json() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    std::io::Write::write_all(
+        &mut temp_file,
+        br#"<mjloggm ver="2.3"><INIT seed="0,0,0,1,2,52" ten="250" oya="0" hai0="" hai1="" hai2="" hai3=""/></mjloggm>"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["validate", temp_file.path().to_str().unwrap(), "--json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["error"]["code"], "invalid-format");
+}
+
+#[test]
+fn test_e2e_stats() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "stats",
+            "tests/data/sample.xml",
+            "tests/data/sample_complex.xml",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout).expect("Failed to parse statistics");
+    assert_eq!(report["games"], 2);
+    assert_eq!(report["riichi"]["テストユーザー1"]["riichiCount"], 2);
+    assert!(
+        report["calls"]["テストユーザー1"]["rounds"]
+            .as_u64()
+            .unwrap()
+            >= 1
+    );
+    assert_eq!(report["scores"]["テストユーザー1"]["games"], 2);
+}
+
+#[test]
+fn test_e2e_stats_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a.xml", "b.xml"] {
+        std::fs::copy("tests/data/sample.xml", dir.path().join(name)).unwrap();
+    }
+    std::fs::write(dir.path().join("notes.txt"), "not a log").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["stats", dir.path().to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["games"], 2);
+    assert_eq!(report["placement"]["テストユーザー1"]["games"], 2);
+}
+
+#[test]
+fn test_e2e_stats_heatmap_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let heatmap_path = dir.path().join("heatmap.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "stats",
+            "tests/data/sample.xml",
+            "-o",
+            dir.path().join("stats.json").to_str().unwrap(),
+            "--heatmap",
+            heatmap_path.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let csv = std::fs::read_to_string(heatmap_path).unwrap();
+    assert!(csv.starts_with("turn,1m,"));
+    assert!(csv.lines().count() > 1);
+}
+
+#[test]
+fn test_e2e_stats_csv_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let stats_path = dir.path().join("stats.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "stats",
+            "tests/data/sample.xml",
+            "-o",
+            stats_path.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let csv = std::fs::read_to_string(stats_path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "section,key,metric,value");
+    assert!(lines.contains(&"riichi,テストユーザー1,riichiCount,1"));
+}
+
+#[test]
+fn test_e2e_mistakes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["mistakes", "tests/data/sample_complex.xml", "--json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rounds: serde_json::Value =
+        serde_json::from_str(&stdout).expect("Failed to parse findings");
+    assert!(rounds.is_array());
+}
+
+#[test]
+fn test_e2e_majsoul_format() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["tests/data/sample.xml", "--stream", "--format", "majsoul"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let record: serde_json::Value =
+        serde_json::from_str(&stdout).expect("Failed to parse MahjongSoul record");
+    assert_eq!(record["head"]["accounts"].as_array().unwrap().len(), 4);
+    assert_eq!(record["data"]["records"][0]["name"], ".lq.RecordNewRound");
+}
+
+#[test]
+fn test_e2e_transcript_format() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "tests/data/sample_complex.xml",
+            "--stream",
+            "--format",
+            "transcript",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("E1: East 1, "));
+    assert!(stdout.contains("Final standings:\n1. "));
+}
+
+#[test]
+fn test_e2e_csv_format() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["tests/data/sample.xml", "--stream", "--format", "csv"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(
+        lines.next(),
+        Some("gameId,round,roundId,event,type,seat,player,tile,tiles,from,riichi,detail,han,fu,scores")
+    );
+    let columns = lines.next().unwrap().split(',').collect::<Vec<_>>();
+    assert_eq!(columns.len(), 15);
+    assert_eq!(columns[2], "Round 1");
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_e2e_sqlite_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("games.db");
+    let convert = |input: &str, extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+            .args([input, "--format", "sqlite", "-o", db.to_str().unwrap()])
+            .args(extra)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let output = convert("tests/data/sample.xml", &[]);
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // An existing database is only added to when asked
+    assert!(!convert("tests/data/sample_complex.xml", &[])
+        .status
+        .success());
+    let output = convert("tests/data/sample_complex.xml", &["--append"]);
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let connection = rusqlite::Connection::open(&db).unwrap();
+    let count = |sql: &str| -> i64 { connection.query_row(sql, [], |r| r.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM games"), 2);
+    assert_eq!(count("SELECT COUNT(*) FROM players"), 8);
+    assert!(count("SELECT COUNT(*) FROM events WHERE type = 'discard'") > 0);
+}
+
+/// `--stream --deterministic` output of sample_complex.xml in `format`
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn deterministic_output(format: &str) -> Vec<u8> {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "tests/data/sample_complex.xml",
+            "--stream",
+            "--deterministic",
+            "--format",
+            format,
+        ])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_e2e_msgpack_format() {
+    let json: serde_json::Value = serde_json::from_slice(&deterministic_output("json")).unwrap();
+    let msgpack: serde_json::Value =
+        rmp_serde::from_slice(&deterministic_output("msgpack")).unwrap();
+    assert_eq!(msgpack, json);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_e2e_cbor_format() {
+    let json: serde_json::Value = serde_json::from_slice(&deterministic_output("json")).unwrap();
+    let bytes = deterministic_output("cbor");
+    let cbor: serde_json::Value = ciborium::from_reader(bytes.as_slice()).unwrap();
+    assert_eq!(cbor, json);
+    // Typed readers get the same game back
+    let game: tenhou_log_parser::ParserOutput = ciborium::from_reader(bytes.as_slice()).unwrap();
+    assert_eq!(game.game_id, json["gameId"]);
+}
+
+#[test]
+fn test_e2e_dataset() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["dataset", "tests/data/sample.xml"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Failed to parse decision"))
+        .collect();
+    assert!(!records.is_empty());
+    assert_eq!(records[0]["round"], 0);
+    assert!(records[0]["state"]["hand"].is_array());
+    assert!(records[0]["action"]["type"].is_string());
+}
+
+#[test]
+fn test_e2e_dataset_discards_csv() {
+    let temp_output = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+    let output_path = temp_output.path();
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "dataset",
+            "tests/data/sample.xml",
+            "--kind",
+            "discards",
+            "-o",
+        ])
+        .arg(output_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let csv = std::fs::read_to_string(output_path).unwrap();
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("gameId,round,event,seat,turn,tile"));
+    assert!(lines.next().is_some());
+}
+
+#[test]
+fn test_e2e_dataset_rounds_csv() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "dataset",
+            "tests/data/sample.xml",
+            "tests/data/sample_complex.xml",
+            "--kind",
+            "rounds",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("gameId,round,roundId,roundNumber,honba,kyoutaku,dealer,events,outcome"));
+    // Every round of both games
+    assert!(lines.count() >= 2);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_e2e_dataset_events_arrow() {
+    let temp_output = tempfile::Builder::new()
+        .suffix(".arrow")
+        .tempfile()
+        .unwrap();
+    let output_path = temp_output.path();
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["dataset", "tests/data/sample.xml", "--kind", "events", "-o"])
+        .arg(output_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Arrow IPC files start and end with this magic
+    let bytes = std::fs::read(output_path).unwrap();
+    assert!(bytes.starts_with(b"ARROW1"));
+    assert!(bytes.ends_with(b"ARROW1"));
+}
+
+#[test]
+fn test_e2e_stats_skip_duplicates() {
+    let manifest = NamedTempFile::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "stats",
+            "tests/data/sample.xml",
+            "tests/data/sample_complex.xml",
+            "tests/data/sample.xml",
+            "--skip-duplicates",
+            "--manifest",
+        ])
+        .arg(manifest.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Failed to parse statistics");
+    assert_eq!(report["games"], 2);
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest.path()).unwrap()).unwrap();
+    assert_eq!(manifest["files"].as_array().unwrap().len(), 3);
+    assert_eq!(manifest["files"][2]["duplicateOf"], "tests/data/sample.xml");
+    assert_eq!(
+        manifest["duplicates"],
+        serde_json::json!([["tests/data/sample.xml", "tests/data/sample.xml"]])
+    );
+}
+
+#[test]
+fn test_e2e_checksum_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let output_path = dir.path().join("sample.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["tests/data/sample.xml", "--checksum", "-o"])
+        .arg(&output_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let sidecar = std::fs::read_to_string(dir.path().join("sample.json.sha256")).unwrap();
+    let (hash, name) = sidecar.trim_end().split_once("  ").unwrap();
+    assert_eq!(name, "sample.json");
+    assert_eq!(
+        hash,
+        tenhou_log_parser::manifest::file_hash(&output_path).unwrap()
+    );
+}
+
+#[test]
+fn test_e2e_normalize_gzip() {
+    let dir = tempfile::tempdir().unwrap();
+    let output_path = dir.path().join("normalized.xml.gz");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["normalize", "tests/data/sample_complex.xml", "--gzip", "-o"])
+        .arg(&output_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let normalized = tenhou_log_parser::parse_mjlog_file(&output_path).unwrap();
+    let original =
+        tenhou_log_parser::parse_mjlog_file(std::path::Path::new("tests/data/sample_complex.xml"))
+            .unwrap();
+    assert_eq!(
+        serde_json::to_value(&normalized.rounds).unwrap(),
+        serde_json::to_value(&original.rounds).unwrap()
+    );
+}
+
+#[test]
+fn test_e2e_verify_reports_mismatches() {
+    let mut replay = NamedTempFile::new().unwrap();
+    write!(replay, r#"{{"name": [], "log": []}}"#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["verify", "tests/data/sample.xml", "--json"])
+        .arg(replay.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(1));
+    let mismatches: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(mismatches[0]["field"], "rounds");
+    assert_eq!(mismatches[0]["tenhou6"], "0");
+}
+
+#[test]
+fn test_e2e_tenhou6_format_verifies() {
+    let replay = NamedTempFile::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "tests/data/sample_complex.xml",
+            "--format",
+            "tenhou6",
+            "-f",
+            "-o",
+        ])
+        .arg(replay.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Tenhou's format read back describes the same game
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["verify", "tests/data/sample_complex.xml"])
+        .arg(replay.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "verify failed with stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_e2e_tenhou6_input_detected() {
+    let replay = NamedTempFile::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["tests/data/sample.xml", "--format", "tenhou6", "-f", "-o"])
+        .arg(replay.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    // No --from: the JSON replay is told from an mjlog by its content
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg(replay.path())
+        .arg("--stream")
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["mjlogVersion"], "tenhou6");
+    assert_eq!(json["rounds"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_e2e_stats_input_list() {
+    let mut list = NamedTempFile::new().unwrap();
+    writeln!(
+        list,
+        "# samples\ntests/data/sample.xml\n\ntests/data/sample_c*.xml"
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["stats", "--input-list"])
+        .arg(list.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Failed to parse statistics");
+    assert_eq!(report["games"], 2);
+}
+
+#[test]
+fn test_e2e_convert_input_list() {
+    let mut list = NamedTempFile::new().unwrap();
+    writeln!(
+        list,
+        "# samples\ntests/data/sample.xml\ntests/data/sample_c*.xml"
+    )
+    .unwrap();
+    let out_dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg("--input-list")
+        .arg(list.path())
+        .arg("--out-dir")
+        .arg(out_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(out_dir.path().join("sample.json").exists());
+    assert!(out_dir.path().join("sample_complex.json").exists());
+}
+
+#[test]
+fn test_e2e_convert_directory_to_out_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    for name in ["a.xml", "sub/a.mjlog"] {
+        std::fs::copy("tests/data/sample.xml", dir.path().join(name)).unwrap();
+    }
+    std::fs::write(dir.path().join("broken.xml"), "<mjloggm><INIT seed=").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not a log").unwrap();
+    let out_dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            dir.path().to_str().unwrap(),
+            "--out-dir",
+            out_dir.path().to_str().unwrap(),
+            "--jobs",
+            "2",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    // One input fails, the others are still converted
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Converted 2 of 3 file(s), 1 failed"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("broken.xml"), "{}", stderr);
+
+    for name in ["a.json", "sub/a.json"] {
+        let content = std::fs::read_to_string(out_dir.path().join(name)).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed["players"].as_array().unwrap().len(), 4);
+    }
+    assert!(!out_dir.path().join("broken.json").exists());
+}
+
+#[test]
+fn test_e2e_convert_with_stats_summary() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let stats_json = out_dir.path().join("stats.json");
+    let stats_csv = out_dir.path().join("stats.csv");
+
+    for stats in [&stats_json, &stats_csv] {
+        let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+            .args([
+                "tests/data/sample.xml",
+                "tests/data/sample_complex.xml",
+                "--out-dir",
+                out_dir.path().to_str().unwrap(),
+                "--force",
+                "--stats",
+                stats.to_str().unwrap(),
+            ])
+            .output()
+            .expect("Failed to execute command");
+        assert!(
+            output.status.success(),
+            "Command failed with stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&stats_json).unwrap()).unwrap();
+    assert_eq!(summary["games"], 2);
+    let players = summary["players"].as_array().unwrap();
+    assert!(!players.is_empty());
+    for player in players {
+        assert!(player["winRate"].as_f64().unwrap() <= 1.0);
+        assert!(player.get("averagePlacement").is_some());
+    }
+
+    let csv = std::fs::read_to_string(&stats_csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("player,games,rounds,winRate,dealInRate,riichiRate,callRate,averageWin,averagePlacement")
+    );
+    assert_eq!(lines.count(), players.len());
+}
+
+#[test]
+fn test_e2e_compact_gzip_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("game.xml");
+    std::fs::copy("tests/data/sample.xml", &input).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg(&input)
+        .args(["--compact", "--gzip"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Written next to the input as .json.gz
+    let file = std::fs::File::open(dir.path().join("game.json.gz")).unwrap();
+    let mut json = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(file), &mut json).unwrap();
+    assert_eq!(json.lines().count(), 1);
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed["players"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_e2e_tile_ids() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args(["tests/data/sample.xml", "--stream", "--tile-ids"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let hand = &parsed["rounds"][0]["init"]["initialHands"][0];
+    let first = &hand[0];
+    let id = first["id"].as_u64().unwrap();
+    assert_eq!(
+        first["name"],
+        tenhou_log_parser::tile_id_to_string(id as u32).as_ref()
+    );
+    let draw = parsed["rounds"][0]["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["type"] == "draw")
+        .unwrap();
+    assert!(draw["tile"]["id"].is_u64());
+}
+
+#[test]
+fn test_e2e_concatenated_games() {
+    let log = std::fs::read_to_string("tests/data/sample.xml").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("games.xml");
+    std::fs::write(&input, format!("{}\n{}", log.trim_end(), log)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg(&input)
+        .arg("--stream")
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let games = parsed.as_array().expect("an array of games");
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[1]["players"][0]["playerId"], "テストユーザー1");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg(&input)
+        .arg("-o")
+        .arg(dir.path().join("game.json"))
+        .arg("--split-games")
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for number in [1, 2] {
+        let path = dir.path().join(format!("game-{}.json", number));
+        let game: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(game["rounds"].as_array().unwrap().len(), 1);
+    }
+    assert!(!dir.path().join("game.json").exists());
+}
+
+#[test]
+fn test_e2e_emit_schema() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg("--emit-schema")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["title"], "ParserOutput");
+    assert!(schema["$defs"]["event"]["oneOf"].is_array());
+}
+
+#[test]
+fn test_e2e_emit_urls() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("2023010100gm-00a9-0000-1a2b3c4d.xml");
+    std::fs::copy("tests/data/sample.xml", &input).unwrap();
+    let links = dir.path().join("links.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg(&input)
+        .arg("--stream")
+        .arg("--pov")
+        .arg("2")
+        .arg("--emit-urls")
+        .arg(&links)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let csv = std::fs::read_to_string(&links).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("round,roundId,url"));
+    assert!(lines
+        .next()
+        .unwrap()
+        .ends_with(",https://tenhou.net/0/?log=2023010100gm-00a9-0000-1a2b3c4d&tw=2&ts=0"));
+
+    // Without a log ID in the file name there is nothing to link to
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .arg("tests/data/sample.xml")
+        .arg("--stream")
+        .arg("--emit-urls")
+        .arg(&links)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_e2e_pov_hides_annotations() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"))
+        .args([
+            "tests/data/sample_complex.xml",
+            "--stream",
+            "--pov",
+            "1",
+            "--annotate",
+            "shanten,waits,counters",
+        ])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "Command failed with stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let mut own = 0;
+    for round in parsed["rounds"].as_array().unwrap() {
+        assert!(round.get("counters").is_none());
+        for event in round["events"].as_array().unwrap() {
+            if !matches!(event["type"].as_str(), Some("draw" | "discard")) {
+                continue;
+            }
+            if event["seat"] == 1 {
+                own += usize::from(event.get("shanten").is_some());
+            } else {
+                assert!(event.get("shanten").is_none(), "{}", event);
+                assert!(event.get("waits").is_none(), "{}", event);
+                assert!(event.get("furiten").is_none(), "{}", event);
+            }
+        }
+    }
+    assert!(own > 0);
+}
+
+#[test]
+fn test_e2e_spill_rounds_keeps_tile_ids() {
+    let convert = |spill: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_tenhou-log-parser"));
+        command
+            .arg("tests/data/sample_complex.xml")
+            .arg("--stream")
+            .arg("--tile-ids")
+            .arg("--deterministic");
+        if spill {
+            command.arg("--spill-rounds");
+        }
+        let output = command.output().expect("Failed to execute command");
+        assert!(
+            output.status.success(),
+            "Command failed with stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output.stdout
+    };
+    assert_eq!(convert(true), convert(false));
+}
@@ -1,7 +1,9 @@
 use std::io::Cursor;
 use std::process::Command;
 use tempfile::NamedTempFile;
-use tenhou_log_parser::{parse_mjlog, ParserOutput};
+use tenhou_log_parser::{
+    parse_events, parse_files_parallel, parse_mjlog, LogEvent, ParserOptions, ParserOutput,
+};
 
 mod helpers;
 use helpers::{complete_mjlog, minimal_mjlog, test_data_path};
@@ -131,3 +133,58 @@ fn test_sample_xml_file_parsing() {
         eprintln!("Sample file {:?} not found, skipping test", sample_path);
     }
 }
+
+#[test]
+fn test_parse_events_matches_parse_mjlog() {
+    let content = std::fs::read(test_data_path("sample_complex.xml")).unwrap();
+    let output = parse_mjlog(Cursor::new(&content)).unwrap();
+
+    let mut round_ids = Vec::new();
+    let mut event_types = Vec::new();
+    for event in parse_events(Cursor::new(&content)) {
+        match event.unwrap() {
+            LogEvent::Version(version) => assert_eq!(version, output.mjlog_version),
+            LogEvent::Players(players) => assert_eq!(players.len(), output.players.len()),
+            LogEvent::RoundStart { round_id, .. } => round_ids.push(round_id),
+            LogEvent::Event(event) => {
+                event_types.push(serde_json::to_value(event).unwrap()["type"].clone())
+            }
+            _ => {}
+        }
+    }
+
+    let expected_ids: Vec<_> = output.rounds.iter().map(|r| r.round_id.clone()).collect();
+    assert_eq!(round_ids, expected_ids);
+    let expected_types: Vec<_> = output
+        .rounds
+        .iter()
+        .flat_map(|r| &r.events)
+        .map(|e| serde_json::to_value(e).unwrap()["type"].clone())
+        .collect();
+    assert_eq!(event_types, expected_types);
+}
+
+#[test]
+fn test_parse_files_parallel_keeps_input_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let broken = dir.path().join("broken.xml");
+    std::fs::write(&broken, "<mjloggm><INIT seed=").unwrap();
+    let inputs = vec![
+        test_data_path("sample.xml"),
+        test_data_path("sample_complex.xml"),
+        broken,
+        test_data_path("sample.xml"),
+    ];
+
+    let results = parse_files_parallel(&inputs, &ParserOptions::default(), 3);
+    assert_eq!(results.len(), inputs.len());
+    for (input, result) in inputs.iter().zip(&results) {
+        match input.file_name().unwrap().to_str().unwrap() {
+            "broken.xml" => assert!(result.is_err()),
+            _ => {
+                let expected = tenhou_log_parser::parse_mjlog_file(input).unwrap();
+                assert_eq!(result.as_ref().unwrap().rounds.len(), expected.rounds.len());
+            }
+        }
+    }
+}